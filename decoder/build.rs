@@ -14,11 +14,369 @@ use std::env;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
 use hex::decode;
 use hkdf::Hkdf;
 use sha2::Sha512;
 
+// The decoder's own key tree code, so the channel 0 check below runs the same
+// derivation as decode_frame.
+#[path = "src/modules/key_tree.rs"]
+mod key_tree;
+
+/// DER SubjectPublicKeyInfo header of an Ed25519 public key; the 32-byte key follows.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Most host verifying keys a build embeds: the primary and up to three fallbacks kept
+/// while the signing key rotates. Every signature is tried against each of them.
+const MAX_HOST_KEYS: usize = 4;
+
+/// Length of the derived DECODER_KEY. Emitted as `KEY_LEN` into secrets.rs, where it is
+/// asserted to equal the key size of the ChaCha20 cipher that uses the key.
+const KEY_LEN: usize = 32;
+
+/// Secrets of `std` (host test) builds: a throwaway deployment whose host private key
+/// is committed, so the tests can sign the frames and subscriptions they feed in.
+const TEST_SECRETS: &str = "host-tests/test.secrets";
+
+/// Decoder id of `std` builds, the one the host test vectors are encoded for.
+const TEST_DECODER_ID: &str = "0xdeadbeef";
+
+/// Hex-encoded secrets needed by the decoder build.
+struct Secrets {
+    decoder_dk: String,
+    host_key_pub: String,
+    /// Older (or newer) host keys also trusted during a key rotation, usually none.
+    host_key_pub_fallbacks: Vec<String>,
+    channel_0_password: String,
+    /// Tree position (node_trunc, node_ext) of the channel 0 password embedded in
+    /// CHANNEL_0_SUBSCRIPTION; the root unless the deployment says otherwise.
+    channel_0_node: (u64, u8),
+}
+
+fn read_secrets_file(secret_path: &Path) -> Secrets {
+    // Read the secrets file.
+    let secrets_contents =
+        fs::read_to_string(secret_path).expect("Unable to read global.secrets file");
+
+    // Parse the JSON content.
+    let secrets_json: serde_json::Value =
+        serde_json::from_str(&secrets_contents).expect("Invalid JSON in global.secrets");
+
+    // Extract the fields you need.
+    let decoder_dk = secrets_json
+        .get("decoder_dk")
+        .and_then(|v| v.as_str())
+        .expect("Missing or invalid decoder_dk");
+    let host_key_pub = secrets_json
+        .get("host_key_pub")
+        .and_then(|v| v.as_str())
+        .expect("Missing or invalid host_key_pub");
+    let host_key_pub_fallbacks = match secrets_json.get("host_key_pub_fallbacks") {
+        Some(keys) => keys
+            .as_array()
+            .expect("host_key_pub_fallbacks must be a list")
+            .iter()
+            .map(|k| k.as_str().expect("Invalid host_key_pub_fallbacks entry").to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+    let channel_0_password = secrets_json["channels"]["0"]
+        .as_str()
+        .expect("Missing channel 0 password");
+    let channel_0_node = match secrets_json.get("channel_0_node") {
+        Some(node) => (
+            node["node_trunc"].as_u64().expect("channel_0_node needs a numeric node_trunc"),
+            node["node_ext"]
+                .as_u64()
+                .and_then(|ext| u8::try_from(ext).ok())
+                .expect("channel_0_node needs a node_ext byte"),
+        ),
+        None => CHANNEL_0_ROOT,
+    };
+
+    Secrets {
+        decoder_dk: decoder_dk.to_string(),
+        host_key_pub: host_key_pub.to_string(),
+        host_key_pub_fallbacks,
+        channel_0_password: channel_0_password.to_string(),
+        channel_0_node,
+    }
+}
+
+fn read_secrets_env() -> Secrets {
+    let var = |name: &str| {
+        env::var(name).unwrap_or_else(|_| {
+            panic!("global.secrets is missing and the {} environment variable is not set", name)
+        })
+    };
+
+    Secrets {
+        decoder_dk: var("DECODER_DK"),
+        host_key_pub: var("HOST_KEY_PUB"),
+        // Optional, comma-separated
+        host_key_pub_fallbacks: env::var("HOST_KEY_PUB_FALLBACKS")
+            .map(|keys| keys.split(',').filter(|k| !k.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+        channel_0_password: var("CHANNEL_0_PASSWORD"),
+        // Optional, "node_trunc:node_ext"
+        channel_0_node: env::var("CHANNEL_0_NODE")
+            .map(|node| {
+                let (trunc, ext) = node.split_once(':').expect("CHANNEL_0_NODE must be node_trunc:node_ext");
+                (
+                    trunc.parse().expect("Invalid node_trunc in CHANNEL_0_NODE"),
+                    ext.parse().expect("Invalid node_ext in CHANNEL_0_NODE"),
+                )
+            })
+            .unwrap_or(CHANNEL_0_ROOT),
+    }
+}
+
+/// Decode a hex host public key, checking it is a DER-encoded Ed25519 key.
+fn parse_host_key(hex: &str) -> Vec<u8> {
+    let key = decode(hex).expect("Invalid hex in host public key");
+    assert!(
+        key.len() == ED25519_SPKI_PREFIX.len() + 32 && key.starts_with(&ED25519_SPKI_PREFIX),
+        "host_key_pub must be a DER-encoded Ed25519 public key"
+    );
+    key
+}
+
+/// Default tree position (node_trunc, node_ext) of the single channel 0 password written
+/// into CHANNEL_0_SUBSCRIPTION: node 1, the root the encoder derives every channel 0
+/// frame key from. A deployment rotating the emergency key per epoch embeds a node
+/// below it instead, whose subtree is the epoch's timestamps.
+const CHANNEL_0_ROOT: (u64, u8) = (0, 2);
+
+/// Timestamp of the sample frame used to check the channel 0 subscription.
+const SAMPLE_TIMESTAMP: u64 = 0x0123_4567_89ab_cdef;
+/// Extended frame key for SAMPLE_TIMESTAMP under root key 00 01 .. 0f, as computed by
+/// the design package's ChannelKeyDerivation. Pins key_tree to the encoder.
+const KEY_TREE_VECTOR: &str = "8ae1ddb7a1564defc89a495c8ec2ba19698418b3faec0b5a2afacf9113d4982c";
+
+/// Extended key for the leaf of `timestamp`, derived from `key` held at tree node
+/// `node_num` the way decode_frame walks the tree. None if the node is not an
+/// ancestor of the leaf.
+fn frame_key_from(node_num: u128, key: [u8; 16], timestamp: u64) -> Option<[u8; 32]> {
+    let leaf = (1u128 << 64) | timestamp as u128;
+    let depth = (127 - node_num.leading_zeros()) as usize;
+    if node_num == 0 || depth > 64 || leaf >> (64 - depth) != node_num {
+        return None;
+    }
+
+    let mut key = key;
+    for d in depth..64 {
+        let branch = ((timestamp >> (63 - d)) & 1) as u8 + 1;
+        key = key_tree::derive_child_key(&key, branch, leaf >> (63 - d));
+    }
+    Some(key_tree::extend_key(&key))
+}
+
+/// Level-order number of the channel 0 node at `(node_trunc, node_ext)`, as
+/// `ChannelPassword::node_num` computes it. Panics unless node_ext is 1 or 2 and the
+/// node is in the tree, the root (node 1) down to the leaves.
+fn channel_0_node_num((node_trunc, node_ext): (u64, u8)) -> u128 {
+    assert!(
+        node_ext == 1 || node_ext == 2,
+        "channel_0_node has node_ext {}, expected 1 or 2",
+        node_ext
+    );
+    let node_num = node_trunc as u128 * 2 + (node_ext as u128 - 1);
+    assert!(node_num >= 1, "channel_0_node is node 0, which is not in the tree");
+    node_num
+}
+
+/// Key of tree node `node_num`, derived down from the channel's root key the way
+/// decode_frame walks the tree.
+fn node_key(root: [u8; 16], node_num: u128) -> [u8; 16] {
+    let depth = 127 - node_num.leading_zeros();
+    let mut key = root;
+    for d in (0..depth).rev() {
+        let child = node_num >> d;
+        key = key_tree::derive_child_key(&key, (child & 1) as u8 + 1, child);
+    }
+    key
+}
+
+/// Encrypts a sample channel 0 frame the way the encoder does, from the channel root,
+/// and decrypts it from `node_password`, embedded in CHANNEL_0_SUBSCRIPTION at node
+/// `node_num`, the way the decoder does. The sample timestamp is moved under the node.
+/// Panics if the two disagree, since emergency frames would then never decode.
+fn check_channel_0(channel_0_password: [u8; 16], node_num: u128, node_password: [u8; 16]) {
+    let vector_root: [u8; 16] = core::array::from_fn(|i| i as u8);
+    let vector = frame_key_from(1, vector_root, SAMPLE_TIMESTAMP).unwrap();
+    assert_eq!(
+        hex::encode(vector),
+        KEY_TREE_VECTOR,
+        "key_tree.rs no longer derives the same frame keys as the design package encoder"
+    );
+
+    // The node's leaves share its bits as their prefix; the rest come from the sample
+    let below = 64 - (127 - node_num.leading_zeros());
+    let mask = u64::MAX.checked_shr(64 - below).unwrap_or(0);
+    let timestamp = ((node_num << below) as u64 & !mask) | (SAMPLE_TIMESTAMP & mask);
+
+    let encoder_key = frame_key_from(1, channel_0_password, timestamp).unwrap();
+    let decoder_key = frame_key_from(node_num, node_password, timestamp)
+        .expect("The channel 0 password is not stored at a node covering channel 0 frames");
+
+    // 64 bytes of content, then the channel id marker decode_frame checks
+    let mut frame = [0u8; 64 + 4];
+    frame[..64].copy_from_slice(&[b'X'; 64]);
+    let nonce = [0u8; 12];
+    ChaCha20::new(&encoder_key.into(), &nonce.into()).apply_keystream(&mut frame);
+    ChaCha20::new(&decoder_key.into(), &nonce.into()).apply_keystream(&mut frame);
+    assert!(
+        frame[..64] == [b'X'; 64] && frame[64..] == 0u32.to_le_bytes(),
+        "The embedded channel 0 subscription does not decode a sample channel 0 frame"
+    );
+}
+
+/// Flash page size of the MAX78000, must match `PAGE_SIZE` in constants.rs.
+const PAGE_SIZE: u64 = 0x2000;
+/// RESERVED pages not used for subscriptions: the two-page state log, the tamper page,
+/// the emergency-only page and the scratch page, plus the key page with the `rekey`
+/// feature, see constants.rs.
+fn non_subscription_pages() -> u64 {
+    let optional = ["CARGO_FEATURE_REKEY"];
+    5 + optional.iter().filter(|feature| env::var_os(feature).is_some()).count() as u64
+}
+/// Subscription capacity used when `MAX_CHANNELS` is not set.
+const DEFAULT_MAX_CHANNELS: u64 = 8;
+
+/// ORIGIN and LENGTH of the memory.x region `name`.
+fn memory_region(name: &str) -> (u64, u64) {
+    find_memory_region(name).unwrap_or_else(|| panic!("memory.x has no {} region", name))
+}
+
+/// ORIGIN and LENGTH of the memory.x region `name`, if memory.x defines it.
+fn find_memory_region(name: &str) -> Option<(u64, u64)> {
+    let line = include_str!("memory.x")
+        .lines()
+        .find(|l| l.split_whitespace().next() == Some(name))?;
+    let field = |key: &str| {
+        line.split(key)
+            .nth(1)
+            .and_then(|rest| rest.trim_start_matches([' ', '=']).split([' ', ',']).next())
+            .map(|hex| u64::from_str_radix(hex.trim_start_matches("0x"), 16))
+            .unwrap_or_else(|| panic!("{} region has no {}", name, key))
+            .unwrap_or_else(|_| panic!("{} {} is not hex", name, key))
+    };
+    Some((field("ORIGIN"), field("LENGTH")))
+}
+
+/// The FLASH region holding the firmware image and the RESERVED region holding the
+/// decoder's persistent data, as (start, end). Panics if they overlap, since erasing
+/// a subscription page would then erase code.
+fn flash_regions() -> ((u64, u64), (u64, u64)) {
+    let (flash_origin, flash_length) = memory_region("FLASH");
+    let (reserved_origin, reserved_length) = memory_region("RESERVED");
+    let firmware = (flash_origin, flash_origin + flash_length);
+    let reserved = (reserved_origin, reserved_origin + reserved_length);
+    assert!(
+        reserved.0 >= firmware.1 || reserved.1 <= firmware.0,
+        "memory.x: RESERVED ({:#x}..{:#x}) overlaps the firmware FLASH region ({:#x}..{:#x})",
+        reserved.0, reserved.1, firmware.0, firmware.1
+    );
+    (firmware, reserved)
+}
+
+/// The optional SUBSCRIPTIONS2 region of memory.x, as (start, end): whole pages of
+/// further subscriptions, after the `MAX_CHANNELS` pages in RESERVED. Panics if it
+/// overlaps the firmware or RESERVED. `(0, 0)` when memory.x has none.
+fn secondary_region(firmware: (u64, u64), reserved: (u64, u64)) -> (u64, u64) {
+    let Some((origin, length)) = find_memory_region("SUBSCRIPTIONS2") else {
+        return (0, 0);
+    };
+    let region = (origin, origin + length);
+    assert!(
+        origin % PAGE_SIZE == 0 && length % PAGE_SIZE == 0,
+        "memory.x: SUBSCRIPTIONS2 must be whole flash pages"
+    );
+    for (name, other) in [("FLASH", firmware), ("RESERVED", reserved)] {
+        assert!(
+            region.0 >= other.1 || region.1 <= other.0,
+            "memory.x: SUBSCRIPTIONS2 ({:#x}..{:#x}) overlaps the {} region ({:#x}..{:#x})",
+            region.0, region.1, name, other.0, other.1
+        );
+    }
+    region
+}
+
+/// Number of subscription pages, from the `MAX_CHANNELS` environment variable or the
+/// default, checked against the size of the RESERVED region in memory.x.
+fn max_channels() -> u64 {
+    println!("cargo:rerun-if-env-changed=MAX_CHANNELS");
+
+    let (_, length) = memory_region("RESERVED");
+    let capacity = length / PAGE_SIZE - non_subscription_pages();
+
+    let max_channels = match env::var("MAX_CHANNELS") {
+        Ok(v) => v.parse().expect("MAX_CHANNELS must be a decimal number"),
+        Err(_) => DEFAULT_MAX_CHANNELS,
+    };
+    assert!(
+        max_channels >= 1 && max_channels <= capacity,
+        "MAX_CHANNELS must be between 1 and {} for the RESERVED region in memory.x",
+        capacity
+    );
+    max_channels
+}
+
+/// Host UART baud rate used when `UART_BAUD` is not set.
+const DEFAULT_UART_BAUD: u32 = 115200;
+/// Standard rates the host tools and the MAX78000 UART both handle.
+const SUPPORTED_UART_BAUDS: [u32; 8] = [9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600];
+
+/// Host UART baud rate from the `UART_BAUD` environment variable or the default.
+fn uart_baud() -> u32 {
+    println!("cargo:rerun-if-env-changed=UART_BAUD");
+
+    let baud = match env::var("UART_BAUD") {
+        Ok(v) => v.parse().expect("UART_BAUD must be a decimal number"),
+        Err(_) => DEFAULT_UART_BAUD,
+    };
+    assert!(
+        SUPPORTED_UART_BAUDS.contains(&baud),
+        "UART_BAUD must be one of {:?}",
+        SUPPORTED_UART_BAUDS
+    );
+    baud
+}
+
+/// Commit the firmware is built from, reported by the Version command: the
+/// `FIRMWARE_COMMIT` environment variable if set, else `git rev-parse HEAD`, else
+/// "unknown", as for a build from a source tarball with no git checkout.
+fn firmware_commit() -> String {
+    println!("cargo:rerun-if-env-changed=FIRMWARE_COMMIT");
+    if let Ok(commit) = env::var("FIRMWARE_COMMIT") {
+        return commit;
+    }
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| String::from_utf8(out.stdout).ok())
+            .map(|out| out.trim().to_owned())
+    };
+    // Rebuild when HEAD moves: to another branch or commit, or by a commit on its branch
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            println!("cargo:rerun-if-changed={}/{}", git_dir, head_ref);
+        }
+    }
+    git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_owned())
+}
+
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
     // on the linker search path.
@@ -35,41 +393,51 @@ fn main() {
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
 
+    // Host test builds (`std`) link no firmware image, and use the test secrets and
+    // decoder id the host tests encode their vectors for.
+    let host_test = env::var_os("CARGO_FEATURE_STD").is_some();
+
     // Specify linker arguments.
 
     // `--nmagic` is required if memory section addresses are not aligned to 0x10000,
     // for example the FLASH and RAM sections in your `memory.x`.
     // See https://github.com/rust-embedded/cortex-m-quickstart/pull/95
-    println!("cargo:rustc-link-arg=--nmagic");
+    if !host_test {
+        println!("cargo:rustc-link-arg=--nmagic");
 
-    // Set the linker script to the one provided by cortex-m-rt.
-    println!("cargo:rustc-link-arg=-Tlink.x");
+        // Set the linker script to the one provided by cortex-m-rt.
+        println!("cargo:rustc-link-arg=-Tlink.x");
+    }
 
     // Use the absolute path for global.secrets since it's mounted at /global.secrets.
-    let secret_path = Path::new("../global.secrets");
+    let secret_path = Path::new(if host_test { TEST_SECRETS } else { "../global.secrets" });
+    println!("cargo:rerun-if-changed={}", TEST_SECRETS);
     println!("cargo:rerun-if-changed=/global.secrets");
+    println!("cargo:rerun-if-changed=../global.secrets");
+    for var in ["DECODER_DK", "HOST_KEY_PUB", "HOST_KEY_PUB_FALLBACKS", "CHANNEL_0_PASSWORD", "CHANNEL_0_NODE"] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
 
-    // Read the secrets file.
-    let secrets_contents =
-        fs::read_to_string(&secret_path).expect("Unable to read global.secrets file");
-
-    // Parse the JSON content.
-    let secrets_json: serde_json::Value =
-        serde_json::from_str(&secrets_contents).expect("Invalid JSON in global.secrets");
-
-    // Extract the fields you need.
-    let decoder_dk = secrets_json
-        .get("decoder_dk")
-        .and_then(|v| v.as_str())
-        .expect("Missing or invalid decoder_dk");
-    let host_key_pub = secrets_json
-        .get("host_key_pub")
-        .and_then(|v| v.as_str())
-        .expect("Missing or invalid host_key_pub");
+    // The secrets file takes precedence; CI builds without one may pass the same
+    // hex-encoded values through environment variables.
+    let secrets = if host_test {
+        read_secrets_file(secret_path)
+    } else if secret_path.exists() {
+        println!("cargo:warning=Using secrets from global.secrets");
+        read_secrets_file(secret_path)
+    } else {
+        println!("cargo:warning=global.secrets not found, using secrets from environment variables");
+        read_secrets_env()
+    };
+    let decoder_dk = secrets.decoder_dk.as_str();
+    let host_key_pub = secrets.host_key_pub.as_str();
 
     // Get and parse the DECODER_ID from the environment.
-    let decoder_id_str =
-        env::var("DECODER_ID").expect("DECODER_ID environment variable must be set");
+    let decoder_id_str = if host_test {
+        TEST_DECODER_ID.to_string()
+    } else {
+        env::var("DECODER_ID").expect("DECODER_ID environment variable must be set")
+    };
 
     // Remove a potential "0x" prefix.
     let decoder_id_str = decoder_id_str.trim_start_matches("0x");
@@ -82,31 +450,64 @@ fn main() {
     // HKDF Derivation
     // Use decoder_dk as the master key and the little-endian decoder id as the context/info.
     let decoder_dk_bytes = decode(decoder_dk).expect("Invalid hex in decoder_dk");
+    assert_eq!(decoder_dk_bytes.len(), 32, "decoder_dk must be exactly 32 bytes");
     let hk = Hkdf::<Sha512>::new(None, &decoder_dk_bytes);
-    let mut decoder_key = [0u8; 32];
+    let mut decoder_key = [0u8; KEY_LEN];
     hk.expand(&decoder_id_le, &mut decoder_key)
         .expect("HKDF expansion failed");
 
-    let host_key_pub_vec = decode(host_key_pub).expect("Invalid hex in host public key");
+    let host_key_pub_vec = parse_host_key(host_key_pub);
     let host_key_pub_bytes = host_key_pub_vec.as_slice();
+    // The primary key first, so it is the one tried first
+    assert!(
+        1 + secrets.host_key_pub_fallbacks.len() <= MAX_HOST_KEYS,
+        "at most {} host keys can be embedded, primary included",
+        MAX_HOST_KEYS
+    );
+    let host_keys_pub = std::iter::once("HOST_KEY_PUB".to_string())
+        .chain(secrets.host_key_pub_fallbacks.iter().map(|k| format!("&{:?}", parse_host_key(k))))
+        .collect::<Vec<_>>();
 
-    // Extract the channel 0 password bytes from the JSON.
-    let channel_0_password_hex = secrets_json["channels"]["0"]
-        .as_str()
-        .expect("Missing channel 0 password");
+    // Decode the channel 0 password bytes.
     let channel_0_password_vec =
-        decode(channel_0_password_hex).expect("Invalid hex for channel 0 password");
+        decode(&secrets.channel_0_password).expect("Invalid hex for channel 0 password");
     let channel_0_password: [u8; 16] = channel_0_password_vec
         .try_into()
         .expect("Channel 0 password must be exactly 16 bytes");
+    // Rotated deployments embed the key of a node below the root, derived here so the
+    // secrets keep holding the root the encoder uses
+    let channel_0_node_num = channel_0_node_num(secrets.channel_0_node);
+    let channel_0_node_password = node_key(channel_0_password, channel_0_node_num);
+    check_channel_0(channel_0_password, channel_0_node_num, channel_0_node_password);
+
+    // Subscription capacity: one flash page per channel in the RESERVED region, and
+    // one per page of the second subscription region.
+    let max_channels = max_channels();
+    let uart_baud = uart_baud();
+    let (firmware, reserved) = flash_regions();
+    let secondary = secondary_region(firmware, reserved);
+    let firmware_commit = firmware_commit();
 
     // Generate the Rust code for the secrets.
     let generated_code = format!(
-        "use crate::modules::channel_manager::{{ChannelSubscription, ChannelPasswords, ChannelPassword}};\n\
+        "use crate::modules::channel_manager::{{ChannelSubscription, ChannelPasswords, ChannelPassword, PASSWORD_TREE_NODES}};\n\
          use crate::modules::hostcom_manager::ChannelInfo;\n\n\
-         pub const DECODER_KEY: [u8; 32] = {:?};\n\
-         pub const HOST_KEY_PUB: &'static [u8] = &{:?};\n\
-         pub const DECODER_ID: u32 = 0x{:x};\n\n\
+         pub const KEY_LEN: usize = {};\n\
+         pub const DECODER_KEY: [u8; KEY_LEN] = {:?};\n\
+         pub const HOST_KEY_PUB: &[u8] = &{:?};\n\
+         pub const HOST_KEYS_PUB: [&[u8]; {}] = [{}];\n\
+         pub const DECODER_ID: u32 = 0x{:x};\n\
+         pub const PRIMARY_CHANNELS: usize = {};\n\
+         pub const SECONDARY_CHANNELS: usize = {};\n\
+         pub const MAX_CHANNELS: usize = PRIMARY_CHANNELS + SECONDARY_CHANNELS;\n\
+         pub const UART_BAUD: u32 = {};\n\
+         pub const FIRMWARE_FLASH_START: u32 = {:#x};\n\
+         pub const FIRMWARE_FLASH_END: u32 = {:#x};\n\
+         pub const RESERVED_FLASH_START: u32 = {:#x};\n\
+         pub const RESERVED_FLASH_END: u32 = {:#x};\n\
+         pub const SECONDARY_FLASH_START: u32 = {:#x};\n\
+         pub const SECONDARY_FLASH_END: u32 = {:#x};\n\
+         pub const FIRMWARE_COMMIT: &str = {:?};\n\n\
          pub const CHANNEL_0_SUBSCRIPTION: ChannelSubscription = ChannelSubscription {{
              info: ChannelInfo {{
                  channel_id: 0,
@@ -115,25 +516,40 @@ fn main() {
              }},
              passwords: ChannelPasswords {{
                  contents: {{
-                     let mut contents: [ChannelPassword; 128] = [ChannelPassword {{
+                     let mut contents: [ChannelPassword; PASSWORD_TREE_NODES] = [ChannelPassword {{
                          node_trunc: 0,
                          node_ext: 0,
                          password: [0; 16],
-                     }}; 128];
+                     }}; PASSWORD_TREE_NODES];
                      
                      contents[0] = ChannelPassword {{
-                         node_trunc: 0,
-                         node_ext: 2,
+                         node_trunc: {},
+                         node_ext: {},
                          password: {:?},
                      }};
                      contents
                  }}
              }}
          }};\n",
+        KEY_LEN,
         decoder_key,
         host_key_pub_bytes,
+        host_keys_pub.len(),
+        host_keys_pub.join(", "),
         decoder_id_val,
-        channel_0_password
+        max_channels,
+        (secondary.1 - secondary.0) / PAGE_SIZE,
+        uart_baud,
+        firmware.0,
+        firmware.1,
+        reserved.0,
+        reserved.1,
+        secondary.0,
+        secondary.1,
+        firmware_commit,
+        secrets.channel_0_node.0,
+        secrets.channel_0_node.1,
+        channel_0_node_password
     );
 
     // Write the generated code to $OUT_DIR/secrets.rs.
@@ -0,0 +1,71 @@
+//! Decode throughput for one channel, with the three costs of a frame timed apart:
+//! the signature check, the subscription read from flash and the key derivation.
+//!
+//! `cargo bench` runs the full sequence; `cargo test` runs a short one, so every test
+//! run prints a baseline. Times are the host's; the MAX78000 runs at 100 MHz, so the
+//! cycle column is host time at that clock, a lower bound for the device.
+use decoder::modules::channel_manager::{find_subscription_page, ChannelFrame, ChannelSubscription};
+use decoder::modules::key_tree::extend_key;
+use decoder::modules::test_vectors::{leaf_node, node_key};
+use decoder_host_tests::{channel_root, frame, host_key, subscription, Decoder};
+use ed25519_dalek::{Signature, Verifier};
+use std::time::{Duration, Instant};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+/// Decoder clock the cycle estimates are given at.
+const DECODER_HZ: f64 = 100e6;
+
+fn report(name: &str, frames: usize, elapsed: Duration) {
+    let per_frame = elapsed.as_secs_f64() / frames as f64;
+    println!(
+        "{:<16} {:>10.0} frames/s {:>10.1} us/frame {:>12.0} cycles/frame",
+        name,
+        1.0 / per_frame,
+        per_frame * 1e6,
+        per_frame * DECODER_HZ
+    );
+}
+
+fn main() {
+    let frames = if std::env::args().any(|arg| arg == "--bench") { 2000 } else { 50 };
+    let encoded: Vec<Vec<u8>> = (0..frames as u64).map(|i| frame(CHANNEL, T + i)).collect();
+
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+
+    println!("decode benchmark, {} frames on channel {}", frames, CHANNEL);
+
+    let start = Instant::now();
+    for body in &encoded {
+        decoder.decode(body).unwrap();
+    }
+    report("decode", frames, start.elapsed());
+
+    // Signature check alone, over the signed prefix as decode_frame checks it
+    let verifying_key = host_key().verifying_key();
+    let start = Instant::now();
+    for body in &encoded {
+        let parsed = ChannelFrame::from_le_bytes(body).unwrap();
+        let signature = Signature::from_bytes(&parsed.signature);
+        verifying_key.verify(&body[..body.len() - 64], &signature).unwrap();
+    }
+    report("verify", frames, start.elapsed());
+
+    // Subscription read, as a frame on a newly switched-to channel pays it
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap();
+    let mut stored: ChannelSubscription = bytemuck::Zeroable::zeroed();
+    let start = Instant::now();
+    for _ in 0..frames {
+        decoder.flash.read_data_verified_into(addr, &mut stored).unwrap();
+    }
+    report("flash read", frames, start.elapsed());
+
+    // Cold key derivation, all 64 levels from the channel root, as no cache helps
+    let root = channel_root(CHANNEL);
+    let start = Instant::now();
+    for i in 0..frames as u64 {
+        std::hint::black_box(extend_key(&node_key(&root, leaf_node(T + i))));
+    }
+    report("derive (cold)", frames, start.elapsed());
+}
@@ -0,0 +1,45 @@
+//! Body of the `framing` fuzz target (`decoder/fuzz`), also run over its seed corpus and
+//! random streams by `tests/framing.rs`.
+use crate::MockUart;
+use decoder::modules::hostcom_manager::{discard_body, read_body, read_header, MessageBody, MessageHeader, MAX_BODY_LEN, MSG_MAGIC};
+
+/// Header the stream is resynchronised on after the fuzzed bytes: List, no body.
+const SENTINEL: [u8; 4] = [MSG_MAGIC, b'L', 0, 0];
+
+/// Feed `data` to the header and body parsers the way the command loop does, then check
+/// the stream is still usable: after at most one longest body (a discarded 64 KiB one)
+/// of idle line, the next header is read exactly, with nothing left over.
+///
+/// Panics on any parser panic or if the sentinel header is not the next one parsed.
+pub fn check_stream(data: &[u8]) {
+    let uart = MockUart::default();
+    uart.queue(data);
+    // A header cut off by the end of `data` is completed by the idle bytes, and the body
+    // it announces, however long, is consumed by them; past that they are noise
+    uart.queue(&[0; u16::MAX as usize + SENTINEL.len()]);
+    uart.queue(&SENTINEL);
+
+    let mut console = uart.clone();
+    let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: 0 };
+    let sentinel_total = SENTINEL.len();
+    loop {
+        let hdr = read_header(&mut console);
+        assert_eq!(hdr.magic, MSG_MAGIC);
+        if uart.pending() == 0 {
+            assert_eq!(header_bytes(&hdr), SENTINEL, "stream did not resynchronise");
+            return;
+        }
+        assert!(uart.pending() >= sentinel_total, "a header swallowed the sentinel");
+        if hdr.length as usize > MAX_BODY_LEN {
+            discard_body(&mut console, hdr.length);
+        } else {
+            read_body(&mut console, hdr.length, &mut body);
+            assert_eq!({ body.length }, { hdr.length });
+        }
+    }
+}
+
+fn header_bytes(hdr: &MessageHeader) -> [u8; 4] {
+    let [lo, hi] = { hdr.length }.to_le_bytes();
+    [hdr.magic, hdr.opcode, lo, hi]
+}
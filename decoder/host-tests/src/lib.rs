@@ -0,0 +1,267 @@
+//! Fixtures for the decoder's host tests: a scripted UART, a decoder booted over RAM
+//! flash, and the vectors generated by `ectf25_design.gen_test_vectors`.
+//!
+//! The decoder library is built with its `std` feature, which compiles in the secrets
+//! of `test.secrets` and decoder id 0xdeadbeef.
+pub mod framing;
+
+use decoder::modules::channel_manager::{
+    apply_subscription_bundle, check_subscription_valid_and_store, decode_frame, finish_subscription_bundle,
+    initialize_active_channels, ActiveChannelsList, ChannelFrame, DecodeContext, SubscriptionError,
+    ACTIVE_CHANNELS_LEN, FRAME_CONTENT_LEN,
+};
+use decoder::modules::crc::Crc32;
+use decoder::modules::emergency_manager::read_emergency_state;
+use decoder::modules::flash_manager::{FlashManager, Flc};
+use decoder::modules::hostcom_manager::{HostConsole, MessageBody, MessageHeader, MsgType, UartHalOps, MAX_BODY_LEN};
+#[cfg(feature = "rekey")]
+use decoder::modules::key_manager::DeviceKey;
+use decoder::modules::state_manager::StateManager;
+#[cfg(feature = "subscribe-checksum")]
+use decoder::modules::test_vectors::add_subscription_checksum;
+use decoder::modules::test_vectors::{encode_frame, encode_subscription};
+#[cfg(feature = "rtc-time")]
+use decoder::modules::time_source::MockClock;
+use decoder::{DECODER_ID, DECODER_KEY};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::SigningKey;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// UART whose received bytes are queued by the test and whose sent bytes are kept.
+/// Clones share both queues, so a test can feed a console that owns one.
+#[derive(Clone, Default)]
+pub struct MockUart {
+    rx: Rc<RefCell<VecDeque<u8>>>,
+    tx: Rc<RefCell<Vec<u8>>>,
+}
+
+impl MockUart {
+    /// Make `bytes` available to the next reads.
+    pub fn queue(&self, bytes: &[u8]) {
+        self.rx.borrow_mut().extend(bytes);
+    }
+
+    /// Bytes queued but not read yet.
+    pub fn pending(&self) -> usize {
+        self.rx.borrow().len()
+    }
+
+    /// Everything written so far, emptying the record.
+    pub fn take_sent(&self) -> Vec<u8> {
+        std::mem::take(&mut self.tx.borrow_mut())
+    }
+}
+
+impl UartHalOps for MockUart {
+    fn read_byte(&mut self) -> u8 {
+        self.try_read_byte().expect("decoder read past the queued input")
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.tx.borrow_mut().push(byte);
+    }
+
+    fn try_read_byte(&mut self) -> Option<u8> {
+        self.rx.borrow_mut().pop_front()
+    }
+}
+
+/// Decoder state as `main` holds it, over a flash the test keeps a handle to.
+pub struct Decoder {
+    pub flc: Flc,
+    pub flash: FlashManager,
+    pub channels: ActiveChannelsList,
+    pub context: DecodeContext,
+    pub console: HostConsole<MockUart>,
+    pub state: StateManager,
+    /// Subscription key, rotated by Rekey commands.
+    #[cfg(feature = "rekey")]
+    pub device_key: DeviceKey,
+    /// Wall clock for expiry, unset until the test sets it.
+    #[cfg(feature = "rtc-time")]
+    pub clock: MockClock,
+}
+
+impl Decoder {
+    /// A decoder booted on erased flash.
+    pub fn new() -> Self {
+        Self::boot(Flc::new())
+    }
+
+    /// A decoder booted on `flc`, as after a reset with whatever it holds: an
+    /// interrupted bundle finished, channels from the subscription pages, replay
+    /// counters from the state log, emergency-only mode from its page, boot counted.
+    pub fn boot(flc: Flc) -> Self {
+        let mut flash = FlashManager::new(flc.clone(), Crc32::new());
+        #[cfg(feature = "rekey")]
+        let device_key = DeviceKey::load(&mut flash);
+        let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: 0 };
+        // As in main, a bundle that cannot be finished does not stop the boot
+        let _ = finish_subscription_bundle(
+            &mut flash,
+            &mut body,
+            #[cfg(feature = "rekey")]
+            &device_key,
+        );
+        let mut channels: ActiveChannelsList = [None; ACTIVE_CHANNELS_LEN];
+        let mut console = HostConsole::new(MockUart::default());
+        initialize_active_channels(&mut channels, &mut flash, &mut console);
+        let mut state = StateManager::load(&mut flash, &mut channels);
+        let mut context = DecodeContext::new();
+        context.set_emergency_only(read_emergency_state(&mut flash).is_enabled());
+        // As in main, a boot that cannot be recorded still runs
+        let _ = state.record_boot(&mut flash, &channels);
+        Decoder {
+            flc,
+            flash,
+            channels,
+            context,
+            console,
+            state,
+            #[cfg(feature = "rekey")]
+            device_key,
+            #[cfg(feature = "rtc-time")]
+            clock: MockClock::new(),
+        }
+    }
+
+    /// Power cycle: boot a new decoder on this one's flash.
+    pub fn reboot(self) -> Self {
+        Self::boot(self.flc)
+    }
+
+    /// Store a subscription, as the Subscribe command does once it is received, with
+    /// the checksum the host appends when the decoder expects one.
+    pub fn subscribe(&mut self, subscription: &[u8]) -> Result<(), SubscriptionError> {
+        #[cfg(feature = "subscribe-checksum")]
+        let subscription = &add_subscription_checksum(subscription)[..];
+        self.subscribe_body(subscription)
+    }
+
+    /// Store a Subscribe body exactly as given.
+    pub fn subscribe_body(&mut self, subscription: &[u8]) -> Result<(), SubscriptionError> {
+        let hdr = MessageHeader::new(MsgType::Subscribe, subscription.len() as u16);
+        let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: subscription.len() as u16 };
+        body.data[..subscription.len()].copy_from_slice(subscription);
+        let result = check_subscription_valid_and_store(
+            &hdr,
+            &body,
+            &mut self.flash,
+            &mut self.channels,
+            #[cfg(feature = "rekey")]
+            &self.device_key,
+        );
+        self.context.invalidate();
+        result
+    }
+
+    /// Apply a SubscribeBundle body as the command does, returning the number of
+    /// subscriptions it holds.
+    pub fn subscribe_bundle(&mut self, body: &[u8]) -> Result<u32, SubscriptionError> {
+        let result = apply_subscription_bundle(
+            &mut self.flash,
+            body,
+            &mut self.channels,
+            #[cfg(feature = "rekey")]
+            &self.device_key,
+        );
+        self.context.invalidate();
+        result
+    }
+
+    /// Decode an encoded frame as the Decode command does, returning the content only
+    /// once its timestamp is committed to the state log.
+    pub fn decode(&mut self, frame: &[u8]) -> Result<[u8; FRAME_CONTENT_LEN], SubscriptionError> {
+        let frame = ChannelFrame::from_le_bytes(frame).ok_or(SubscriptionError::InvalidLength)?;
+        let content = decode_frame(
+            &mut self.flash,
+            frame,
+            &mut self.channels,
+            &mut self.context,
+            #[cfg(feature = "rtc-time")]
+            &self.clock,
+        )?;
+        self.state.save(&mut self.flash, &self.channels)?;
+        Ok(content)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `vectors/decode.json`, from the Python encoder and gen_subscription.
+pub fn decode_vectors() -> Value {
+    serde_json::from_str(include_str!("../vectors/decode.json")).expect("invalid decode.json")
+}
+
+/// Decode a hex string field of a vector.
+pub fn hex_field(value: &Value, field: &str) -> Vec<u8> {
+    hex::decode(value[field].as_str().unwrap_or_else(|| panic!("vector has no {}", field))).expect("invalid hex in vector")
+}
+
+/// `test.secrets`, the deployment the `std` build embeds.
+pub fn test_secrets() -> Value {
+    serde_json::from_str(include_str!("../test.secrets")).expect("invalid test.secrets")
+}
+
+/// Root key of `channel` in `test.secrets`.
+pub fn channel_root(channel: u32) -> [u8; 16] {
+    hex_field(&test_secrets()["channels"], &channel.to_string()).try_into().expect("channel roots are 16 bytes")
+}
+
+/// The host signing key of `test.secrets`, which the `std` build trusts.
+pub fn host_key() -> SigningKey {
+    SigningKey::from_pkcs8_der(&hex_field(&test_secrets(), "host_key_priv")).expect("invalid host_key_priv")
+}
+
+/// Subscribe body for this decoder to `channel` from `start` to `end`.
+pub fn subscription(channel: u32, start: u64, end: u64) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &channel_root(channel), channel, start, end, [0x5A; 12])
+}
+
+/// Content of the generated frame at `timestamp`.
+pub fn frame_content(timestamp: u64) -> [u8; FRAME_CONTENT_LEN] {
+    let mut content = [0u8; FRAME_CONTENT_LEN];
+    for (i, chunk) in content.chunks_mut(8).enumerate() {
+        chunk.copy_from_slice(&(timestamp ^ i as u64).to_le_bytes());
+    }
+    content
+}
+
+/// Decode body of `channel` at `timestamp`, with `frame_content(timestamp)`.
+pub fn frame(channel: u32, timestamp: u64) -> Vec<u8> {
+    let mut nonce = [0xA5; 12];
+    nonce[..8].copy_from_slice(&timestamp.to_le_bytes());
+    encode_frame(&host_key(), &channel_root(channel), channel, timestamp, &frame_content(timestamp), nonce)
+}
+
+/// Small seeded PRNG (xorshift64*) for the randomized tests, so failures reproduce.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0..n`, for `n` well below 2^64.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        buf.iter_mut().for_each(|b| *b = self.next_u64() as u8);
+    }
+}
@@ -0,0 +1,50 @@
+//! A body is read in `CHUNK_SIZE` chunks with one ACK after each, the last short chunk
+//! included, whether it is kept or discarded. The host waits on every one of them, so
+//! the count must be exactly one per chunk.
+use decoder::modules::hostcom_manager::{
+    discard_body, read_body, HostConsole, MessageBody, MsgType, CHUNK_SIZE, MAX_BODY_LEN, MSG_MAGIC,
+};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+const LENGTHS: [usize; 6] = [1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, 3 * CHUNK_SIZE + 7, MAX_BODY_LEN];
+
+fn body_bytes(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i * 7) as u8).collect()
+}
+
+fn acks(length: usize) -> Vec<u8> {
+    ACK.repeat(length.div_ceil(CHUNK_SIZE))
+}
+
+#[test]
+fn read_body_acks_every_chunk() {
+    for length in LENGTHS {
+        let mut uart = MockUart::default();
+        uart.queue(&body_bytes(length));
+        let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: 0 };
+        read_body(&mut uart, length as u16, &mut body);
+
+        assert_eq!(body.data[..length], body_bytes(length)[..]);
+        assert_eq!(uart.take_sent(), acks(length), "{} byte body", length);
+        assert_eq!(uart.pending(), 0);
+    }
+}
+
+#[test]
+fn console_and_discard_ack_the_same_chunks() {
+    for length in LENGTHS {
+        let uart = MockUart::default();
+        uart.queue(&body_bytes(length));
+        let mut console = HostConsole::new(uart.clone());
+        let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: 0 };
+        assert_eq!(console.read_body(length as u16, &mut body), Ok(length as u16));
+        assert_eq!(uart.take_sent(), acks(length), "{} byte body", length);
+
+        let mut uart = MockUart::default();
+        uart.queue(&body_bytes(length));
+        discard_body(&mut uart, length as u16);
+        assert_eq!(uart.take_sent(), acks(length), "{} byte discarded body", length);
+        assert_eq!(uart.pending(), 0);
+    }
+}
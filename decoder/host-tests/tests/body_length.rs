@@ -0,0 +1,20 @@
+//! read_body returns how many body bytes it read and sets `body.length` to the same
+//! count, whole chunks or not.
+use bytemuck::Zeroable;
+use decoder::modules::hostcom_manager::{read_body, MessageBody, MAX_BODY_LEN};
+use decoder_host_tests::MockUart;
+
+#[test]
+fn count_read_is_returned_and_stored() {
+    for length in [0, 1, 255, 256, 257, 1000, MAX_BODY_LEN] {
+        let mut uart = MockUart::default();
+        let sent: Vec<u8> = (0..length).map(|i| i as u8).collect();
+        uart.queue(&sent);
+
+        let mut body = MessageBody::zeroed();
+        assert_eq!(read_body(&mut uart, length as u16, &mut body), length as u16);
+        assert_eq!({ body.length } as usize, length);
+        assert_eq!(body.data[..length], sent[..]);
+        assert_eq!(uart.pending(), 0);
+    }
+}
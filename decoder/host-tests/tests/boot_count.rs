@@ -0,0 +1,35 @@
+//! Every boot is counted in the state log. The count survives power cycles, and a boot
+//! whose record is cut off part way leaves the previous count, never a lower one.
+use decoder::modules::state_manager::ChannelStateRecord;
+use decoder_host_tests::Decoder;
+
+/// 128-bit writes of one state record: magic, record, CRC.
+const RECORD_WRITES: u32 = (4 + size_of::<ChannelStateRecord>() + 4).div_ceil(16) as u32;
+
+#[test]
+fn boot_count_increments_across_power_cycles() {
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.state.boot_count(), 1);
+    for boots in 2..=40 {
+        decoder = decoder.reboot();
+        assert_eq!(decoder.state.boot_count(), boots);
+    }
+}
+
+#[test]
+fn torn_boot_record_never_lowers_the_count() {
+    let mut decoder = Decoder::new();
+    decoder = decoder.reboot();
+    let mut count = decoder.state.boot_count();
+    for writes in 0..RECORD_WRITES {
+        decoder.flc.fail_after_writes(writes);
+        decoder = decoder.reboot();
+        decoder.flc.clear_failures();
+        // The torn boot counts or not, but the count is never behind the last one seen
+        assert!(decoder.state.boot_count() >= count);
+
+        decoder = decoder.reboot();
+        assert!(decoder.state.boot_count() > count, "cut after {} writes", writes);
+        count = decoder.state.boot_count();
+    }
+}
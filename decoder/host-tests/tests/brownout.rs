@@ -0,0 +1,53 @@
+//! With the `brownout` feature FlashManager checks the supply before every erase and
+//! write. While it reads low, a Subscribe fails with LowVoltage before touching flash,
+//! and the subscription already stored keeps decoding; once it recovers, writes resume.
+#![cfg(feature = "brownout")]
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::crc::Crc32;
+use decoder::modules::flash_manager::{FlashManager, FlashManagerError};
+use decoder::modules::supply_monitor::{MockSupplyMonitor, VDDIO_MIN_MV};
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn monitored_decoder() -> (Decoder, MockSupplyMonitor) {
+    let mut decoder = Decoder::new();
+    let supply = MockSupplyMonitor::new();
+    decoder.flash = FlashManager::new(decoder.flc.clone(), Crc32::new()).with_supply_monitor(supply.clone());
+    (decoder, supply)
+}
+
+#[test]
+fn low_supply_refuses_subscribe() {
+    let (mut decoder, supply) = monitored_decoder();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+    let (writes, erases) = (decoder.flc.write_count(), decoder.flc.erase_count());
+
+    supply.set_mv(VDDIO_MIN_MV - 1);
+    let result = decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX));
+    assert!(matches!(result, Err(SubscriptionError::FlashManagerError(FlashManagerError::LowVoltage))));
+    assert_eq!(decoder.flc.write_count(), writes);
+    assert_eq!(decoder.flc.erase_count(), erases);
+
+    // The old window is still the one stored
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 1)), Err(SubscriptionError::SubscriptionExpired)));
+
+    supply.set_mv(VDDIO_MIN_MV);
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+}
+
+#[test]
+fn low_supply_refuses_erase_and_raw_write() {
+    let (mut decoder, supply) = monitored_decoder();
+    let page = FlashManager::scratch_page_addr();
+    let (writes, erases) = (decoder.flc.write_count(), decoder.flc.erase_count());
+
+    supply.set_mv(0);
+    assert!(matches!(decoder.flash.wipe_data(page), Err(FlashManagerError::LowVoltage)));
+    assert!(matches!(decoder.flash.write_raw(page, &[0; 16]), Err(FlashManagerError::LowVoltage)));
+    assert_eq!(decoder.flc.write_count(), writes);
+    assert_eq!(decoder.flc.erase_count(), erases);
+}
@@ -0,0 +1,170 @@
+//! A signed subscription bundle replaces every stored subscription at once. A failure
+//! before its journal is committed leaves them all as they were; one after is finished
+//! at the next boot. A full decoder takes a bundle as large as its set.
+use decoder::modules::channel_manager::{
+    find_subscription_page, free_subscription_pages, ChannelSubscription, SubscriptionError,
+};
+use decoder::modules::flash_manager::FlashManagerError;
+use decoder::modules::test_vectors::{encode_frame, encode_subscription, encode_subscription_bundle};
+use decoder::{DECODER_ID, DECODER_KEY, MAX_CHANNELS};
+use decoder_host_tests::{frame, frame_content, host_key, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+/// A channel outside test.secrets, stored before the bundle and not part of it.
+const DROPPED: u32 = 7;
+/// 128-bit writes of one subscription record.
+const PAGE_WRITES: u32 = (4 + size_of::<ChannelSubscription>() + 4).div_ceil(16) as u32;
+
+/// 128-bit writes journaling `body` when no kept channel is paused: the body, then the
+/// header.
+fn journal_writes(body: &[u8]) -> u32 {
+    body.len().div_ceil(16) as u32 + 1
+}
+
+/// Root key of a channel outside test.secrets; the decoder only needs its passwords.
+fn root(channel: u32) -> [u8; 16] {
+    [channel as u8; 16]
+}
+
+fn outside_subscription(channel: u32) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root(channel), channel, 0, u64::MAX, [0x5A; 12])
+}
+
+fn outside_frame(channel: u32, timestamp: u64) -> Vec<u8> {
+    encode_frame(&host_key(), &root(channel), channel, timestamp, &frame_content(timestamp), [0xA5; 12])
+}
+
+fn active_ids(decoder: &Decoder) -> Vec<u32> {
+    decoder.channels.iter().flatten().map(|c| c.channel_id).collect()
+}
+
+fn stored(decoder: &mut Decoder, channel: u32) -> bool {
+    find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).is_some()
+}
+
+/// A decoder holding channel 1, with a decoded frame, and channel `DROPPED`.
+fn subscribed_decoder() -> Decoder {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&outside_subscription(DROPPED)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+    decoder
+}
+
+fn bundle(channels: &[u32]) -> Vec<u8> {
+    let subscriptions: Vec<_> = channels.iter().map(|&c| subscription(c, 0, u64::MAX)).collect();
+    encode_subscription_bundle(&host_key(), DECODER_ID, &subscriptions)
+}
+
+#[test]
+fn bundle_replaces_every_subscription() {
+    let mut decoder = subscribed_decoder();
+    assert_eq!(decoder.subscribe_bundle(&bundle(&[1, 2, 3])).unwrap(), 3);
+
+    assert_eq!(active_ids(&decoder), [0, 1, 2, 3]);
+    assert!(!stored(&mut decoder, DROPPED));
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32 - 3);
+    // Channel 1 was kept, and with it its replay state
+    assert!(matches!(decoder.decode(&frame(1, T)), Err(SubscriptionError::InvalidTimestamp)));
+    for channel in [1, 2, 3] {
+        decoder.decode(&frame(channel, T + 1)).unwrap();
+    }
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(active_ids(&decoder), [0, 1, 2, 3]);
+    assert!(matches!(decoder.decode(&outside_frame(DROPPED, T + 2)), Err(SubscriptionError::NoSubscription)));
+    decoder.decode(&frame(3, T + 2)).unwrap();
+}
+
+#[test]
+fn torn_journal_keeps_the_old_set() {
+    let mut decoder = subscribed_decoder();
+    let body = bundle(&[1, 2, 3]);
+    // The whole body journaled, the header that commits it lost
+    decoder.flc.fail_after_writes(journal_writes(&body) - 1);
+    assert!(matches!(
+        decoder.subscribe_bundle(&body),
+        Err(SubscriptionError::FlashManagerError(FlashManagerError::FlashError(_)))
+    ));
+    decoder.flc.clear_failures();
+
+    assert_eq!(active_ids(&decoder), [0, 1, DROPPED]);
+    assert!(!stored(&mut decoder, 2));
+    decoder.decode(&frame(1, T + 1)).unwrap();
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(active_ids(&decoder), [0, 1, DROPPED]);
+    assert!(stored(&mut decoder, DROPPED));
+    assert!(matches!(decoder.decode(&frame(2, T)), Err(SubscriptionError::NoSubscription)));
+    // Resent, the bundle applies
+    assert_eq!(decoder.subscribe_bundle(&body).unwrap(), 3);
+    decoder.decode(&frame(2, T)).unwrap();
+}
+
+#[test]
+fn failed_third_write_is_finished_at_boot() {
+    let mut decoder = subscribed_decoder();
+    let body = bundle(&[1, 2, 3]);
+    // Journal committed and two subscriptions written, the third torn after its first chunk
+    decoder.flc.fail_after_writes(journal_writes(&body) + 2 * PAGE_WRITES + 1);
+    assert!(matches!(
+        decoder.subscribe_bundle(&body),
+        Err(SubscriptionError::FlashManagerError(FlashManagerError::FlashError(_)))
+    ));
+    decoder.flc.clear_failures();
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(active_ids(&decoder), [0, 1, 2, 3]);
+    assert!(!stored(&mut decoder, DROPPED));
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32 - 3);
+    // Channel 1 kept its replay state through the bundle and the reboot
+    assert!(matches!(decoder.decode(&frame(1, T)), Err(SubscriptionError::InvalidTimestamp)));
+    for channel in [1, 2, 3] {
+        decoder.decode(&frame(channel, T + 1)).unwrap();
+    }
+
+    // The journal was cleared once finished
+    let mut decoder = decoder.reboot();
+    assert_eq!(active_ids(&decoder), [0, 1, 2, 3]);
+    decoder.decode(&frame(3, T + 2)).unwrap();
+}
+
+#[test]
+fn full_decoder_takes_a_bundle_of_its_size() {
+    let mut decoder = Decoder::new();
+    for channel in (1..=MAX_CHANNELS as u32).map(|i| i * 10) {
+        decoder.subscribe(&outside_subscription(channel)).unwrap();
+    }
+    assert_eq!(free_subscription_pages(&mut decoder.flash), 0);
+
+    let channels: Vec<u32> = (1..=MAX_CHANNELS as u32).map(|i| i * 10 + 1).collect();
+    let subscriptions: Vec<_> = channels.iter().map(|&c| outside_subscription(c)).collect();
+    let body = encode_subscription_bundle(&host_key(), DECODER_ID, &subscriptions);
+    assert_eq!(decoder.subscribe_bundle(&body).unwrap(), MAX_CHANNELS as u32);
+
+    assert_eq!(active_ids(&decoder)[1..], channels[..]);
+    assert!(!stored(&mut decoder, 10));
+    let mut decoder = decoder.reboot();
+    for &channel in &channels {
+        assert_eq!(decoder.decode(&outside_frame(channel, T)).unwrap(), frame_content(T), "channel {}", channel);
+    }
+}
+
+#[test]
+fn bundle_is_refused_whole() {
+    let mut decoder = subscribed_decoder();
+
+    let mut forged = bundle(&[1, 2]);
+    let last = forged.len() - 1;
+    forged[last] ^= 1;
+    assert!(matches!(decoder.subscribe_bundle(&forged), Err(SubscriptionError::InvalidSignature)));
+    assert!(matches!(decoder.subscribe_bundle(&bundle(&[2, 2])), Err(SubscriptionError::InvalidChannelId)));
+
+    // One subscription for another decoder spoils the bundle
+    let foreign = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID ^ 1, &[2; 16], 2, 0, u64::MAX, [0x5A; 12]);
+    let body = encode_subscription_bundle(&host_key(), DECODER_ID, &[subscription(3, 0, u64::MAX), foreign]);
+    assert!(matches!(decoder.subscribe_bundle(&body), Err(SubscriptionError::InvalidDecoderId)));
+
+    assert_eq!(active_ids(&decoder), [0, 1, DROPPED]);
+    assert!(!stored(&mut decoder, 3));
+}
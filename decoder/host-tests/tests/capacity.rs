@@ -0,0 +1,42 @@
+//! With every subscription page taken, every stored channel still has an active slot
+//! and decodes, before and after a reboot.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::test_vectors::{encode_frame, encode_subscription};
+use decoder::{DECODER_ID, DECODER_KEY, MAX_CHANNELS};
+use decoder_host_tests::{frame_content, host_key, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// Root key of a channel outside test.secrets; the decoder only needs its passwords.
+fn root(channel: u32) -> [u8; 16] {
+    [channel as u8; 16]
+}
+
+fn subscription(channel: u32) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root(channel), channel, 0, u64::MAX, [0x5A; 12])
+}
+
+fn decode(decoder: &mut Decoder, channel: u32, timestamp: u64) {
+    let frame = encode_frame(&host_key(), &root(channel), channel, timestamp, &frame_content(timestamp), [0xA5; 12]);
+    assert_eq!(decoder.decode(&frame).unwrap(), frame_content(timestamp), "channel {}", channel);
+}
+
+#[test]
+fn every_stored_channel_decodes_with_all_pages_taken() {
+    let channels: Vec<u32> = (1..=MAX_CHANNELS as u32).map(|i| i * 10).collect();
+    let mut decoder = Decoder::new();
+    for &channel in &channels {
+        decoder.subscribe(&subscription(channel)).unwrap();
+    }
+    assert!(matches!(decoder.subscribe(&subscription(999)), Err(SubscriptionError::NoPageFound)));
+
+    for &channel in &channels {
+        decode(&mut decoder, channel, T);
+    }
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(decoder.channels.iter().flatten().count(), MAX_CHANNELS + 1);
+    for &channel in &channels {
+        decode(&mut decoder, channel, T + 1);
+    }
+}
@@ -0,0 +1,25 @@
+//! Consecutive channel 0 frames restart the derivation from the path of the last one,
+//! and get the same content as a cold derivation whatever prefix the two share.
+use decoder_host_tests::{frame, frame_content, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn warm_path_matches_a_cold_derivation() {
+    let mut decoder = Decoder::new();
+    // Sharing 63 levels, a handful, and down to the top of the tree
+    for timestamp in [T, T + 1, T + 2, T + 1000, T + (1 << 40), (1 << 63) - 1] {
+        let content = decoder.decode(&frame(0, timestamp)).unwrap();
+        assert_eq!(content, frame_content(timestamp), "timestamp {}", timestamp);
+        assert_eq!(Decoder::new().decode(&frame(0, timestamp)).unwrap(), content, "timestamp {}", timestamp);
+    }
+}
+
+#[test]
+fn refused_frame_leaves_the_path_usable() {
+    let mut decoder = Decoder::new();
+    decoder.decode(&frame(0, T)).unwrap();
+    // Outside the channel 0 node, so nothing is derived
+    assert!(decoder.decode(&frame(0, 1 << 63)).is_err());
+    assert_eq!(decoder.decode(&frame(0, T + 1)).unwrap(), frame_content(T + 1));
+}
@@ -0,0 +1,31 @@
+//! The channel 0 password is embedded at the node `channel_0_node` of test.secrets
+//! names, below the root: frames under that node decode, and no others.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::test_vectors::node_key;
+use decoder::CHANNEL_0_SUBSCRIPTION;
+use decoder_host_tests::{channel_root, frame, test_secrets, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn embedded_password_is_the_configured_node() {
+    let node = &test_secrets()["channel_0_node"];
+    let password = CHANNEL_0_SUBSCRIPTION.passwords.contents[0];
+    assert_eq!({ password.node_trunc }, node["node_trunc"].as_u64().unwrap());
+    assert_eq!(password.node_ext as u64, node["node_ext"].as_u64().unwrap());
+    // The left child of the root, holding every timestamp below 2^63
+    assert_eq!(password.node_num(), 2);
+    assert_eq!(password.password, node_key(&channel_root(0), 2));
+}
+
+#[test]
+fn frames_under_the_node_decode_and_others_do_not() {
+    let mut decoder = Decoder::new();
+    decoder.decode(&frame(0, T)).unwrap();
+    decoder.decode(&frame(0, (1 << 63) - 1)).unwrap();
+
+    let mut decoder = Decoder::new();
+    assert!(matches!(decoder.decode(&frame(0, 1 << 63)), Err(SubscriptionError::PasswordNotFound)));
+    // A refused frame leaves the channel's replay state alone
+    decoder.decode(&frame(0, T)).unwrap();
+}
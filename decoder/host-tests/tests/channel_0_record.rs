@@ -0,0 +1,33 @@
+//! The built-in channel 0 subscription is checked for the shape emergency decoding
+//! relies on.
+use decoder::modules::channel_manager::{check_channel_0_subscription, ChannelPassword, Channel0Error};
+use decoder::CHANNEL_0_SUBSCRIPTION;
+
+#[test]
+fn generated_record_is_well_formed() {
+    assert_eq!(check_channel_0_subscription(&CHANNEL_0_SUBSCRIPTION), Ok(()));
+}
+
+#[test]
+fn malformed_records_are_refused() {
+    let mut wrong_channel = CHANNEL_0_SUBSCRIPTION;
+    wrong_channel.info.channel_id = 3;
+    assert_eq!(check_channel_0_subscription(&wrong_channel), Err(Channel0Error::WrongChannel(3)));
+
+    let mut bad_ext = CHANNEL_0_SUBSCRIPTION;
+    bad_ext.passwords.contents[0].node_ext = 3;
+    assert_eq!(check_channel_0_subscription(&bad_ext), Err(Channel0Error::InvalidNodeExt(3)));
+
+    let mut empty = CHANNEL_0_SUBSCRIPTION;
+    empty.passwords.contents[0].node_ext = 0;
+    assert_eq!(check_channel_0_subscription(&empty), Err(Channel0Error::PopulatedNodes(0)));
+
+    let mut extra = CHANNEL_0_SUBSCRIPTION;
+    extra.passwords.contents[1] = ChannelPassword { node_trunc: 1, node_ext: 1, password: [7; 16] };
+    assert_eq!(check_channel_0_subscription(&extra), Err(Channel0Error::PopulatedNodes(2)));
+
+    // The one password has to be in the entry decoding reads
+    let mut moved = CHANNEL_0_SUBSCRIPTION;
+    moved.passwords.contents.swap(0, 1);
+    assert_eq!(check_channel_0_subscription(&moved), Err(Channel0Error::PopulatedNodes(1)));
+}
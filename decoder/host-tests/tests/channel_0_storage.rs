@@ -0,0 +1,34 @@
+//! Channel 0 is the built-in emergency channel and nothing else: the storage layer
+//! refuses to store it, and a page claiming it is never taken for a subscription.
+use decoder::modules::channel_manager::{
+    find_subscription_page, read_subscription, save_subscription, SubscriptionError,
+};
+use decoder::modules::constants::{subscription_page_addr, SUBSCRIPTION_MAGIC};
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn channel_0_is_refused_everywhere() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 1).unwrap();
+    let mut record = read_subscription(&mut decoder.flash, addr).unwrap();
+    record.info.channel_id = 0;
+
+    // Below the Subscribe checks, storage refuses it too
+    let result = save_subscription(&mut decoder.flash, record, &mut decoder.channels);
+    assert!(matches!(result, Err(SubscriptionError::InvalidChannelId)));
+
+    // A page claiming channel 0, however it got to flash, is not a subscription
+    let page = subscription_page_addr(1);
+    decoder.flash.write_data(page, SUBSCRIPTION_MAGIC, &record).unwrap();
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == 0).is_none());
+
+    let mut decoder = decoder.reboot();
+    let zeros = decoder.channels.iter().flatten().filter(|c| c.channel_id == 0).count();
+    assert_eq!(zeros, 1);
+    // Channel 0 frames decode with the built-in password, not the stored page's
+    decoder.decode(&frame(0, T)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+}
@@ -0,0 +1,87 @@
+//! With `chunk-crc` every body chunk ends in its CRC-16. A chunk that arrives corrupt is
+//! NACKed and read again, so one bad chunk costs a resend rather than the command; a
+//! chunk that stays corrupt is refused with ChecksumMismatch after MAX_CHUNK_RETRIES.
+#![cfg(feature = "chunk-crc")]
+use decoder::modules::crc::crc16;
+use decoder::modules::hostcom_manager::{
+    BodyError, ErrorCode, HostConsole, MessageBody, MsgType, CHUNK_SIZE, MAX_BODY_LEN, MAX_CHUNK_RETRIES, MSG_MAGIC,
+};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+const NACK: [u8; 4] = [MSG_MAGIC, MsgType::Nack as u8, 0, 0];
+const LENGTH: usize = CHUNK_SIZE + 44;
+
+fn body_bytes() -> Vec<u8> {
+    (0..LENGTH).map(|i| (i * 3) as u8).collect()
+}
+
+/// `chunk` on the wire: its bytes, then its CRC-16, spoilt by one flipped bit if `corrupt`.
+fn wire_chunk(chunk: &[u8], corrupt: bool) -> Vec<u8> {
+    let mut wire = chunk.to_vec();
+    wire.extend_from_slice(&crc16(chunk).to_le_bytes());
+    if corrupt {
+        wire[chunk.len() / 2] ^= 0x10;
+    }
+    wire
+}
+
+fn empty_body() -> MessageBody {
+    MessageBody { data: [0; MAX_BODY_LEN], length: 0 }
+}
+
+#[test]
+fn corrupt_chunk_is_read_again() {
+    let sent = body_bytes();
+    let (first, second) = sent.split_at(CHUNK_SIZE);
+    let uart = MockUart::default();
+    uart.queue(&wire_chunk(first, false));
+    uart.queue(&wire_chunk(second, true));
+    uart.queue(&wire_chunk(second, false));
+
+    let mut console = HostConsole::new(uart.clone()).with_read_timeout(16);
+    let mut body = empty_body();
+    assert_eq!(console.read_body(LENGTH as u16, &mut body), Ok(LENGTH as u16));
+    assert_eq!(body.data[..LENGTH], sent[..]);
+    assert_eq!(uart.take_sent(), [ACK, NACK, ACK].concat());
+    assert_eq!(uart.pending(), 0);
+}
+
+#[test]
+fn chunk_corrupt_on_every_resend_is_refused() {
+    let sent = body_bytes();
+    let (first, second) = sent.split_at(CHUNK_SIZE);
+    let uart = MockUart::default();
+    uart.queue(&wire_chunk(first, false));
+    for _ in 0..=MAX_CHUNK_RETRIES {
+        uart.queue(&wire_chunk(second, true));
+    }
+    // The host's ACKs of the Error packet's header and body
+    uart.queue(&ACK.repeat(2));
+
+    // Log lines go to a sink of their own, leaving only protocol packets on the host UART
+    let mut console = HostConsole::new(uart.clone()).with_read_timeout(16).with_debug_sink(MockUart::default());
+    let mut body = empty_body();
+    assert_eq!(console.read_body(LENGTH as u16, &mut body), Err(BodyError::ChunkCorrupt));
+    assert_eq!({ body.length }, CHUNK_SIZE as u16);
+    assert_eq!(uart.pending(), 0);
+
+    let mut expected = [ACK.to_vec(), NACK.repeat(MAX_CHUNK_RETRIES as usize)].concat();
+    expected.extend_from_slice(&[MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::ChecksumMismatch as u8]);
+    assert_eq!(uart.take_sent(), expected);
+}
+
+#[test]
+fn discarded_body_drains_the_crcs() {
+    let sent = body_bytes();
+    let uart = MockUart::default();
+    for chunk in sent.chunks(CHUNK_SIZE) {
+        uart.queue(&wire_chunk(chunk, true));
+    }
+    uart.queue(&[MSG_MAGIC, MsgType::List as u8, 0, 0]);
+
+    let mut console = HostConsole::new(uart.clone()).with_read_timeout(16);
+    console.discard_body(LENGTH as u16);
+    assert_eq!(uart.take_sent(), ACK.repeat(2));
+    assert_eq!(console.read_header().opcode, MsgType::List as u8);
+}
@@ -0,0 +1,36 @@
+//! Wall-clock expiry against a clock the test moves by hand: a subscription ends when
+//! the clock passes its end, whatever the frames' own timestamps say.
+#![cfg(feature = "rtc-time")]
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::clock::TIMESTAMP_TICKS_PER_SEC;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const START: u64 = 1_700_000_000_000_000;
+const END: u64 = START + 60 * TIMESTAMP_TICKS_PER_SEC;
+
+#[test]
+fn advancing_the_clock_expires_the_window() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+    decoder.clock.set(START);
+    decoder.decode(&frame(CHANNEL, START)).unwrap();
+
+    // Up to the end itself the subscription holds
+    decoder.clock.advance(END - START);
+    decoder.decode(&frame(CHANNEL, START + 1)).unwrap();
+
+    // One second on, a frame stamped inside the window is still refused
+    decoder.clock.advance(TIMESTAMP_TICKS_PER_SEC);
+    assert!(matches!(decoder.decode(&frame(CHANNEL, START + 2)), Err(SubscriptionError::SubscriptionExpired)));
+    // Channel 0 never expires
+    decoder.decode(&frame(0, START + 2)).unwrap();
+}
+
+#[test]
+fn unset_clock_checks_frame_timestamps_only() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+    decoder.clock.advance(u64::MAX);
+    decoder.decode(&frame(CHANNEL, END)).unwrap();
+}
@@ -0,0 +1,74 @@
+//! With `constant-depth`, a frame key takes the same derivations whether the stored
+//! password is the root or the leaf itself, so decode time does not show its depth.
+#![cfg(feature = "constant-depth")]
+use bytemuck::Zeroable;
+use decoder::modules::channel_manager::{
+    derive_frame_key, stored_ancestor, ChannelPassword, ChannelSubscription, FrameKeyCache, TREE_DEPTH,
+};
+use decoder::modules::hostcom_manager::ChannelInfo;
+use decoder::modules::test_vectors::node_key;
+use decoder_host_tests::channel_root;
+use std::time::{Duration, Instant};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+const LEAF: u128 = (1 << 64) | T as u128;
+
+fn password(node_num: u128) -> ChannelPassword {
+    ChannelPassword {
+        node_trunc: (node_num >> 1) as u64,
+        node_ext: (node_num & 1) as u8 + 1,
+        password: node_key(&channel_root(CHANNEL), node_num),
+    }
+}
+
+/// A subscription holding the ancestors of `T`'s leaf at `depths`, shallowest first.
+fn subscription_at(depths: &[usize]) -> ChannelSubscription {
+    let mut subscription = ChannelSubscription::zeroed();
+    subscription.info = ChannelInfo { channel_id: CHANNEL, start_timestamp: 0, end_timestamp: u64::MAX };
+    for (entry, &depth) in subscription.passwords.contents.iter_mut().zip(depths) {
+        *entry = password(LEAF >> (TREE_DEPTH - depth));
+    }
+    subscription
+}
+
+/// Fastest of several batches of key derivations, to see past scheduler noise.
+fn time_derivation(subscription: &ChannelSubscription) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..200 {
+                derive_frame_key(subscription, T, &mut FrameKeyCache::new()).unwrap();
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+#[test]
+fn every_depth_derives_the_same_key() {
+    let leaf_key = node_key(&channel_root(CHANNEL), LEAF);
+    for depth in [0, 1, 32, 63, TREE_DEPTH] {
+        let subscription = subscription_at(&[depth]);
+        assert_eq!(derive_frame_key(&subscription, T, &mut FrameKeyCache::new()).unwrap(), leaf_key, "depth {}", depth);
+    }
+}
+
+#[test]
+fn full_scan_still_finds_the_shallowest_password() {
+    let subscription = subscription_at(&[10, 40]);
+    let (depth, found) = stored_ancestor(&subscription, T).unwrap();
+    assert_eq!(depth, 10);
+    assert_eq!(found.node_num(), LEAF >> (TREE_DEPTH - 10));
+}
+
+#[test]
+fn derivation_time_does_not_depend_on_depth() {
+    // Best effort: host timing is noisy, so only a gross difference fails. Without the
+    // padding a leaf password derives nothing and the root 64 levels.
+    let times: Vec<Duration> = [0, 32, TREE_DEPTH].iter().map(|&depth| time_derivation(&subscription_at(&[depth]))).collect();
+    let fastest = *times.iter().min().unwrap();
+    let slowest = *times.iter().max().unwrap();
+    assert!(slowest.as_secs_f64() < fastest.as_secs_f64() * 1.5, "{:?}", times);
+}
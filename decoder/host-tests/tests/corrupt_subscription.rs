@@ -0,0 +1,47 @@
+//! A subscription page found corrupt while decoding is erased and reported once,
+//! instead of failing every later frame on the channel.
+use decoder::modules::channel_manager::{find_subscription_page, SubscriptionError};
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn corrupt_page_is_erased_and_can_be_resubscribed() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap();
+    // A password byte, past the header the page scan reads
+    decoder.flc.corrupt_byte(addr + 100);
+
+    let err = decoder.decode(&frame(CHANNEL, T)).unwrap_err();
+    assert!(matches!(err, SubscriptionError::SubscriptionCorrupt));
+    assert_eq!(err.error_code(), ErrorCode::SubscriptionCorrupt);
+
+    // The page is gone from flash and from the active list
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_none());
+    assert!(!decoder.channels.iter().flatten().any(|c| c.channel_id == CHANNEL));
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 1)), Err(SubscriptionError::NoSubscription)));
+
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T + 2)).unwrap();
+}
+
+#[test]
+fn badly_signed_frame_does_not_erase_a_corrupt_page() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap();
+    decoder.flc.corrupt_byte(addr + 100);
+
+    let mut forged = frame(CHANNEL, T);
+    let last = forged.len() - 1;
+    forged[last] ^= 1;
+    assert!(matches!(decoder.decode(&forged), Err(SubscriptionError::InvalidSignature)));
+
+    // The page and its active entry are left for a signed frame to judge
+    assert_eq!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap().0, addr);
+    assert!(decoder.channels.iter().flatten().any(|c| c.channel_id == CHANNEL));
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::SubscriptionCorrupt)));
+}
@@ -0,0 +1,50 @@
+//! Counters at the top of their range: a last frame at u64::MAX still refuses equal
+//! and older frames, decode counts saturate, and the state log's generation wraps
+//! without losing the newest record.
+use decoder::modules::channel_manager::{ActiveChannel, SubscriptionError};
+use decoder::modules::state_manager::generation_is_newer;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+
+fn channel(decoder: &mut Decoder) -> &mut ActiveChannel {
+    decoder.channels.iter_mut().flatten().find(|c| c.channel_id == CHANNEL).unwrap()
+}
+
+#[test]
+fn last_frame_at_u64_max_refuses_equal_and_older() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, u64::MAX - 1)).unwrap();
+    decoder.decode(&frame(CHANNEL, u64::MAX)).unwrap();
+
+    for timestamp in [u64::MAX, u64::MAX - 1, 0] {
+        assert!(matches!(decoder.decode(&frame(CHANNEL, timestamp)), Err(SubscriptionError::InvalidTimestamp)));
+    }
+    // And still after a reboot
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, u64::MAX)), Err(SubscriptionError::InvalidTimestamp)));
+}
+
+#[test]
+fn decode_count_saturates_at_u32_max() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    channel(&mut decoder).decode_count = u32::MAX - 1;
+
+    for timestamp in 1..=3 {
+        decoder.decode(&frame(CHANNEL, timestamp)).unwrap();
+        assert_eq!(channel(&mut decoder).decode_count, u32::MAX);
+    }
+    let mut decoder = decoder.reboot();
+    assert_eq!(channel(&mut decoder).decode_count, u32::MAX);
+}
+
+#[test]
+fn generation_compares_across_the_wrap() {
+    assert!(generation_is_newer(1, 0));
+    assert!(generation_is_newer(0, u32::MAX));
+    assert!(generation_is_newer(5, u32::MAX - 5));
+    assert!(!generation_is_newer(u32::MAX, 0));
+    assert!(!generation_is_newer(7, 7));
+}
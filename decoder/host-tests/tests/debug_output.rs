@@ -0,0 +1,51 @@
+//! Without the `debug-output` feature no log call sends a byte, so a refused command
+//! is answered with protocol packets only. Run under --no-default-features for that
+//! build; the default build checks the logs still arrive.
+use decoder::modules::hostcom_manager::{write_debug, write_log, ErrorCode, HostConsole, LogLevel, MsgType, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+
+// Checked when the build is compiled, not when the test runs
+#[cfg(not(feature = "debug-output"))]
+const _: () = assert!(!LogLevel::Trace.enabled() && !LogLevel::Info.enabled() && !LogLevel::Error.enabled());
+
+/// Every way of logging, then an unsupported command answered as the dispatcher does.
+fn log_everything() -> Vec<u8> {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    for level in [LogLevel::Trace, LogLevel::Info, LogLevel::Warn, LogLevel::Error] {
+        console.write_log(level, "Error: Signature verification failed\n");
+        console.write_log_fmt(level, format_args!("Error: {}\n", 7));
+    }
+    console.write_debug("debug\n");
+    let mut raw = uart.clone();
+    write_debug(&mut raw, "debug\n");
+    write_log(&mut raw, LogLevel::Error, "error\n");
+
+    // The host ACKs the Error packet's header and body
+    uart.queue(&ACK.repeat(2));
+    console.reject_command(0);
+    uart.take_sent()
+}
+
+fn error_reply() -> Vec<u8> {
+    let mut reply = ACK.to_vec();
+    reply.extend_from_slice(&[MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::UnknownCommand as u8]);
+    reply
+}
+
+#[cfg(not(feature = "debug-output"))]
+#[test]
+fn no_debug_bytes_without_the_feature() {
+    assert_eq!(log_everything(), error_reply());
+}
+
+#[cfg(feature = "debug-output")]
+#[test]
+fn logs_are_sent_with_the_feature() {
+    let sent = log_everything();
+    assert!(sent.len() > error_reply().len());
+    assert!(sent.windows(2).any(|w| w == [MSG_MAGIC, MsgType::Debug as u8]));
+    assert!(sent.ends_with(&[MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::UnknownCommand as u8]));
+}
@@ -0,0 +1,46 @@
+//! With a debug sink attached, log messages go to it as plain text and the host UART
+//! carries protocol packets only; without one, they go to the host as Debug packets.
+use decoder::modules::hostcom_manager::{ErrorCode, HostConsole, LogLevel, MsgType, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+
+#[test]
+fn logs_go_to_the_debug_sink() {
+    let host = MockUart::default();
+    let sink = MockUart::default();
+    let mut console = HostConsole::new(host.clone()).with_debug_sink(sink.clone());
+
+    console.write_debug("Decoding frame\n");
+    console.write_log_fmt(LogLevel::Error, format_args!("Error: {} failed\n", "Subscribe"));
+    host.queue(&ACK.repeat(2));
+    assert_eq!(console.write_error(ErrorCode::InvalidSignature), 0);
+
+    assert_eq!(
+        host.take_sent(),
+        [MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::InvalidSignature as u8]
+    );
+    assert_eq!(host.pending(), 0);
+    // Nothing of the protocol reaches the sink
+    let logged = sink.take_sent();
+    if cfg!(feature = "debug-output") {
+        assert_eq!(logged, b"Decoding frame\nError: Subscribe failed\n");
+    } else {
+        assert!(logged.is_empty());
+    }
+}
+
+#[test]
+fn without_a_sink_logs_are_debug_packets() {
+    let host = MockUart::default();
+    let mut console = HostConsole::new(host.clone());
+    console.write_debug("Decoding frame\n");
+
+    let sent = host.take_sent();
+    if cfg!(feature = "debug-output") {
+        assert_eq!(sent[..2], [MSG_MAGIC, MsgType::Debug as u8]);
+        assert!(sent.ends_with(b"Decoding frame\n"));
+    } else {
+        assert!(sent.is_empty());
+    }
+}
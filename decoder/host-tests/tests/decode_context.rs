@@ -0,0 +1,43 @@
+//! Consecutive frames on one channel reuse the subscription `DecodeContext` already
+//! holds instead of reading and CRC-checking it from flash again, until the stored
+//! subscriptions change.
+use core::mem::size_of;
+use decoder::modules::channel_manager::ChannelSubscription;
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+/// 128-bit words in a whole subscription record.
+const RECORD_READS: u32 = (size_of::<ChannelSubscription>() / 16) as u32;
+
+/// Decode `channel` at `timestamp`, returning how many words it read.
+fn decode_reads(decoder: &mut Decoder, channel: u32, timestamp: u64) -> u32 {
+    let before = decoder.flc.read_count();
+    assert_eq!(decoder.decode(&frame(channel, timestamp)).unwrap(), frame_content(timestamp));
+    decoder.flc.read_count() - before
+}
+
+#[test]
+fn same_channel_skips_the_record_read() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+
+    let cold = decode_reads(&mut decoder, 1, T);
+    for i in 1..5 {
+        let warm = decode_reads(&mut decoder, 1, T + 10 * i);
+        assert!(warm + RECORD_READS <= cold, "warm {} cold {}", warm, cold);
+    }
+}
+
+#[test]
+fn switching_channel_or_subscribing_reloads() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(2, 0, u64::MAX)).unwrap();
+
+    decode_reads(&mut decoder, 1, T);
+    assert!(decode_reads(&mut decoder, 2, T) >= RECORD_READS);
+    assert!(decode_reads(&mut decoder, 1, T + 10) >= RECORD_READS);
+
+    decoder.subscribe(&subscription(3, 0, u64::MAX)).unwrap();
+    assert!(decode_reads(&mut decoder, 1, T + 20) >= RECORD_READS);
+}
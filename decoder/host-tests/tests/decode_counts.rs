@@ -0,0 +1,52 @@
+//! Per-channel decode counts: bumped by every decoded frame only, reported after the
+//! telemetry counters, and carried across a reboot by the state log.
+use bytemuck::Zeroable;
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::telemetry::{Telemetry, TELEMETRY_MAX_LEN};
+use decoder::modules::wire::read_u32_le;
+use decoder_host_tests::{frame, subscription, Decoder};
+use std::mem::size_of;
+
+const T: u64 = 1_700_000_000_000_000;
+
+fn count(decoder: &Decoder, channel: u32) -> u32 {
+    decoder.channels.iter().flatten().find(|c| c.channel_id == channel).unwrap().decode_count
+}
+
+#[test]
+fn counts_follow_each_channel_and_survive_a_reboot() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(2, 0, u64::MAX)).unwrap();
+
+    for t in 0..3 {
+        decoder.decode(&frame(1, T + t)).unwrap();
+    }
+    decoder.decode(&frame(2, T)).unwrap();
+    // A replay is not a decode
+    assert!(matches!(decoder.decode(&frame(2, T)), Err(SubscriptionError::InvalidTimestamp)));
+
+    assert_eq!([count(&decoder, 0), count(&decoder, 1), count(&decoder, 2)], [0, 3, 1]);
+
+    let mut out = [0u8; TELEMETRY_MAX_LEN];
+    let len = Telemetry::zeroed().write_report(&decoder.channels, &mut out);
+    let report = &out[size_of::<Telemetry>()..len];
+    assert_eq!(read_u32_le(report, 0), 4);
+    assert_eq!(read_u32_le(report, 4), 3);
+    let entries: Vec<(u32, u32)> = report[8..].chunks(8).map(|e| (read_u32_le(e, 0), read_u32_le(e, 4))).collect();
+    assert_eq!(entries, [(0, 0), (1, 3), (2, 1)]);
+
+    let mut decoder = decoder.reboot();
+    assert_eq!([count(&decoder, 1), count(&decoder, 2)], [3, 1]);
+    decoder.decode(&frame(2, T + 1)).unwrap();
+    assert_eq!(count(&decoder, 2), 2);
+}
+
+#[test]
+fn counts_saturate() {
+    let mut decoder = Decoder::new();
+    decoder.channels[0].as_mut().unwrap().decode_count = u32::MAX - 1;
+    decoder.decode(&frame(0, T)).unwrap();
+    decoder.decode(&frame(0, T + 1)).unwrap();
+    assert_eq!(count(&decoder, 0), u32::MAX);
+}
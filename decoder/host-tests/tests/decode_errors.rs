@@ -0,0 +1,56 @@
+//! Every way a Decode fails has its own error and error code, so the host can tell an
+//! unsubscribed channel from a bad frame.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const START: u64 = 1000;
+const END: u64 = 5000;
+
+fn decode_error(decoder: &mut Decoder, frame: &[u8]) -> SubscriptionError {
+    decoder.decode(frame).expect_err("frame decoded")
+}
+
+#[test]
+fn each_decode_failure_has_its_own_error() {
+    let mut decoder = Decoder::new();
+    let e = decode_error(&mut decoder, &frame(CHANNEL, 2000));
+    assert!(matches!(e, SubscriptionError::NoSubscription));
+    assert_eq!(e.error_code(), ErrorCode::NoSubscription);
+
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+    let e = decode_error(&mut decoder, &frame(CHANNEL, START - 1));
+    assert!(matches!(e, SubscriptionError::PasswordNotFound));
+    assert_eq!(e.error_code(), ErrorCode::KeyNotFound);
+
+    // One flipped content byte, after the channel, timestamp and nonce
+    let mut forged = frame(CHANNEL, 2000);
+    forged[4 + 8 + 12] ^= 1;
+    let e = decode_error(&mut decoder, &forged);
+    assert!(matches!(e, SubscriptionError::InvalidSignature));
+    assert_eq!(e.error_code(), ErrorCode::InvalidSignature);
+
+    decoder.decode(&frame(CHANNEL, 2000)).unwrap();
+    let e = decode_error(&mut decoder, &frame(CHANNEL, 2000));
+    assert!(matches!(e, SubscriptionError::InvalidTimestamp));
+    assert_eq!(e.error_code(), ErrorCode::ReplayedTimestamp);
+
+    let e = decode_error(&mut decoder, &frame(CHANNEL, END + 1));
+    assert!(matches!(e, SubscriptionError::SubscriptionExpired));
+    assert_eq!(e.error_code(), ErrorCode::SubscriptionExpired);
+}
+
+#[test]
+fn error_codes_are_distinct() {
+    let codes = [
+        ErrorCode::NoSubscription,
+        ErrorCode::InvalidSignature,
+        ErrorCode::ReplayedTimestamp,
+        ErrorCode::SubscriptionExpired,
+        ErrorCode::KeyNotFound,
+    ];
+    for (i, a) in codes.iter().enumerate() {
+        assert!(codes[i + 1..].iter().all(|b| *b as u8 != *a as u8));
+    }
+}
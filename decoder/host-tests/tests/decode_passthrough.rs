@@ -0,0 +1,36 @@
+//! With `decode-passthrough`, Decode echoes a frame's encrypted content whatever its
+//! channel or signature, with no flash or keys involved, so framing can be tested alone.
+#![cfg(feature = "decode-passthrough")]
+use decoder::modules::channel_manager::{ChannelFrame, FRAME_CONTENT_LEN};
+use decoder::modules::hostcom_manager::{write_passthrough, ErrorCode, HostConsole, MsgType, MSG_MAGIC};
+use decoder_host_tests::{frame, MockUart};
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+/// Channel, timestamp and nonce ahead of the content.
+const CONTENT_OFFSET: usize = 4 + 8 + 12;
+
+#[test]
+fn encrypted_content_is_echoed() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+
+    // Unsubscribed and with its signature spoilt, it is echoed all the same
+    let mut body = frame(7, 1000);
+    let last = body.len() - 1;
+    body[last] ^= 1;
+    uart.queue(&ACK.repeat(2));
+    assert_eq!(console.write_passthrough(&body), 0);
+
+    let mut expected = vec![MSG_MAGIC, MsgType::Decode as u8, FRAME_CONTENT_LEN as u8, 0];
+    expected.extend_from_slice(&body[CONTENT_OFFSET..CONTENT_OFFSET + FRAME_CONTENT_LEN]);
+    assert_eq!(uart.take_sent(), expected);
+}
+
+#[test]
+fn body_of_another_length_is_refused() {
+    let mut uart = MockUart::default();
+    let body = frame(1, 1000);
+    uart.queue(&ACK.repeat(2));
+    assert_eq!(write_passthrough(&mut uart, &body[..size_of::<ChannelFrame>() - 1]), 0);
+    assert_eq!(uart.take_sent(), [MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::InvalidFrameLength as u8]);
+}
@@ -0,0 +1,97 @@
+//! Known-answer tests pinning the decoder to the Python encoder: every frame and
+//! subscription in `vectors/decode.json` comes from `ectf25_design`, regenerated with
+//! `python -m ectf25_design.gen_test_vectors`.
+use decoder::modules::channel_manager::{find_subscription_page, read_subscription, SubscriptionError};
+use decoder::modules::key_tree::derive_child_key;
+use decoder_host_tests::{channel_root, decode_vectors, hex_field, Decoder};
+use serde_json::Value;
+
+/// Key of leaf `timestamp` derived down from `key`, the key of ancestor `node`.
+fn derive_leaf(mut node: u128, mut key: [u8; 16], timestamp: u64) -> [u8; 16] {
+    let leaf = (1u128 << 64) | timestamp as u128;
+    let depth = 127 - node.leading_zeros();
+    for shift in (0..64 - depth).rev() {
+        let branch = ((leaf >> shift) & 1) as u8;
+        node = node * 2 + branch as u128;
+        key = derive_child_key(&key, branch + 1, node);
+    }
+    assert_eq!(node, leaf);
+    key
+}
+
+fn case(name: &str) -> Value {
+    decode_vectors()["cases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == name)
+        .unwrap_or_else(|| panic!("no vector case {}", name))
+        .clone()
+}
+
+/// Subscribe (unless channel 0), then decode every frame of the case in order.
+fn check_case(name: &str) {
+    let case = case(name);
+    let channel = case["channel"].as_u64().unwrap() as u32;
+    let mut decoder = Decoder::new();
+    if channel != 0 {
+        decoder.subscribe(&hex_field(&case, "subscription")).expect("vector subscription rejected");
+    }
+
+    for frame in case["frames"].as_array().unwrap() {
+        let timestamp = frame["timestamp"].as_u64().unwrap();
+        let frame_key: [u8; 16] = hex_field(frame, "frame_key").try_into().unwrap();
+        assert_eq!(derive_leaf(1, channel_root(channel), timestamp), frame_key, "{} @ {}: key from the root", name, timestamp);
+
+        // The stored password the decoder starts from is the node the encoder chose
+        if channel != 0 {
+            let node: u128 = frame["node"].as_str().unwrap().parse().unwrap();
+            let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).unwrap();
+            let stored = read_subscription(&mut decoder.flash, addr).unwrap();
+            let password = stored.passwords.find(node).unwrap_or_else(|| panic!("{}: node {} not stored", name, node));
+            assert_eq!(derive_leaf(node, password.password, timestamp), frame_key, "{} @ {}: key from node {}", name, timestamp, node);
+        }
+
+        let content = decoder.decode(&hex_field(frame, "frame")).unwrap_or_else(|e| panic!("{} @ {}: {}", name, timestamp, e));
+        assert_eq!(content.to_vec(), hex_field(frame, "content"), "{} @ {}", name, timestamp);
+    }
+
+    // Every frame advanced the replay counter, so the first one is now refused
+    let first = &case["frames"][0];
+    assert!(matches!(decoder.decode(&hex_field(first, "frame")), Err(SubscriptionError::InvalidTimestamp)));
+}
+
+#[test]
+fn password_at_root() {
+    check_case("root");
+}
+
+#[test]
+fn password_at_leaf() {
+    check_case("leaf");
+}
+
+#[test]
+fn timestamp_zero() {
+    check_case("timestamp-zero");
+}
+
+#[test]
+fn window_covered_by_several_nodes() {
+    check_case("mid-range");
+}
+
+#[test]
+fn emergency_channel() {
+    check_case("channel-0");
+}
+
+#[test]
+fn tampered_vector_is_rejected() {
+    let case = case("root");
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&hex_field(&case, "subscription")).unwrap();
+    let mut frame = hex_field(&case["frames"][0], "frame");
+    frame[40] ^= 1;
+    assert!(matches!(decoder.decode(&frame), Err(SubscriptionError::InvalidSignature)));
+}
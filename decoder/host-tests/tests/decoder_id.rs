@@ -0,0 +1,17 @@
+//! The DecoderId command returns the decoder ID the firmware was built with.
+use decoder::modules::hostcom_manager::{HostConsole, MsgType, MSG_MAGIC};
+use decoder::DECODER_ID;
+use decoder_host_tests::MockUart;
+
+#[test]
+fn response_carries_the_build_time_id() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(2));
+    assert_eq!(console.write_decoder_id(), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent, [&[MSG_MAGIC, MsgType::DecoderId as u8, 4, 0][..], &DECODER_ID.to_le_bytes()].concat());
+    // The std build always carries build.rs's test ID
+    assert_eq!(DECODER_ID, 0xdead_beef);
+}
@@ -0,0 +1,42 @@
+//! DECODER_KEY is HKDF-SHA512 of `decoder_dk` with the little-endian decoder id as
+//! context, KEY_LEN bytes long, as get_decoder_key derives it, and it is the ChaCha20
+//! key subscription passwords are decrypted with.
+use decoder::modules::test_vectors::encode_subscription;
+use decoder::{DECODER_ID, DECODER_KEY, KEY_LEN};
+use decoder_host_tests::{channel_root, frame, hex_field, host_key, test_secrets, Decoder};
+use hkdf::Hkdf;
+use sha2::Sha512;
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn derive_key(decoder_id: u32) -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    Hkdf::<Sha512>::new(None, &hex_field(&test_secrets(), "decoder_dk"))
+        .expand(&decoder_id.to_le_bytes(), &mut key)
+        .unwrap();
+    key
+}
+
+fn subscription_under(key: &[u8; KEY_LEN]) -> Vec<u8> {
+    encode_subscription(&host_key(), key, DECODER_ID, &channel_root(CHANNEL), CHANNEL, 0, u64::MAX, [0x5A; 12])
+}
+
+#[test]
+fn decoder_key_is_derived_from_decoder_dk() {
+    assert_eq!(KEY_LEN, 32);
+    assert_eq!(DECODER_KEY, derive_key(DECODER_ID));
+}
+
+#[test]
+fn passwords_encrypted_under_the_derived_key_decrypt() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription_under(&derive_key(DECODER_ID))).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    // Another decoder's key decrypts the same blob to noise
+    let mut decoder = Decoder::new();
+    if decoder.subscribe(&subscription_under(&derive_key(DECODER_ID ^ 1))).is_ok() {
+        assert!(decoder.decode(&frame(CHANNEL, T)).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! Subscription password blobs of every validated size decrypt to exactly the node
+//! passwords that were encrypted, with no entry past the blob touched, and frames
+//! decrypt under each of them.
+use decoder::modules::channel_manager::{find_subscription_page, read_subscription};
+use decoder::modules::test_vectors::{covering_nodes, node_key};
+use decoder_host_tests::{channel_root, frame, frame_content, subscription, Decoder};
+
+const CHANNEL: u32 = 2;
+
+#[test]
+fn blobs_of_several_sizes_decrypt() {
+    let windows = [(0, u64::MAX), (7, 7), (1000, 1001), (1000, 5000), (3, u64::MAX - 3), (1, u64::MAX - 1)];
+    let mut sizes = Vec::new();
+    for (start, end) in windows {
+        let nodes = covering_nodes(start, end);
+        sizes.push(nodes.len());
+
+        let mut decoder = Decoder::new();
+        decoder.subscribe(&subscription(CHANNEL, start, end)).unwrap();
+        let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap();
+        let stored = read_subscription(&mut decoder.flash, addr).unwrap();
+
+        for &node in &nodes {
+            let password = stored.passwords.find(node).unwrap_or_else(|| panic!("node {} not stored", node));
+            assert_eq!(password.password, node_key(&channel_root(CHANNEL), node), "node {}", node);
+        }
+        // The rest of the table stays empty
+        assert!(stored.passwords.contents[nodes.len()..].iter().all(|p| p.node_ext == 0));
+
+        assert_eq!(decoder.decode(&frame(CHANNEL, start)).unwrap(), frame_content(start));
+        if end > start {
+            assert_eq!(decoder.decode(&frame(CHANNEL, end)).unwrap(), frame_content(end));
+        }
+    }
+    // The windows span one entry up to the largest cover
+    sizes.sort();
+    sizes.dedup();
+    assert!(sizes.len() >= 4 && sizes[0] == 1 && *sizes.last().unwrap() >= 100, "{:?}", sizes);
+}
@@ -0,0 +1,48 @@
+//! The descent from a stored password to a frame's leaf takes exactly one derivation
+//! per level between them: 64 from the root (found at depth 0), none from the leaf
+//! itself (found at depth 64), and the rest in between.
+use bytemuck::Zeroable;
+use decoder::modules::channel_manager::{
+    derive_frame_key, ChannelPassword, ChannelSubscription, FrameKeyCache, SubscriptionError, TREE_DEPTH,
+};
+use decoder::modules::test_vectors::{leaf_node, node_key};
+
+const ROOT: [u8; 16] = [0x17; 16];
+const T: u64 = 1_700_000_000_000_000;
+
+/// A subscription holding the single password of `node_num`, derived from `ROOT`.
+fn holding(node_num: u128) -> ChannelSubscription {
+    let mut subscription = ChannelSubscription::zeroed();
+    subscription.passwords.contents[0] = ChannelPassword {
+        node_trunc: (node_num >> 1) as u64,
+        node_ext: (node_num & 1) as u8 + 1,
+        password: node_key(&ROOT, node_num),
+    };
+    subscription
+}
+
+#[test]
+fn descent_starts_at_every_depth() {
+    assert_eq!(TREE_DEPTH, 64);
+    for timestamp in [0, 1, T, u64::MAX] {
+        let leaf = leaf_node(timestamp);
+        for depth in 0..=TREE_DEPTH {
+            let ancestor = holding(leaf >> (TREE_DEPTH - depth));
+            let key = derive_frame_key(&ancestor, timestamp, &mut FrameKeyCache::new()).unwrap();
+            assert_eq!(key, node_key(&ROOT, leaf), "timestamp {} from depth {}", timestamp, depth);
+        }
+    }
+}
+
+#[test]
+fn boundaries_refuse_other_leaves() {
+    // Found at the root: every leaf is below it
+    let root = holding(1);
+    for timestamp in [0, u64::MAX] {
+        assert!(derive_frame_key(&root, timestamp, &mut FrameKeyCache::new()).is_ok());
+    }
+    // Found at the leaf: its sibling is not
+    let leaf = holding(leaf_node(T));
+    let result = derive_frame_key(&leaf, T + 1, &mut FrameKeyCache::new());
+    assert!(matches!(result, Err(SubscriptionError::PasswordNotFound)));
+}
@@ -0,0 +1,37 @@
+//! Emergency-only mode: every channel but 0 is refused, across a reboot, until a
+//! signed command clears it.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::emergency_manager::{set_emergency_only, EmergencyError};
+use decoder::modules::test_vectors::encode_emergency;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, host_key, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+fn emergency(decoder: &mut Decoder, epoch: u32, enabled: bool) -> Result<u32, EmergencyError> {
+    let state = set_emergency_only(&mut decoder.flash, &encode_emergency(&host_key(), DECODER_ID, epoch, enabled))?;
+    decoder.context.set_emergency_only(state.is_enabled());
+    Ok(state.epoch)
+}
+
+#[test]
+fn emergency_only_refuses_all_but_channel_0_until_cleared() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+
+    assert_eq!(emergency(&mut decoder, 0, true).unwrap(), 1);
+    assert!(matches!(decoder.decode(&frame(1, T)), Err(SubscriptionError::EmergencyOnly)));
+    decoder.decode(&frame(0, T)).unwrap();
+
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(1, T)), Err(SubscriptionError::EmergencyOnly)));
+    decoder.decode(&frame(0, T + 1)).unwrap();
+
+    assert_eq!(emergency(&mut decoder, 1, false).unwrap(), 2);
+    decoder.decode(&frame(1, T)).unwrap();
+
+    // The recorded command was signed for epoch 0
+    assert!(matches!(emergency(&mut decoder, 0, true), Err(EmergencyError::InvalidSignature)));
+    let mut decoder = decoder.reboot();
+    decoder.decode(&frame(1, T + 1)).unwrap();
+}
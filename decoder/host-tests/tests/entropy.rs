@@ -0,0 +1,68 @@
+//! The TRNG self-test rejects degenerate output and keeps a failed source from handing
+//! out bytes until a later test passes.
+use decoder::modules::entropy::{CheckedRng, EntropyError};
+use decoder_host_tests::Rng;
+use rand::RngCore;
+
+/// Random source replaying `next` for every word.
+struct MockRng<F: FnMut() -> u32> {
+    next: F,
+}
+
+impl<F: FnMut() -> u32> RngCore for MockRng<F> {
+    fn next_u32(&mut self) -> u32 {
+        (self.next)()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            chunk.copy_from_slice(&self.next_u32().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+fn mock(next: impl FnMut() -> u32) -> MockRng<impl FnMut() -> u32> {
+    MockRng { next }
+}
+
+#[test]
+fn degenerate_output_is_rejected() {
+    assert_eq!(CheckedRng::init(mock(|| 0)).err(), Some(EntropyError::Stuck));
+    assert_eq!(CheckedRng::init(mock(|| u32::MAX)).err(), Some(EntropyError::Stuck));
+
+    // Never repeats, but nearly all zeros or all ones
+    let mut i = 0u32;
+    assert_eq!(CheckedRng::init(mock(|| { i += 1; i })).err(), Some(EntropyError::Biased));
+    let mut i = 0u32;
+    assert_eq!(CheckedRng::init(mock(|| { i += 1; !i })).err(), Some(EntropyError::Biased));
+}
+
+#[test]
+fn healthy_output_passes_and_failures_disable_the_source() {
+    let mut rng = Rng::new(0x1904);
+    let mut checked = CheckedRng::init(mock(move || rng.next_u64() as u32)).unwrap();
+    let mut nonce = [0u8; 12];
+    checked.try_fill_bytes(&mut nonce).unwrap();
+    assert_ne!(nonce, [0; 12]);
+
+    // The source goes stuck after the first self-test
+    let mut rng = Rng::new(0x1904);
+    let mut words = 0;
+    let mut checked = CheckedRng::init(mock(move || {
+        words += 1;
+        if words > 16 { 0 } else { rng.next_u64() as u32 }
+    }))
+    .unwrap();
+    assert!(checked.is_healthy());
+    assert_eq!(checked.self_test(), Err(EntropyError::Stuck));
+    assert_eq!(checked.try_fill_bytes(&mut nonce), Err(EntropyError::Unhealthy));
+}
@@ -0,0 +1,62 @@
+//! Every error variant formats as its own short, human-readable message.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::flash_manager::FlashManagerError;
+use decoder::FlashError;
+use std::collections::HashSet;
+
+fn flash_errors() -> Vec<(FlashManagerError, &'static str)> {
+    vec![
+        (FlashManagerError::FlashError(FlashError::InvalidAddress), "invalid flash address"),
+        (FlashManagerError::FlashError(FlashError::AccessViolation), "flash access violation"),
+        (FlashManagerError::FlashError(FlashError::NeedsErase), "flash needs erase"),
+        (FlashManagerError::MagicMismatch, "magic mismatch"),
+        (FlashManagerError::CrcMismatch, "crc mismatch"),
+        (FlashManagerError::Misaligned, "misaligned flash address"),
+    ]
+}
+
+#[test]
+fn flash_manager_errors_display() {
+    for (err, expected) in flash_errors() {
+        assert_eq!(err.to_string(), expected);
+    }
+}
+
+#[test]
+fn subscription_errors_display() {
+    let errors = [
+        (SubscriptionError::InvalidChannelId, "invalid channel id"),
+        (SubscriptionError::NoPageFound, "no free subscription page"),
+        (SubscriptionError::InvalidKey, "invalid host key"),
+        (SubscriptionError::InvalidSignature, "invalid signature"),
+        (SubscriptionError::InvalidDecoderId, "invalid decoder id"),
+        (SubscriptionError::InvalidTimestamp, "invalid timestamp"),
+        (SubscriptionError::PasswordNotFound, "no password for frame"),
+        (SubscriptionError::SubscriptionExpired, "subscription expired"),
+        (SubscriptionError::StaleSubscription, "stored subscription ends later"),
+        (SubscriptionError::InvalidPath, "invalid tree path"),
+        (SubscriptionError::InvalidLength, "invalid subscription length"),
+        (SubscriptionError::NoSubscription, "not subscribed to channel"),
+        (SubscriptionError::InconsistentFrame, "frame marker mismatch"),
+        (SubscriptionError::PasswordBlobTooLarge, "too many subscription passwords"),
+        (SubscriptionError::ChannelPaused, "channel paused"),
+        (SubscriptionError::EmergencyOnly, "emergency channel only"),
+        (SubscriptionError::ZeroNonce, "all-zero subscription nonce"),
+        (SubscriptionError::ChecksumMismatch, "subscription checksum mismatch"),
+        (SubscriptionError::ChannelInactive, "channel not active"),
+        (SubscriptionError::SubscriptionCorrupt, "stored subscription corrupt, erased"),
+        (SubscriptionError::NotSubscriptionPage, "not a subscription page"),
+        (SubscriptionError::NoUsablePasswords, "no usable password after decryption"),
+        (SubscriptionError::UploadOutOfOrder, "upload chunk out of order"),
+    ];
+    let mut seen = HashSet::new();
+    for (err, expected) in errors {
+        assert_eq!(err.to_string(), expected);
+        assert!(seen.insert(expected), "{expected} is shared by two variants");
+    }
+
+    // A flash failure keeps the flash error's own message behind a prefix
+    for (err, expected) in flash_errors() {
+        assert_eq!(SubscriptionError::FlashManagerError(err).to_string(), format!("flash: {expected}"));
+    }
+}
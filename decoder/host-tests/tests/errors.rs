@@ -0,0 +1,33 @@
+//! Failures keep their variant from flash up through the public functions, and the
+//! error types compose with `core::error::Error` for host tooling.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::flash_manager::FlashManagerError;
+use decoder::FlashError;
+use decoder_host_tests::{frame, subscription, Decoder};
+use std::error::Error;
+
+const CHANNEL: u32 = 1;
+
+#[test]
+fn failed_flash_write_surfaces_the_flash_error() {
+    let mut decoder = Decoder::new();
+    decoder.flc.fail_after_writes(0);
+
+    let err = decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap_err();
+    assert!(matches!(err, SubscriptionError::FlashManagerError(FlashManagerError::FlashError(FlashError::AccessViolation))));
+    assert_eq!(err.to_string(), "flash: flash access violation");
+    let source = err.source().and_then(|e| e.downcast_ref::<FlashManagerError>());
+    assert!(matches!(source, Some(FlashManagerError::FlashError(FlashError::AccessViolation))));
+}
+
+#[test]
+fn errors_convert_with_the_question_mark() -> Result<(), Box<dyn Error>> {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX))?;
+    decoder.decode(&frame(CHANNEL, 10))?;
+
+    let err: Box<dyn Error> = decoder.decode(&frame(CHANNEL, 10)).unwrap_err().into();
+    assert!(matches!(err.downcast_ref::<SubscriptionError>(), Some(SubscriptionError::InvalidTimestamp)));
+    assert!(err.source().is_none());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! A frame past its subscription's end is refused with its own error code. The page is
+//! kept by default; with `PRUNE_EXPIRED_SUBSCRIPTIONS` it is wiped and its slot freed,
+//! as `expire_subscription` does.
+use decoder::modules::channel_manager::{
+    expire_subscription, find_subscription_page, free_subscription_pages, SubscriptionError,
+    PRUNE_EXPIRED_SUBSCRIPTIONS,
+};
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn active(decoder: &Decoder) -> Vec<u32> {
+    decoder.channels.iter().flatten().map(|c| c.channel_id).collect()
+}
+
+#[test]
+fn expired_frame_is_refused_and_pruned_by_policy() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    let err = decoder.decode(&frame(CHANNEL, T + 1)).unwrap_err();
+    assert!(matches!(err, SubscriptionError::SubscriptionExpired));
+    assert_eq!(err.error_code(), ErrorCode::SubscriptionExpired);
+
+    let stored = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_some();
+    assert_eq!(stored, !PRUNE_EXPIRED_SUBSCRIPTIONS);
+    assert_eq!(active(&decoder).contains(&CHANNEL), !PRUNE_EXPIRED_SUBSCRIPTIONS);
+}
+
+#[test]
+fn pruning_frees_the_page_and_the_slot() {
+    let mut decoder = Decoder::new();
+    for channel in 1..=3 {
+        decoder.subscribe(&subscription(channel, 0, T)).unwrap();
+    }
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 2).unwrap();
+    let free = free_subscription_pages(&mut decoder.flash);
+
+    expire_subscription(&mut decoder.flash, addr, 2, &mut decoder.channels).unwrap();
+    decoder.context.invalidate();
+    assert_eq!(free_subscription_pages(&mut decoder.flash), free + 1);
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == 2).is_none());
+    // The active list closes up behind the removed channel
+    assert_eq!(active(&decoder), [0, 1, 3]);
+    assert!(matches!(decoder.decode(&frame(2, T)), Err(SubscriptionError::NoSubscription)));
+    decoder.decode(&frame(3, T)).unwrap();
+
+    // The freed page takes a new subscription
+    decoder.subscribe(&subscription(2, T, u64::MAX)).unwrap();
+    decoder.decode(&frame(2, T + 1)).unwrap();
+    assert_eq!(free_subscription_pages(&mut decoder.flash), free);
+}
@@ -0,0 +1,33 @@
+//! Frames at timestamps 0, 1 and u64::MAX decode, both from the root password of a
+//! whole-range subscription, descending all 64 levels, and from a single-leaf one.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const TIMESTAMPS: [u64; 3] = [0, 1, u64::MAX];
+
+#[test]
+fn extreme_timestamps_decode_from_the_root() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    for timestamp in TIMESTAMPS {
+        assert_eq!(decoder.decode(&frame(CHANNEL, timestamp)).unwrap(), frame_content(timestamp), "{}", timestamp);
+    }
+}
+
+#[test]
+fn extreme_timestamps_decode_from_a_leaf() {
+    for timestamp in TIMESTAMPS {
+        let mut decoder = Decoder::new();
+        decoder.subscribe(&subscription(CHANNEL, timestamp, timestamp)).unwrap();
+        assert_eq!(decoder.decode(&frame(CHANNEL, timestamp)).unwrap(), frame_content(timestamp), "{}", timestamp);
+        // Its neighbours are outside the one-leaf window
+        if timestamp > 0 {
+            assert!(decoder.decode(&frame(CHANNEL, timestamp - 1)).is_err());
+        }
+        if timestamp < u64::MAX {
+            let next = decoder.decode(&frame(CHANNEL, timestamp + 1));
+            assert!(matches!(next, Err(SubscriptionError::SubscriptionExpired)));
+        }
+    }
+}
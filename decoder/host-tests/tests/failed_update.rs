@@ -0,0 +1,39 @@
+//! A subscription update is written to a free page before the old one is retired, so
+//! a flash failure at any write of the new page keeps the old subscription, and the
+//! flash error reaches the caller.
+use decoder::modules::channel_manager::{find_subscription_page, ChannelSubscription, SubscriptionError};
+use decoder::modules::flash_manager::FlashManagerError;
+use decoder::FlashError;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+/// 128-bit writes of one subscription record.
+const PAGE_WRITES: u32 = (4 + size_of::<ChannelSubscription>() + 4).div_ceil(16) as u32;
+
+fn stored_end(decoder: &mut Decoder) -> u64 {
+    find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap().1.end_timestamp
+}
+
+#[test]
+fn failed_write_keeps_the_old_subscription() {
+    for good_writes in 0..PAGE_WRITES {
+        let mut decoder = Decoder::new();
+        decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+
+        decoder.flc.fail_after_writes(good_writes);
+        let err = decoder.subscribe(&subscription(CHANNEL, 0, T + 10)).unwrap_err();
+        assert!(matches!(err, SubscriptionError::FlashManagerError(FlashManagerError::FlashError(FlashError::AccessViolation))));
+        decoder.flc.clear_failures();
+
+        assert_eq!(stored_end(&mut decoder), T);
+        decoder.decode(&frame(CHANNEL, T)).unwrap();
+        assert!(matches!(decoder.decode(&frame(CHANNEL, T + 1)), Err(SubscriptionError::SubscriptionExpired)));
+
+        // The torn new page is free after a reset, and the update can be retried
+        let mut decoder = decoder.reboot();
+        assert_eq!(stored_end(&mut decoder), T);
+        decoder.subscribe(&subscription(CHANNEL, 0, T + 10)).unwrap();
+        decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+//! `find_subscription_page` returns the first stored page whose header satisfies the
+//! predicate, whatever the predicate looks at.
+use decoder::modules::channel_manager::find_subscription_page;
+use decoder::modules::constants::subscription_page_addr;
+use decoder::modules::hostcom_manager::ChannelInfo;
+use decoder_host_tests::{subscription, Decoder};
+
+/// Channels 1 to 3 on pages 0 to 2, with overlapping windows for 2 and 3.
+fn decoder() -> Decoder {
+    let mut decoder = Decoder::new();
+    for (channel, start, end) in [(1, 0, 100), (2, 1_000, 2_000), (3, 500, 5_000)] {
+        decoder.subscribe(&subscription(channel, start, end)).unwrap();
+    }
+    decoder
+}
+
+/// Whether a window covers `t`.
+fn covering(t: u64) -> impl Fn(&ChannelInfo) -> bool {
+    move |info| { info.start_timestamp } <= t && t <= { info.end_timestamp }
+}
+
+#[test]
+fn finds_by_channel_id() {
+    let mut decoder = decoder();
+    for channel in 1..=3 {
+        let (addr, info) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).unwrap();
+        assert_eq!(addr, subscription_page_addr(channel as usize - 1));
+        assert_eq!({ info.channel_id }, channel);
+    }
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == 4).is_none());
+}
+
+#[test]
+fn finds_the_first_window_covering_a_timestamp() {
+    let mut decoder = decoder();
+    // Both 2 and 3 cover 1500; 2 is on the earlier page
+    let (addr, info) = find_subscription_page(&mut decoder.flash, covering(1_500)).unwrap();
+    assert_eq!(addr, subscription_page_addr(1));
+    assert_eq!(({ info.start_timestamp }, { info.end_timestamp }), (1_000, 2_000));
+
+    let (_, info) = find_subscription_page(&mut decoder.flash, covering(3_000)).unwrap();
+    assert_eq!({ info.channel_id }, 3);
+    assert!(find_subscription_page(&mut decoder.flash, covering(300)).is_none());
+}
@@ -0,0 +1,28 @@
+//! The Version command reports the commit the firmware was built from, so a running
+//! decoder can be tied to an exact source revision.
+use decoder::modules::hostcom_manager::{HostConsole, MsgType, MSG_MAGIC};
+use decoder::FIRMWARE_COMMIT;
+use decoder_host_tests::MockUart;
+
+#[test]
+fn version_response_carries_the_commit() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(2));
+    assert_eq!(console.write_version(), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent[..2], [MSG_MAGIC, MsgType::Version as u8]);
+    assert_eq!(u16::from_le_bytes([sent[2], sent[3]]) as usize, FIRMWARE_COMMIT.len());
+    assert_eq!(&sent[4..], FIRMWARE_COMMIT.as_bytes());
+}
+
+#[test]
+fn commit_is_a_hash_or_unknown() {
+    // FIRMWARE_COMMIT set in the environment is taken as given
+    if option_env!("FIRMWARE_COMMIT").is_some() {
+        return;
+    }
+    let is_hash = FIRMWARE_COMMIT.len() == 40 && FIRMWARE_COMMIT.bytes().all(|b| b.is_ascii_hexdigit());
+    assert!(is_hash || FIRMWARE_COMMIT == "unknown", "{}", FIRMWARE_COMMIT);
+}
@@ -0,0 +1,42 @@
+//! Every page the decoder erases comes from the shared layout constants: each is page
+//! aligned, inside the flash, and the top of the data pages stays within RESERVED.
+use decoder::hal::flc::{FLASH_BASE, FLASH_SIZE};
+use decoder::modules::constants::{
+    subscription_page_addr, BASE_ADDRESS, EMERGENCY_ADDRESS, FLASH_DATA_END, PAGE_SIZE, RESERVED_END, RESERVED_START,
+    SCRATCH_ADDRESS, STATE_BASE_ADDRESS, STATE_PAGES, TAMPER_ADDRESS,
+};
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::Decoder;
+use std::collections::HashSet;
+
+/// Every page holding persistent data.
+fn data_pages() -> Vec<u32> {
+    let mut pages: Vec<u32> = (0..MAX_CHANNELS).map(subscription_page_addr).collect();
+    pages.extend((0..STATE_PAGES).map(|page| STATE_BASE_ADDRESS + page * PAGE_SIZE));
+    pages.extend([TAMPER_ADDRESS, EMERGENCY_ADDRESS, SCRATCH_ADDRESS]);
+    pages
+}
+
+#[test]
+fn top_of_region_is_inside_the_flash() {
+    assert!(BASE_ADDRESS >= RESERVED_START);
+    assert!(FLASH_DATA_END <= RESERVED_END);
+    assert!(FLASH_DATA_END <= FLASH_BASE + FLASH_SIZE);
+    assert_eq!(SCRATCH_ADDRESS + PAGE_SIZE, FLASH_DATA_END);
+}
+
+#[test]
+fn data_pages_are_distinct_aligned_pages_in_flash() {
+    let pages = data_pages();
+    assert_eq!(pages.iter().collect::<HashSet<_>>().len(), pages.len());
+    for &page in &pages {
+        assert!(page.is_multiple_of(PAGE_SIZE), "{page:#x} is not page aligned");
+        assert!(page >= FLASH_BASE && page + PAGE_SIZE <= FLASH_BASE + FLASH_SIZE, "{page:#x} is outside the flash");
+    }
+
+    // The RAM flash refuses any address outside the MAX78000's, so each page erases
+    let mut decoder = Decoder::new();
+    for page in pages {
+        decoder.flash.wipe_data(page).unwrap();
+    }
+}
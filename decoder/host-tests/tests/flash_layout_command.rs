@@ -0,0 +1,34 @@
+//! The FlashLayout response carries this build's flash parameters, enough for a host to
+//! work out where every subscription page is.
+use decoder::modules::constants::{subscription_page_addr, BASE_ADDRESS, PAGE_SIZE};
+use decoder::modules::hostcom_manager::{HostConsole, MsgType, MSG_MAGIC};
+use decoder::modules::wire::read_u32_le;
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::MockUart;
+
+#[test]
+fn response_matches_the_layout_constants() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(2));
+    assert_eq!(console.write_flash_layout(), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent[..4], [MSG_MAGIC, MsgType::FlashLayout as u8, 20, 0]);
+    let body = &sent[4..];
+    assert_eq!(body.len(), 20);
+    let [base, page_size, capacity, primary, secondary_base] = [0, 4, 8, 12, 16].map(|offset| read_u32_le(body, offset));
+    assert_eq!(base, BASE_ADDRESS);
+    assert_eq!(page_size, PAGE_SIZE);
+    assert_eq!(capacity as usize, MAX_CHANNELS);
+
+    // The host's view of each page is where the decoder keeps it
+    for page in 0..capacity {
+        let addr = if page < primary {
+            base + page * page_size
+        } else {
+            secondary_base + (page - primary) * page_size
+        };
+        assert_eq!(addr, subscription_page_addr(page as usize));
+    }
+}
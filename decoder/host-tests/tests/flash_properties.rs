@@ -0,0 +1,119 @@
+//! Randomized properties of FlashManager records over the RAM flash: what `write_data`
+//! stores reads back exactly, with zero padding to the 16-byte write size and nothing
+//! outside the record touched, a written word cannot be written again before an erase,
+//! a wiped record no longer reads as one, and a record address off the 16-byte write
+//! size is refused without writing.
+use bytemuck::Pod;
+use decoder::modules::constants::{BASE_ADDRESS, PAGE_SIZE, SUBSCRIPTION_MAGIC};
+use decoder::modules::crc::Crc32;
+use decoder::modules::flash_manager::{FlashManager, FlashManagerError, Flc};
+use decoder::{FlashError, MAX_CHANNELS};
+use decoder_host_tests::Rng;
+
+/// Cases per record size.
+const CASES: usize = 40;
+
+/// Bytes of flash from `addr`, read the way the controller allows.
+fn raw(flc: &Flc, addr: u32, len: usize) -> Vec<u8> {
+    (0..len.div_ceil(16))
+        .flat_map(|i| bytemuck::cast::<[u32; 4], [u8; 16]>(flc.read_128(addr + i as u32 * 16).unwrap()))
+        .take(len)
+        .collect()
+}
+
+/// Random record of type `T` written at a random 16-byte aligned address where it fits
+/// in one subscription page, checked against every property.
+fn check_records<T: Pod + PartialEq + std::fmt::Debug>(rng: &mut Rng) {
+    let record_len = 4 + size_of::<T>() + 4;
+    assert!(record_len <= PAGE_SIZE as usize, "record larger than a page");
+    for _ in 0..CASES {
+        let flc = Flc::new();
+        let mut flash = FlashManager::new(flc.clone(), Crc32::new());
+
+        let page = BASE_ADDRESS + rng.below(MAX_CHANNELS as u64) as u32 * PAGE_SIZE;
+        let slots = (PAGE_SIZE as usize - record_len) / 16;
+        let addr = page + 16 * rng.below(slots as u64 + 1) as u32;
+        let mut data = T::zeroed();
+        rng.fill(bytemuck::bytes_of_mut(&mut data));
+        let magic = rng.next_u64() as u32;
+
+        flash.write_data(addr, magic, &data).unwrap();
+        assert_eq!(flash.read_magic(addr).unwrap(), magic);
+        assert_eq!(flash.read_data::<T>(addr).unwrap(), data);
+        assert_eq!(flash.read_data_verified::<T>(addr).unwrap(), data);
+
+        // Padded with zeros to the chunk boundary, erased before and after
+        let padded = record_len.div_ceil(16) * 16;
+        let page_bytes = raw(&flc, page, PAGE_SIZE as usize);
+        let offset = (addr - page) as usize;
+        assert!(page_bytes[..offset].iter().all(|b| *b == 0xFF), "bytes before the record written");
+        assert!(page_bytes[offset + record_len..offset + padded].iter().all(|b| *b == 0), "padding not zero");
+        assert!(page_bytes[offset + padded..].iter().all(|b| *b == 0xFF), "bytes after the record written");
+
+        // Any rewrite needs an erase first, even one that only clears bits, which
+        // wipe_data provides
+        for rewrite in [magic | 1, magic & !1] {
+            assert!(matches!(
+                flash.write_data(addr, rewrite, &data),
+                Err(FlashManagerError::FlashError(FlashError::NeedsErase))
+            ));
+        }
+        flash.wipe_data(addr).unwrap();
+        assert_ne!(flash.read_magic(addr).unwrap(), SUBSCRIPTION_MAGIC);
+        flash.write_data(addr, SUBSCRIPTION_MAGIC, &data).unwrap();
+        assert_eq!(flash.read_magic(addr).unwrap(), SUBSCRIPTION_MAGIC);
+        flash.wipe_data(addr).unwrap();
+        assert_ne!(flash.read_magic(addr).unwrap(), SUBSCRIPTION_MAGIC);
+        assert!(raw(&flc, page, PAGE_SIZE as usize).iter().all(|b| *b == 0xFF), "wipe left data");
+        assert_eq!(flc.write_count(), 2 * record_len.div_ceil(16) as u32);
+    }
+}
+
+macro_rules! sizes {
+    ($rng:expr; $($t:ty),*) => {
+        $(check_records::<$t>($rng);)*
+    };
+}
+
+#[test]
+fn byte_records_round_trip() {
+    // Lengths around the 16-byte chunk boundaries, given the 4-byte magic and CRC
+    let mut rng = Rng::new(0x1824);
+    sizes!(&mut rng; [u8; 1], [u8; 7], [u8; 8], [u8; 9], [u8; 23], [u8; 24], [u8; 25], [u8; 100], [u8; 1000], [u8; 4088]);
+}
+
+#[test]
+fn word_records_round_trip() {
+    let mut rng = Rng::new(0x1824_0002);
+    sizes!(&mut rng; [u32; 1], [u32; 2], [u32; 3], [u64; 5], [u128; 7], [u32; 1022]);
+}
+
+#[test]
+fn wiped_subscription_page_is_not_a_subscription() {
+    let mut rng = Rng::new(0x1824_0003);
+    let mut flash = FlashManager::new(Flc::new(), Crc32::new());
+    for _ in 0..CASES {
+        let addr = BASE_ADDRESS + rng.below(MAX_CHANNELS as u64) as u32 * PAGE_SIZE;
+        let mut data = [0u8; 64];
+        rng.fill(&mut data);
+        flash.wipe_data(addr).unwrap();
+        flash.write_data(addr, SUBSCRIPTION_MAGIC, &data).unwrap();
+        flash.wipe_data(addr + rng.below(PAGE_SIZE as u64) as u32).unwrap();
+        assert_ne!(flash.read_magic(addr).unwrap(), SUBSCRIPTION_MAGIC);
+        assert!(matches!(flash.read_data_verified::<[u8; 64]>(addr), Err(FlashManagerError::CrcMismatch)));
+    }
+}
+
+#[test]
+fn misaligned_record_is_refused_untouched() {
+    let mut rng = Rng::new(0x1895);
+    let flc = Flc::new();
+    let mut flash = FlashManager::new(flc.clone(), Crc32::new());
+    for _ in 0..CASES {
+        let page = BASE_ADDRESS + rng.below(MAX_CHANNELS as u64) as u32 * PAGE_SIZE;
+        let addr = page + 16 * rng.below(64) as u32 + 1 + rng.below(15) as u32;
+        assert!(matches!(flash.write_data(addr, SUBSCRIPTION_MAGIC, &[0u8; 32]), Err(FlashManagerError::Misaligned)));
+        assert!(matches!(flash.write_raw(addr, &[0u8; 32]), Err(FlashManagerError::Misaligned)));
+    }
+    assert_eq!(flc.write_count(), 0);
+}
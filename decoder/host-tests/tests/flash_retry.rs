@@ -0,0 +1,65 @@
+//! A write or erase the controller refuses with AccessViolation is tried again, up to
+//! FLASH_OP_ATTEMPTS times in all, and goes through once the controller takes it. An
+//! invalid address or a write needing an erase fails every time, so it is not retried.
+use decoder::modules::constants::{BASE_ADDRESS, SUBSCRIPTION_MAGIC};
+use decoder::modules::crc::Crc32;
+use decoder::modules::flash_manager::{FlashManager, FlashManagerError, Flc, FLASH_OP_ATTEMPTS};
+use decoder::FlashError;
+
+const RECORD: [u8; 40] = [0x5A; 40];
+/// 128-bit writes of the magic, RECORD and CRC.
+const RECORD_WRITES: u32 = (4 + RECORD.len() as u32 + 4).div_ceil(16);
+
+fn flash() -> (Flc, FlashManager) {
+    let flc = Flc::new();
+    (flc.clone(), FlashManager::new(flc, Crc32::new()))
+}
+
+#[test]
+fn write_failing_once_completes() {
+    let (flc, mut flash) = flash();
+    flc.fail_next_ops(1);
+    flash.write_data(BASE_ADDRESS, SUBSCRIPTION_MAGIC, &RECORD).unwrap();
+    assert_eq!(flash.read_data_verified::<[u8; 40]>(BASE_ADDRESS).unwrap(), RECORD);
+    assert_eq!(flc.write_count(), RECORD_WRITES);
+    assert_eq!(flc.attempt_count(), RECORD_WRITES + 1);
+}
+
+#[test]
+fn erase_failing_once_completes() {
+    let (flc, mut flash) = flash();
+    flash.write_data(BASE_ADDRESS, SUBSCRIPTION_MAGIC, &RECORD).unwrap();
+    flc.fail_next_ops(1);
+    flash.wipe_data(BASE_ADDRESS).unwrap();
+    assert_eq!(flc.erase_count(), 1);
+    assert!(matches!(flash.read_magic(BASE_ADDRESS), Ok(u32::MAX)));
+}
+
+#[test]
+fn error_is_returned_after_the_last_attempt() {
+    // One fault short of the limit still completes
+    let (flc, mut flash) = flash();
+    flc.fail_next_ops(FLASH_OP_ATTEMPTS - 1);
+    flash.wipe_data(BASE_ADDRESS).unwrap();
+    assert_eq!(flc.attempt_count(), FLASH_OP_ATTEMPTS);
+
+    let (flc, mut flash) = flash();
+    flc.fail_next_ops(FLASH_OP_ATTEMPTS);
+    let result = flash.write_data(BASE_ADDRESS, SUBSCRIPTION_MAGIC, &RECORD);
+    assert!(matches!(result, Err(FlashManagerError::FlashError(FlashError::AccessViolation))));
+    assert_eq!(flc.attempt_count(), FLASH_OP_ATTEMPTS);
+    assert_eq!(flc.write_count(), 0);
+}
+
+#[test]
+fn permanent_errors_are_not_retried() {
+    let (flc, mut flash) = flash();
+    assert!(matches!(flash.wipe_data(0), Err(FlashManagerError::FlashError(FlashError::InvalidAddress))));
+    assert_eq!(flc.attempt_count(), 1);
+
+    flash.write_data(BASE_ADDRESS, SUBSCRIPTION_MAGIC, &RECORD).unwrap();
+    let attempts = flc.attempt_count();
+    let result = flash.write_data(BASE_ADDRESS, SUBSCRIPTION_MAGIC, &[0u8; 40]);
+    assert!(matches!(result, Err(FlashManagerError::FlashError(FlashError::NeedsErase))));
+    assert_eq!(flc.attempt_count(), attempts + 1);
+}
@@ -0,0 +1,37 @@
+//! A Decode body is turned into a frame without a cast that can panic: at any offset
+//! in the body buffer it parses to the frame that was sent, and a body of any other
+//! length is refused with InvalidFrameLength instead.
+use bytemuck::bytes_of;
+use decoder::modules::channel_manager::{validate_frame_length, ChannelFrame};
+use decoder::modules::hostcom_manager::{ErrorCode, MessageBody, MAX_BODY_LEN};
+use decoder_host_tests::frame;
+
+const FRAME_LEN: usize = size_of::<ChannelFrame>();
+
+#[test]
+fn frame_fits_the_body_buffer() {
+    assert!(FRAME_LEN <= size_of::<MessageBody>() && FRAME_LEN <= MAX_BODY_LEN);
+    // Packed, so any byte offset is as good as another
+    assert_eq!(align_of::<ChannelFrame>(), 1);
+}
+
+#[test]
+fn misaligned_body_parses() {
+    let encoded = frame(1, 1000);
+    let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: 0 };
+    for offset in 0..8 {
+        body.data[offset..offset + FRAME_LEN].copy_from_slice(&encoded);
+        let parsed = ChannelFrame::from_le_bytes(&body.data[offset..offset + FRAME_LEN]).unwrap();
+        assert_eq!(bytes_of(&parsed), &encoded[..], "offset {}", offset);
+    }
+}
+
+#[test]
+fn body_of_another_length_is_refused() {
+    let body = [0x5A; MAX_BODY_LEN];
+    for length in [0, 1, FRAME_LEN - 1, FRAME_LEN + 1, MAX_BODY_LEN] {
+        assert!(ChannelFrame::from_le_bytes(&body[..length]).is_none(), "{} bytes", length);
+        assert_eq!(validate_frame_length(length as u16), Err(ErrorCode::InvalidFrameLength));
+    }
+    assert_eq!(validate_frame_length(u16::MAX), Err(ErrorCode::InvalidFrameLength));
+}
@@ -0,0 +1,35 @@
+//! Every frame size is derived from FRAME_CONTENT_LEN: the wire frame, the length check,
+//! the encoder's output and the decoded content, so changing the constant moves them
+//! all together.
+use decoder::modules::channel_manager::{
+    validate_frame_length, ChannelFrame, FRAME_CONTENT_LEN, FRAME_MARKER_LEN, FRAME_SIGNED_LEN, NONCE_LEN,
+};
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+use std::mem::size_of;
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+const FRAME_LEN: usize = 4 + 8 + NONCE_LEN + FRAME_CONTENT_LEN + FRAME_MARKER_LEN + 64;
+
+#[test]
+fn frame_sizes_follow_the_content_length() {
+    assert_eq!(size_of::<ChannelFrame>(), FRAME_LEN);
+    assert_eq!(FRAME_SIGNED_LEN, FRAME_LEN - 64);
+    assert_eq!(frame(CHANNEL, T).len(), FRAME_LEN);
+
+    assert_eq!(validate_frame_length(FRAME_LEN as u16), Ok(()));
+    for length in [FRAME_LEN - 1, FRAME_LEN + 1] {
+        assert_eq!(validate_frame_length(length as u16), Err(ErrorCode::InvalidFrameLength));
+    }
+    assert!(ChannelFrame::from_le_bytes(&frame(CHANNEL, T)[1..]).is_none());
+}
+
+#[test]
+fn decoded_content_is_the_whole_payload() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    let content = decoder.decode(&frame(CHANNEL, T)).unwrap();
+    assert_eq!(content.len(), FRAME_CONTENT_LEN);
+    assert_eq!(content, frame_content(T));
+}
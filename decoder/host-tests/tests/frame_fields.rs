@@ -0,0 +1,27 @@
+//! `ChannelFrame::from_le_bytes` reads every field from its own bytes, little-endian,
+//! so a frame at an odd offset in a buffer parses to the values it was encoded with.
+use decoder::modules::channel_manager::{ChannelFrame, FRAME_CONTENT_LEN};
+use decoder::modules::test_vectors::encode_frame;
+use decoder_host_tests::{channel_root, frame_content, host_key};
+
+const CHANNEL: u32 = 3;
+const T: u64 = 0x0102_0304_0506_0708;
+const NONCE: [u8; 12] = [0xC3; 12];
+
+#[test]
+fn fields_parse_at_any_offset() {
+    let encoded = encode_frame(&host_key(), &channel_root(CHANNEL), CHANNEL, T, &frame_content(T), NONCE);
+    let len = encoded.len();
+    for offset in [1, 3, 5, 7] {
+        let mut buffer = vec![0xEE; offset];
+        buffer.extend_from_slice(&encoded);
+        let frame = ChannelFrame::from_le_bytes(&buffer[offset..]).unwrap();
+
+        assert_eq!({ frame.channel }, CHANNEL);
+        assert_eq!({ frame.timestamp }, T);
+        assert_eq!(frame.nonce, NONCE);
+        assert_eq!(frame.encrypted_content[..], encoded[24..24 + FRAME_CONTENT_LEN]);
+        assert_eq!(frame.encrypted_marker[..], encoded[24 + FRAME_CONTENT_LEN..len - 64]);
+        assert_eq!(frame.signature[..], encoded[len - 64..]);
+    }
+}
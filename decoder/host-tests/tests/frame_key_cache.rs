@@ -0,0 +1,52 @@
+//! Keys derived from a warm `FrameKeyCache` match cold derivations, across channels and
+//! past eviction, and the cache never reaches past a subscription's own cover.
+use bytemuck::Zeroable;
+use decoder::modules::channel_manager::{
+    derive_frame_key, ChannelPassword, ChannelSubscription, FrameKeyCache, SubscriptionError,
+};
+use decoder::modules::test_vectors::{leaf_node, node_key};
+use decoder_host_tests::Rng;
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// A subscription to `channel` holding the single password of `node_num` under `root`.
+fn holding(channel: u32, root: &[u8; 16], node_num: u128) -> ChannelSubscription {
+    let mut subscription = ChannelSubscription::zeroed();
+    subscription.info.channel_id = channel;
+    subscription.passwords.contents[0] = ChannelPassword {
+        node_trunc: (node_num >> 1) as u64,
+        node_ext: (node_num & 1) as u8 + 1,
+        password: node_key(root, node_num),
+    };
+    subscription
+}
+
+#[test]
+fn warm_keys_match_cold_keys() {
+    let roots = [[0x11; 16], [0x22; 16]];
+    let subscriptions = [holding(1, &roots[0], 1), holding(2, &roots[1], 1)];
+    let mut cache = FrameKeyCache::new();
+    let mut rng = Rng::new(1861);
+    // Nearby timestamps share most of their path, far ones evict it
+    for i in 0..200u64 {
+        let channel = (i % 2) as usize;
+        let timestamp = if i % 7 == 0 { rng.next_u64() } else { T + i * 3 + rng.below(1 << 12) };
+        let warm = derive_frame_key(&subscriptions[channel], timestamp, &mut cache).unwrap();
+        let cold = derive_frame_key(&subscriptions[channel], timestamp, &mut FrameKeyCache::new()).unwrap();
+        assert_eq!(warm, cold, "timestamp {} on channel {}", timestamp, channel + 1);
+        assert_eq!(warm, node_key(&roots[channel], leaf_node(timestamp)));
+    }
+}
+
+#[test]
+fn warm_cache_does_not_widen_a_subscription() {
+    let root = [0x33; 16];
+    let mut cache = FrameKeyCache::new();
+    derive_frame_key(&holding(1, &root, 1), T, &mut cache).unwrap();
+
+    // The same channel now only holds T's depth-40 ancestor
+    let narrow = holding(1, &root, leaf_node(T) >> 24);
+    assert_eq!(derive_frame_key(&narrow, T + 1, &mut cache).unwrap(), node_key(&root, leaf_node(T + 1)));
+    let outside = T ^ (1 << 30);
+    assert!(matches!(derive_frame_key(&narrow, outside, &mut cache), Err(SubscriptionError::PasswordNotFound)));
+}
@@ -0,0 +1,32 @@
+//! A frame parsed once from the body bytes, at any offset, is the frame that was
+//! encoded and decodes as the Decode command decodes it.
+use bytemuck::bytes_of;
+use decoder::modules::channel_manager::{decode_frame, ChannelFrame};
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn single_parse_decodes() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+
+    // Off by one byte, as a body buffer gives no alignment guarantee
+    let encoded = frame(CHANNEL, T);
+    let mut body = vec![0u8; 1];
+    body.extend_from_slice(&encoded);
+    let parsed = ChannelFrame::from_le_bytes(&body[1..]).unwrap();
+    assert_eq!(bytes_of(&parsed), &encoded[..]);
+
+    let content = decode_frame(
+        &mut decoder.flash,
+        parsed,
+        &mut decoder.channels,
+        &mut decoder.context,
+        #[cfg(feature = "rtc-time")]
+        &decoder.clock,
+    )
+    .unwrap();
+    assert_eq!(content, frame_content(T));
+}
@@ -0,0 +1,74 @@
+//! Decoded frames are handed to the configured sink; only the host sink sends them
+//! back in the Decode response.
+use decoder::modules::channel_manager::FRAME_CONTENT_LEN;
+use decoder::modules::frame_sink::{route_frame, FrameSink, HostSink, SinkError};
+use decoder::modules::hostcom_manager::{ErrorCode, HostConsole, MsgType, MSG_MAGIC};
+use decoder_host_tests::{frame, frame_content, subscription, Decoder, MockUart};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// A downstream peripheral that keeps every frame it is given, or refuses them.
+#[derive(Default)]
+struct CaptureSink {
+    frames: Vec<[u8; FRAME_CONTENT_LEN]>,
+    busy: bool,
+}
+
+impl FrameSink for CaptureSink {
+    fn write_frame(&mut self, frame: &[u8; FRAME_CONTENT_LEN]) -> Result<(), SinkError> {
+        if self.busy {
+            return Err(SinkError::Busy);
+        }
+        self.frames.push(*frame);
+        Ok(())
+    }
+}
+
+#[test]
+fn downstream_sink_receives_decoded_frames() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    let mut sink = CaptureSink::default();
+
+    for (channel, timestamp) in [(0, T), (1, T), (1, T + 1)] {
+        let content = decoder.decode(&frame(channel, timestamp)).unwrap();
+        // The host only gets an empty Decode response
+        assert_eq!(route_frame(&mut sink, &content).unwrap(), None);
+    }
+    assert_eq!(sink.frames, [frame_content(T), frame_content(T), frame_content(T + 1)]);
+}
+
+#[test]
+fn host_sink_returns_the_frame() {
+    let mut decoder = Decoder::new();
+    let content = decoder.decode(&frame(0, T)).unwrap();
+    assert_eq!(route_frame(&mut HostSink, &content).unwrap(), Some(&frame_content(T)[..]));
+}
+
+#[test]
+fn decode_response_length_is_the_frame_length() {
+    let mut decoder = Decoder::new();
+    let content = decoder.decode(&frame(0, T)).unwrap();
+    let response = route_frame(&mut HostSink, &content).unwrap().unwrap();
+
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    // The host ACKs the header and the one body chunk
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(2));
+    assert_eq!(console.write_packet(MsgType::Decode, Some(response)), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent[..2], [MSG_MAGIC, MsgType::Decode as u8]);
+    assert_eq!(u16::from_le_bytes([sent[2], sent[3]]) as usize, content.len());
+    assert_eq!(sent[4..], content);
+}
+
+#[test]
+fn refused_frame_is_reported() {
+    let mut decoder = Decoder::new();
+    let content = decoder.decode(&frame(0, T)).unwrap();
+    let mut sink = CaptureSink { busy: true, ..Default::default() };
+    let err = route_frame(&mut sink, &content).unwrap_err();
+    assert_eq!(err.error_code(), ErrorCode::SinkFailure);
+    assert!(sink.frames.is_empty());
+}
@@ -0,0 +1,35 @@
+//! The framing fuzz target's checks over its seed corpus and random streams, so they
+//! run with the other tests; `cargo fuzz run framing` in `decoder/fuzz` explores further.
+use decoder::modules::hostcom_manager::MSG_MAGIC;
+use decoder_host_tests::framing::check_stream;
+use decoder_host_tests::Rng;
+use std::fs;
+
+#[test]
+fn seed_corpus() {
+    let mut seeds = 0;
+    for entry in fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../fuzz/corpus/framing")).unwrap() {
+        check_stream(&fs::read(entry.unwrap().path()).unwrap());
+        seeds += 1;
+    }
+    assert!(seeds > 0, "empty seed corpus");
+}
+
+#[test]
+fn random_streams() {
+    let mut rng = Rng::new(0x1823);
+    for _ in 0..2000 {
+        let mut stream = vec![0u8; rng.below(600) as usize];
+        rng.fill(&mut stream);
+        // Plant headers, some with lengths around the body limit, to reach the body paths
+        for _ in 0..rng.below(4) {
+            if stream.len() >= 4 {
+                let at = rng.below(stream.len() as u64 - 3) as usize;
+                let length = [0, 1, 156, 4095, 4096, 4097, u16::MAX][rng.below(7) as usize];
+                stream[at] = MSG_MAGIC;
+                stream[at + 2..at + 4].copy_from_slice(&u16::to_le_bytes(length));
+            }
+        }
+        check_stream(&stream);
+    }
+}
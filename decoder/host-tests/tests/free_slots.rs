@@ -0,0 +1,48 @@
+//! FreeSlots counts the subscription pages a new channel could still be stored in, as
+//! `write_subscription` would pick them: one fewer for each channel stored, none for a
+//! resubscription, and none left exactly when Subscribe runs out of pages.
+use decoder::modules::channel_manager::{find_subscription_page, free_subscription_pages, read_subscription, SubscriptionError};
+use decoder::modules::constants::SUBSCRIPTION_MAGIC;
+use decoder::modules::test_vectors::encode_subscription;
+use decoder::{DECODER_ID, DECODER_KEY, MAX_CHANNELS};
+use decoder_host_tests::{host_key, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// Subscription to a channel outside test.secrets; the decoder only needs its passwords.
+fn subscription(channel: u32, end: u64) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &[channel as u8; 16], channel, 0, end, [0x5A; 12])
+}
+
+#[test]
+fn count_falls_with_each_new_channel() {
+    let mut decoder = Decoder::new();
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32);
+    for n in 1..=MAX_CHANNELS as u32 {
+        decoder.subscribe(&subscription(n * 10, T)).unwrap();
+        assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32 - n);
+
+        // Resubscribing a stored channel reuses its page
+        decoder.subscribe(&subscription(n * 10, T + 1)).unwrap();
+        assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32 - n);
+    }
+
+    // Zero free exactly when a new channel is refused
+    assert!(matches!(decoder.subscribe(&subscription(999, T)), Err(SubscriptionError::NoPageFound)));
+    let mut decoder = decoder.reboot();
+    assert_eq!(free_subscription_pages(&mut decoder.flash), 0);
+}
+
+#[test]
+fn uncommitted_page_counts_as_free() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(10, T)).unwrap();
+    decoder.subscribe(&subscription(20, T)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 20).unwrap();
+    let stored = read_subscription(&mut decoder.flash, addr).unwrap();
+
+    // As a write caught before its commit leaves it, the page is Subscribe's to take
+    decoder.flash.wipe_data(addr).unwrap();
+    decoder.flash.stage_data(addr, SUBSCRIPTION_MAGIC, &stored).unwrap();
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32 - 1);
+}
@@ -0,0 +1,59 @@
+//! A whole command exchange through `HostConsole` over the mock UART: the header, its
+//! ACK, a two-chunk body and a response, byte for byte the exchange the free
+//! functions it wraps produce.
+use decoder::modules::hostcom_manager::{
+    read_body, read_header, write_ack, write_packet, HostConsole, MessageBody, MessageHeader, MsgType, MAX_BODY_LEN,
+    MSG_MAGIC,
+};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+const LENGTH: u16 = 300;
+
+/// The host's side: a Decode command, then the ACKs of the response header and chunk.
+fn host_input(uart: &MockUart) -> Vec<u8> {
+    let body: Vec<u8> = (0..LENGTH).map(|i| i as u8).collect();
+    uart.queue(bytemuck::bytes_of(&MessageHeader::new(MsgType::Decode, LENGTH)));
+    uart.queue(&body);
+    uart.queue(&ACK.repeat(2));
+    body
+}
+
+fn empty_body() -> MessageBody {
+    MessageBody { data: [0; MAX_BODY_LEN], length: 0 }
+}
+
+#[test]
+fn console_drives_a_command_exchange() {
+    let uart = MockUart::default();
+    let sent_body = host_input(&uart);
+    let mut console = HostConsole::new(uart.clone());
+
+    let hdr = console.read_header();
+    assert_eq!((hdr.opcode, { hdr.length }), (MsgType::Decode as u8, LENGTH));
+    assert_eq!(console.write_ack(), 0);
+    let mut body = empty_body();
+    assert_eq!(console.read_body(hdr.length, &mut body), Ok(LENGTH));
+    assert_eq!(body.data[..LENGTH as usize], sent_body[..]);
+    assert_eq!(console.write_packet(MsgType::Decode, Some(&body.data[..16])), 0);
+    assert_eq!(uart.pending(), 0);
+
+    // The header ACK, one per body chunk, then the response
+    let sent = uart.take_sent();
+    assert_eq!(sent[..12], ACK.repeat(3));
+    assert_eq!(sent[12..16], [MSG_MAGIC, MsgType::Decode as u8, 16, 0]);
+    assert_eq!(sent[16..], sent_body[..16]);
+
+    // The free functions make the same exchange
+    let mut raw = MockUart::default();
+    host_input(&raw);
+    let hdr = read_header(&mut raw);
+    write_ack(&mut raw);
+    let mut raw_body = empty_body();
+    read_body(&mut raw, hdr.length, &mut raw_body);
+    write_packet(&mut raw, MsgType::Decode, Some(&raw_body.data[..16]));
+    assert_eq!(raw.take_sent(), sent);
+
+    // The UART can be taken back from the console
+    assert_eq!(console.release().pending(), 0);
+}
@@ -0,0 +1,64 @@
+//! Subscriptions, their preambles and frames signed by a fallback host key verify like
+//! the primary's, and a key that is not embedded is still refused.
+use decoder::modules::channel_manager::{check_subscribe_preamble, SubscriptionError};
+use decoder::modules::test_vectors::{encode_frame, encode_subscribe_preamble, encode_subscription};
+use decoder::{DECODER_ID, DECODER_KEY, HOST_KEYS_PUB, HOST_KEY_PUB};
+use decoder_host_tests::{channel_root, frame_content, hex_field, host_key, test_secrets, Decoder};
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::SigningKey;
+
+const CHANNEL: u32 = 2;
+const T: u64 = 1_700_000_000_000_000;
+
+/// The second key of `test.secrets`, embedded after the primary.
+fn fallback_key() -> SigningKey {
+    SigningKey::from_pkcs8_der(&hex_field(&test_secrets(), "host_key_fallback_priv")).unwrap()
+}
+
+fn subscription_by(key: &SigningKey) -> Vec<u8> {
+    encode_subscription(key, &DECODER_KEY, DECODER_ID, &channel_root(CHANNEL), CHANNEL, 0, u64::MAX, [0x5A; 12])
+}
+
+fn frame_by(key: &SigningKey, channel: u32, timestamp: u64) -> Vec<u8> {
+    encode_frame(key, &channel_root(channel), channel, timestamp, &frame_content(timestamp), [0xA5; 12])
+}
+
+#[test]
+fn primary_is_embedded_first() {
+    assert_eq!(HOST_KEYS_PUB.len(), 2);
+    assert_eq!(HOST_KEYS_PUB[0], HOST_KEY_PUB);
+}
+
+#[test]
+fn fallback_key_signs_subscriptions_and_frames() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription_by(&fallback_key())).unwrap();
+    assert_eq!(decoder.decode(&frame_by(&fallback_key(), CHANNEL, T)).unwrap(), frame_content(T));
+    // Either key's frames decode on the one subscription
+    assert_eq!(decoder.decode(&frame_by(&host_key(), CHANNEL, T + 1)).unwrap(), frame_content(T + 1));
+    decoder.decode(&frame_by(&fallback_key(), 0, T)).unwrap();
+}
+
+#[test]
+fn unknown_key_is_refused() {
+    let stranger = SigningKey::from_bytes(&[0x42; 32]);
+    let mut decoder = Decoder::new();
+    assert!(matches!(decoder.subscribe(&subscription_by(&stranger)), Err(SubscriptionError::InvalidSignature)));
+    assert!(matches!(decoder.decode(&frame_by(&stranger, 0, T)), Err(SubscriptionError::InvalidSignature)));
+}
+
+#[test]
+fn fallback_key_signs_the_preamble() {
+    let mut decoder = Decoder::new();
+    let body = subscription_by(&fallback_key());
+    let length = body.len();
+    #[cfg(feature = "subscribe-checksum")]
+    let length = length + decoder::modules::channel_manager::SUBSCRIPTION_CHECKSUM_LEN;
+    let preamble = encode_subscribe_preamble(&fallback_key(), DECODER_ID, CHANNEL, length as u16);
+    check_subscribe_preamble(&mut decoder.flash, &preamble).unwrap();
+    decoder.subscribe(&body).unwrap();
+
+    let stranger = SigningKey::from_bytes(&[0x42; 32]);
+    let preamble = encode_subscribe_preamble(&stranger, DECODER_ID, CHANNEL, length as u16);
+    assert!(matches!(check_subscribe_preamble(&mut decoder.flash, &preamble), Err(SubscriptionError::InvalidSignature)));
+}
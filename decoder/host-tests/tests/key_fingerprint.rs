@@ -0,0 +1,43 @@
+//! The KeyFingerprint command names every host key the decoder trusts: the primary
+//! key and each fallback, in order.
+use decoder::modules::channel_manager::{host_key_fingerprint, host_keys_fingerprint, HOST_KEY_FINGERPRINT_LEN};
+use decoder::modules::hostcom_manager::MsgType;
+use decoder::{HOST_KEYS_PUB, HOST_KEY_PUB};
+use decoder_host_tests::{host_key, test_secrets};
+use ed25519_dalek::pkcs8::DecodePublicKey;
+use ed25519_dalek::VerifyingKey;
+use sha2::{Digest, Sha256};
+
+#[test]
+fn fingerprint_is_the_truncated_hash_of_every_embedded_key() {
+    let mut hashed = Vec::new();
+    for der in HOST_KEYS_PUB {
+        hashed.extend_from_slice(&(der.len() as u32).to_le_bytes());
+        hashed.extend_from_slice(der);
+    }
+    let digest = Sha256::digest(&hashed);
+    assert_eq!(host_key_fingerprint(), digest[..HOST_KEY_FINGERPRINT_LEN]);
+
+    // ...the primary first, which is the key the test signer's messages verify under
+    assert_eq!(HOST_KEYS_PUB[0], HOST_KEY_PUB);
+    let embedded = VerifyingKey::from_public_key_der(HOST_KEY_PUB).unwrap();
+    assert_eq!(embedded, host_key().verifying_key());
+}
+
+#[test]
+fn fallback_keys_change_the_fingerprint() {
+    let fallback = hex::decode(test_secrets()["host_key_pub_fallbacks"][0].as_str().unwrap()).unwrap();
+    assert_eq!(HOST_KEYS_PUB, [HOST_KEY_PUB, &fallback[..]]);
+
+    let primary_only = host_keys_fingerprint(&[HOST_KEY_PUB]);
+    assert_ne!(host_key_fingerprint(), primary_only);
+    assert_ne!(host_key_fingerprint(), host_keys_fingerprint(&[&fallback[..], HOST_KEY_PUB]));
+    // Length prefixes keep a split key apart from the whole one
+    let (head, tail) = HOST_KEY_PUB.split_at(10);
+    assert_ne!(primary_only, host_keys_fingerprint(&[head, tail]));
+}
+
+#[test]
+fn opcode_round_trips() {
+    assert_eq!(MsgType::try_from(b'Z'), Ok(MsgType::KeyFingerprint));
+}
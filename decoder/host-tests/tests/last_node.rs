@@ -0,0 +1,47 @@
+//! Consecutive frames whose leaves share a parent take the second key from that
+//! parent, and get the same content as a cold derivation.
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+/// Even, so T and T + 1 are the two children of one node.
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn sibling_leaf_uses_the_cached_parent() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+    assert_eq!(decoder.context.last_node_hits(), 0);
+    let content = decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+    assert_eq!(decoder.context.last_node_hits(), 1);
+    assert_eq!(content, frame_content(T + 1));
+
+    // The cold derivation on a fresh decoder agrees
+    let mut cold = Decoder::new();
+    cold.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    assert_eq!(cold.decode(&frame(CHANNEL, T + 1)).unwrap(), content);
+    assert_eq!(cold.context.last_node_hits(), 0);
+
+    // T + 2 has another parent
+    decoder.decode(&frame(CHANNEL, T + 2)).unwrap();
+    assert_eq!(decoder.context.last_node_hits(), 1);
+}
+
+#[test]
+fn cached_parent_is_dropped_with_the_subscription_and_channel() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(2, 0, u64::MAX)).unwrap();
+
+    // A frame on another channel does not take channel 1's parent
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+    decoder.decode(&frame(2, T + 1)).unwrap();
+    assert_eq!(decoder.context.last_node_hits(), 0);
+
+    // Nor does one after the subscription is replaced
+    decoder.decode(&frame(CHANNEL, T + 2)).unwrap();
+    decoder.subscribe(&subscription(CHANNEL, 1, u64::MAX)).unwrap();
+    assert_eq!(decoder.decode(&frame(CHANNEL, T + 3)).unwrap(), frame_content(T + 3));
+    assert_eq!(decoder.context.last_node_hits(), 0);
+}
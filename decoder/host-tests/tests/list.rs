@@ -0,0 +1,64 @@
+//! The List command reports exactly the stored subscriptions, read through the same
+//! page iterator that stores them, with each one's window, in ascending channel order
+//! whatever pages they are stored in.
+use core::mem::size_of;
+use decoder::modules::hostcom_manager::{ChannelInfo, HostConsole, MsgType, MSG_MAGIC};
+use decoder_host_tests::{subscription, Decoder, MockUart};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// The (channel, start, end) of every entry of a List response, in response order.
+fn list(decoder: &mut Decoder) -> Vec<(u32, u64, u64)> {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(2));
+    assert_eq!(console.write_list(&mut decoder.flash), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent[..2], [MSG_MAGIC, MsgType::List as u8]);
+    let body = &sent[4..];
+    let count = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+    assert_eq!(body.len(), 4 + count * size_of::<ChannelInfo>());
+    body[4..]
+        .chunks_exact(size_of::<ChannelInfo>())
+        .map(bytemuck::pod_read_unaligned::<ChannelInfo>)
+        .map(|c| (c.channel_id, c.start_timestamp, c.end_timestamp))
+        .collect()
+}
+
+#[test]
+fn list_matches_what_was_stored() {
+    let mut decoder = Decoder::new();
+    assert!(list(&mut decoder).is_empty());
+
+    let mut stored = vec![(1, 0, T), (2, T, T + 10), (3, 5, u64::MAX)];
+    for &(channel, start, end) in &stored {
+        decoder.subscribe(&subscription(channel, start, end)).unwrap();
+    }
+    let mut listed = list(&mut decoder);
+    listed.sort();
+    assert_eq!(listed, stored);
+
+    // Replacing a subscription moves it to another page, and it is still listed once
+    decoder.subscribe(&subscription(2, T, T + 20)).unwrap();
+    stored[1] = (2, T, T + 20);
+    let mut listed = list(&mut decoder);
+    listed.sort();
+    assert_eq!(listed, stored);
+}
+
+#[test]
+fn list_is_sorted_by_channel() {
+    let mut decoder = Decoder::new();
+    for channel in [3, 1, 2] {
+        decoder.subscribe(&subscription(channel, 0, T)).unwrap();
+    }
+    let channels = |listed: Vec<(u32, u64, u64)>| listed.into_iter().map(|(channel, _, _)| channel).collect::<Vec<_>>();
+    assert_eq!(channels(list(&mut decoder)), [1, 2, 3]);
+
+    // Replaced, channel 1 moves to another page and still comes first
+    decoder.subscribe(&subscription(1, 0, T + 1)).unwrap();
+    assert_eq!(list(&mut decoder), [(1, 0, T + 1), (2, 0, T), (3, 0, T)]);
+    let mut decoder = decoder.reboot();
+    assert_eq!(channels(list(&mut decoder)), [1, 2, 3]);
+}
@@ -0,0 +1,42 @@
+//! The List body length is computed in full and checked against the u16 header field,
+//! so the header of a full List is its true length and never a truncated one.
+use core::mem::size_of;
+use decoder::modules::hostcom_manager::{ChannelInfo, HostConsole, MsgType, CHUNK_SIZE, LIST_MAX_LEN, MSG_MAGIC};
+use decoder::modules::test_vectors::encode_subscription;
+use decoder::{DECODER_ID, DECODER_KEY, MAX_CHANNELS};
+use decoder_host_tests::{host_key, Decoder, MockUart};
+
+/// The header length and body of a List response.
+fn list(decoder: &mut Decoder) -> (u16, Vec<u8>) {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(1 + LIST_MAX_LEN.div_ceil(CHUNK_SIZE)));
+    assert_eq!(console.write_list(&mut decoder.flash), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent[..2], [MSG_MAGIC, MsgType::List as u8]);
+    (u16::from_le_bytes([sent[2], sent[3]]), sent[4..].to_vec())
+}
+
+#[test]
+fn list_bound_follows_max_channels() {
+    assert_eq!(LIST_MAX_LEN, size_of::<u32>() + MAX_CHANNELS * size_of::<ChannelInfo>());
+    assert!(LIST_MAX_LEN <= u16::MAX as usize);
+}
+
+#[test]
+fn full_list_header_is_its_length() {
+    let mut decoder = Decoder::new();
+    let (length, body) = list(&mut decoder);
+    assert_eq!((length, body.len()), (4, 4));
+
+    for channel in 1..=MAX_CHANNELS as u32 {
+        let root = [channel as u8; 16];
+        let body = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root, channel, 0, u64::MAX, [0x5A; 12]);
+        decoder.subscribe(&body).unwrap();
+    }
+    let (length, body) = list(&mut decoder);
+    assert_eq!(length as usize, LIST_MAX_LEN);
+    assert_eq!(body.len(), LIST_MAX_LEN);
+    assert_eq!(u32::from_le_bytes(body[..4].try_into().unwrap()), MAX_CHANNELS as u32);
+}
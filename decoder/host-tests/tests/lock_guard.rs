@@ -0,0 +1,101 @@
+//! The dispatch guard refuses commands touching subscriptions while the tamper lock is
+//! set, and leaves status queries and Ping alone. Only a Recover signed for the current
+//! epoch clears the lock.
+use decoder::modules::hostcom_manager::{ErrorCode, MsgType};
+use decoder::modules::tamper_manager::{
+    can_accept_command, clear_tamper_flag, read_tamper_state, set_tamper_flag, TamperError,
+};
+use decoder::modules::test_vectors::encode_unlock;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, host_key, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+const GATED: [MsgType; 8] = [
+    MsgType::Subscribe,
+    MsgType::SubscribeBundle,
+    MsgType::SubscribePreamble,
+    MsgType::UploadChunk,
+    MsgType::Decode,
+    MsgType::VerifyProbe,
+    MsgType::Window,
+    MsgType::Pause,
+];
+const OPEN: [MsgType; 8] = [
+    MsgType::Ping,
+    MsgType::List,
+    MsgType::DecoderId,
+    MsgType::KeyFingerprint,
+    MsgType::Version,
+    MsgType::Telemetry,
+    MsgType::Tamper,
+    MsgType::Recover,
+];
+
+#[test]
+fn locked_decoder_refuses_subscribe_and_decode() {
+    let mut decoder = Decoder::new();
+    set_tamper_flag(&mut decoder.flash).unwrap();
+    // The lock is read back from flash, as at boot
+    let locked = read_tamper_state(&mut decoder.reboot().flash).is_locked();
+    assert!(locked);
+
+    for cmd in GATED {
+        assert_eq!(can_accept_command(cmd, locked), Err(ErrorCode::Locked), "{:?}", cmd);
+    }
+    for cmd in OPEN {
+        assert_eq!(can_accept_command(cmd, locked), Ok(()), "{:?}", cmd);
+    }
+}
+
+#[test]
+fn unlocked_decoder_accepts_everything() {
+    for cmd in GATED.into_iter().chain(OPEN) {
+        assert_eq!(can_accept_command(cmd, false), Ok(()));
+    }
+}
+
+/// Whether the dispatch guard lets a Decode through, with the lock as read at boot.
+fn decode_allowed(decoder: &mut Decoder) -> Result<(), ErrorCode> {
+    can_accept_command(MsgType::Decode, read_tamper_state(&mut decoder.flash).is_locked())
+}
+
+#[test]
+fn signed_recover_unlocks_decoding() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+
+    let epoch = set_tamper_flag(&mut decoder.flash).unwrap();
+    let mut decoder = decoder.reboot();
+    assert_eq!(decode_allowed(&mut decoder), Err(ErrorCode::Locked));
+
+    clear_tamper_flag(&mut decoder.flash, &encode_unlock(&host_key(), DECODER_ID, epoch)).unwrap();
+    let mut decoder = decoder.reboot();
+    assert_eq!(decode_allowed(&mut decoder), Ok(()));
+    decoder.decode(&frame(1, T + 1)).unwrap();
+
+    // The recovery was for that epoch only
+    set_tamper_flag(&mut decoder.flash).unwrap();
+    assert!(matches!(
+        clear_tamper_flag(&mut decoder.flash, &encode_unlock(&host_key(), DECODER_ID, epoch)),
+        Err(TamperError::InvalidSignature)
+    ));
+    assert_eq!(decode_allowed(&mut decoder), Err(ErrorCode::Locked));
+}
+
+#[test]
+fn badly_signed_recover_keeps_the_lock() {
+    let mut decoder = Decoder::new();
+    let epoch = set_tamper_flag(&mut decoder.flash).unwrap();
+
+    let mut signature = encode_unlock(&host_key(), DECODER_ID, epoch);
+    signature[0] ^= 1;
+    assert!(matches!(clear_tamper_flag(&mut decoder.flash, &signature), Err(TamperError::InvalidSignature)));
+    // Signed for another decoder
+    let other = encode_unlock(&host_key(), DECODER_ID ^ 1, epoch);
+    assert!(matches!(clear_tamper_flag(&mut decoder.flash, &other), Err(TamperError::InvalidSignature)));
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(decode_allowed(&mut decoder), Err(ErrorCode::Locked));
+}
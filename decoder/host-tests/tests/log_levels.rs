@@ -0,0 +1,83 @@
+//! Every Debug packet opens with its LogLevel byte, ahead of the text, so the host can
+//! filter. Trace is only sent with the `trace-log` feature; without it a Trace message
+//! is dropped before it is formatted. Run with --features trace-log for that build.
+#![cfg(feature = "debug-output")]
+use core::cell::Cell;
+use core::fmt;
+
+use decoder::modules::hostcom_manager::{write_log, HostConsole, LogLevel, MsgType, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+/// Counts how many times it is formatted.
+struct Counted<'a>(&'a Cell<u32>);
+
+impl fmt::Display for Counted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.set(self.0.get() + 1);
+        f.write_str("counted")
+    }
+}
+
+fn debug_packet(level: LogLevel, msg: &str) -> Vec<u8> {
+    let mut packet = vec![MSG_MAGIC, MsgType::Debug as u8, msg.len() as u8 + 1, 0, level as u8];
+    packet.extend_from_slice(msg.as_bytes());
+    packet
+}
+
+#[test]
+fn level_byte_leads_the_body() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    for level in [LogLevel::Info, LogLevel::Warn, LogLevel::Error] {
+        console.write_log(level, "message\n");
+        assert_eq!(uart.take_sent(), debug_packet(level, "message\n"));
+        console.write_log_fmt(level, format_args!("code {}\n", 7));
+        assert_eq!(uart.take_sent(), debug_packet(level, "code 7\n"));
+    }
+
+    // write_debug is Info
+    console.write_debug("debug\n");
+    assert_eq!(uart.take_sent(), debug_packet(LogLevel::Info, "debug\n"));
+    let mut raw = uart.clone();
+    write_log(&mut raw, LogLevel::Error, "error\n");
+    assert_eq!(uart.take_sent(), debug_packet(LogLevel::Error, "error\n"));
+}
+
+#[cfg(not(feature = "trace-log"))]
+#[test]
+fn trace_is_dropped_unformatted() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    let formatted = Cell::new(0);
+
+    console.write_log(LogLevel::Trace, "trace\n");
+    console.write_log_fmt(LogLevel::Trace, format_args!("{}\n", Counted(&formatted)));
+    assert!(uart.take_sent().is_empty());
+    assert_eq!(formatted.get(), 0);
+
+    // The levels above it are formatted and sent
+    console.write_log_fmt(LogLevel::Info, format_args!("{}\n", Counted(&formatted)));
+    assert_eq!(formatted.get(), 1);
+    assert_eq!(uart.take_sent(), debug_packet(LogLevel::Info, "counted\n"));
+}
+
+#[cfg(feature = "trace-log")]
+#[test]
+fn trace_is_sent_with_the_feature() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    console.write_log(LogLevel::Trace, "trace\n");
+    assert_eq!(uart.take_sent(), debug_packet(LogLevel::Trace, "trace\n"));
+}
+
+#[test]
+fn debug_sink_gets_text_without_the_level() {
+    let uart = MockUart::default();
+    let sink = MockUart::default();
+    let mut console = HostConsole::new(uart.clone()).with_debug_sink(sink.clone());
+    console.write_log(LogLevel::Error, "error\n");
+    console.write_log(LogLevel::Trace, "trace\n");
+    assert!(uart.take_sent().is_empty());
+    let expected: &[u8] = if cfg!(feature = "trace-log") { b"error\ntrace\n" } else { b"error\n" };
+    assert_eq!(sink.take_sent(), expected);
+}
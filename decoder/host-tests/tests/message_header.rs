@@ -0,0 +1,23 @@
+//! `MessageHeader::new` fills in the magic, and a header serializes as magic, opcode,
+//! then the length little-endian.
+use decoder::modules::hostcom_manager::{MessageHeader, MsgType, MSG_MAGIC};
+
+#[test]
+fn header_serializes_to_its_wire_bytes() {
+    let header = MessageHeader::new(MsgType::Decode, 0x0144);
+    assert_eq!(bytemuck::bytes_of(&header), [MSG_MAGIC, b'D', 0x44, 0x01]);
+
+    assert_eq!(bytemuck::bytes_of(&MessageHeader::new(MsgType::Ack, 0)), b"%A\0\0");
+    assert_eq!(bytemuck::bytes_of(&MessageHeader::new(MsgType::Error, u16::MAX)), [MSG_MAGIC, b'E', 0xFF, 0xFF]);
+}
+
+#[test]
+fn header_fields_are_what_was_given() {
+    let header = MessageHeader::new(MsgType::Subscribe, 3000);
+    assert_eq!(header.magic, MSG_MAGIC);
+    assert_eq!(header.opcode, MsgType::Subscribe as u8);
+    assert_eq!({ header.length }, 3000);
+    // And it reads back from its bytes
+    let parsed: MessageHeader = bytemuck::pod_read_unaligned(bytemuck::bytes_of(&header));
+    assert_eq!((parsed.magic, parsed.opcode, { parsed.length }), (MSG_MAGIC, b'S', 3000));
+}
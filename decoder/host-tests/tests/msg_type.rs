@@ -0,0 +1,105 @@
+//! Every message type converts to its opcode byte and back, and no other byte is
+//! taken for one.
+use decoder::modules::hostcom_manager::MsgType;
+
+const ALL: [MsgType; 32] = [
+    MsgType::Decode,
+    MsgType::Subscribe,
+    MsgType::List,
+    MsgType::Ack,
+    MsgType::Debug,
+    MsgType::Error,
+    MsgType::Tamper,
+    MsgType::Recover,
+    MsgType::DecoderId,
+    MsgType::SetTime,
+    MsgType::Telemetry,
+    MsgType::FlashLayout,
+    MsgType::Rekey,
+    MsgType::Nack,
+    MsgType::NodeDump,
+    MsgType::Window,
+    MsgType::Ping,
+    MsgType::ReplayState,
+    MsgType::FreeSlots,
+    MsgType::Resync,
+    MsgType::Pause,
+    MsgType::Emergency,
+    MsgType::SelfTest,
+    MsgType::SubscribePreamble,
+    MsgType::VerifyProbe,
+    MsgType::KeyFingerprint,
+    MsgType::PageDump,
+    MsgType::SubscribeBundle,
+    MsgType::Reset,
+    MsgType::UploadChunk,
+    MsgType::UploadStatus,
+    MsgType::Version,
+];
+
+/// Fails to build when a variant is added, so `ALL` is kept complete.
+fn is_listed(msg_type: MsgType) -> bool {
+    match msg_type {
+        MsgType::Decode
+        | MsgType::Subscribe
+        | MsgType::List
+        | MsgType::Ack
+        | MsgType::Debug
+        | MsgType::Error
+        | MsgType::Tamper
+        | MsgType::Recover
+        | MsgType::DecoderId
+        | MsgType::SetTime
+        | MsgType::Telemetry
+        | MsgType::FlashLayout
+        | MsgType::Rekey
+        | MsgType::Nack
+        | MsgType::NodeDump
+        | MsgType::Window
+        | MsgType::Ping
+        | MsgType::ReplayState
+        | MsgType::FreeSlots
+        | MsgType::Resync
+        | MsgType::Pause
+        | MsgType::Emergency
+        | MsgType::SelfTest
+        | MsgType::SubscribePreamble
+        | MsgType::VerifyProbe
+        | MsgType::KeyFingerprint
+        | MsgType::PageDump
+        | MsgType::SubscribeBundle
+        | MsgType::Reset
+        | MsgType::UploadChunk
+        | MsgType::UploadStatus
+        | MsgType::Version => true,
+    }
+}
+
+#[test]
+fn every_variant_round_trips() {
+    for msg_type in ALL {
+        assert!(is_listed(msg_type));
+        let opcode = u8::from(msg_type);
+        assert_eq!(opcode, msg_type as u8);
+        assert_eq!(MsgType::try_from(opcode), Ok(msg_type));
+    }
+}
+
+#[test]
+fn unknown_bytes_are_given_back() {
+    let known: Vec<u8> = ALL.iter().map(|&t| u8::from(t)).collect();
+    for byte in 0..=u8::MAX {
+        match MsgType::try_from(byte) {
+            Ok(msg_type) => assert_eq!(u8::from(msg_type), byte),
+            Err(unknown) => {
+                assert_eq!(unknown, byte);
+                assert!(!known.contains(&byte));
+            }
+        }
+    }
+    // No two variants share an opcode
+    let mut unique = known.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), ALL.len());
+}
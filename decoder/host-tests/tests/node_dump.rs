@@ -0,0 +1,46 @@
+//! The NodeDump command lists the tree nodes a stored subscription holds, and nothing
+//! of their passwords.
+#![cfg(feature = "debug-dump")]
+use decoder::modules::channel_manager::{dump_subscription_nodes, SubscriptionError, NODE_DUMP_MAX_LEN};
+use decoder::modules::test_vectors::covering_nodes;
+use decoder::modules::wire::read_u32_le;
+use decoder_host_tests::{subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// `(node_trunc, node_ext)` of every entry in a NodeDump response.
+fn dumped_nodes(dump: &[u8]) -> Vec<(u64, u8)> {
+    let count = read_u32_le(dump, 0) as usize;
+    assert_eq!(dump.len(), 4 + count * 9);
+    dump[4..]
+        .chunks(9)
+        .map(|entry| (u64::from_le_bytes(entry[..8].try_into().unwrap()), entry[8]))
+        .collect()
+}
+
+#[test]
+fn dump_lists_the_uploaded_nodes() {
+    let (start, end) = (T + 3, T + 1000);
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, start, end)).unwrap();
+
+    let mut dump = [0u8; NODE_DUMP_MAX_LEN];
+    let len = dump_subscription_nodes(&mut decoder.flash, 1, &mut dump).unwrap();
+    let mut nodes = dumped_nodes(&dump[..len]);
+    nodes.sort();
+
+    // Stored as gen_subscription encodes them: the node number without its last bit,
+    // and that bit as 1 (left) or 2 (right)
+    let mut expected: Vec<(u64, u8)> =
+        covering_nodes(start, end).into_iter().map(|node| ((node >> 1) as u64, (node & 1) as u8 + 1)).collect();
+    expected.sort();
+    assert_eq!(nodes, expected);
+}
+
+#[test]
+fn dump_of_an_unstored_channel_is_refused() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    let mut dump = [0u8; NODE_DUMP_MAX_LEN];
+    assert!(matches!(dump_subscription_nodes(&mut decoder.flash, 2, &mut dump), Err(SubscriptionError::NoSubscription)));
+}
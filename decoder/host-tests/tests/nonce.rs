@@ -0,0 +1,43 @@
+//! Nonces on the subscribe and decode paths: a signed subscription with the all-zero
+//! nonce is refused, while a frame's nonce only needs to be present.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::test_vectors::{encode_frame, encode_subscription};
+use decoder::{DECODER_ID, DECODER_KEY};
+use decoder_host_tests::{channel_root, frame_content, host_key, Decoder, Rng};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn subscription_with_nonce(nonce: [u8; 12]) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &channel_root(CHANNEL), CHANNEL, 0, u64::MAX, nonce)
+}
+
+fn frame_with_nonce(timestamp: u64, nonce: [u8; 12]) -> Vec<u8> {
+    encode_frame(&host_key(), &channel_root(CHANNEL), CHANNEL, timestamp, &frame_content(timestamp), nonce)
+}
+
+#[test]
+fn zero_subscription_nonce_is_refused() {
+    let mut decoder = Decoder::new();
+    assert!(matches!(decoder.subscribe(&subscription_with_nonce([0; 12])), Err(SubscriptionError::ZeroNonce)));
+    assert!(matches!(decoder.decode(&frame_with_nonce(T, [1; 12])), Err(SubscriptionError::NoSubscription)));
+
+    let mut nonce = [0; 12];
+    nonce[11] = 1;
+    decoder.subscribe(&subscription_with_nonce(nonce)).unwrap();
+    assert_eq!(decoder.decode(&frame_with_nonce(T, [1; 12])).unwrap(), frame_content(T));
+}
+
+#[test]
+fn frames_decode_under_any_nonce() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription_with_nonce([0x5A; 12])).unwrap();
+    assert_eq!(decoder.decode(&frame_with_nonce(T, [0; 12])).unwrap(), frame_content(T));
+
+    let mut rng = Rng::new(0x1900);
+    for timestamp in T + 1..T + 9 {
+        let mut nonce = [0; 12];
+        rng.fill(&mut nonce);
+        assert_eq!(decoder.decode(&frame_with_nonce(timestamp, nonce)).unwrap(), frame_content(timestamp));
+    }
+}
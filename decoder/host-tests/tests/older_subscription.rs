@@ -0,0 +1,37 @@
+//! A second subscription for a stored channel replaces it only if it ends no earlier,
+//! and the channel's replay counter survives the replacement.
+use decoder::modules::channel_manager::{find_subscription_page, SubscriptionError};
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn stored_window(decoder: &mut Decoder) -> (u64, u64) {
+    let (_, info) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap();
+    (info.start_timestamp, info.end_timestamp)
+}
+
+#[test]
+fn newer_window_replaces_the_stored_one() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+    decoder.decode(&frame(CHANNEL, T - 5)).unwrap();
+
+    decoder.subscribe(&subscription(CHANNEL, T - 10, T + 10)).unwrap();
+    assert_eq!(stored_window(&mut decoder), (T - 10, T + 10));
+    // The last frame was kept, so frames up to it are still replays
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T - 5)), Err(SubscriptionError::InvalidTimestamp)));
+    decoder.decode(&frame(CHANNEL, T - 4)).unwrap();
+    decoder.decode(&frame(CHANNEL, T + 10)).unwrap();
+}
+
+#[test]
+fn older_window_is_refused() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+
+    let older = subscription(CHANNEL, 0, T - 1);
+    assert!(matches!(decoder.subscribe(&older), Err(SubscriptionError::StaleSubscription)));
+    assert_eq!(stored_window(&mut decoder), (0, T));
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+}
@@ -0,0 +1,45 @@
+//! The PageDump command reads back a subscription page byte for byte, and nothing but
+//! subscription pages.
+#![cfg(feature = "page-dump")]
+use decoder::modules::channel_manager::{dump_subscription_page, find_subscription_page, SubscriptionError, PAGE_DUMP_LEN};
+use decoder::modules::constants::{subscription_page_addr, PAGE_SIZE, STATE_BASE_ADDRESS, SUBSCRIPTION_MAGIC};
+use decoder::modules::wire::read_u32_le;
+use decoder::{FIRMWARE_FLASH_START, MAX_CHANNELS};
+use decoder_host_tests::{subscription, Decoder};
+
+#[test]
+fn dump_returns_the_written_page() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 1).unwrap();
+
+    let mut dump = [0u8; PAGE_DUMP_LEN];
+    assert_eq!(dump_subscription_page(&mut decoder.flash, addr, &mut dump).unwrap(), PAGE_DUMP_LEN);
+    assert_eq!(read_u32_le(&dump, 0), addr);
+    assert_eq!(read_u32_le(&dump, 4), PAGE_SIZE);
+
+    let page = &dump[8..];
+    let mut expected = vec![0u8; PAGE_SIZE as usize];
+    decoder.flash.read_raw(addr, &mut expected).unwrap();
+    assert_eq!(page, &expected[..]);
+    assert_eq!(read_u32_le(page, 0), SUBSCRIPTION_MAGIC);
+    // The record's ChannelInfo starts with the channel id
+    assert_eq!(read_u32_le(page, 4), 1);
+
+    // An empty page reads as erased flash
+    let empty = (0..MAX_CHANNELS).map(subscription_page_addr).find(|&a| a != addr).unwrap();
+    dump_subscription_page(&mut decoder.flash, empty, &mut dump).unwrap();
+    assert!(dump[8..].iter().all(|&b| b == 0xFF));
+}
+
+#[test]
+fn only_subscription_pages_are_dumped() {
+    let mut decoder = Decoder::new();
+    let mut dump = [0u8; PAGE_DUMP_LEN];
+    for addr in [FIRMWARE_FLASH_START, STATE_BASE_ADDRESS, subscription_page_addr(0) + 16] {
+        assert!(matches!(
+            dump_subscription_page(&mut decoder.flash, addr, &mut dump),
+            Err(SubscriptionError::NotSubscriptionPage)
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+//! Subscription pages need not be contiguous: with a middle page wiped, the
+//! subscriptions behind the gap are still found, by lookup, at boot and by List.
+use core::mem::size_of;
+use decoder::modules::channel_manager::find_subscription_page;
+use decoder::modules::constants::subscription_page_addr;
+use decoder::modules::hostcom_manager::{ChannelInfo, HostConsole, MsgType, MSG_MAGIC};
+use decoder_host_tests::{frame, subscription, Decoder, MockUart};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// A decoder with channels 1 to 3 on pages 0 to 2, and page 1 wiped.
+fn decoder_with_gap() -> Decoder {
+    let mut decoder = Decoder::new();
+    for channel in 1..=3 {
+        decoder.subscribe(&subscription(channel, 0, u64::MAX)).unwrap();
+        let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).unwrap();
+        assert_eq!(addr, subscription_page_addr(channel as usize - 1));
+    }
+    decoder.flash.wipe_data(subscription_page_addr(1)).unwrap();
+    decoder
+}
+
+#[test]
+fn subscription_behind_a_gap_is_found() {
+    let mut decoder = decoder_with_gap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 3).unwrap();
+    assert_eq!(addr, subscription_page_addr(2));
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == 2).is_none());
+}
+
+#[test]
+fn boot_activates_channels_behind_a_gap() {
+    // Booting runs initialize_active_channels over the pages
+    let mut decoder = decoder_with_gap().reboot();
+    let mut active: Vec<u32> = decoder.channels.iter().flatten().map(|c| c.channel_id).collect();
+    active.sort();
+    assert_eq!(active, [0, 1, 3]);
+
+    // Decode looks the page up by channel
+    decoder.decode(&frame(3, T)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+}
+
+#[test]
+fn list_reports_exactly_the_occupied_pages() {
+    let mut decoder = decoder_with_gap();
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    // The host ACKs the header and the one body chunk
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0].repeat(2));
+    assert_eq!(console.write_list(&mut decoder.flash), 0);
+
+    let sent = uart.take_sent();
+    assert_eq!(sent[..2], [MSG_MAGIC, MsgType::List as u8]);
+    let body = &sent[4..];
+    assert_eq!(u16::from_le_bytes([sent[2], sent[3]]) as usize, body.len());
+    assert_eq!(u32::from_le_bytes(body[..4].try_into().unwrap()), 2);
+
+    let channels: Vec<ChannelInfo> = body[4..].chunks_exact(size_of::<ChannelInfo>()).map(bytemuck::pod_read_unaligned).collect();
+    assert_eq!(channels.len(), 2);
+    assert_eq!(channels.iter().map(|c| c.channel_id).collect::<Vec<_>>(), [1, 3]);
+    assert!(channels.iter().all(|c| { c.start_timestamp } == 0 && { c.end_timestamp } == u64::MAX));
+}
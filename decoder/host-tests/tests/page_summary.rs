@@ -0,0 +1,42 @@
+//! One pass over the subscription pages sorts each into valid, empty or corrupt, and
+//! lists corrupt pages instead of skipping them.
+use decoder::modules::channel_manager::{find_subscription_page, validate_all_subscriptions, PageState};
+use decoder::modules::constants::subscription_page_addr;
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::{subscription, Decoder};
+
+/// Any magic but the erased and subscription ones.
+const FOREIGN_MAGIC: u32 = 0x1234_5678;
+
+fn page_of(decoder: &mut Decoder, channel: u32) -> u32 {
+    find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).unwrap().0
+}
+
+#[test]
+fn summary_counts_valid_empty_and_corrupt_pages() {
+    let mut decoder = Decoder::new();
+    for channel in 1..=3 {
+        decoder.subscribe(&subscription(channel, 0, u64::MAX)).unwrap();
+    }
+    // A password byte of channel 2, past the header
+    let torn = page_of(&mut decoder, 2);
+    decoder.flc.corrupt_byte(torn + 100);
+    // Bytes with no subscription magic on a free page
+    let foreign = (0..MAX_CHANNELS).map(subscription_page_addr).find(|&a| a > page_of(&mut decoder, 3)).unwrap();
+    decoder.flash.write_data(foreign, FOREIGN_MAGIC, &[0u8; 16]).unwrap();
+
+    let summary = validate_all_subscriptions(&mut decoder.flash).unwrap();
+    assert_eq!(summary.valid(), 2);
+    assert_eq!(summary.corrupt(), 2);
+    assert_eq!(summary.empty(), MAX_CHANNELS as u32 - 4);
+    assert_eq!(summary.pages_in(PageState::CrcMismatch).collect::<Vec<_>>(), [torn]);
+    assert_eq!(summary.pages_in(PageState::Malformed).collect::<Vec<_>>(), [foreign]);
+    let channels: Vec<u32> = summary.pages.iter().filter_map(|s| if let PageState::Valid(c) = s { Some(*c) } else { None }).collect();
+    assert_eq!(channels, [1, 3]);
+
+    // Boot erases the page that failed its CRC and leaves the rest alone
+    let mut decoder = decoder.reboot();
+    let summary = validate_all_subscriptions(&mut decoder.flash).unwrap();
+    assert_eq!((summary.valid(), summary.corrupt(), summary.empty()), (2, 1, MAX_CHANNELS as u32 - 3));
+    assert_eq!(summary.pages[(0..MAX_CHANNELS).find(|&p| subscription_page_addr(p) == torn).unwrap()], PageState::Empty);
+}
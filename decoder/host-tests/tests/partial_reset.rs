@@ -0,0 +1,41 @@
+//! A reset interrupted partway leaves some subscription pages erased, one possibly
+//! half-erased, and the rest intact; the decoder boots with exactly the intact ones.
+use decoder::modules::channel_manager::{find_subscription_page, free_subscription_pages, SubscriptionError};
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+fn page_of(decoder: &mut Decoder, channel: u32) -> u32 {
+    find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).unwrap().0
+}
+
+#[test]
+fn remaining_subscriptions_load_after_an_interrupted_reset() {
+    let mut decoder = Decoder::new();
+    for channel in 1..=3 {
+        decoder.subscribe(&subscription(channel, 0, u64::MAX)).unwrap();
+    }
+    let page_1 = page_of(&mut decoder, 1);
+    let page_3 = page_of(&mut decoder, 3);
+
+    // Channel 1's page was erased, channel 3's erase stopped with a byte still set
+    decoder.flash.wipe_data(page_1).unwrap();
+    decoder.flash.wipe_data(page_3).unwrap();
+    decoder.flc.corrupt_byte(page_3);
+
+    let mut decoder = decoder.reboot();
+    let active: Vec<u32> = decoder.channels.iter().flatten().map(|c| c.channel_id).collect();
+    assert_eq!(active, [0, 2]);
+    decoder.decode(&frame(2, T)).unwrap();
+    for channel in [1, 3] {
+        assert!(matches!(decoder.decode(&frame(channel, T)), Err(SubscriptionError::NoSubscription)));
+    }
+
+    // Both pages are usable again
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32 - 1);
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(3, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+    decoder.decode(&frame(3, T)).unwrap();
+}
@@ -0,0 +1,35 @@
+//! A Subscribe whose password blob is larger than one full table is refused with
+//! PasswordBlobTooLarge before anything is copied or decrypted, at every length up to
+//! the body buffer, and nothing is written.
+use decoder::modules::channel_manager::{ChannelPassword, ChannelPasswords, SubscriptionError};
+use decoder::modules::hostcom_manager::MAX_BODY_LEN;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+/// Subscribe header and signature around the password blob.
+const FRAMING_LEN: usize = 36 + 64;
+
+/// A Subscribe body for this decoder with a `blob_len`-byte password blob of noise.
+fn with_blob(blob_len: usize) -> Vec<u8> {
+    let mut body = DECODER_ID.to_le_bytes().to_vec();
+    body.resize(FRAMING_LEN + blob_len, 0xA7);
+    body
+}
+
+#[test]
+fn oversized_blob_is_refused_cleanly() {
+    let mut decoder = Decoder::new();
+    let writes = decoder.flc.write_count();
+    let table = size_of::<ChannelPasswords>();
+    let entry = size_of::<ChannelPassword>();
+    // The checksum, when the build appends one, must fit in the buffer too
+    let largest = MAX_BODY_LEN - FRAMING_LEN - 2;
+    for blob_len in [table + 1, table + entry, table + entry + 3, largest] {
+        let result = decoder.subscribe(&with_blob(blob_len));
+        assert!(matches!(result, Err(SubscriptionError::PasswordBlobTooLarge)), "{} byte blob", blob_len);
+    }
+    assert_eq!(decoder.flc.write_count(), writes);
+
+    decoder.subscribe(&subscription(1, 0, 1000)).unwrap();
+    decoder.decode(&frame(1, 10)).unwrap();
+}
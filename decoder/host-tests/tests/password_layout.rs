@@ -0,0 +1,44 @@
+//! A password entry is the 25 bytes the encoder packs as `<Qb16s`: node_trunc,
+//! node_ext, then the key. Subscribe blobs are copied in by that layout and decode
+//! reads entries back by it, so the two must agree byte for byte.
+use core::mem::{offset_of, size_of};
+use decoder::modules::channel_manager::{ChannelPassword, ChannelPasswords, PASSWORD_TREE_NODES};
+use decoder::CHANNEL_0_SUBSCRIPTION;
+use decoder_host_tests::test_secrets;
+
+#[test]
+fn entry_is_25_bytes_in_encoder_order() {
+    assert_eq!(size_of::<ChannelPassword>(), 25);
+    assert_eq!(offset_of!(ChannelPassword, node_trunc), 0);
+    assert_eq!(offset_of!(ChannelPassword, node_ext), 8);
+    assert_eq!(offset_of!(ChannelPassword, password), 9);
+    assert_eq!(size_of::<ChannelPasswords>(), PASSWORD_TREE_NODES * 25);
+}
+
+#[test]
+fn entry_round_trips_through_bytes() {
+    let key: [u8; 16] = core::array::from_fn(|i| 0xA0 + i as u8);
+    let mut packed = 0x0123_4567_89AB_CDEFu64.to_le_bytes().to_vec();
+    packed.push(2);
+    packed.extend_from_slice(&key);
+
+    let entry: ChannelPassword = bytemuck::pod_read_unaligned(&packed);
+    assert_eq!({ entry.node_trunc }, 0x0123_4567_89AB_CDEF);
+    assert_eq!(entry.node_ext, 2);
+    assert_eq!(entry.password, key);
+    assert_eq!(bytemuck::bytes_of(&entry), &packed[..]);
+}
+
+#[test]
+fn generated_channel_0_entry_reads_back_by_the_same_layout() {
+    let node = &test_secrets()["channel_0_node"];
+    let entry = CHANNEL_0_SUBSCRIPTION.passwords.contents[0];
+    let bytes = bytemuck::bytes_of(&entry);
+
+    assert_eq!(bytes[..8], node["node_trunc"].as_u64().unwrap().to_le_bytes());
+    assert_eq!(bytes[8] as u64, node["node_ext"].as_u64().unwrap());
+    assert_eq!(bytes[9..], entry.password);
+    // Every entry after it is the empty terminator
+    let table = bytemuck::bytes_of(&CHANNEL_0_SUBSCRIPTION.passwords);
+    assert!(table[25..].iter().all(|&b| b == 0));
+}
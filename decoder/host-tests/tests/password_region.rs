@@ -0,0 +1,45 @@
+//! The bytes between a Subscribe header and its signature must be whole 25-byte
+//! password entries. A table cut short by one entry no longer matches its signature,
+//! and one cut mid-entry is refused as malformed; neither is stored as a shorter tree.
+use core::mem::size_of;
+use decoder::modules::channel_manager::{free_subscription_pages, ChannelPassword, SubscriptionError};
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const ENTRY: usize = size_of::<ChannelPassword>();
+
+/// `body` with `cut` bytes removed from the end of its password region.
+fn cut_passwords(body: &[u8], cut: usize) -> Vec<u8> {
+    let signature_at = body.len() - 64;
+    [&body[..signature_at - cut], &body[signature_at..]].concat()
+}
+
+#[test]
+fn table_one_entry_short_is_refused() {
+    let mut decoder = Decoder::new();
+    // A window over several tree nodes, so one entry can go and others stay
+    let body = subscription(CHANNEL, 1000, 5000);
+    assert!(body.len() - 36 - 64 >= 2 * ENTRY);
+
+    let short = cut_passwords(&body, ENTRY);
+    assert!(matches!(decoder.subscribe(&short), Err(SubscriptionError::InvalidSignature)));
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32);
+}
+
+#[test]
+fn partial_entry_is_refused() {
+    let mut decoder = Decoder::new();
+    let body = subscription(CHANNEL, 1000, 5000);
+    for cut in [1, ENTRY - 1, ENTRY + 1] {
+        let short = cut_passwords(&body, cut);
+        assert!(matches!(decoder.subscribe(&short), Err(SubscriptionError::InvalidLength)), "{} bytes cut", cut);
+    }
+    // A stray byte past the last entry is a partial entry too
+    let long = [&body[..body.len() - 64], &[0][..], &body[body.len() - 64..]].concat();
+    assert!(matches!(decoder.subscribe(&long), Err(SubscriptionError::InvalidLength)));
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32);
+
+    decoder.subscribe(&body).unwrap();
+    decoder.decode(&frame(CHANNEL, 2000)).unwrap();
+}
@@ -0,0 +1,32 @@
+//! The password table capacity, PASSWORD_TREE_NODES, against every size derived from it:
+//! the stored table, the embedded channel 0 record and the Subscribe length checks.
+use decoder::modules::channel_manager::{ChannelPassword, ChannelPasswords, SubscriptionError, PASSWORD_TREE_NODES};
+use decoder::{CHANNEL_0_SUBSCRIPTION, DECODER_ID};
+use decoder_host_tests::Decoder;
+
+/// Subscribe body for this decoder with `entries` zeroed password entries and a zeroed
+/// signature; the length checks run before the signature is looked at.
+fn unsigned_subscription(entries: usize) -> Vec<u8> {
+    let mut body = DECODER_ID.to_le_bytes().to_vec();
+    body.resize(4 + 8 + 8 + 4 + 12 + entries * size_of::<ChannelPassword>() + 64, 0);
+    body
+}
+
+#[test]
+fn table_sizes_follow_the_node_count() {
+    assert_eq!(size_of::<ChannelPasswords>(), PASSWORD_TREE_NODES * size_of::<ChannelPassword>());
+    assert_eq!(CHANNEL_0_SUBSCRIPTION.passwords.contents.len(), PASSWORD_TREE_NODES);
+}
+
+#[test]
+fn subscribe_accepts_up_to_a_full_table() {
+    let mut decoder = Decoder::new();
+    assert!(matches!(
+        decoder.subscribe(&unsigned_subscription(PASSWORD_TREE_NODES)),
+        Err(SubscriptionError::InvalidSignature)
+    ));
+    assert!(matches!(
+        decoder.subscribe(&unsigned_subscription(PASSWORD_TREE_NODES + 1)),
+        Err(SubscriptionError::PasswordBlobTooLarge)
+    ));
+}
@@ -0,0 +1,56 @@
+//! Pausing a channel refuses its frames but keeps its stored subscription, across a
+//! reboot, until a signed Pause command resumes it.
+use decoder::modules::channel_manager::{find_subscription_page, set_channel_paused, SubscriptionError};
+use decoder::modules::test_vectors::encode_pause;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, host_key, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn pause(decoder: &mut Decoder, sequence: u32, paused: bool) -> Result<u32, SubscriptionError> {
+    let body = encode_pause(&host_key(), DECODER_ID, CHANNEL, sequence, paused);
+    set_channel_paused(&mut decoder.flash, &body, &mut decoder.channels)
+}
+
+#[test]
+fn paused_channel_refuses_frames_and_keeps_its_subscription() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    assert_eq!(pause(&mut decoder, 0, true).unwrap(), 1);
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 1)), Err(SubscriptionError::ChannelPaused)));
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_some());
+    // Channel 0 is unaffected
+    decoder.decode(&frame(0, T)).unwrap();
+
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 1)), Err(SubscriptionError::ChannelPaused)));
+}
+
+#[test]
+fn resumed_channel_decodes_and_old_commands_do_not_replay() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    assert_eq!(pause(&mut decoder, 0, true).unwrap(), 1);
+    assert_eq!(pause(&mut decoder, 1, false).unwrap(), 2);
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    // The recorded pause was for sequence 0
+    assert!(matches!(pause(&mut decoder, 0, true), Err(SubscriptionError::StaleSubscription)));
+
+    // The state moves with the subscription when it is replaced
+    assert_eq!(pause(&mut decoder, 2, true).unwrap(), 3);
+    decoder.subscribe(&subscription(CHANNEL, 1, u64::MAX)).unwrap();
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 1)), Err(SubscriptionError::ChannelPaused)));
+    assert_eq!(pause(&mut decoder, 3, false).unwrap(), 4);
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+}
+
+#[test]
+fn pause_needs_a_stored_subscription() {
+    let mut decoder = Decoder::new();
+    assert!(matches!(pause(&mut decoder, 0, true), Err(SubscriptionError::NoSubscription)));
+}
@@ -0,0 +1,50 @@
+//! A Ping header with no body is answered at once with an empty Ping, with no ACK
+//! either way. One carrying a body is refused as an unknown command instead.
+use decoder::modules::hostcom_manager::{ErrorCode, HostConsole, MsgType, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+const PING: [u8; 4] = [MSG_MAGIC, MsgType::Ping as u8, 0, 0];
+
+#[test]
+fn bare_ping_is_answered_at_once() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone()).with_debug_sink(MockUart::default());
+    uart.queue(&PING);
+
+    let hdr = console.read_header();
+    assert_eq!(MsgType::try_from(hdr.opcode), Ok(MsgType::Ping));
+    console.answer_ping(hdr.length);
+    // Nothing queued was needed, and nothing but the Ping was sent
+    assert_eq!(uart.take_sent(), PING);
+
+    // Any number of times, each the same
+    for _ in 0..3 {
+        console.answer_ping(0);
+        assert_eq!(uart.take_sent(), PING);
+    }
+}
+
+#[test]
+fn ping_with_a_body_is_refused() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone()).with_debug_sink(MockUart::default());
+    let body = [1u8, 2, 3, 4];
+    uart.queue(&body);
+    #[cfg(feature = "chunk-crc")]
+    uart.queue(&[0, 0]);
+    uart.queue(&ACK.repeat(2));
+
+    console.answer_ping(body.len() as u16);
+    // ACK of the header and of the drained chunk, then the error
+    let mut expected = ACK.repeat(2);
+    expected.extend_from_slice(&[MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::UnknownCommand as u8]);
+    assert_eq!(uart.take_sent(), expected);
+    assert_eq!(uart.pending(), 0);
+
+    // The console is still in sync for the next Ping
+    uart.queue(&PING);
+    let hdr = console.read_header();
+    console.answer_ping(hdr.length);
+    assert_eq!(uart.take_sent(), PING);
+}
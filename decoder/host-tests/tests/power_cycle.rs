@@ -0,0 +1,61 @@
+//! Replay protection across a power cycle: the decoder is rebooted from the same RAM
+//! flash, restoring its subscriptions and the state log's replay counters.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn subscribed() -> Decoder {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder
+}
+
+fn decode(decoder: &mut Decoder, timestamp: u64) -> Result<(), SubscriptionError> {
+    let content = decoder.decode(&frame(CHANNEL, timestamp))?;
+    assert_eq!(content, frame_content(timestamp));
+    Ok(())
+}
+
+#[test]
+fn frames_up_to_t_are_replays_after_a_reboot() {
+    let mut decoder = subscribed();
+    for timestamp in [T - 2, T - 1, T] {
+        decode(&mut decoder, timestamp).unwrap();
+    }
+
+    let mut decoder = decoder.reboot();
+    for timestamp in [0, T - 1, T] {
+        assert!(matches!(decode(&mut decoder, timestamp), Err(SubscriptionError::InvalidTimestamp)));
+    }
+    decode(&mut decoder, T + 1).unwrap();
+
+    // The frame accepted after the reboot is persisted in turn
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decode(&mut decoder, T + 1), Err(SubscriptionError::InvalidTimestamp)));
+    decode(&mut decoder, T + 2).unwrap();
+}
+
+#[test]
+fn torn_state_record_keeps_the_last_committed_frame() {
+    let mut decoder = subscribed();
+    decode(&mut decoder, T).unwrap();
+    let writes = decoder.flc.write_count();
+    decode(&mut decoder, T + 1).unwrap();
+    let record_writes = decoder.flc.write_count() - writes;
+
+    // Power lost at every point of the next record's write: the frame is withheld,
+    // and after the reboot it is still new while everything before it is not
+    for good_writes in 0..record_writes {
+        let mut decoder = subscribed();
+        decode(&mut decoder, T).unwrap();
+        decoder.flc.fail_after_writes(good_writes);
+        assert!(decode(&mut decoder, T + 1).is_err(), "frame released without a record");
+        decoder.flc.clear_failures();
+
+        let mut decoder = decoder.reboot();
+        assert!(matches!(decode(&mut decoder, T), Err(SubscriptionError::InvalidTimestamp)));
+        decode(&mut decoder, T + 1).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+//! A signed preamble lets the decoder refuse a subscription before its body is sent.
+use decoder::modules::channel_manager::{check_subscribe_preamble, SubscriptionError};
+use decoder::modules::test_vectors::{encode_subscribe_preamble, encode_subscription};
+use decoder::{DECODER_ID, DECODER_KEY, MAX_CHANNELS};
+use decoder_host_tests::{frame, host_key, subscription, Decoder};
+
+/// Root key of a channel outside test.secrets; the decoder only needs its passwords.
+fn root(channel: u32) -> [u8; 16] {
+    [channel as u8; 16]
+}
+
+/// Preamble announcing `body` as Decoder::subscribe sends it.
+fn preamble(decoder_id: u32, channel: u32, body: &[u8]) -> Vec<u8> {
+    // Decoder::subscribe appends the checksum when the decoder expects one
+    let length = body.len();
+    #[cfg(feature = "subscribe-checksum")]
+    let length = length + decoder::modules::channel_manager::SUBSCRIPTION_CHECKSUM_LEN;
+    encode_subscribe_preamble(&host_key(), decoder_id, channel, length as u16)
+}
+
+/// The host side of the exchange: the preamble first, and the body only once the
+/// decoder accepts it. Returns the outcome and whether the body was sent.
+fn upload(decoder: &mut Decoder, decoder_id: u32, channel: u32, body: &[u8]) -> (Result<(), SubscriptionError>, bool) {
+    let preamble = preamble(decoder_id, channel, body);
+    if let Err(e) = check_subscribe_preamble(&mut decoder.flash, &preamble) {
+        return (Err(e), false);
+    }
+    (decoder.subscribe(body), true)
+}
+
+#[test]
+fn accepted_preamble_is_followed_by_the_body() {
+    let mut decoder = Decoder::new();
+    let (result, sent) = upload(&mut decoder, DECODER_ID, 1, &subscription(1, 0, u64::MAX));
+    result.unwrap();
+    assert!(sent);
+    decoder.decode(&frame(1, 10)).unwrap();
+}
+
+#[test]
+fn rejected_preamble_sends_no_body() {
+    let mut decoder = Decoder::new();
+    let body = subscription(1, 0, u64::MAX);
+
+    let (result, sent) = upload(&mut decoder, DECODER_ID ^ 1, 1, &body);
+    assert!(matches!(result, Err(SubscriptionError::InvalidDecoderId)));
+    assert!(!sent);
+
+    let (result, sent) = upload(&mut decoder, DECODER_ID, 0, &body);
+    assert!(matches!(result, Err(SubscriptionError::InvalidChannelId)));
+    assert!(!sent);
+
+    let (result, sent) = upload(&mut decoder, DECODER_ID, 1, &body[..body.len() - 1]);
+    assert!(matches!(result, Err(SubscriptionError::InvalidLength)));
+    assert!(!sent);
+
+    // A forged preamble
+    let mut preamble = preamble(DECODER_ID, 1, &body);
+    *preamble.last_mut().unwrap() ^= 1;
+    assert!(matches!(check_subscribe_preamble(&mut decoder.flash, &preamble), Err(SubscriptionError::InvalidSignature)));
+}
+
+#[test]
+fn preamble_reports_a_full_decoder_unless_the_channel_is_stored() {
+    let mut decoder = Decoder::new();
+    for channel in (1..=MAX_CHANNELS as u32).map(|i| i * 10) {
+        let body = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root(channel), channel, 0, u64::MAX, [0x5A; 12]);
+        upload(&mut decoder, DECODER_ID, channel, &body).0.unwrap();
+    }
+
+    let body = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root(999), 999, 0, u64::MAX, [0x5A; 12]);
+    let (result, sent) = upload(&mut decoder, DECODER_ID, 999, &body);
+    assert!(matches!(result, Err(SubscriptionError::NoPageFound)));
+    assert!(!sent);
+
+    // A stored channel is replaced in place
+    let body = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root(10), 10, 1, u64::MAX, [0x5A; 12]);
+    let (result, sent) = upload(&mut decoder, DECODER_ID, 10, &body);
+    result.unwrap();
+    assert!(sent);
+}
@@ -0,0 +1,28 @@
+//! Subscriptions for another decoder or for channel 0 are refused on their public
+//! fields, before the signature is verified: a broken signature does not change the
+//! answer.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::test_vectors::encode_subscription;
+use decoder::{DECODER_ID, DECODER_KEY};
+use decoder_host_tests::{channel_root, host_key, Decoder};
+
+fn unsigned(decoder_id: u32, channel: u32) -> Vec<u8> {
+    let mut body = encode_subscription(&host_key(), &DECODER_KEY, decoder_id, &channel_root(channel), channel, 0, 1000, [0x5A; 12]);
+    let len = body.len();
+    body[len - 64..].fill(0);
+    body
+}
+
+#[test]
+fn wrong_decoder_is_refused_before_verify() {
+    let mut decoder = Decoder::new();
+    assert!(matches!(decoder.subscribe(&unsigned(DECODER_ID ^ 1, 1)), Err(SubscriptionError::InvalidDecoderId)));
+    // The same body for this decoder reaches the signature check
+    assert!(matches!(decoder.subscribe(&unsigned(DECODER_ID, 1)), Err(SubscriptionError::InvalidSignature)));
+}
+
+#[test]
+fn channel_0_is_refused_before_verify() {
+    let mut decoder = Decoder::new();
+    assert!(matches!(decoder.subscribe(&unsigned(DECODER_ID, 0)), Err(SubscriptionError::InvalidChannelId)));
+}
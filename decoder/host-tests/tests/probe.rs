@@ -0,0 +1,51 @@
+//! A probe frame proves a stored subscription decodes, answering with the content's
+//! SHA-256 and never the content.
+use decoder::modules::channel_manager::{verify_probe_frame, ChannelFrame, SubscriptionError};
+use decoder::modules::test_vectors::encode_subscription;
+use decoder::{DECODER_ID, DECODER_KEY};
+use decoder_host_tests::{frame, frame_content, host_key, subscription, Decoder};
+use sha2::{Digest, Sha256};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn probe(decoder: &mut Decoder, body: &[u8]) -> Result<[u8; 32], SubscriptionError> {
+    let frame = ChannelFrame::from_le_bytes(body).unwrap();
+    verify_probe_frame(
+        &mut decoder.flash,
+        frame,
+        &mut decoder.channels,
+        &mut decoder.context,
+        #[cfg(feature = "rtc-time")]
+        &decoder.clock,
+    )
+}
+
+#[test]
+fn good_subscription_answers_the_content_digest() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+
+    let digest = probe(&mut decoder, &frame(CHANNEL, T)).unwrap();
+    let expected: [u8; 32] = Sha256::digest(frame_content(T)).into();
+    assert_eq!(digest, expected);
+    assert!(!digest.windows(8).any(|w| frame_content(T).windows(8).any(|c| c == w)));
+
+    // The probe used up its timestamp
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::InvalidTimestamp)));
+}
+
+#[test]
+fn tampered_subscription_fails_the_probe() {
+    let mut decoder = Decoder::new();
+    // Signed, but carrying another tree's passwords
+    let wrong = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &[0x77; 16], CHANNEL, 0, u64::MAX, [0x5A; 12]);
+    decoder.subscribe(&wrong).unwrap();
+    assert!(matches!(probe(&mut decoder, &frame(CHANNEL, T)), Err(SubscriptionError::InconsistentFrame)));
+
+    // And a tampered probe frame is refused outright
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    let mut body = frame(CHANNEL, T + 1);
+    body[20] ^= 1;
+    assert!(matches!(probe(&mut decoder, &body), Err(SubscriptionError::InvalidSignature)));
+}
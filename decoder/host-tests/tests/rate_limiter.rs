@@ -0,0 +1,63 @@
+//! Commands are delayed after FAILURES_BEFORE_DELAY consecutive signature failures,
+//! longer with each further one up to MAX_DELAY_MS, and a correctly signed message
+//! lifts the delay. The delay only adds up time, so nothing waits.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::rate_limiter::{MockDelay, RateLimiter, BASE_DELAY_MS, FAILURES_BEFORE_DELAY, MAX_DELAY_MS};
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+/// Throttle as the main loop does before a command, returning the time blocked.
+fn throttle(limiter: &mut RateLimiter<MockDelay>) -> u64 {
+    let before = limiter.delay().slept_ms();
+    limiter.throttle();
+    limiter.delay().slept_ms() - before
+}
+
+fn forged_frame(timestamp: u64) -> Vec<u8> {
+    let mut frame = frame(CHANNEL, timestamp);
+    // A signed content byte
+    frame[4 + 8 + 12] ^= 1;
+    frame
+}
+
+#[test]
+fn delay_starts_after_repeated_failures_and_clears_on_success() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    let mut limiter = RateLimiter::new(MockDelay::new());
+
+    for _ in 0..FAILURES_BEFORE_DELAY {
+        assert_eq!(throttle(&mut limiter), 0);
+        let result = decoder.decode(&forged_frame(T));
+        assert!(matches!(result, Err(SubscriptionError::InvalidSignature)));
+        limiter.record(&result);
+    }
+    assert_eq!(throttle(&mut limiter), BASE_DELAY_MS as u64);
+
+    // Each further failure doubles the delay
+    limiter.record(&decoder.decode(&forged_frame(T)));
+    assert_eq!(throttle(&mut limiter), 2 * BASE_DELAY_MS as u64);
+
+    // Other failures do not count either way
+    let unsubscribed = decoder.decode(&frame(2, T));
+    assert!(matches!(unsubscribed, Err(SubscriptionError::NoSubscription)));
+    limiter.record(&unsubscribed);
+    assert_eq!(throttle(&mut limiter), 2 * BASE_DELAY_MS as u64);
+
+    let result = decoder.decode(&frame(CHANNEL, T));
+    assert!(result.is_ok());
+    limiter.record(&result);
+    assert_eq!(throttle(&mut limiter), 0);
+}
+
+#[test]
+fn delay_is_capped() {
+    let mut limiter = RateLimiter::new(MockDelay::new());
+    for _ in 0..100 {
+        limiter.record::<()>(&Err(SubscriptionError::InvalidSignature));
+    }
+    assert_eq!(limiter.current_delay_ms(), MAX_DELAY_MS);
+    assert_eq!(throttle(&mut limiter), MAX_DELAY_MS as u64);
+}
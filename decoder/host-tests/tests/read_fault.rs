@@ -0,0 +1,51 @@
+//! A flash read fault on one subscription page is retried once, and a page that stays
+//! unreadable is skipped without hiding the subscriptions after it.
+use decoder::modules::channel_manager::channel_subscriptions;
+use decoder::modules::constants::subscription_page_addr;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// A decoder with channels 1 to 3 on pages 0 to 2.
+fn subscribed() -> Decoder {
+    let mut decoder = Decoder::new();
+    for channel in 1..=3 {
+        decoder.subscribe(&subscription(channel, 0, u64::MAX)).unwrap();
+    }
+    decoder
+}
+
+fn listed(decoder: &mut Decoder) -> Vec<u32> {
+    channel_subscriptions(&mut decoder.flash, false).filter_map(|(_, info)| info).map(|info| info.channel_id).collect()
+}
+
+#[test]
+fn transient_fault_is_retried() {
+    let mut decoder = subscribed();
+    decoder.flc.fail_reads(subscription_page_addr(1), 1);
+    assert_eq!(listed(&mut decoder), [1, 2, 3]);
+}
+
+#[test]
+fn faulted_magic_skips_only_its_page() {
+    let mut decoder = subscribed();
+    decoder.flc.fail_reads(subscription_page_addr(1), u32::MAX);
+    assert_eq!(listed(&mut decoder), [1, 3]);
+
+    // Boot scans the same way, and the channel behind the fault still decodes
+    let mut decoder = decoder.reboot();
+    let mut active: Vec<u32> = decoder.channels.iter().flatten().map(|c| c.channel_id).collect();
+    active.sort();
+    assert_eq!(active, [0, 1, 3]);
+    decoder.decode(&frame(3, T)).unwrap();
+}
+
+#[test]
+fn unreadable_header_skips_only_its_page() {
+    let mut decoder = subscribed();
+    // The magic reads, but the header's second word fails the read and its retry
+    decoder.flc.fail_reads(subscription_page_addr(1) + 16, 2);
+    assert_eq!(listed(&mut decoder), [1, 3]);
+    // Once the fault clears the page is found again
+    assert_eq!(listed(&mut decoder), [1, 2, 3]);
+}
@@ -0,0 +1,34 @@
+//! `read_subscription` returns the same `ChannelInfo` the page scan reads from each
+//! page's header, and refuses a record whose CRC does not match.
+use bytemuck::bytes_of;
+use decoder::modules::channel_manager::{channel_subscriptions, find_subscription_page, read_subscription};
+use decoder::modules::flash_manager::FlashManagerError;
+use decoder_host_tests::{subscription, Decoder};
+
+#[test]
+fn full_record_matches_the_page_header() {
+    let mut decoder = Decoder::new();
+    for (channel, start, end) in [(1, 0, u64::MAX), (2, 1_000, 9_000), (3, 5, 50)] {
+        decoder.subscribe(&subscription(channel, start, end)).unwrap();
+    }
+
+    let headers: Vec<_> = channel_subscriptions(&mut decoder.flash, false).collect();
+    assert_eq!(headers.len(), 3);
+    for (addr, info) in headers {
+        let info = info.unwrap();
+        let record = read_subscription(&mut decoder.flash, addr).unwrap();
+        assert_eq!(bytes_of(&record.info), bytes_of(&info), "page {:#x}", addr);
+    }
+}
+
+#[test]
+fn corrupt_record_is_refused() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 1).unwrap();
+    // A password byte, past the header the page scan reads
+    decoder.flc.corrupt_byte(addr + 100);
+
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == 1).is_some());
+    assert!(matches!(read_subscription(&mut decoder.flash, addr), Err(FlashManagerError::CrcMismatch)));
+}
@@ -0,0 +1,111 @@
+//! A body the host stops sending partway times out, and the console resyncs so the
+//! next command parses cleanly.
+use std::collections::VecDeque;
+
+use bytemuck::Zeroable;
+use decoder::modules::hostcom_manager::{BodyError, HostConsole, MessageBody, MsgType, UartHalOps, MSG_MAGIC};
+
+const TIMEOUT: u32 = 20;
+
+/// UART fed from a script in which `None` is a poll that finds the FIFO empty.
+#[derive(Default)]
+struct ScriptedUart {
+    rx: VecDeque<Option<u8>>,
+    tx: Vec<u8>,
+}
+
+impl ScriptedUart {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.rx.extend(bytes.iter().copied().map(Some));
+    }
+
+    fn idle(&mut self, polls: u32) {
+        self.rx.extend((0..polls).map(|_| None));
+    }
+}
+
+impl UartHalOps for ScriptedUart {
+    fn read_byte(&mut self) -> u8 {
+        loop {
+            if let Some(b) = self.try_read_byte() {
+                return b;
+            }
+            assert!(!self.rx.is_empty(), "read past the script");
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.tx.push(byte);
+    }
+
+    fn try_read_byte(&mut self) -> Option<u8> {
+        self.rx.pop_front().flatten()
+    }
+}
+
+fn header(msg_type: MsgType, length: u16) -> Vec<u8> {
+    let mut bytes = vec![MSG_MAGIC, msg_type as u8];
+    bytes.extend_from_slice(&length.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn command_after_a_mid_body_timeout_parses() {
+    let mut uart = ScriptedUart::default();
+    uart.bytes(&header(MsgType::Subscribe, 300));
+    uart.bytes(&[0xAB; 100]);
+    uart.idle(TIMEOUT);
+    // The rest of the body straggles in during the resync, magic bytes and all
+    uart.bytes(&[MSG_MAGIC; 50]);
+    uart.idle(TIMEOUT / 2);
+    uart.bytes(&header(MsgType::Decode, 8));
+    uart.idle(TIMEOUT);
+    // Then the host starts over
+    uart.bytes(&header(MsgType::DecoderId, 4));
+    uart.bytes(&[1, 2, 3, 4]);
+
+    let mut console = HostConsole::new(uart).with_read_timeout(TIMEOUT);
+    let mut body = MessageBody::zeroed();
+
+    let hdr = console.read_header();
+    assert_eq!(hdr.opcode, MsgType::Subscribe as u8);
+    assert_eq!(console.read_body(hdr.length, &mut body), Err(BodyError::Timeout));
+    assert_eq!({ body.length }, 100);
+
+    let hdr = console.read_header();
+    assert_eq!(hdr.opcode, MsgType::DecoderId as u8);
+    assert_eq!({ hdr.length }, 4);
+    assert_eq!(console.read_body(hdr.length, &mut body), Ok(4));
+    assert_eq!(body.data[..4], [1, 2, 3, 4]);
+}
+
+#[test]
+fn discard_resyncs_on_timeout_too() {
+    let mut uart = ScriptedUart::default();
+    uart.bytes(&header(MsgType::Decode, 200));
+    uart.bytes(&[0x25; 10]);
+    // One timeout ends the discard, the next the resync
+    uart.idle(2 * TIMEOUT);
+    uart.bytes(&header(MsgType::List, 0));
+
+    let mut console = HostConsole::new(uart).with_read_timeout(TIMEOUT);
+    let hdr = console.read_header();
+    console.discard_body(hdr.length);
+    assert_eq!(console.read_header().opcode, MsgType::List as u8);
+}
+
+#[test]
+fn short_body_reports_the_bytes_received() {
+    let mut uart = ScriptedUart::default();
+    uart.bytes(&header(MsgType::Subscribe, 300));
+    // Past the first chunk, then the host goes quiet
+    uart.bytes(&[0x5A; 260]);
+    uart.idle(2 * TIMEOUT);
+
+    let mut console = HostConsole::new(uart).with_read_timeout(TIMEOUT);
+    let mut body = MessageBody::zeroed();
+    let hdr = console.read_header();
+    assert_eq!(console.read_body(hdr.length, &mut body), Err(BodyError::Timeout));
+    assert_eq!({ body.length }, 260);
+    assert!(body.data[..260].iter().all(|&b| b == 0x5A));
+}
@@ -0,0 +1,80 @@
+//! A signed Rekey command moves the decoder to the next subscription key, derived from
+//! the current one with HKDF-SHA512 as get_decoder_key derives it. Subscriptions sent
+//! after the rotation use the new key; ones already stored keep decoding.
+#![cfg(feature = "rekey")]
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::key_manager::KeyError;
+use decoder::modules::test_vectors::{encode_rekey, encode_subscription};
+use decoder::{DECODER_ID, DECODER_KEY, KEY_LEN};
+use decoder_host_tests::{channel_root, frame, host_key, subscription, Decoder};
+use hkdf::Hkdf;
+use sha2::Sha512;
+
+const T: u64 = 1_700_000_000_000_000;
+const CONTEXT: [u8; 32] = [0x3C; 32];
+
+fn next_key(key: &[u8; KEY_LEN], context: &[u8]) -> [u8; KEY_LEN] {
+    let mut next = [0u8; KEY_LEN];
+    Hkdf::<Sha512>::new(None, key).expand(context, &mut next).unwrap();
+    next
+}
+
+fn subscription_under(key: &[u8; KEY_LEN], channel: u32) -> Vec<u8> {
+    encode_subscription(&host_key(), key, DECODER_ID, &channel_root(channel), channel, 0, u64::MAX, [0x5A; 12])
+}
+
+fn rekey(decoder: &mut Decoder, generation: u32) -> Result<u32, KeyError> {
+    decoder.device_key.rekey_signed(&mut decoder.flash, &encode_rekey(&host_key(), DECODER_ID, generation, &CONTEXT))
+}
+
+#[test]
+fn rekey_round_trip() {
+    let mut decoder = Decoder::new();
+    // Stored under the build-time key, before the rotation
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+
+    assert_eq!(rekey(&mut decoder, 1).unwrap(), 1);
+    let key = next_key(&DECODER_KEY, &CONTEXT);
+    assert_eq!(decoder.device_key.key(), &key);
+
+    decoder.subscribe(&subscription_under(&key, 2)).unwrap();
+    decoder.decode(&frame(2, T)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+
+    // Under the old key the passwords decrypt to noise: refused as unusable, or stored
+    // and never opening a frame
+    match decoder.subscribe(&subscription_under(&DECODER_KEY, 3)) {
+        Ok(()) => assert!(decoder.decode(&frame(3, T)).is_err()),
+        Err(e) => assert!(matches!(e, SubscriptionError::NoUsablePasswords)),
+    }
+}
+
+#[test]
+fn generation_is_accepted_once() {
+    let mut decoder = Decoder::new();
+    assert!(matches!(rekey(&mut decoder, 2), Err(KeyError::InvalidGeneration)));
+    assert_eq!(decoder.device_key.key(), &DECODER_KEY);
+
+    rekey(&mut decoder, 1).unwrap();
+    assert!(matches!(rekey(&mut decoder, 1), Err(KeyError::InvalidGeneration)));
+    assert_eq!(decoder.device_key.key(), &next_key(&DECODER_KEY, &CONTEXT));
+
+    let mut forged = encode_rekey(&host_key(), DECODER_ID, 2, &CONTEXT);
+    forged[4] ^= 1;
+    let result = decoder.device_key.rekey_signed(&mut decoder.flash, &forged);
+    assert!(matches!(result, Err(KeyError::InvalidSignature)));
+}
+
+#[test]
+fn rotated_key_survives_a_reboot() {
+    let mut decoder = Decoder::new();
+    rekey(&mut decoder, 1).unwrap();
+    rekey(&mut decoder, 2).unwrap();
+    let key = next_key(&next_key(&DECODER_KEY, &CONTEXT), &CONTEXT);
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(decoder.device_key.key(), &key);
+    decoder.subscribe(&subscription_under(&key, 1)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+    rekey(&mut decoder, 3).unwrap();
+}
@@ -0,0 +1,60 @@
+//! The ReplayState dump reports, for every active channel, the `received` flag and the
+//! `last_frame` that the next frame's timestamp is checked against.
+#![cfg(feature = "debug-dump")]
+use decoder::modules::channel_manager::{dump_replay_state, SubscriptionError, REPLAY_STATE_MAX_LEN};
+use decoder::modules::wire::read_u32_le;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+/// `(channel_id, received, last_frame)` of every entry in a ReplayState response.
+fn replay_state(decoder: &Decoder) -> Vec<(u32, bool, u64)> {
+    let mut dump = [0u8; REPLAY_STATE_MAX_LEN];
+    let len = dump_replay_state(&decoder.channels, &mut dump);
+    let count = read_u32_le(&dump, 0) as usize;
+    assert_eq!(len, 4 + count * 13);
+    dump[4..len]
+        .chunks(13)
+        .map(|entry| {
+            let received = match entry[4] {
+                0 => false,
+                1 => true,
+                flag => panic!("received flag {}", flag),
+            };
+            (read_u32_le(entry, 0), received, u64::from_le_bytes(entry[5..].try_into().unwrap()))
+        })
+        .collect()
+}
+
+fn entry(decoder: &Decoder, channel: u32) -> (bool, u64) {
+    let (_, received, last_frame) =
+        replay_state(decoder).into_iter().find(|entry| entry.0 == channel).expect("channel not listed");
+    (received, last_frame)
+}
+
+#[test]
+fn last_frame_matches_the_decoded_frame() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(2, 0, u64::MAX)).unwrap();
+    assert!(!entry(&decoder, CHANNEL).0);
+
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+    assert_eq!(entry(&decoder, CHANNEL), (true, T));
+    // A replay is refused against it and leaves it as it was
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::InvalidTimestamp)));
+    assert_eq!(entry(&decoder, CHANNEL), (true, T));
+    decoder.decode(&frame(CHANNEL, T + 5)).unwrap();
+    assert_eq!(entry(&decoder, CHANNEL), (true, T + 5));
+
+    // Only the decoding channel moved; channel 0 is listed too
+    assert!(!entry(&decoder, 2).0);
+    assert!(!entry(&decoder, 0).0);
+    decoder.decode(&frame(0, T + 1)).unwrap();
+    assert_eq!(entry(&decoder, 0), (true, T + 1));
+
+    // The state log brings it back after a reboot
+    let decoder = decoder.reboot();
+    assert_eq!(entry(&decoder, CHANNEL), (true, T + 5));
+}
@@ -0,0 +1,41 @@
+//! A Subscribe resent unchanged, as after a lost response, succeeds without erasing or
+//! writing flash, as the stored page already holds it. One that differs is written.
+use decoder::modules::channel_manager::{free_subscription_pages, SubscriptionError};
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn unchanged_resend_writes_nothing() {
+    let mut decoder = Decoder::new();
+    let body = subscription(CHANNEL, 0, T);
+    decoder.subscribe(&body).unwrap();
+    let free = free_subscription_pages(&mut decoder.flash);
+    let (writes, erases) = (decoder.flc.write_count(), decoder.flc.erase_count());
+
+    decoder.subscribe(&body).unwrap();
+    assert_eq!(decoder.flc.erase_count(), erases);
+    assert_eq!(decoder.flc.write_count(), writes);
+    assert_eq!(free_subscription_pages(&mut decoder.flash), free);
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    // Also after a reboot, against the page as read back
+    let mut decoder = decoder.reboot();
+    let (writes, erases) = (decoder.flc.write_count(), decoder.flc.erase_count());
+    decoder.subscribe(&body).unwrap();
+    assert_eq!(decoder.flc.erase_count(), erases);
+    assert_eq!(decoder.flc.write_count(), writes);
+}
+
+#[test]
+fn changed_resend_is_written() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+    let erases = decoder.flc.erase_count();
+
+    decoder.subscribe(&subscription(CHANNEL, 0, T + 1)).unwrap();
+    assert_eq!(decoder.flc.erase_count(), erases + 1);
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 2)), Err(SubscriptionError::SubscriptionExpired)));
+}
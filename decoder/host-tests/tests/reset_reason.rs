@@ -0,0 +1,30 @@
+//! The reset reason follows the watchdog flags found at startup and is reported in the
+//! Telemetry response right after the boot count.
+use bytemuck::Zeroable;
+use decoder::modules::reset_cause::{ResetFlags, ResetReason};
+use decoder::modules::telemetry::{Telemetry, TELEMETRY_MAX_LEN};
+use decoder::modules::wire::read_u32_le;
+use decoder_host_tests::Decoder;
+use std::mem::{offset_of, size_of};
+
+#[test]
+fn flags_map_to_a_reason() {
+    let flags = |watchdog_late, watchdog_early| ResetReason::from_flags(ResetFlags { watchdog_late, watchdog_early });
+    assert_eq!(flags(false, false), ResetReason::PowerOn);
+    assert_eq!(flags(true, false), ResetReason::WatchdogTimeout);
+    assert_eq!(flags(false, true), ResetReason::WatchdogEarlyFeed);
+    assert_eq!(flags(true, true), ResetReason::WatchdogTimeout);
+}
+
+#[test]
+fn reason_is_reported_by_telemetry() {
+    let decoder = Decoder::new();
+    let mut telemetry = Telemetry::zeroed();
+    telemetry.reset_reason = ResetReason::from_flags(ResetFlags { watchdog_late: true, ..Default::default() }) as u32;
+
+    let mut out = [0u8; TELEMETRY_MAX_LEN];
+    let len = telemetry.write_report(&decoder.channels, &mut out);
+    assert!(len >= size_of::<Telemetry>());
+    assert_eq!(offset_of!(Telemetry, reset_reason), offset_of!(Telemetry, boot_count) + 4);
+    assert_eq!(read_u32_le(&out, offset_of!(Telemetry, reset_reason)), 1);
+}
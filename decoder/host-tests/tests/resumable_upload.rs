@@ -0,0 +1,98 @@
+//! A Subscribe body sent as upload chunks survives a dropped connection: after a reset
+//! the decoder reports how far it got, and the rest completes the subscription.
+#![cfg(feature = "resumable-upload")]
+use decoder::modules::channel_manager::{find_subscription_page, SubscriptionError};
+use decoder::modules::hostcom_manager::{MessageBody, MAX_BODY_LEN};
+#[cfg(feature = "subscribe-checksum")]
+use decoder::modules::test_vectors::add_subscription_checksum;
+use decoder::modules::upload_manager::{receive_upload_chunk, upload_received, UploadProgress, UPLOAD_CHUNK_LEN};
+use decoder_host_tests::{frame, frame_content, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const UPLOAD_ID: u32 = 0x51DE_CAFE;
+const T: u64 = 1_700_000_000_000_000;
+
+/// The Subscribe body, as the host would send it whole.
+fn subscribe_body() -> Vec<u8> {
+    let body = subscription(CHANNEL, 0, u64::MAX);
+    #[cfg(feature = "subscribe-checksum")]
+    let body = add_subscription_checksum(&body);
+    body
+}
+
+fn send_chunk(decoder: &mut Decoder, upload_id: u32, full: &[u8], index: usize) -> Result<UploadProgress, SubscriptionError> {
+    let data = full.chunks(UPLOAD_CHUNK_LEN).nth(index).unwrap_or(&[]);
+    let mut chunk = upload_id.to_le_bytes().to_vec();
+    chunk.extend_from_slice(&(full.len() as u16).to_le_bytes());
+    chunk.extend_from_slice(&(index as u16).to_le_bytes());
+    chunk.extend_from_slice(data);
+
+    let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: chunk.len() as u16 };
+    body.data[..chunk.len()].copy_from_slice(&chunk);
+    let result = receive_upload_chunk(&mut decoder.flash, &mut body, chunk.len(), &mut decoder.channels);
+    decoder.context.invalidate();
+    result
+}
+
+#[test]
+fn half_an_upload_resumes_after_a_reset() {
+    let full = subscribe_body();
+    let chunks = full.len().div_ceil(UPLOAD_CHUNK_LEN);
+    let half = chunks / 2;
+    let mut decoder = Decoder::new();
+    for index in 0..half {
+        let progress = send_chunk(&mut decoder, UPLOAD_ID, &full, index).unwrap();
+        assert_eq!(progress.received as usize, (index + 1) * UPLOAD_CHUNK_LEN);
+        assert!(!progress.is_complete());
+    }
+
+    // The link drops and the decoder resets
+    let mut decoder = decoder.reboot();
+    assert_eq!(upload_received(&mut decoder.flash, UPLOAD_ID) as usize, half * UPLOAD_CHUNK_LEN);
+    assert_eq!(upload_received(&mut decoder.flash, UPLOAD_ID + 1), 0);
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_none());
+
+    // Only the next missing chunk is taken
+    assert!(matches!(send_chunk(&mut decoder, UPLOAD_ID, &full, half + 1), Err(SubscriptionError::UploadOutOfOrder)));
+    assert!(matches!(send_chunk(&mut decoder, UPLOAD_ID, &full, half - 1), Err(SubscriptionError::UploadOutOfOrder)));
+
+    for index in half..chunks {
+        let progress = send_chunk(&mut decoder, UPLOAD_ID, &full, index).unwrap();
+        assert_eq!(progress.is_complete(), index == chunks - 1);
+    }
+    assert_eq!(decoder.decode(&frame(CHANNEL, T)).unwrap(), frame_content(T));
+    // The scratch page was cleared with the upload done
+    assert_eq!(upload_received(&mut decoder.flash, UPLOAD_ID), 0);
+}
+
+#[test]
+fn a_new_upload_replaces_an_unfinished_one() {
+    let full = subscribe_body();
+    let mut decoder = Decoder::new();
+    send_chunk(&mut decoder, UPLOAD_ID, &full, 0).unwrap();
+    // Resuming an upload the decoder does not hold fails until it is restarted
+    assert!(matches!(send_chunk(&mut decoder, UPLOAD_ID + 1, &full, 1), Err(SubscriptionError::UploadOutOfOrder)));
+
+    send_chunk(&mut decoder, UPLOAD_ID + 1, &full, 0).unwrap();
+    assert_eq!(upload_received(&mut decoder.flash, UPLOAD_ID), 0);
+    assert_eq!(upload_received(&mut decoder.flash, UPLOAD_ID + 1) as usize, UPLOAD_CHUNK_LEN);
+}
+
+#[test]
+fn a_bad_signature_is_refused_and_not_kept() {
+    let mut full = subscribe_body();
+    let last = full.len() - 1;
+    full[last - 2] ^= 1;
+    let chunks = full.len().div_ceil(UPLOAD_CHUNK_LEN);
+
+    let mut decoder = Decoder::new();
+    for index in 0..chunks - 1 {
+        send_chunk(&mut decoder, UPLOAD_ID, &full, index).unwrap();
+    }
+    assert!(matches!(
+        send_chunk(&mut decoder, UPLOAD_ID, &full, chunks - 1),
+        Err(SubscriptionError::InvalidSignature | SubscriptionError::ChecksumMismatch)
+    ));
+    assert_eq!(upload_received(&mut decoder.flash, UPLOAD_ID), 0);
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_none());
+}
@@ -0,0 +1,33 @@
+//! Rebuilding the active channel list from flash without a reboot.
+use decoder::modules::channel_manager::{find_subscription_page, resync_active_channels, SubscriptionError};
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+
+fn active_ids(decoder: &Decoder) -> Vec<u32> {
+    decoder.channels.iter().flatten().map(|c| c.channel_id).collect()
+}
+
+#[test]
+fn resync_drops_a_wiped_page_and_keeps_replay_state() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(2, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(1, T)).unwrap();
+    assert_eq!(active_ids(&decoder), [0, 1, 2]);
+
+    // Lose channel 2's page behind the decoder's back
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == 2).unwrap();
+    decoder.flash.wipe_data(addr).unwrap();
+    assert_eq!(active_ids(&decoder), [0, 1, 2]);
+
+    let locked = resync_active_channels(&mut decoder.channels, &mut decoder.flash, &mut decoder.console);
+    assert!(!locked);
+    assert_eq!(active_ids(&decoder), [0, 1]);
+
+    let channel_1 = decoder.channels[1].unwrap();
+    assert!(channel_1.received);
+    assert_eq!(channel_1.last_frame, T);
+    assert!(matches!(decoder.decode(&frame(1, T)), Err(SubscriptionError::InvalidTimestamp)));
+    decoder.decode(&frame(1, T + 1)).unwrap();
+}
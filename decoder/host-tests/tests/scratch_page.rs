@@ -0,0 +1,50 @@
+//! The scratch page is a RESERVED page of its own: erasing and filling it leaves every
+//! subscription page byte for byte as it was.
+use decoder::modules::constants::{subscription_page_addr, PAGE_SIZE, RESERVED_END, RESERVED_START};
+use decoder::modules::flash_manager::FlashManager;
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const T: u64 = 1_700_000_000_000_000;
+const SCRATCH_MAGIC: u32 = 0x5C2A_7C11;
+
+fn subscription_pages(decoder: &mut Decoder) -> Vec<Vec<u8>> {
+    (0..MAX_CHANNELS)
+        .map(|page| {
+            let mut bytes = vec![0; PAGE_SIZE as usize];
+            decoder.flash.read_raw(subscription_page_addr(page), &mut bytes).unwrap();
+            bytes
+        })
+        .collect()
+}
+
+#[test]
+fn scratch_page_is_no_subscription_page() {
+    let scratch = FlashManager::scratch_page_addr();
+    assert!(scratch >= RESERVED_START && scratch + PAGE_SIZE <= RESERVED_END);
+    assert!((0..MAX_CHANNELS).map(subscription_page_addr).all(|addr| addr != scratch));
+}
+
+#[test]
+fn scratch_writes_leave_subscriptions_alone() {
+    let scratch = FlashManager::scratch_page_addr();
+    let mut decoder = Decoder::new();
+    for channel in 1..=3 {
+        decoder.subscribe(&subscription(channel, 0, u64::MAX)).unwrap();
+    }
+    let before = subscription_pages(&mut decoder);
+
+    // Fill the whole page, then erase it and write a record, as its users do
+    decoder.flash.wipe_data(scratch).unwrap();
+    decoder.flash.write_raw(scratch, &vec![0xA5; PAGE_SIZE as usize]).unwrap();
+    decoder.flash.wipe_data(scratch).unwrap();
+    decoder.flash.write_data(scratch, SCRATCH_MAGIC, &[0x3Cu8; 64]).unwrap();
+    assert_eq!(decoder.flash.read_data_verified::<[u8; 64]>(scratch).unwrap(), [0x3C; 64]);
+
+    assert_eq!(subscription_pages(&mut decoder), before);
+    // Nor is the page taken for a channel after a reset
+    let mut decoder = decoder.reboot();
+    for channel in 1..=3 {
+        decoder.decode(&frame(channel, T)).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+//! Subscriptions past the RESERVED pages are stored in the second subscription region
+//! and are listed and decoded like the others.
+use decoder::modules::channel_manager::{channel_subscriptions, SubscriptionError};
+use decoder::modules::constants::SECONDARY_BASE_ADDRESS;
+use decoder::modules::hostcom_manager::{write_list, MsgType, MSG_MAGIC};
+use decoder::modules::test_vectors::{encode_frame, encode_subscription};
+use decoder::{DECODER_ID, DECODER_KEY, MAX_CHANNELS, PRIMARY_CHANNELS, SECONDARY_CHANNELS};
+use decoder_host_tests::{frame_content, host_key, Decoder, MockUart};
+
+const T: u64 = 1_700_000_000_000_000;
+
+/// Root key of a channel outside test.secrets; the decoder only needs its passwords.
+fn root(channel: u32) -> [u8; 16] {
+    [channel as u8; 16]
+}
+
+fn subscription(channel: u32) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root(channel), channel, 0, u64::MAX, [0x5A; 12])
+}
+
+/// Channel ids in the body of a List response.
+fn listed(decoder: &mut Decoder) -> Vec<u32> {
+    let uart = MockUart::default();
+    // ACKs for the header and the one body chunk
+    uart.queue(&[MSG_MAGIC, MsgType::Ack as u8, 0, 0, MSG_MAGIC, MsgType::Ack as u8, 0, 0]);
+    assert_eq!(write_list(&mut uart.clone(), &mut decoder.flash), 0);
+    let sent = uart.take_sent();
+    let body = &sent[4..];
+    let count = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+    (0..count).map(|i| u32::from_le_bytes(body[4 + i * 20..8 + i * 20].try_into().unwrap())).collect()
+}
+
+#[test]
+fn subscriptions_span_both_regions() {
+    assert!(SECONDARY_CHANNELS > 0, "memory.x has no SUBSCRIPTIONS2 region");
+    assert!(MAX_CHANNELS > 8);
+
+    let channels: Vec<u32> = (1..=MAX_CHANNELS as u32).map(|i| i * 10).collect();
+    let mut decoder = Decoder::new();
+    for &channel in &channels {
+        decoder.subscribe(&subscription(channel)).unwrap();
+    }
+    assert!(matches!(decoder.subscribe(&subscription(999)), Err(SubscriptionError::NoPageFound)));
+
+    let secondary = channel_subscriptions(&mut decoder.flash, false)
+        .filter(|(addr, _)| *addr >= SECONDARY_BASE_ADDRESS)
+        .count();
+    assert_eq!(secondary, MAX_CHANNELS - PRIMARY_CHANNELS);
+
+    assert_eq!(listed(&mut decoder), channels);
+    let mut decoder = decoder.reboot();
+    assert_eq!(listed(&mut decoder), channels);
+    for &channel in &channels {
+        let frame = encode_frame(&host_key(), &root(channel), channel, T, &frame_content(T), [0xA5; 12]);
+        assert_eq!(decoder.decode(&frame).unwrap(), frame_content(T), "channel {}", channel);
+    }
+}
@@ -0,0 +1,51 @@
+//! A signed SetTime seeds the wall clock that expires subscriptions: only the host key
+//! can set it, for this decoder only, and never backwards.
+#![cfg(feature = "rtc-time")]
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::clock::{verify_set_time, ClockError, TIMESTAMP_TICKS_PER_SEC};
+use decoder::modules::test_vectors::encode_set_time;
+use decoder::modules::time_source::TimeSource;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, host_key, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const START: u64 = 1_700_000_000_000_000;
+const END: u64 = START + 60 * TIMESTAMP_TICKS_PER_SEC;
+
+/// Apply a SetTime body to the decoder's clock, as the command does.
+fn set_time(decoder: &mut Decoder, body: &[u8]) -> Result<(), ClockError> {
+    let now = verify_set_time(body, decoder.clock.now())?;
+    decoder.clock.set(now);
+    Ok(())
+}
+
+#[test]
+fn subscription_expires_once_the_clock_is_set_past_it() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+    set_time(&mut decoder, &encode_set_time(&host_key(), DECODER_ID, START)).unwrap();
+    decoder.decode(&frame(CHANNEL, START)).unwrap();
+
+    // No frame arrives for a while; the next one, stamped inside the window, is late
+    set_time(&mut decoder, &encode_set_time(&host_key(), DECODER_ID, END + 1)).unwrap();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, START + 1)), Err(SubscriptionError::SubscriptionExpired)));
+}
+
+#[test]
+fn clock_never_moves_back() {
+    let mut decoder = Decoder::new();
+    set_time(&mut decoder, &encode_set_time(&host_key(), DECODER_ID, END + 1)).unwrap();
+    let old = encode_set_time(&host_key(), DECODER_ID, START);
+    assert!(matches!(set_time(&mut decoder, &old), Err(ClockError::TimeRollback)));
+    assert_eq!(decoder.clock.now(), Some(END + 1));
+}
+
+#[test]
+fn unsigned_or_foreign_time_is_refused() {
+    let mut body = encode_set_time(&host_key(), DECODER_ID, START);
+    body[0] ^= 1;
+    assert!(matches!(verify_set_time(&body, None), Err(ClockError::InvalidSignature)));
+
+    let foreign = encode_set_time(&host_key(), DECODER_ID ^ 1, START);
+    assert!(matches!(verify_set_time(&foreign, None), Err(ClockError::InvalidSignature)));
+}
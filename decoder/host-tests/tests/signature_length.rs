@@ -0,0 +1,30 @@
+//! Bodies whose length claims a signature one byte short of or past an Ed25519
+//! signature are refused as malformed before any signature is parsed.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+/// `body` with its trailing 64-byte signature cut to `len` bytes.
+fn with_signature_len(mut body: Vec<u8>, len: usize) -> Vec<u8> {
+    body.resize(body.len() - 64 + len, 0xEE);
+    body
+}
+
+#[test]
+fn subscription_signature_must_be_64_bytes() {
+    let mut decoder = Decoder::new();
+    for len in [63, 65] {
+        let body = with_signature_len(subscription(1, 0, 1000), len);
+        assert!(matches!(decoder.subscribe(&body), Err(SubscriptionError::InvalidLength)), "{}-byte signature", len);
+    }
+    decoder.subscribe(&subscription(1, 0, 1000)).unwrap();
+}
+
+#[test]
+fn frame_signature_must_be_64_bytes() {
+    let mut decoder = Decoder::new();
+    for len in [63, 65] {
+        let body = with_signature_len(frame(0, 10), len);
+        assert!(matches!(decoder.decode(&body), Err(SubscriptionError::InvalidLength)), "{}-byte signature", len);
+    }
+    decoder.decode(&frame(0, 10)).unwrap();
+}
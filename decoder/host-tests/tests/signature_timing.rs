@@ -0,0 +1,47 @@
+//! Best-effort timing check on the host: a frame whose signature fails is parsed, its
+//! subscription loaded and its signature verified in full like a valid one, so a
+//! rejection is not much quicker than a decode. Medians over interleaved runs keep
+//! the comparison loose enough for a shared machine.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder_host_tests::{frame, subscription, Decoder};
+use std::time::{Duration, Instant};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+const RUNS: u64 = 25;
+/// Offset of the first encrypted content byte: channel, timestamp, then nonce.
+const CONTENT_OFFSET: usize = 4 + 8 + 12;
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+#[test]
+fn rejected_signature_takes_about_as_long_as_a_decode() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    // Load the subscription and the key cache, as for any frame after the first
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    for i in 1..=RUNS {
+        let good = frame(CHANNEL, T + i);
+        // Signed bytes altered, so the whole verification runs and fails
+        let mut bad = frame(CHANNEL, T + i);
+        bad[CONTENT_OFFSET] ^= 1;
+
+        let start = Instant::now();
+        let result = decoder.decode(&bad);
+        invalid.push(start.elapsed());
+        assert!(matches!(result, Err(SubscriptionError::InvalidSignature)));
+
+        let start = Instant::now();
+        decoder.decode(&good).unwrap();
+        valid.push(start.elapsed());
+    }
+
+    let (valid, invalid) = (median(valid), median(invalid));
+    assert!(invalid * 2 >= valid, "rejected in {invalid:?}, decoded in {valid:?}");
+}
@@ -0,0 +1,33 @@
+//! The signed part of a Subscribe is laid out from its fields, the header plus whole
+//! password entries, not taken as "all but the last 64 bytes". A header length that
+//! moves the signature boundary is refused, so no parsed field escapes the signature.
+use core::mem::size_of;
+use decoder::modules::channel_manager::{free_subscription_pages, ChannelPassword, SubscriptionError};
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const ENTRY: usize = size_of::<ChannelPassword>();
+
+#[test]
+fn shifted_length_is_refused() {
+    let mut decoder = Decoder::new();
+    let body = subscription(CHANNEL, 1000, 5000);
+    let mut padded = body.clone();
+    padded.resize(body.len() + 2 * ENTRY, 0);
+
+    // The header claims fewer or more bytes than were signed, by less than an entry
+    for length in [body.len() - 1, body.len() - 24, body.len() + 1, body.len() + 24, body.len() - 64, body.len() + 64] {
+        let result = decoder.subscribe(&padded[..length]);
+        assert!(matches!(result, Err(SubscriptionError::InvalidLength)), "length {}", length);
+    }
+    // By whole entries the layout holds, but the signature now sits over other bytes
+    for length in [body.len() - ENTRY, body.len() + ENTRY] {
+        let result = decoder.subscribe(&padded[..length]);
+        assert!(matches!(result, Err(SubscriptionError::InvalidSignature)), "length {}", length);
+    }
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32);
+
+    decoder.subscribe(&body).unwrap();
+    decoder.decode(&frame(CHANNEL, 2000)).unwrap();
+}
@@ -0,0 +1,53 @@
+//! The Reset command ACKs, waits out the flash controller and only then resets, so
+//! the decoder boots again on intact flash.
+#![cfg(feature = "soft-reset")]
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::flash_manager::Flc;
+use decoder::modules::hostcom_manager::{HostConsole, MsgType, MSG_MAGIC};
+use decoder::modules::reset::{handle_reset, SystemReset};
+use decoder_host_tests::{frame, subscription, Decoder, MockUart};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+/// Records whether the reset was reached and whether flash was busy at that moment.
+struct RecordingReset {
+    flc: Flc,
+    reached: bool,
+    flash_busy: bool,
+}
+
+impl SystemReset for RecordingReset {
+    fn reset(&mut self) {
+        self.reached = true;
+        self.flash_busy = self.flc.is_busy();
+    }
+}
+
+#[test]
+fn reset_waits_for_flash_and_reboots_intact() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    let mut reset = RecordingReset { flc: decoder.flc.clone(), reached: false, flash_busy: false };
+    // An operation still running when the command arrives
+    decoder.flc.hold_busy(3);
+    handle_reset(&mut console, 0, &mut decoder.flash, &mut reset);
+
+    assert!(reset.reached);
+    assert!(!reset.flash_busy);
+    assert_eq!(uart.take_sent(), [MSG_MAGIC, MsgType::Ack as u8, 0, 0]);
+
+    // The subscription and the replay counter survive the reset
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::InvalidTimestamp)));
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+}
+
+#[test]
+fn opcode_round_trips() {
+    assert_eq!(MsgType::try_from(b'r'), Ok(MsgType::Reset));
+}
@@ -0,0 +1,51 @@
+//! A subscription page written but not yet committed is invisible to lookups, reads
+//! and decode, as if the channel had no subscription, until its magic is committed.
+use decoder::modules::channel_manager::{
+    find_subscription_page, free_subscription_pages, read_subscription, ChannelSubscription, SubscriptionError,
+};
+use decoder::modules::constants::SUBSCRIPTION_MAGIC;
+use decoder::modules::flash_manager::FlashManagerError;
+use decoder::MAX_CHANNELS;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+/// 128-bit writes of one subscription record.
+const PAGE_WRITES: u32 = (4 + size_of::<ChannelSubscription>() + 4).div_ceil(16) as u32;
+
+#[test]
+fn uncommitted_page_reads_as_absent() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap();
+    let stored = read_subscription(&mut decoder.flash, addr).unwrap();
+
+    // Rewrite the page as a write caught between its erase and its commit leaves it
+    decoder.flash.wipe_data(addr).unwrap();
+    decoder.flash.stage_data(addr, SUBSCRIPTION_MAGIC, &stored).unwrap();
+    decoder.context.invalidate();
+
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_none());
+    assert!(matches!(read_subscription(&mut decoder.flash, addr), Err(FlashManagerError::MagicMismatch)));
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::NoSubscription)));
+
+    // Committing writes the magic's chunk, and the channel decodes again
+    decoder.flash.commit_data(addr, SUBSCRIPTION_MAGIC, &stored).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+}
+
+#[test]
+fn subscribe_cut_before_its_commit_stores_nothing() {
+    let mut decoder = Decoder::new();
+    // Everything but the magic's chunk written, and the page cannot be wiped after
+    decoder.flc.fail_after_writes(PAGE_WRITES - 1);
+    assert!(decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).is_err());
+    decoder.flc.clear_failures();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::NoSubscription)));
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(free_subscription_pages(&mut decoder.flash), MAX_CHANNELS as u32);
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::NoSubscription)));
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+}
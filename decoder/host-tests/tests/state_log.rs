@@ -0,0 +1,80 @@
+//! The state log's ping-pong pages: the record with the highest generation that passes
+//! its CRC is restored, so a damaged newest record falls back to the one before it,
+//! and moving the log onto the other page never loses the last good record.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::constants::{ERASED_MAGIC, PAGE_SIZE, STATE_BASE_ADDRESS};
+use decoder::modules::state_manager::ChannelStateRecord;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+/// Flash taken by one state record: magic, record and CRC in 128-bit words.
+const SLOT_SIZE: u32 = (4 + size_of::<ChannelStateRecord>() + 4).div_ceil(16) as u32 * 16;
+const SLOTS_PER_PAGE: u32 = PAGE_SIZE / SLOT_SIZE;
+
+fn subscribed() -> Decoder {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder
+}
+
+/// Address of the last record written on the first state page.
+fn newest_record(decoder: &mut Decoder) -> u32 {
+    let mut addr = STATE_BASE_ADDRESS;
+    while decoder.flash.read_magic(addr + SLOT_SIZE).unwrap() != ERASED_MAGIC {
+        addr += SLOT_SIZE;
+    }
+    addr
+}
+
+#[test]
+fn corrupt_newest_record_falls_back_to_the_one_before() {
+    let mut decoder = subscribed();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+    let addr = newest_record(&mut decoder);
+    decoder.flc.corrupt_byte(addr + 8);
+
+    // The record of T + 1 fails its CRC, so the record of T is restored
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::InvalidTimestamp)));
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+}
+
+#[test]
+fn torn_record_on_a_fresh_page_keeps_the_full_one() {
+    let mut decoder = subscribed();
+    // The boot record and these fill the first page
+    let mut timestamp = T;
+    for _ in 1..SLOTS_PER_PAGE {
+        decoder.decode(&frame(CHANNEL, timestamp)).unwrap();
+        timestamp += 1;
+    }
+    let erases = decoder.flc.erase_count();
+
+    // The next record erases the second page and is torn after its first word
+    decoder.flc.fail_after_writes(1);
+    assert!(decoder.decode(&frame(CHANNEL, timestamp)).is_err());
+    decoder.flc.clear_failures();
+    assert_eq!(decoder.flc.erase_count(), erases + 1);
+
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, timestamp - 1)), Err(SubscriptionError::InvalidTimestamp)));
+    decoder.decode(&frame(CHANNEL, timestamp)).unwrap();
+}
+
+#[test]
+fn log_wraps_around_both_pages() {
+    let mut decoder = subscribed();
+    let mut timestamp = T;
+    for _ in 0..2 * SLOTS_PER_PAGE + 1 {
+        decoder.decode(&frame(CHANNEL, timestamp)).unwrap();
+        timestamp += 1;
+    }
+
+    // The first page was erased again for the newest records, which win over the
+    // older ones still on the second page
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, timestamp - 1)), Err(SubscriptionError::InvalidTimestamp)));
+    decoder.decode(&frame(CHANNEL, timestamp)).unwrap();
+}
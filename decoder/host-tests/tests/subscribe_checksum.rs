@@ -0,0 +1,36 @@
+//! With `subscribe-checksum`, a Subscribe body damaged in transit is caught by its
+//! CRC-16 and reported as retriable, before the signature is checked.
+//!
+//! Run with `cargo test --features subscribe-checksum`.
+#![cfg(feature = "subscribe-checksum")]
+use decoder::modules::channel_manager::{find_subscription_page, SubscriptionError};
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder::modules::test_vectors::add_subscription_checksum;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+
+#[test]
+fn corrupted_subscription_is_caught_before_verify() {
+    let mut decoder = Decoder::new();
+    let body = add_subscription_checksum(&subscription(CHANNEL, 0, 1000));
+
+    // A flipped bit in the signature would otherwise be reported as InvalidSignature
+    let mut corrupted = body.clone();
+    corrupted[body.len() - 10] ^= 0x04;
+    let err = decoder.subscribe_body(&corrupted).unwrap_err();
+    assert!(matches!(err, SubscriptionError::ChecksumMismatch));
+    assert_eq!(err.error_code(), ErrorCode::ChecksumMismatch);
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_none());
+
+    // The resent body is stored
+    decoder.subscribe_body(&body).unwrap();
+    decoder.decode(&frame(CHANNEL, 10)).unwrap();
+}
+
+#[test]
+fn subscription_without_checksum_is_refused() {
+    let mut decoder = Decoder::new();
+    assert!(decoder.subscribe_body(&subscription(CHANNEL, 0, 1000)).is_err());
+    assert!(matches!(decoder.subscribe_body(&[0x12]), Err(SubscriptionError::InvalidLength)));
+}
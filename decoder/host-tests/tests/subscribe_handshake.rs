@@ -0,0 +1,176 @@
+//! The Subscribe exchange stepped one transition at a time: the happy path, and a
+//! failure at each step ending either with an Error packet or with a resync.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::hostcom_manager::{ErrorCode, HostConsole, MessageBody, MsgType, CHUNK_SIZE, MAX_BODY_LEN, MSG_MAGIC};
+use decoder::modules::subscribe_handshake::{SubscribeHandshake, SubscribeState};
+#[cfg(feature = "subscribe-checksum")]
+use decoder::modules::test_vectors::add_subscription_checksum;
+use decoder_host_tests::{frame, subscription, Decoder, MockUart};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+
+fn subscribe_body() -> Vec<u8> {
+    let body = subscription(CHANNEL, 0, u64::MAX);
+    #[cfg(feature = "subscribe-checksum")]
+    let body = add_subscription_checksum(&body);
+    body
+}
+
+fn error_packet(code: ErrorCode) -> Vec<u8> {
+    vec![MSG_MAGIC, MsgType::Error as u8, 1, 0, code as u8]
+}
+
+/// A console over `uart` whose logs go elsewhere, so the host stream is protocol only.
+fn console(uart: &MockUart) -> HostConsole<MockUart> {
+    HostConsole::new(uart.clone()).with_debug_sink(MockUart::default()).with_read_timeout(10)
+}
+
+fn empty_body() -> MessageBody {
+    MessageBody { data: [0; MAX_BODY_LEN], length: 0 }
+}
+
+/// Store the body as the Subscribe handler does.
+fn store(decoder: &mut Decoder) -> impl FnMut(&MessageBody) -> Result<(), SubscriptionError> + '_ {
+    |body| decoder.subscribe_body(&body.data[..body.length as usize])
+}
+
+#[test]
+fn happy_path_steps_through_every_state() {
+    let sub = subscribe_body();
+    let mut decoder = Decoder::new();
+    let uart = MockUart::default();
+    let mut console = console(&uart);
+    let mut body = empty_body();
+    let mut handshake = SubscribeHandshake::new(sub.len() as u16);
+    let mut verify = store(&mut decoder);
+
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Body { received: 0 });
+    assert_eq!(uart.take_sent(), ACK);
+
+    // Each chunk is ACKed as it arrives
+    uart.queue(&sub);
+    for received in (CHUNK_SIZE..sub.len()).step_by(CHUNK_SIZE) {
+        assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Body { received });
+        assert_eq!(uart.take_sent(), ACK);
+    }
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Verify);
+    assert_eq!(uart.take_sent(), ACK);
+    assert_eq!(body.data[..sub.len()], sub[..]);
+
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Respond);
+    assert!(matches!(handshake.outcome(), Some(Ok(()))));
+    assert!(uart.take_sent().is_empty());
+
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::AwaitAck);
+    assert_eq!(uart.take_sent(), [MSG_MAGIC, MsgType::Subscribe as u8, 0, 0]);
+
+    uart.queue(&ACK);
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Done);
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Done);
+    assert_eq!(uart.pending(), 0);
+
+    drop(verify);
+    decoder.context.invalidate();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+}
+
+#[test]
+fn run_finishes_the_exchange() {
+    let sub = subscribe_body();
+    let mut decoder = Decoder::new();
+    let uart = MockUart::default();
+    uart.queue(&sub);
+    uart.queue(&ACK);
+
+    let outcome = SubscribeHandshake::new(sub.len() as u16).run(&mut console(&uart), &mut empty_body(), store(&mut decoder));
+    assert!(matches!(outcome, Some(Ok(()))));
+    assert_eq!(uart.pending(), 0);
+}
+
+#[test]
+fn oversized_header_is_drained_and_refused() {
+    let length = MAX_BODY_LEN + 1;
+    let mut decoder = Decoder::new();
+    let uart = MockUart::default();
+    let mut console = console(&uart);
+    let mut body = empty_body();
+    let mut handshake = SubscribeHandshake::new(length as u16);
+    uart.queue(&vec![0x5A; length]);
+    uart.queue(&ACK);
+
+    assert_eq!(handshake.step(&mut console, &mut body, &mut store(&mut decoder)), SubscribeState::Done);
+    let mut expected = ACK.repeat(1 + length.div_ceil(CHUNK_SIZE));
+    expected.extend(error_packet(ErrorCode::SubscriptionTooLarge));
+    assert_eq!(uart.take_sent(), expected);
+    assert!(handshake.outcome().is_none());
+    assert_eq!(uart.pending(), 0);
+}
+
+#[test]
+fn body_cut_off_partway_aborts() {
+    let sub = subscribe_body();
+    let mut decoder = Decoder::new();
+    let uart = MockUart::default();
+    let mut console = console(&uart);
+    let mut body = empty_body();
+    let mut handshake = SubscribeHandshake::new(sub.len() as u16);
+    let mut verify = store(&mut decoder);
+
+    handshake.step(&mut console, &mut body, &mut verify);
+    uart.queue(&sub[..CHUNK_SIZE / 2]);
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Aborted);
+    // Nothing after the header ACK, and the body was never verified
+    assert_eq!(uart.take_sent(), ACK);
+    assert!(handshake.outcome().is_none());
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Aborted);
+}
+
+#[test]
+fn refused_subscription_is_reported() {
+    let mut sub = subscribe_body();
+    let last = sub.len() - 1;
+    sub[last - 2] ^= 1;
+    let mut decoder = Decoder::new();
+    let uart = MockUart::default();
+    let mut console = console(&uart);
+    let mut body = empty_body();
+    let mut handshake = SubscribeHandshake::new(sub.len() as u16);
+    let mut verify = store(&mut decoder);
+
+    uart.queue(&sub);
+    while handshake.state() != SubscribeState::Respond {
+        handshake.step(&mut console, &mut body, &mut verify);
+    }
+    uart.take_sent();
+    let code = handshake.outcome().unwrap().as_ref().unwrap_err().error_code();
+    assert!(matches!(code, ErrorCode::InvalidSignature | ErrorCode::ChecksumMismatch));
+
+    uart.queue(&ACK);
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Done);
+    assert_eq!(uart.take_sent(), error_packet(code));
+    assert_eq!(uart.pending(), 0);
+}
+
+#[test]
+fn response_answered_out_of_turn_resyncs() {
+    let sub = subscribe_body();
+    let mut decoder = Decoder::new();
+    let uart = MockUart::default();
+    let mut console = console(&uart);
+    let mut body = empty_body();
+    let mut handshake = SubscribeHandshake::new(sub.len() as u16);
+    let mut verify = store(&mut decoder);
+
+    uart.queue(&sub);
+    while handshake.state() != SubscribeState::AwaitAck {
+        handshake.step(&mut console, &mut body, &mut verify);
+    }
+    // The host sends a new command instead of the ACK
+    uart.queue(&[MSG_MAGIC, MsgType::List as u8, 0, 0, 0x11, 0x22]);
+    assert_eq!(handshake.step(&mut console, &mut body, &mut verify), SubscribeState::Aborted);
+    assert_eq!(uart.pending(), 0);
+    // The subscription was stored before the response went out
+    assert!(matches!(handshake.outcome(), Some(Ok(()))));
+}
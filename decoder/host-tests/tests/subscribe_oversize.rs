@@ -0,0 +1,31 @@
+//! A Subscribe body longer than the body buffer is refused up front and drained, so
+//! the next command still parses.
+use decoder::modules::channel_manager::validate_subscribe_length;
+use decoder::modules::hostcom_manager::{ErrorCode, HostConsole, MessageHeader, MsgType, MAX_BODY_LEN};
+use decoder_host_tests::{subscription, MockUart};
+
+#[test]
+fn over_capacity_length_is_refused() {
+    assert_eq!(validate_subscribe_length(MAX_BODY_LEN as u16 + 1), Err(ErrorCode::SubscriptionTooLarge));
+    assert_eq!(validate_subscribe_length(u16::MAX), Err(ErrorCode::SubscriptionTooLarge));
+    assert_eq!(validate_subscribe_length(MAX_BODY_LEN as u16), Ok(()));
+    assert_eq!(validate_subscribe_length(subscription(1, 0, 1000).len() as u16), Ok(()));
+}
+
+#[test]
+fn oversized_body_is_drained() {
+    let uart = MockUart::default();
+    let length = MAX_BODY_LEN as u16 + 100;
+    uart.queue(bytemuck::bytes_of(&MessageHeader::new(MsgType::Subscribe, length)));
+    uart.queue(&vec![0x5A; length as usize]);
+    uart.queue(bytemuck::bytes_of(&MessageHeader::new(MsgType::List, 0)));
+
+    // As the Subscribe handler does
+    let mut console = HostConsole::new(uart.clone());
+    let hdr = console.read_header();
+    assert_eq!(validate_subscribe_length(hdr.length), Err(ErrorCode::SubscriptionTooLarge));
+    console.discard_body(hdr.length);
+
+    assert_eq!(console.read_header().opcode, MsgType::List as u8);
+    assert_eq!(uart.pending(), 0);
+}
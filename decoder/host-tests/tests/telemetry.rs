@@ -0,0 +1,54 @@
+//! The telemetry counters advance once per decode or subscription, by outcome, and
+//! lead the Telemetry response.
+use bytemuck::Zeroable;
+use decoder::modules::channel_manager::{SubscriptionError, FRAME_CONTENT_LEN};
+use decoder::modules::telemetry::{Telemetry, TELEMETRY_MAX_LEN};
+use decoder_host_tests::{frame, subscription, Decoder};
+use std::mem::size_of;
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn counters_follow_decodes_and_subscriptions() {
+    let mut decoder = Decoder::new();
+    let mut telemetry = Telemetry::zeroed();
+
+    let result = decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX));
+    telemetry.record_subscription(&result);
+    let mut forged = subscription(2, 0, u64::MAX);
+    *forged.last_mut().unwrap() ^= 1;
+    let result = decoder.subscribe(&forged);
+    assert!(matches!(result, Err(SubscriptionError::InvalidSignature)));
+    telemetry.record_subscription(&result);
+
+    for timestamp in [T, T + 1, T + 1, T + 2] {
+        let result = decoder.decode(&frame(CHANNEL, timestamp));
+        telemetry.record_decode(&result);
+    }
+    let result = decoder.decode(&frame(3, T));
+    telemetry.record_decode(&result);
+    telemetry.record_bad_frame_length();
+
+    assert_eq!({ telemetry.frames_decoded }, 3);
+    assert_eq!({ telemetry.frames_bad_timestamp }, 1);
+    assert_eq!({ telemetry.frames_no_subscription }, 1);
+    assert_eq!({ telemetry.frames_other_error }, 1);
+    assert_eq!({ telemetry.frames_bad_signature }, 0);
+    assert_eq!({ telemetry.subscriptions_stored }, 1);
+    assert_eq!({ telemetry.subscriptions_rejected }, 1);
+    assert_eq!({ telemetry.signature_failures }, 1);
+
+    let mut out = [0u8; TELEMETRY_MAX_LEN];
+    let len = telemetry.write_report(&decoder.channels, &mut out);
+    assert!(len > size_of::<Telemetry>());
+    assert_eq!(out[..size_of::<Telemetry>()], *bytemuck::bytes_of(&telemetry));
+}
+
+#[test]
+fn counters_saturate() {
+    let mut telemetry = Telemetry::zeroed();
+    telemetry.frames_decoded = u32::MAX;
+    telemetry.record_decode(&Ok([0; FRAME_CONTENT_LEN]));
+    assert_eq!({ telemetry.frames_decoded }, u32::MAX);
+}
@@ -0,0 +1,74 @@
+//! The `std` encoder in `decoder::modules::test_vectors` against the Python one, and
+//! its output through the decoder.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::test_vectors::{covering_nodes, encode_frame, encode_subscription, leaf_node};
+use decoder::{DECODER_ID, DECODER_KEY};
+use decoder_host_tests::{channel_root, decode_vectors, hex_field, host_key, Decoder};
+
+#[test]
+fn reproduces_python_frames() {
+    for case in decode_vectors()["cases"].as_array().unwrap() {
+        let channel = case["channel"].as_u64().unwrap() as u32;
+        for frame in case["frames"].as_array().unwrap() {
+            let expected = hex_field(frame, "frame");
+            let content = hex_field(frame, "content").try_into().unwrap();
+            let nonce = expected[12..24].try_into().unwrap();
+            let timestamp = frame["timestamp"].as_u64().unwrap();
+            let encoded = encode_frame(&host_key(), &channel_root(channel), channel, timestamp, &content, nonce);
+            assert_eq!(encoded, expected, "{} @ {}", case["name"], timestamp);
+        }
+    }
+}
+
+#[test]
+fn reproduces_python_subscriptions() {
+    for case in decode_vectors()["cases"].as_array().unwrap() {
+        if case["channel"] == 0 {
+            continue;
+        }
+        let expected = hex_field(case, "subscription");
+        let encoded = encode_subscription(
+            &host_key(),
+            &DECODER_KEY,
+            DECODER_ID,
+            &channel_root(case["channel"].as_u64().unwrap() as u32),
+            case["channel"].as_u64().unwrap() as u32,
+            case["start"].as_u64().unwrap(),
+            case["end"].as_u64().unwrap(),
+            expected[24..36].try_into().unwrap(),
+        );
+        assert_eq!(encoded, expected, "{}", case["name"]);
+    }
+}
+
+#[test]
+fn cover_is_exact_and_minimal() {
+    assert_eq!(covering_nodes(0, u64::MAX), [1]);
+    assert_eq!(covering_nodes(7, 7), [leaf_node(7)]);
+    assert_eq!(covering_nodes(0, 3), [leaf_node(0) >> 2]);
+    // The worst case: every level but the root on both sides
+    assert_eq!(covering_nodes(1, u64::MAX - 1).len(), 126);
+}
+
+#[test]
+fn generated_inputs_round_trip() {
+    let (start, end) = (1_000_000, 9_000_000);
+    let mut decoder = Decoder::new();
+    let root = channel_root(3);
+    decoder
+        .subscribe(&encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID, &root, 3, start, end, [7; 12]))
+        .unwrap();
+
+    for (i, timestamp) in [start, 4_321_987, end].into_iter().enumerate() {
+        let content = [i as u8 + 1; 64];
+        let frame = encode_frame(&host_key(), &root, 3, timestamp, &content, [i as u8 + 1; 12]);
+        assert_eq!(decoder.decode(&frame).unwrap(), content);
+    }
+
+    let late = encode_frame(&host_key(), &root, 3, end + 1, &[0; 64], [9; 12]);
+    assert!(matches!(decoder.decode(&late), Err(SubscriptionError::SubscriptionExpired)));
+
+    // Frames for another decoder's subscription never get that far
+    let other = encode_subscription(&host_key(), &DECODER_KEY, DECODER_ID ^ 1, &root, 3, start, end, [7; 12]);
+    assert!(matches!(decoder.subscribe(&other), Err(SubscriptionError::InvalidDecoderId)));
+}
@@ -0,0 +1,50 @@
+//! The timestamp check holds a frame against its subscription window as well as the
+//! last decoded frame: a newer frame outside the window is refused and moves nothing.
+use decoder::modules::channel_manager::{validate_channel_timestamp, ChannelFrame, SubscriptionError, TimestampCheck};
+use decoder::modules::hostcom_manager::ChannelInfo;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const START: u64 = 1000;
+const END: u64 = 5000;
+
+#[test]
+fn newer_frame_past_the_end_is_expired() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+    decoder.decode(&frame(CHANNEL, 2000)).unwrap();
+
+    assert!(matches!(decoder.decode(&frame(CHANNEL, END + 1)), Err(SubscriptionError::SubscriptionExpired)));
+    // The replay counter stayed at the last frame within the window
+    decoder.decode(&frame(CHANNEL, 3000)).unwrap();
+    decoder.decode(&frame(CHANNEL, END)).unwrap();
+}
+
+#[test]
+fn first_frame_before_the_start_is_refused() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+
+    assert!(matches!(decoder.decode(&frame(CHANNEL, START - 1)), Err(SubscriptionError::PasswordNotFound)));
+    decoder.decode(&frame(CHANNEL, START)).unwrap();
+}
+
+#[test]
+fn validator_checks_the_window_before_the_counter() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, START, END)).unwrap();
+    let window = ChannelInfo { channel_id: CHANNEL, start_timestamp: START, end_timestamp: END };
+    let check = |decoder: &mut Decoder, timestamp| {
+        let frame = ChannelFrame::from_le_bytes(&frame(CHANNEL, timestamp)).unwrap();
+        validate_channel_timestamp(&frame, &window, &mut decoder.channels)
+    };
+
+    assert_eq!(check(&mut decoder, END + 1), TimestampCheck::Expired);
+    assert_eq!(check(&mut decoder, START - 1), TimestampCheck::NotYetValid);
+    assert_eq!(check(&mut decoder, START), TimestampCheck::Accepted);
+    assert_eq!(check(&mut decoder, u64::MAX), TimestampCheck::Expired);
+    assert_eq!(check(&mut decoder, START), TimestampCheck::Replayed);
+
+    let last_frame = decoder.channels.iter().flatten().find(|c| c.channel_id == CHANNEL).unwrap().last_frame;
+    assert_eq!(last_frame, START);
+}
@@ -0,0 +1,35 @@
+//! A replayed frame and a frame for a channel missing from the active list are
+//! reported with different error codes.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder_host_tests::{frame, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn replayed_frame_reports_replayed_timestamp() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    for timestamp in [T, T - 1] {
+        let err = decoder.decode(&frame(CHANNEL, timestamp)).unwrap_err();
+        assert!(matches!(err, SubscriptionError::InvalidTimestamp));
+        assert_eq!(err.error_code(), ErrorCode::ReplayedTimestamp);
+    }
+}
+
+#[test]
+fn inactive_channel_reports_channel_inactive() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+
+    // The page stays stored, but the list no longer holds the channel
+    let slot = decoder.channels.iter().position(|c| matches!(c, Some(c) if c.channel_id == CHANNEL)).unwrap();
+    decoder.channels[slot] = None;
+
+    let err = decoder.decode(&frame(CHANNEL, T)).unwrap_err();
+    assert!(matches!(err, SubscriptionError::ChannelInactive));
+    assert_eq!(err.error_code(), ErrorCode::ChannelInactive);
+}
@@ -0,0 +1,63 @@
+//! The key tree descent is bounded by the tree depth: a stored password is found within
+//! 64 steps of the root for every timestamp, and no step can go below a leaf.
+use decoder::modules::channel_manager::{
+    child_node, find_subscription_page, read_subscription, stored_ancestor, ChannelSubscription, SubscriptionError,
+    TREE_DEPTH,
+};
+use decoder_host_tests::{frame, subscription, Decoder, Rng};
+
+const START: u64 = 1_700_000_000_000_001;
+const END: u64 = 1_700_000_123_456_789;
+
+fn stored(decoder: &mut Decoder, channel: u32) -> ChannelSubscription {
+    let (addr, _) = find_subscription_page(&mut decoder.flash, |info| info.channel_id == channel).unwrap();
+    read_subscription(&mut decoder.flash, addr).unwrap()
+}
+
+#[test]
+fn descent_never_exceeds_the_tree_depth() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(1, 0, u64::MAX)).unwrap();
+    decoder.subscribe(&subscription(2, 1000, 1000)).unwrap();
+    decoder.subscribe(&subscription(3, START, END)).unwrap();
+    let full = stored(&mut decoder, 1);
+    let single = stored(&mut decoder, 2);
+    let ranged = stored(&mut decoder, 3);
+
+    let mut rng = Rng::new(0x7EE5);
+    let timestamps = [0, 1, u64::MAX - 1, u64::MAX].into_iter().chain((0..1000).map(|_| rng.next_u64()));
+    for t in timestamps {
+        let (depth, password) = stored_ancestor(&full, t).unwrap();
+        assert!(depth <= TREE_DEPTH, "timestamp {:#x}", t);
+        let leaf = (1u128 << 64) | t as u128;
+        assert_eq!(password.node_num(), leaf >> (TREE_DEPTH - depth));
+
+        let t = START + t % (END - START + 1);
+        let (depth, password) = stored_ancestor(&ranged, t).unwrap();
+        assert!(depth <= TREE_DEPTH, "timestamp {:#x}", t);
+        assert_eq!(password.node_num(), ((1u128 << 64) | t as u128) >> (TREE_DEPTH - depth));
+    }
+
+    // A one-timestamp subscription stores the leaf, the deepest a descent goes
+    let (depth, _) = stored_ancestor(&single, 1000).unwrap();
+    assert_eq!(depth, TREE_DEPTH);
+    assert!(matches!(stored_ancestor(&single, 1001), Err(SubscriptionError::PasswordNotFound)));
+
+    for t in [0, u64::MAX] {
+        decoder.decode(&frame(1, t)).unwrap_or_else(|e| panic!("timestamp {:#x}: {}", t, e));
+    }
+}
+
+#[test]
+fn child_steps_stop_at_the_leaves() {
+    assert_eq!(child_node(1, 1).unwrap(), 2);
+    assert_eq!(child_node(1, 2).unwrap(), 3);
+    // The last internal node's children are the last leaf, and nothing below it
+    let last_internal = (1u128 << 64) - 1;
+    assert_eq!(child_node(last_internal, 2).unwrap(), (1u128 << 65) - 1);
+    assert!(matches!(child_node((1u128 << 65) - 1, 1), Err(SubscriptionError::InvalidPath)));
+    assert!(matches!(child_node(u128::MAX, 2), Err(SubscriptionError::InvalidPath)));
+    for branch in [0, 3] {
+        assert!(matches!(child_node(1, branch), Err(SubscriptionError::InvalidPath)));
+    }
+}
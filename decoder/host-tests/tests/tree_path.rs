@@ -0,0 +1,56 @@
+//! The path to a frame's leaf is checked at every step: a branch other than 1 or 2,
+//! the value an unfilled path entry holds, is refused, and the deepest timestamps
+//! descend the full 64 levels without reading past the path.
+use bytemuck::Zeroable;
+use decoder::modules::channel_manager::{
+    child_node, derive_frame_key, ChannelPassword, ChannelSubscription, FrameKeyCache, SubscriptionError, TREE_DEPTH,
+};
+use decoder::modules::test_vectors::{leaf_node, node_key};
+
+const ROOT: [u8; 16] = [0x42; 16];
+
+/// A subscription holding the single password of `node_num`, derived from `ROOT`.
+fn holding(node_num: u128) -> ChannelSubscription {
+    let mut subscription = ChannelSubscription::zeroed();
+    subscription.passwords.contents[0] = ChannelPassword {
+        node_trunc: (node_num >> 1) as u64,
+        node_ext: (node_num & 1) as u8 + 1,
+        password: node_key(&ROOT, node_num),
+    };
+    subscription
+}
+
+#[test]
+fn malformed_branches_are_refused() {
+    for node in [1, leaf_node(0) >> 1] {
+        for branch in [0, 3, u8::MAX] {
+            assert!(matches!(child_node(node, branch), Err(SubscriptionError::InvalidPath)));
+        }
+    }
+    // A path one step longer than the tree runs past the leaves
+    let mut node = 1;
+    for _ in 0..TREE_DEPTH {
+        node = child_node(node, 2).unwrap();
+    }
+    assert_eq!(node, leaf_node(u64::MAX));
+    assert!(matches!(child_node(node, 1), Err(SubscriptionError::InvalidPath)));
+}
+
+#[test]
+fn deepest_timestamps_take_the_whole_path() {
+    // From the root, every level of the path is derived
+    let root = holding(1);
+    for timestamp in [0, 1, u64::MAX - 1, u64::MAX] {
+        let key = derive_frame_key(&root, timestamp, &mut FrameKeyCache::new()).unwrap();
+        assert_eq!(key, node_key(&ROOT, leaf_node(timestamp)));
+    }
+
+    // From the leaf itself, none is, and the descent ends at depth 64
+    for timestamp in [0, u64::MAX] {
+        let leaf = holding(leaf_node(timestamp));
+        let key = derive_frame_key(&leaf, timestamp, &mut FrameKeyCache::new()).unwrap();
+        assert_eq!(key, node_key(&ROOT, leaf_node(timestamp)));
+        let other = timestamp ^ 1;
+        assert!(matches!(derive_frame_key(&leaf, other, &mut FrameKeyCache::new()), Err(SubscriptionError::PasswordNotFound)));
+    }
+}
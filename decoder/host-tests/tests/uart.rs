@@ -0,0 +1,42 @@
+//! The non-blocking UART read, and the console's header polling built on it.
+use decoder::modules::hostcom_manager::{HostConsole, MsgType, UartHalOps};
+use decoder_host_tests::MockUart;
+
+#[test]
+fn try_read_byte_is_none_until_data_is_queued() {
+    let mut uart = MockUart::default();
+    assert_eq!(uart.try_read_byte(), None);
+    assert_eq!(uart.try_read_byte(), None);
+
+    uart.queue(&[0x41, 0x42]);
+    assert_eq!(uart.try_read_byte(), Some(0x41));
+    assert_eq!(uart.try_read_byte(), Some(0x42));
+    assert_eq!(uart.try_read_byte(), None);
+}
+
+#[test]
+fn poll_once_returns_a_header_once_all_pieces_arrive() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    assert!(console.poll_once().is_none());
+
+    // Line noise before the magic, then the header split over several polls
+    for piece in [&[0x00, 0x7f][..], b"%", b"D", &[156]] {
+        uart.queue(piece);
+        assert!(console.poll_once().is_none());
+        assert_eq!(uart.pending(), 0);
+    }
+    uart.queue(&[0]);
+    let header = console.poll_once().unwrap();
+    assert_eq!(header.opcode, MsgType::Decode as u8);
+    assert_eq!({ header.length }, 156);
+    assert!(console.poll_once().is_none());
+
+    // A header started by polling is finished by the blocking read
+    uart.queue(b"%L");
+    assert!(console.poll_once().is_none());
+    uart.queue(&[0, 0]);
+    let header = console.read_header();
+    assert_eq!(header.opcode, MsgType::List as u8);
+    assert_eq!({ header.length }, 0);
+}
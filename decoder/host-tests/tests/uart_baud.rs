@@ -0,0 +1,15 @@
+//! The host UART baud rate comes from `UART_BAUD` at build time, 115200 by default, and
+//! only a standard rate builds.
+use decoder::UART_BAUD;
+
+#[test]
+fn baud_rate_follows_the_build_environment() {
+    // Read when the tests are built, as build.rs reads it for the decoder
+    let expected = option_env!("UART_BAUD").map_or(115_200, |baud| baud.parse().unwrap());
+    assert_eq!(UART_BAUD, expected);
+}
+
+#[test]
+fn baud_rate_is_a_standard_rate() {
+    assert!([9600, 19200, 38400, 57600, 115_200, 230_400, 460_800, 921_600].contains(&UART_BAUD), "{UART_BAUD}");
+}
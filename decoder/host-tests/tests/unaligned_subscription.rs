@@ -0,0 +1,31 @@
+//! Subscription bodies are parsed without assuming any alignment: the password table
+//! is read by copy, so a body starting at an odd address stores and decodes.
+use decoder::modules::channel_manager::{ChannelPasswords, ChannelSubscription};
+use decoder::modules::test_vectors::encode_subscription_bundle;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, frame_content, host_key, subscription, Decoder};
+use std::mem::align_of;
+
+const T: u64 = 1_700_000_000_000_000;
+
+#[test]
+fn records_need_no_alignment() {
+    assert_eq!(align_of::<ChannelPasswords>(), 1);
+    assert_eq!(align_of::<ChannelSubscription>(), 1);
+}
+
+#[test]
+fn odd_offset_bodies_store_and_decode() {
+    let bundle = encode_subscription_bundle(&host_key(), DECODER_ID, &[subscription(1, 0, u64::MAX)]);
+    // The entry starts 3 bytes into the bundle; shifting the whole bundle by one byte
+    // moves it to the other parity
+    for shift in [0, 1] {
+        let mut buffer = vec![0u8; shift];
+        buffer.extend_from_slice(&bundle);
+        let body = &buffer[shift..];
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.subscribe_bundle(body).unwrap(), 1);
+        assert_eq!(decoder.decode(&frame(1, T)).unwrap(), frame_content(T));
+    }
+}
@@ -0,0 +1,36 @@
+//! A command with an unknown opcode is refused with an Error packet after its body is
+//! drained, so the command after it parses cleanly.
+use decoder::modules::hostcom_manager::{ErrorCode, HostConsole, MessageHeader, MsgType, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+const UNKNOWN: u8 = b'z';
+
+#[test]
+fn unknown_opcode_body_is_drained_before_the_next_command() {
+    assert!(MsgType::try_from(UNKNOWN).is_err());
+    let uart = MockUart::default();
+    let length: u16 = 300;
+    uart.queue(&[MSG_MAGIC, UNKNOWN]);
+    uart.queue(&length.to_le_bytes());
+    // The body's first bytes look like a header, as a misparse would take them
+    uart.queue(bytemuck::bytes_of(&MessageHeader::new(MsgType::Decode, 0)));
+    uart.queue(&vec![0x5A; length as usize - 4]);
+    // The host ACKs the Error packet, then sends a List
+    uart.queue(&ACK);
+    uart.queue(bytemuck::bytes_of(&MessageHeader::new(MsgType::List, 0)));
+
+    // As the main loop's catch-all does
+    let mut console = HostConsole::new(uart.clone());
+    let hdr = console.read_header();
+    assert_eq!(hdr.opcode, UNKNOWN);
+    console.reject_command(hdr.length);
+
+    assert_eq!(console.read_header().opcode, MsgType::List as u8);
+    assert_eq!(uart.pending(), 0);
+
+    // The header and both body chunks were ACKed, and the answer is an Error packet
+    let sent = uart.take_sent();
+    assert_eq!(sent[..12], ACK.repeat(3));
+    assert_eq!(sent[sent.len() - 5..], [MSG_MAGIC, MsgType::Error as u8, 1, 0, ErrorCode::UnknownCommand as u8]);
+}
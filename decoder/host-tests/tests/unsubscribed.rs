@@ -0,0 +1,25 @@
+//! Decode on a decoder holding only the built-in channel 0.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder_host_tests::{frame, Decoder};
+
+#[test]
+fn unsubscribed_channel_is_reported_before_the_signature_check() {
+    let mut decoder = Decoder::new();
+
+    let signed = frame(1, 1000);
+    let result = decoder.decode(&signed);
+    assert!(matches!(result, Err(SubscriptionError::NoSubscription)));
+    assert_eq!(result.unwrap_err().error_code(), ErrorCode::NoSubscription);
+
+    // Verifying would have failed this one, so the signature was never checked
+    let mut unsigned = signed;
+    unsigned[unsigned.len() - 64..].fill(0);
+    assert!(matches!(decoder.decode(&unsigned), Err(SubscriptionError::NoSubscription)));
+
+    // Channel 0 needs no subscription, and its signature is still checked
+    decoder.decode(&frame(0, 1000)).unwrap();
+    let mut unsigned = frame(0, 1001);
+    unsigned[unsigned.len() - 64..].fill(0);
+    assert!(matches!(decoder.decode(&unsigned), Err(SubscriptionError::InvalidSignature)));
+}
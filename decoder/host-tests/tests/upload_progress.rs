@@ -0,0 +1,51 @@
+//! Body upload progress: with reports on, every chunk ACK is followed by a Debug packet
+//! giving the bytes received so far; without, `read_body` sends ACKs only.
+use decoder::modules::hostcom_manager::{HostConsole, LogLevel, MessageBody, MsgType, CHUNK_SIZE, MAX_BODY_LEN, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
+
+fn progress_packet(received: usize, total: usize) -> Vec<u8> {
+    let text = format!("progress {}/{}\n", received, total);
+    let mut packet = vec![MSG_MAGIC, MsgType::Debug as u8];
+    packet.extend_from_slice(&(1 + text.len() as u16).to_le_bytes());
+    packet.push(LogLevel::Info as u8);
+    packet.extend_from_slice(text.as_bytes());
+    packet
+}
+
+fn read(console: &mut HostConsole<MockUart>, uart: &MockUart, length: usize) -> Vec<u8> {
+    let data: Vec<u8> = (0..length).map(|i| i as u8).collect();
+    uart.queue(&data);
+    let mut body = MessageBody { data: [0; MAX_BODY_LEN], length: 0 };
+    console.read_body(length as u16, &mut body).unwrap();
+    assert_eq!(body.data[..length], data[..]);
+    uart.take_sent()
+}
+
+#[test]
+fn progress_follows_every_chunk_ack() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone()).with_progress_reports();
+
+    // Two full chunks and a partial one
+    let total = 2 * CHUNK_SIZE + 88;
+    let mut expected = Vec::new();
+    for received in [CHUNK_SIZE, 2 * CHUNK_SIZE, total] {
+        expected.extend_from_slice(&ACK);
+        expected.extend(progress_packet(received, total));
+    }
+    assert_eq!(read(&mut console, &uart, total), expected);
+
+    // A body of exactly one chunk reports once
+    let mut expected = ACK.to_vec();
+    expected.extend(progress_packet(CHUNK_SIZE, CHUNK_SIZE));
+    assert_eq!(read(&mut console, &uart, CHUNK_SIZE), expected);
+}
+
+#[test]
+fn without_reports_only_acks_are_sent() {
+    let uart = MockUart::default();
+    let mut console = HostConsole::new(uart.clone());
+    assert_eq!(read(&mut console, &uart, 2 * CHUNK_SIZE + 88), ACK.repeat(3));
+}
@@ -0,0 +1,46 @@
+//! A subscription must decrypt to at least one usable password, so a key or nonce
+//! mismatch is refused at Subscribe instead of failing every frame after it.
+use bytemuck::bytes_of;
+use decoder::modules::channel_manager::{find_subscription_page, ChannelPassword, SubscriptionError};
+use decoder::modules::hostcom_manager::ErrorCode;
+use decoder::modules::test_vectors::{covering_nodes, node_key};
+use decoder_host_tests::{channel_root, frame, host_key, subscription, Decoder};
+use ed25519_dalek::Signer;
+
+const CHANNEL: u32 = 1;
+const HEADER_LEN: usize = 4 + 8 + 8 + 4 + 12;
+
+/// A signed subscription for `CHANNEL` whose password blob is the keystream itself,
+/// so it decrypts to all zeros.
+fn zero_blob_subscription() -> Vec<u8> {
+    let mut body = subscription(CHANNEL, 0, u64::MAX);
+    let msg_len = body.len() - 64;
+
+    // The whole window is covered by the root alone
+    let [node] = covering_nodes(0, u64::MAX)[..] else { panic!("expected a single covering node") };
+    let password = ChannelPassword {
+        node_trunc: (node >> 1) as u64,
+        node_ext: (node & 1) as u8 + 1,
+        password: node_key(&channel_root(CHANNEL), node),
+    };
+    // Ciphertext xor plaintext is the keystream
+    for (byte, plain) in body[HEADER_LEN..msg_len].iter_mut().zip(bytes_of(&password)) {
+        *byte ^= plain;
+    }
+
+    let signature = host_key().sign(&body[..msg_len]).to_bytes();
+    body[msg_len..].copy_from_slice(&signature);
+    body
+}
+
+#[test]
+fn all_zero_passwords_are_refused() {
+    let mut decoder = Decoder::new();
+    let result = decoder.subscribe(&zero_blob_subscription());
+    assert!(matches!(result, Err(SubscriptionError::NoUsablePasswords)));
+    assert_eq!(result.unwrap_err().error_code(), ErrorCode::NoUsablePasswords);
+
+    assert!(find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).is_none());
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, 10)).unwrap();
+}
@@ -0,0 +1,87 @@
+//! A signed Window command moves the end of a stored subscription: later at will,
+//! earlier only with the shrink flag, and only from the end it was issued for. A body
+//! of any other length is refused before its signature is checked. The new end goes
+//! in the page's log, so moving the window erases no flash.
+use decoder::modules::channel_manager::{find_subscription_page, update_subscription_window, SubscriptionError};
+use decoder::modules::test_vectors::encode_window;
+use decoder::DECODER_ID;
+use decoder_host_tests::{frame, host_key, subscription, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn window(decoder: &mut Decoder, body: &[u8]) -> Result<(), SubscriptionError> {
+    let result = update_subscription_window(&mut decoder.flash, body, &mut decoder.channels);
+    decoder.context.invalidate();
+    result
+}
+
+fn stored_end(decoder: &mut Decoder) -> u64 {
+    find_subscription_page(&mut decoder.flash, |info| info.channel_id == CHANNEL).unwrap().1.end_timestamp
+}
+
+#[test]
+fn window_is_extended() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+
+    let extend = encode_window(&host_key(), DECODER_ID, CHANNEL, T, T + 1_000, false);
+    window(&mut decoder, &extend).unwrap();
+    assert_eq!(stored_end(&mut decoder), T + 1_000);
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+
+    // The update names the end it replaces, so it applies once
+    assert!(matches!(window(&mut decoder, &extend), Err(SubscriptionError::StaleSubscription)));
+    let mut decoder = decoder.reboot();
+    assert_eq!(stored_end(&mut decoder), T + 1_000);
+}
+
+#[test]
+fn shrink_is_refused_without_its_flag() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+
+    let shrink = encode_window(&host_key(), DECODER_ID, CHANNEL, u64::MAX, T, false);
+    assert!(matches!(window(&mut decoder, &shrink), Err(SubscriptionError::StaleSubscription)));
+    assert_eq!(stored_end(&mut decoder), u64::MAX);
+    decoder.decode(&frame(CHANNEL, T + 1)).unwrap();
+
+    // With the flag the window ends at T, and later frames are refused
+    let shrink = encode_window(&host_key(), DECODER_ID, CHANNEL, u64::MAX, T + 1, true);
+    window(&mut decoder, &shrink).unwrap();
+    assert_eq!(stored_end(&mut decoder), T + 1);
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 2)), Err(SubscriptionError::SubscriptionExpired)));
+}
+
+#[test]
+fn body_of_another_length_is_refused() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, T)).unwrap();
+
+    let body = encode_window(&host_key(), DECODER_ID, CHANNEL, T, T + 1_000, false);
+    assert!(matches!(window(&mut decoder, &body[..body.len() - 1]), Err(SubscriptionError::InvalidLength)));
+    let mut long = body.clone();
+    long.push(0);
+    assert!(matches!(window(&mut decoder, &long), Err(SubscriptionError::InvalidLength)));
+    assert_eq!(stored_end(&mut decoder), T);
+}
+
+#[test]
+fn window_moves_without_an_erase() {
+    let mut decoder = Decoder::new();
+    decoder.subscribe(&subscription(CHANNEL, 0, u64::MAX)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+    let erases = decoder.flc.erase_count();
+
+    // Narrowed, then widened again: setting bits back needs no erase either
+    window(&mut decoder, &encode_window(&host_key(), DECODER_ID, CHANNEL, u64::MAX, T + 1, true)).unwrap();
+    assert_eq!(stored_end(&mut decoder), T + 1);
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 2)), Err(SubscriptionError::SubscriptionExpired)));
+    window(&mut decoder, &encode_window(&host_key(), DECODER_ID, CHANNEL, T + 1, T + 2, false)).unwrap();
+    assert_eq!(decoder.flc.erase_count(), erases);
+    decoder.decode(&frame(CHANNEL, T + 2)).unwrap();
+
+    let mut decoder = decoder.reboot();
+    assert_eq!(stored_end(&mut decoder), T + 2);
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T + 3)), Err(SubscriptionError::SubscriptionExpired)));
+}
@@ -0,0 +1,29 @@
+//! The host protocol's integers are little-endian, read at any offset of a body.
+use decoder::modules::channel_manager::ChannelFrame;
+use decoder::modules::wire::{read_u16_le, read_u32_le, read_u64_le};
+use decoder_host_tests::frame;
+
+const BYTES: [u8; 9] = [0xAA, 1, 2, 3, 4, 5, 6, 7, 8];
+
+#[test]
+fn helpers_read_little_endian_at_an_offset() {
+    assert_eq!(read_u16_le(&BYTES, 1), 0x0201);
+    assert_eq!(read_u32_le(&BYTES, 1), 0x0403_0201);
+    assert_eq!(read_u64_le(&BYTES, 1), 0x0807_0605_0403_0201);
+    assert_eq!(read_u16_le(&BYTES, 0), 0x01AA);
+}
+
+#[test]
+#[should_panic]
+fn helpers_panic_past_the_end() {
+    read_u32_le(&BYTES, 6);
+}
+
+#[test]
+fn frame_header_fields_are_little_endian() {
+    let body = frame(3, 0x1122_3344_5566_7788);
+    assert_eq!(body[..12], [3, 0, 0, 0, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11]);
+    let parsed = ChannelFrame::from_le_bytes(&body).unwrap();
+    assert_eq!({ parsed.channel }, 3);
+    assert_eq!({ parsed.timestamp }, 0x1122_3344_5566_7788);
+}
@@ -0,0 +1,30 @@
+//! The wire structs have the sizes the host tools pack them to, field for field, and
+//! the encoder's bodies come out at exactly those sizes.
+use core::mem::size_of;
+use decoder::modules::channel_manager::{
+    ChannelFrame, ChannelPassword, ChannelPasswords, ChannelSubscription, PASSWORD_TREE_NODES,
+};
+use decoder::modules::hostcom_manager::{ChannelInfo, MessageHeader};
+use decoder::modules::test_vectors::covering_nodes;
+use decoder_host_tests::{frame, subscription};
+
+#[test]
+fn wire_structs_have_their_packed_sizes() {
+    // magic, opcode, length
+    assert_eq!(size_of::<MessageHeader>(), 4);
+    // channel, start, end
+    assert_eq!(size_of::<ChannelInfo>(), 20);
+    // node_trunc, node_ext, password
+    assert_eq!(size_of::<ChannelPassword>(), 25);
+    assert_eq!(size_of::<ChannelPasswords>(), PASSWORD_TREE_NODES * 25);
+    assert_eq!(size_of::<ChannelSubscription>(), 20 + PASSWORD_TREE_NODES * 25);
+    // channel, timestamp, nonce, content, marker, signature
+    assert_eq!(size_of::<ChannelFrame>(), 4 + 8 + 12 + 64 + 4 + 64);
+}
+
+#[test]
+fn encoded_bodies_match_the_struct_sizes() {
+    assert_eq!(frame(1, 1000).len(), size_of::<ChannelFrame>());
+    let nodes = covering_nodes(1000, 5000).len();
+    assert_eq!(subscription(1, 1000, 5000).len(), 36 + nodes * size_of::<ChannelPassword>() + 64);
+}
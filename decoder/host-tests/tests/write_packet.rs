@@ -0,0 +1,71 @@
+//! `write_packet` sends the header, waits for the host's ACK of it and of every body
+//! chunk, and skips the handshake for packets the host never acknowledges. A body a
+//! u16 length cannot describe is refused before anything is sent.
+use decoder::modules::hostcom_manager::{write_packet, MsgType, CHUNK_SIZE, MSG_MAGIC};
+use decoder_host_tests::MockUart;
+
+const ACK: [u8; 4] = [MSG_MAGIC, b'A', 0, 0];
+
+fn header(msg_type: MsgType, length: u16) -> Vec<u8> {
+    let mut header = vec![MSG_MAGIC, msg_type as u8];
+    header.extend_from_slice(&length.to_le_bytes());
+    header
+}
+
+#[test]
+fn packet_without_a_body_is_a_header_and_one_ack() {
+    let mut uart = MockUart::default();
+    uart.queue(&ACK);
+    assert_eq!(write_packet(&mut uart, MsgType::List, None), 0);
+    assert_eq!(uart.take_sent(), header(MsgType::List, 0));
+    assert_eq!(uart.pending(), 0);
+
+    // An empty body is the same packet
+    uart.queue(&ACK);
+    assert_eq!(write_packet(&mut uart, MsgType::List, Some(&[])), 0);
+    assert_eq!(uart.take_sent(), header(MsgType::List, 0));
+    assert_eq!(uart.pending(), 0);
+}
+
+#[test]
+fn body_is_sent_in_acknowledged_chunks() {
+    let body: Vec<u8> = (0..CHUNK_SIZE + 20).map(|i| i as u8).collect();
+    let mut uart = MockUart::default();
+    // The header's ACK and one per chunk: a full one and the 20 byte rest
+    for _ in 0..3 {
+        uart.queue(&ACK);
+    }
+    assert_eq!(write_packet(&mut uart, MsgType::Decode, Some(&body)), 0);
+
+    let mut expected = header(MsgType::Decode, body.len() as u16);
+    expected.extend_from_slice(&body);
+    assert_eq!(uart.take_sent(), expected);
+    assert_eq!(uart.pending(), 0);
+}
+
+#[test]
+fn body_waits_for_the_header_ack() {
+    let mut uart = MockUart::default();
+    // The host answers the header with anything but an ACK
+    uart.queue(&[MSG_MAGIC, b'E']);
+    assert_eq!(write_packet(&mut uart, MsgType::Decode, Some(&[1, 2, 3])), -1);
+    assert_eq!(uart.take_sent(), header(MsgType::Decode, 3));
+}
+
+#[test]
+fn debug_packet_needs_no_ack() {
+    // Nothing is queued, so any ACK read would panic
+    let mut uart = MockUart::default();
+    assert_eq!(write_packet(&mut uart, MsgType::Debug, Some(b"hi")), 0);
+    let mut expected = header(MsgType::Debug, 2);
+    expected.extend_from_slice(b"hi");
+    assert_eq!(uart.take_sent(), expected);
+}
+
+#[test]
+fn oversized_body_is_never_sent() {
+    let mut uart = MockUart::default();
+    let body = vec![0; usize::from(u16::MAX) + 1];
+    assert_eq!(write_packet(&mut uart, MsgType::Decode, Some(&body)), -1);
+    assert!(uart.take_sent().is_empty());
+}
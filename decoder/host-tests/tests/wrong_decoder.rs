@@ -0,0 +1,35 @@
+//! A Subscribe for another decoder is turned away on its decoder id, before its
+//! signature is checked, and nothing of it reaches flash.
+use decoder::modules::channel_manager::SubscriptionError;
+use decoder::modules::test_vectors::encode_subscription;
+use decoder::{DECODER_ID, DECODER_KEY};
+use decoder_host_tests::{channel_root, frame, host_key, Decoder};
+
+const CHANNEL: u32 = 1;
+const T: u64 = 1_700_000_000_000_000;
+
+fn subscription_for(decoder_id: u32) -> Vec<u8> {
+    encode_subscription(&host_key(), &DECODER_KEY, decoder_id, &channel_root(CHANNEL), CHANNEL, 0, u64::MAX, [0x5A; 12])
+}
+
+#[test]
+fn other_decoder_is_refused_without_a_write() {
+    let mut decoder = Decoder::new();
+    let (writes, erases) = (decoder.flc.write_count(), decoder.flc.erase_count());
+
+    let body = subscription_for(DECODER_ID ^ 1);
+    assert!(matches!(decoder.subscribe(&body), Err(SubscriptionError::InvalidDecoderId)));
+    // A forged signature makes no difference: the id is checked first
+    let mut forged = body.clone();
+    *forged.last_mut().unwrap() ^= 1;
+    assert!(matches!(decoder.subscribe(&forged), Err(SubscriptionError::InvalidDecoderId)));
+
+    assert_eq!((decoder.flc.write_count(), decoder.flc.erase_count()), (writes, erases));
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::NoSubscription)));
+    let mut decoder = decoder.reboot();
+    assert!(matches!(decoder.decode(&frame(CHANNEL, T)), Err(SubscriptionError::NoSubscription)));
+
+    // The same subscription addressed to this decoder is stored
+    decoder.subscribe(&subscription_for(DECODER_ID)).unwrap();
+    decoder.decode(&frame(CHANNEL, T)).unwrap();
+}
@@ -2,7 +2,8 @@ MEMORY
 {
     ROM         (rx) : ORIGIN = 0x00000000, LENGTH = 0x00010000 /* 64kB ROM */
     BOOTLOADER  (rx) : ORIGIN = 0x10000000, LENGTH = 0x0000E000 /* Bootloader flash */
-    FLASH       (rx) : ORIGIN = 0x1000E000, LENGTH = 0x00054000 /* Location of team firmware */
+    FLASH       (rx) : ORIGIN = 0x1000E000, LENGTH = 0x0004C000 /* Location of team firmware */
+    SUBSCRIPTIONS2 (rw) : ORIGIN = 0x1005A000, LENGTH = 0x00008000 /* Second subscription region */
     RESERVED    (rw) : ORIGIN = 0x10062000, LENGTH = 0x0001C000 /* Reserved */
     ROM_BL_PAGE (rw) : ORIGIN = 0x1007E000, LENGTH = 0x00002000 /* Reserved */
     RAM         (rwx): ORIGIN = 0x20000000, LENGTH = 0x00010000 /* 64kB RAM */
@@ -0,0 +1,20 @@
+//! The decoder's protocol, key tree and flash logic, shared by the firmware binary and,
+//! with the `std` feature, by the host tests in `host-tests/`.
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+// The std build swaps flash for RAM and must never reach the device.
+#[cfg(all(feature = "std", target_os = "none"))]
+compile_error!("the std feature is for host test builds only");
+
+// Include the generated secrets.
+include!(concat!(env!("OUT_DIR"), "/secrets.rs"));
+
+pub mod modules;
+
+pub extern crate max7800x_hal as hal;
+
+pub use hal::flc::FlashError;
+pub use hal::pac;
@@ -1,31 +1,85 @@
 #![no_std]
 #![no_main]
 
-// Include the generated secrets.
-include!(concat!(env!("OUT_DIR"), "/secrets.rs"));
+use decoder::modules;
+use decoder::UART_BAUD;
 
-pub mod modules;
+// Passthrough skips every frame check; the release build shipped to the device must
+// never contain it.
+#[cfg(all(feature = "decode-passthrough", not(debug_assertions)))]
+compile_error!("decode-passthrough is for debug builds only and cannot be built with --release");
+
+// The dumps expose each subscription's tree layout and replay state to any host, so
+// no release build may answer them.
+#[cfg(all(feature = "debug-dump", not(debug_assertions)))]
+compile_error!("debug-dump is for debug builds only and cannot be built with --release");
+
+// The page dump sends out subscription passwords, so no release build may answer it.
+#[cfg(all(feature = "page-dump", not(debug_assertions)))]
+compile_error!("page-dump is for debug builds only and cannot be built with --release");
+
+// Any host could reset the device at will, so release builds leave the reset out.
+#[cfg(all(feature = "soft-reset", not(debug_assertions)))]
+compile_error!("soft-reset is for debug builds only and cannot be built with --release");
+
+// Any host could lock the decoder without a signature, so release builds leave it out.
+#[cfg(all(feature = "tamper-command", not(debug_assertions)))]
+compile_error!("tamper-command is for debug builds only and cannot be built with --release");
 
 pub extern crate max7800x_hal as hal;
 
-use bytemuck;
+use bytemuck::Zeroable;
 pub use hal::entry;
 pub use hal::flc::{FlashError, Flc};
 pub use hal::gcr::clocks::{Clock, SystemClock};
 pub use hal::pac;
-use modules::channel_manager::check_subscription_valid_and_store;
-use modules::channel_manager::{decode_frame, ChannelFrame, ActiveChannelsList, initialize_active_channels};
+use modules::channel_manager::{apply_subscription_bundle, check_subscribe_preamble, finish_subscription_bundle, check_subscription_valid_and_store, validate_subscribe_length, set_channel_paused, update_subscription_window, PAUSE_BODY_LEN, PREAMBLE_BODY_LEN, WINDOW_BODY_LEN};
+#[cfg(feature = "page-dump")]
+use modules::channel_manager::{dump_subscription_page, PAGE_DUMP_LEN};
+#[cfg(feature = "debug-dump")]
+use modules::channel_manager::{dump_replay_state, dump_subscription_nodes, resync_active_channels, NODE_DUMP_MAX_LEN, REPLAY_STATE_MAX_LEN};
+#[cfg(not(feature = "decode-passthrough"))]
+use modules::channel_manager::{decode_frame, verify_probe_frame, ChannelFrame};
+use modules::channel_manager::{free_subscription_pages, host_key_fingerprint, validate_all_subscriptions, validate_frame_length, ActiveChannelsList, initialize_active_channels, DecodeContext, ACTIVE_CHANNELS_LEN};
+#[cfg(feature = "rtc-time")]
+use modules::clock::{WallClock, SET_TIME_BODY_LEN};
+use modules::crc::Crc32;
+#[cfg(feature = "dma-uart")]
+use modules::dma_uart::DmaRx;
+use modules::emergency_manager::{read_emergency_state, set_emergency_only, EMERGENCY_BODY_LEN};
+use modules::entropy::CheckedRng;
 use modules::flash_manager::FlashManager;
-use modules::hostcom_manager::{
-    read_ack, read_body, read_header, write_ack, write_debug, write_error, write_list,
-    MessageHeader, MsgType, MSG_MAGIC,
-};
+#[cfg(not(feature = "decode-passthrough"))]
+use modules::frame_sink::{route_frame, HostSink};
+use modules::rate_limiter::RateLimiter;
+use modules::reset_cause::{take_reset_flags, ResetReason};
+#[cfg(feature = "soft-reset")]
+use modules::reset::{handle_reset, ScbReset};
+#[cfg(feature = "resumable-upload")]
+use modules::channel_manager::SubscriptionError;
+#[cfg(feature = "resumable-upload")]
+use modules::upload_manager::{receive_upload_chunk, upload_received, UPLOAD_CHUNK_HEADER_LEN, UPLOAD_CHUNK_MAX_LEN, UPLOAD_STATUS_BODY_LEN};
+use modules::state_manager::StateManager;
+use modules::subscribe_handshake::SubscribeHandshake;
+#[cfg(feature = "brownout")]
+use modules::supply_monitor::SupplyMonitor;
+use modules::tamper_manager::{can_accept_command, clear_tamper_flag};
+#[cfg(feature = "tamper-command")]
+use modules::tamper_manager::set_tamper_flag;
+use modules::telemetry::{Telemetry, TELEMETRY_MAX_LEN};
+#[cfg(any(feature = "debug-dump", feature = "page-dump", feature = "resumable-upload"))]
+use modules::wire::read_u32_le;
+#[cfg(feature = "rekey")]
+use modules::key_manager::{DeviceKey, REKEY_BODY_LEN};
+use modules::hostcom_manager::{ErrorCode, HostConsole, LogLevel, MessageBody, MsgType};
 use panic_halt as _; // Import panic handler
 
 #[entry]
 fn main() -> ! {
     // Take ownership of the MAX78000 peripherals.
     let p = pac::Peripherals::take().unwrap();
+    // Read before anything else can reset the watchdogs' flags.
+    let reset_reason = ResetReason::from_flags(take_reset_flags([&p.wdt, &p.wdt1]));
 
     // Initialize system peripherals and clocks.
     let mut gcr = hal::gcr::Gcr::new(p.gcr, p.lpgcr);
@@ -37,104 +91,708 @@ fn main() -> ! {
 
     // Initialize and split the GPIO0 peripheral into pins.
     let gpio0_pins = hal::gpio::Gpio0::new(p.gpio0, &mut gcr.reg).split();
-    // Configure UART to host computer with 115200 8N1 settings.
+    // Configure UART to host computer with UART_BAUD (115200 by default) 8N1 settings.
     let rx_pin = gpio0_pins.p0_0.into_af1();
     let tx_pin = gpio0_pins.p0_1.into_af1();
-    let mut console = hal::uart::UartPeripheral::uart0(p.uart0, &mut gcr.reg, rx_pin, tx_pin)
-        .baud(115200)
+    let uart = hal::uart::UartPeripheral::uart0(p.uart0, &mut gcr.reg, rx_pin, tx_pin)
+        .baud(UART_BAUD)
         .clock_pclk(&clks.pclk)
         .parity(hal::uart::ParityBit::None)
         .build();
 
+    // SysTick-based delay for throttling repeated signature failures.
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let mut rate_limiter = RateLimiter::new(cortex_m::delay::Delay::new(cp.SYST, clks.sys_clk.frequency));
+
     // Initialize the flash controller.
     let flc = hal::flc::Flc::new(p.flc, clks.sys_clk);
     // Use the HAL's blocking write_byte for text output.
     for &b in b"Flash controller initialized!\r\n" {
-        console.write_byte(b);
+        uart.write_byte(b);
+    }
+
+    // All further traffic goes through the host protocol.
+    let console = HostConsole::new(uart);
+    #[cfg(feature = "dma-uart")]
+    let console = console.with_dma(DmaRx::new(p.dma, &mut gcr.reg));
+    #[cfg(feature = "upload-progress")]
+    let console = console.with_progress_reports();
+    // Keep debug text off the host stream, on its own UART.
+    #[cfg(feature = "debug-uart")]
+    let console = console.with_debug_sink(
+        hal::uart::UartPeripheral::uart1(
+            p.uart1,
+            &mut gcr.reg,
+            gpio0_pins.p0_12.into_af1(),
+            gpio0_pins.p0_13.into_af1(),
+        )
+        .baud(UART_BAUD)
+        .clock_pclk(&clks.pclk)
+        .parity(hal::uart::ParityBit::None)
+        .build(),
+    );
+    let mut console = console;
+
+    // Checksum engine for flash record integrity.
+    #[cfg(feature = "hw-crc")]
+    let crc = Crc32::new(p.crc, &mut gcr.reg);
+    #[cfg(not(feature = "hw-crc"))]
+    let crc = Crc32::new();
+
+    let flash_manager = FlashManager::new(flc, crc);
+    // Refuse flash erases and writes during a brownout.
+    #[cfg(feature = "brownout")]
+    let flash_manager = flash_manager.with_supply_monitor(SupplyMonitor::new(p.adc, &mut gcr.reg));
+    let mut flash_manager = flash_manager;
+
+    // Wall clock for subscription expiry, unset until the host sends SetTime.
+    #[cfg(feature = "rtc-time")]
+    let mut clock = WallClock::new(p.rtc, &mut gcr.reg);
+
+    // Subscription decryption key, rotated by signed Rekey commands.
+    #[cfg(feature = "rekey")]
+    let mut device_key = DeviceKey::load(&mut flash_manager);
+
+    // TRNG for anything the decoder generates itself, usable once its self-test passes.
+    let mut rng = CheckedRng::new(hal::trng::Trng::new(p.trng, &mut gcr.reg));
+    if let Err(e) = rng.self_test() {
+        console.write_log_fmt(LogLevel::Error, format_args!("Error: TRNG self-test failed: {}\n", e));
     }
 
-    let mut flash_manager = FlashManager::new(flc);
+    // Decoded frames go back to the host; another FrameSink here feeds a downstream codec.
+    #[cfg(not(feature = "decode-passthrough"))]
+    let mut frame_sink = HostSink;
+
+    // Single long-lived body buffer, filled in place by read_body for every command.
+    let mut body = MessageBody::zeroed();
+
+    // Complete a subscription bundle cut short by a reset before reading the pages.
+    if let Err(e) = finish_subscription_bundle(
+        &mut flash_manager,
+        &mut body,
+        #[cfg(feature = "rekey")]
+        &device_key,
+    ) {
+        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not finish subscription bundle: {}\n", e));
+    }
 
-    let mut channels: ActiveChannelsList = [None; 9];
+    let mut channels: ActiveChannelsList = [None; ACTIVE_CHANNELS_LEN];
 
-    initialize_active_channels(&mut channels, &mut flash_manager);
+    let mut locked = initialize_active_channels(&mut channels, &mut flash_manager, &mut console);
+    // Restore the last accepted timestamp of every channel from the state log.
+    let mut state_manager = StateManager::load(&mut flash_manager, &mut channels);
+
+    // Key caches and the last read subscription, reused from frame to frame.
+    let mut decode_context = DecodeContext::new();
+    // Restore emergency-only mode, which refuses every channel but 0.
+    decode_context.set_emergency_only(read_emergency_state(&mut flash_manager).is_enabled());
+
+    // Decode and subscription counters for the Telemetry command.
+    let mut telemetry = Telemetry::zeroed();
+    telemetry.reset_reason = reset_reason as u32;
+    if reset_reason != ResetReason::PowerOn {
+        console.write_log_fmt(LogLevel::Warn, format_args!("Warning: Reset by {:?}\n", reset_reason));
+    }
+    // Count this boot; a failed write leaves the previous count in flash.
+    match state_manager.record_boot(&mut flash_manager, &channels) {
+        Ok(boot_count) => telemetry.boot_count = boot_count,
+        Err(e) => console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not record boot: {}\n", e)),
+    }
 
     loop {
         // Read the header using our new low-overhead function.
-        let hdr = read_header(&mut console);
-        match hdr.opcode {
-            x if x == MsgType::List as u8 => {
-                let _ = write_ack(&mut console);
-                let _ = write_list(&mut console, &mut flash_manager);
-            }
-            x if x == MsgType::Subscribe as u8 => {
-                let _ = write_ack(&mut console);
-                let body: modules::hostcom_manager::MessageBody = read_body(&mut console, hdr.length);
-
-                let result = check_subscription_valid_and_store(&hdr, body, &mut flash_manager, &mut channels);
-
-                // Prepare a subscribe response header.
-                let resp_hdr = MessageHeader {
-                    magic: MSG_MAGIC,
-                    opcode: MsgType::Subscribe as u8,
-                    length: 0,
+        let hdr = console.read_header();
+        console.write_log_fmt(LogLevel::Trace, format_args!("Command {:#04x}, {} bytes\n", hdr.opcode, { hdr.length }));
+        // Back off while the host keeps sending bad signatures.
+        rate_limiter.throttle();
+        // Commands that add or use subscriptions wait for the tamper lock to be cleared
+        let msg_type = MsgType::try_from(hdr.opcode);
+        if let Some(Err(code)) = msg_type.ok().map(|cmd| can_accept_command(cmd, locked)) {
+            let _ = console.write_ack();
+            console.discard_body(hdr.length);
+            console.write_log(LogLevel::Error, "Error: Decoder is locked\n");
+            let _ = console.write_error(code);
+            continue;
+        }
+        match msg_type {
+            Ok(MsgType::Ping) => console.answer_ping(hdr.length),
+            Ok(MsgType::List) => {
+                let _ = console.write_ack();
+                let _ = console.write_list(&mut flash_manager);
+            }
+            Ok(MsgType::Subscribe) => {
+                let outcome = SubscribeHandshake::new(hdr.length).run(&mut console, &mut body, |body| {
+                    check_subscription_valid_and_store(
+                        &hdr,
+                        body,
+                        &mut flash_manager,
+                        &mut channels,
+                        #[cfg(feature = "rekey")]
+                        &device_key,
+                    )
+                });
+                // Only a body that arrived whole was checked, and only it is counted
+                if let Some(result) = outcome {
+                    telemetry.record_subscription(&result);
+                    rate_limiter.record(&result);
+                    if result.is_ok() {
+                        decode_context.invalidate();
+                    }
+                }
+            }
+            Ok(MsgType::SubscribeBundle) => {
+                let _ = console.write_ack();
+                if let Err(code) = validate_subscribe_length(hdr.length) {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Bundle larger than the body buffer\n");
+                    let _ = console.write_error(code);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                let result = apply_subscription_bundle(
+                    &mut flash_manager,
+                    &body.data[..hdr.length as usize],
+                    &mut channels,
+                    #[cfg(feature = "rekey")]
+                    &device_key,
+                );
+                telemetry.record_subscription(&result);
+                rate_limiter.record(&result);
+                // Pages may have been replaced even when the bundle fails part way
+                decode_context.invalidate();
+
+                match result {
+                    Ok(count) => {
+                        let _ = console.write_packet(MsgType::SubscribeBundle, Some(&count.to_le_bytes()));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Failed to apply subscription bundle: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            // Provisioning check: a full decode whose content stays on the decoder
+            #[cfg(not(feature = "decode-passthrough"))]
+            Ok(MsgType::VerifyProbe) => {
+                let _ = console.write_ack();
+                if let Err(code) = validate_frame_length(hdr.length) {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid frame length\n");
+                    let _ = console.write_error(code);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                let Some(frame) = ChannelFrame::from_le_bytes(&body.data[..hdr.length as usize]) else {
+                    let _ = console.write_error(ErrorCode::InvalidFrameLength);
+                    continue;
                 };
+                let result = verify_probe_frame(
+                    &mut flash_manager,
+                    frame,
+                    &mut channels,
+                    &mut decode_context,
+                    #[cfg(feature = "rtc-time")]
+                    &clock,
+                );
+                rate_limiter.record(&result);
+
+                match result {
+                    Ok(digest) => {
+                        // The probe used up its timestamp like any decoded frame
+                        if let Err(e) = state_manager.save(&mut flash_manager, &channels) {
+                            console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not persist channel state: {}\n", e));
+                            let _ = console.write_error(ErrorCode::Generic);
+                            continue;
+                        }
+                        let _ = console.write_packet(MsgType::VerifyProbe, Some(&digest));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Probe frame failed: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            #[cfg(feature = "resumable-upload")]
+            Ok(MsgType::UploadChunk) => {
+                let _ = console.write_ack();
+                if !(UPLOAD_CHUNK_HEADER_LEN..=UPLOAD_CHUNK_MAX_LEN).contains(&(hdr.length as usize)) {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid upload chunk length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                let result = receive_upload_chunk(
+                    &mut flash_manager,
+                    &mut body,
+                    hdr.length as usize,
+                    &mut channels,
+                    #[cfg(feature = "rekey")]
+                    &device_key,
+                );
+                rate_limiter.record(&result);
+                // Only the chunk ending an upload is a subscription stored or refused
+                let finished = match &result {
+                    Ok(progress) => progress.is_complete(),
+                    Err(e) => !matches!(e, SubscriptionError::UploadOutOfOrder),
+                };
+                if finished {
+                    telemetry.record_subscription(&result);
+                    decode_context.invalidate();
+                }
+
+                match result {
+                    Ok(progress) => {
+                        let _ = console.write_packet(MsgType::UploadChunk, Some(&progress.received.to_le_bytes()));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Upload chunk refused: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            #[cfg(feature = "resumable-upload")]
+            Ok(MsgType::UploadStatus) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != UPLOAD_STATUS_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid upload status length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+                let received = upload_received(&mut flash_manager, read_u32_le(&body.data, 0));
+                let _ = console.write_packet(MsgType::UploadStatus, Some(&received.to_le_bytes()));
+            }
+            #[cfg(not(feature = "resumable-upload"))]
+            Ok(MsgType::UploadChunk | MsgType::UploadStatus) => console.reject_command(hdr.length),
+            Ok(MsgType::SubscribePreamble) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != PREAMBLE_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid subscribe preamble length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                // An Error here spares the host sending a subscription that would be refused
+                let result = check_subscribe_preamble(&mut flash_manager, &body.data[..PREAMBLE_BODY_LEN]);
+                rate_limiter.record(&result);
 
-                if let Err(_) = result {
-                    write_debug(&mut console, "Failed to add subscription!");
-                    let _ = write_error(&mut console);
-                } else {
-                    // Write the response header byte-by-byte.
-                    for &b in bytemuck::bytes_of(&resp_hdr) {
-                        console.write_byte(b);
+                match result {
+                    Ok(()) => {
+                        let _ = console.write_packet(MsgType::SubscribePreamble, None);
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Subscription refused: {}\n", e));
+                        let _ = console.write_error(e.error_code());
                     }
-                    let _ = read_ack(&mut console);
                 }
             }
-            x if x == MsgType::Decode as u8 => {
-                let _ = write_ack(&mut console);
+            // Framing bring-up: echo the ciphertext without checking or decrypting it
+            #[cfg(feature = "decode-passthrough")]
+            Ok(MsgType::Decode) => {
+                let _ = console.write_ack();
+                if let Err(code) = validate_frame_length(hdr.length) {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid frame length\n");
+                    let _ = console.write_error(code);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                let _ = console.write_passthrough(&body.data[..hdr.length as usize]);
+            }
+            #[cfg(not(feature = "decode-passthrough"))]
+            Ok(MsgType::Decode) => {
+                let _ = console.write_ack();
+                if let Err(code) = validate_frame_length(hdr.length) {
+                    telemetry.record_bad_frame_length();
+                    // Drain the rejected body so the next header is read in sync.
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid frame length\n");
+                    let _ = console.write_error(code);
+                    continue;
+                }
 
-                if hdr.length != core::mem::size_of::<ChannelFrame>() as u16 {
-                    write_debug(&mut console, "Error: Invalid frame length\n");
-                    let _ = write_error(&mut console);
+                if console.read_body(hdr.length, &mut body).is_err() {
                     continue;
                 }
 
-                let body = read_body(&mut console, hdr.length);
+                // Parsed field by field, so nothing depends on the body buffer's alignment
+                let frame = match ChannelFrame::from_le_bytes(&body.data[..hdr.length as usize]) {
+                    Some(frame) => frame,
+                    None => {
+                        telemetry.record_bad_frame_length();
+                        console.write_log(LogLevel::Error, "Error: Frame layout mismatch\n");
+                        let _ = console.write_error(ErrorCode::InvalidFrameLength);
+                        continue;
+                    }
+                };
 
-                let frame: &ChannelFrame = bytemuck::from_bytes::<ChannelFrame>(
-                    &body.data[0..core::mem::size_of::<ChannelFrame>()],
+                let result = decode_frame(
+                    &mut flash_manager,
+                    frame,
+                    &mut channels,
+                    &mut decode_context,
+                    #[cfg(feature = "rtc-time")]
+                    &clock,
                 );
+                telemetry.record_decode(&result);
+                rate_limiter.record(&result);
+
+                match result {
+                    Ok(frame_content) => {
+                        // Commit the new timestamp before releasing the frame, so a reset
+                        // can never roll the replay counter back past an emitted frame.
+                        if let Err(e) = state_manager.save(&mut flash_manager, &channels) {
+                            console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not persist channel state: {}\n", e));
+                            let _ = console.write_error(ErrorCode::Generic);
+                            continue;
+                        }
+                        // Hand the decrypted frame on; the host sink sends it back here
+                        match route_frame(&mut frame_sink, &frame_content) {
+                            Ok(response) => {
+                                let _ = console.write_packet(MsgType::Decode, response);
+                            }
+                            Err(e) => {
+                                console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not output frame: {}\n", e));
+                                let _ = console.write_error(e.error_code());
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not decode frame: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                        continue;
+                    }
+                }
+            }
+            Ok(MsgType::Telemetry) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                match validate_all_subscriptions(&mut flash_manager) {
+                    Ok(summary) => telemetry.record_page_summary(&summary),
+                    Err(e) => console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not check subscription pages: {}\n", e)),
+                }
+                let mut report = [0u8; TELEMETRY_MAX_LEN];
+                let len = telemetry.write_report(&channels, &mut report);
+                let _ = console.write_packet(MsgType::Telemetry, Some(&report[..len]));
+            }
+            Ok(MsgType::DecoderId) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                let _ = console.write_decoder_id();
+            }
+            Ok(MsgType::KeyFingerprint) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                // Which signer this decoder trusts, without sending the key itself
+                let _ = console.write_packet(MsgType::KeyFingerprint, Some(&host_key_fingerprint()));
+            }
+            Ok(MsgType::FlashLayout) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                let _ = console.write_flash_layout();
+            }
+            Ok(MsgType::Version) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                let _ = console.write_version();
+            }
+            Ok(MsgType::FreeSlots) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                // Lets the host check for room before sending a new subscription
+                let free = free_subscription_pages(&mut flash_manager);
+                let _ = console.write_packet(MsgType::FreeSlots, Some(&free.to_le_bytes()));
+            }
+            Ok(MsgType::SelfTest) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                match rng.self_test() {
+                    Ok(()) => {
+                        let _ = console.write_packet(MsgType::SelfTest, None);
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: TRNG self-test failed: {}\n", e));
+                        let _ = console.write_error(ErrorCode::EntropyFailure);
+                    }
+                }
+            }
+            #[cfg(feature = "rtc-time")]
+            Ok(MsgType::SetTime) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != SET_TIME_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid set time length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
 
-                if let Ok(frame_content) = decode_frame(&mut flash_manager, &frame, &mut channels) {
-                    // Prepare a decode response header.
-                    let resp_hdr = MessageHeader {
-                        magic: MSG_MAGIC,
-                        opcode: MsgType::Decode as u8,
-                        length: 64,
-                    };
+                match clock.set_time_signed(&body.data[..SET_TIME_BODY_LEN]) {
+                    Ok(()) => {
+                        let _ = console.write_packet(MsgType::SetTime, None);
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not set time: {}\n", e));
+                        let _ = console.write_error(ErrorCode::Generic);
+                    }
+                }
+            }
+            #[cfg(feature = "tamper-command")]
+            Ok(MsgType::Tamper) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
 
-                    for &b in bytemuck::bytes_of(&resp_hdr) {
-                        console.write_byte(b);
+                match set_tamper_flag(&mut flash_manager) {
+                    Ok(epoch) => {
+                        locked = true;
+                        // Reply with the epoch the recovery command must be signed for
+                        let _ = console.write_packet(MsgType::Tamper, Some(&epoch.to_le_bytes()));
                     }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not set tamper flag: {}\n", e));
+                        let _ = console.write_error(ErrorCode::Generic);
+                    }
+                }
+            }
+            Ok(MsgType::Recover) => {
+                let _ = console.write_ack();
+                // The recovery body is exactly one Ed25519 signature
+                if hdr.length != 64 {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid recovery length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
 
-                    let _ = read_ack(&mut console);
+                match clear_tamper_flag(&mut flash_manager, &body.data[..hdr.length as usize]) {
+                    Ok(()) => {
+                        locked = false;
+                        let _ = console.write_packet(MsgType::Recover, None);
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Recovery failed: {}\n", e));
+                        let _ = console.write_error(ErrorCode::Generic);
+                    }
+                }
+            }
+            Ok(MsgType::Emergency) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != EMERGENCY_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid emergency length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
 
-                    // Write the decrypted frame
-                    for b in frame_content {
-                        console.write_byte(b);
+                match set_emergency_only(&mut flash_manager, &body.data[..EMERGENCY_BODY_LEN]) {
+                    Ok(state) => {
+                        decode_context.set_emergency_only(state.is_enabled());
+                        // Reply with the epoch the next command must be signed for
+                        let _ = console.write_packet(MsgType::Emergency, Some(&{ state.epoch }.to_le_bytes()));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not set emergency mode: {}\n", e));
+                        let _ = console.write_error(ErrorCode::Generic);
                     }
-                } else {
-                    write_debug(&mut console, "Error: Could not decode frame!\n");
-                    let _ = write_error(&mut console);
+                }
+            }
+            Ok(MsgType::Window) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != WINDOW_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid window update length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
                     continue;
                 }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                let result = update_subscription_window(&mut flash_manager, &body.data[..WINDOW_BODY_LEN], &mut channels);
+                rate_limiter.record(&result);
+
+                match result {
+                    Ok(()) => {
+                        decode_context.invalidate();
+                        let _ = console.write_packet(MsgType::Window, None);
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not update window: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            Ok(MsgType::Pause) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != PAUSE_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid pause length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                let result = set_channel_paused(&mut flash_manager, &body.data[..PAUSE_BODY_LEN], &mut channels);
+                rate_limiter.record(&result);
+
+                match result {
+                    Ok(sequence) => {
+                        // A subscription moved to a fresh page invalidates the cached copy
+                        decode_context.invalidate();
+                        // Reply with the sequence the next Pause command must be signed for
+                        let _ = console.write_packet(MsgType::Pause, Some(&sequence.to_le_bytes()));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not pause channel: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            #[cfg(feature = "rekey")]
+            Ok(MsgType::Rekey) => {
+                let _ = console.write_ack();
+                if hdr.length as usize != REKEY_BODY_LEN {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid rekey length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+
+                match device_key.rekey_signed(&mut flash_manager, &body.data[..REKEY_BODY_LEN]) {
+                    Ok(generation) => {
+                        // Reply with the generation now in use
+                        let _ = console.write_packet(MsgType::Rekey, Some(&generation.to_le_bytes()));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Rekey failed: {}\n", e));
+                        let _ = console.write_error(ErrorCode::Generic);
+                    }
+                }
             }
-            _ => {
-                // Unsupported command: send a simple error message.
-                for &b in b"Unsupported command!\n" {
-                    console.write_byte(b);
+            #[cfg(feature = "debug-dump")]
+            Ok(MsgType::NodeDump) => {
+                let _ = console.write_ack();
+                // The body is the channel id (u32 LE)
+                if hdr.length != 4 {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid node dump length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
                 }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+                let channel_id = read_u32_le(&body.data, 0);
+
+                let mut dump = [0u8; NODE_DUMP_MAX_LEN];
+                match dump_subscription_nodes(&mut flash_manager, channel_id, &mut dump) {
+                    Ok(len) => {
+                        let _ = console.write_packet(MsgType::NodeDump, Some(&dump[..len]));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not dump nodes: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            #[cfg(feature = "debug-dump")]
+            Ok(MsgType::ReplayState) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                // channel_id, received and last_frame of every active channel
+                let mut dump = [0u8; REPLAY_STATE_MAX_LEN];
+                let len = dump_replay_state(&channels, &mut dump);
+                let _ = console.write_packet(MsgType::ReplayState, Some(&dump[..len]));
+            }
+            #[cfg(feature = "debug-dump")]
+            Ok(MsgType::Resync) => {
+                let _ = console.write_ack();
+                console.discard_body(hdr.length);
+                // Rescan flash without a reset, then report the list as ReplayState does
+                locked = resync_active_channels(&mut channels, &mut flash_manager, &mut console);
+                decode_context.invalidate();
+                let mut dump = [0u8; REPLAY_STATE_MAX_LEN];
+                let len = dump_replay_state(&channels, &mut dump);
+                let _ = console.write_packet(MsgType::Resync, Some(&dump[..len]));
+            }
+            #[cfg(not(feature = "debug-dump"))]
+            Ok(MsgType::NodeDump | MsgType::ReplayState | MsgType::Resync) => console.reject_command(hdr.length),
+            #[cfg(feature = "page-dump")]
+            Ok(MsgType::PageDump) => {
+                let _ = console.write_ack();
+                // The body is the page address (u32 LE)
+                if hdr.length != 4 {
+                    console.discard_body(hdr.length);
+                    console.write_log(LogLevel::Error, "Error: Invalid page dump length\n");
+                    let _ = console.write_error(ErrorCode::Generic);
+                    continue;
+                }
+                if console.read_body(hdr.length, &mut body).is_err() {
+                    continue;
+                }
+                let addr = read_u32_le(&body.data, 0);
+
+                let mut dump = [0u8; PAGE_DUMP_LEN];
+                match dump_subscription_page(&mut flash_manager, addr, &mut dump) {
+                    Ok(len) => {
+                        let _ = console.write_packet(MsgType::PageDump, Some(&dump[..len]));
+                    }
+                    Err(e) => {
+                        console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not dump page: {}\n", e));
+                        let _ = console.write_error(e.error_code());
+                    }
+                }
+            }
+            #[cfg(not(feature = "page-dump"))]
+            Ok(MsgType::PageDump) => console.reject_command(hdr.length),
+            #[cfg(feature = "soft-reset")]
+            Ok(MsgType::Reset) => handle_reset(&mut console, hdr.length, &mut flash_manager, &mut ScbReset),
+            #[cfg(not(feature = "soft-reset"))]
+            Ok(MsgType::Reset) => console.reject_command(hdr.length),
+            #[cfg(not(feature = "rekey"))]
+            Ok(MsgType::Rekey) => console.reject_command(hdr.length),
+            #[cfg(not(feature = "rtc-time"))]
+            Ok(MsgType::SetTime) => console.reject_command(hdr.length),
+            #[cfg(not(feature = "tamper-command"))]
+            Ok(MsgType::Tamper) => console.reject_command(hdr.length),
+            #[cfg(feature = "decode-passthrough")]
+            Ok(MsgType::VerifyProbe) => console.reject_command(hdr.length),
+            // Response-only types and unknown opcodes
+            Ok(MsgType::Ack | MsgType::Debug | MsgType::Error | MsgType::Nack) | Err(_) => {
+                console.reject_command(hdr.length)
             }
         }
     }
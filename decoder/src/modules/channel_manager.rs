@@ -1,31 +1,173 @@
-use crate::modules::flash_manager::{FlashManager, FlashManagerError};
-use crate::modules::hostcom_manager::{ChannelInfo, MessageBody, MessageHeader};
-use crate::modules::constants::{BASE_ADDRESS, MAX_SUBS};
+use crate::modules::flash_manager::{FlashManager, FlashManagerError, FLASH_WORD_SIZE};
+use crate::modules::key_tree::{derive_child_key, extend_key};
+use crate::modules::host_keys::{verify_host_signature, InvalidHostKey};
+use crate::modules::hostcom_manager::{ChannelInfo, ErrorCode, HostConsole, LogLevel, MessageBody, MessageHeader, UartHalOps, MAX_BODY_LEN};
+use crate::modules::constants::{
+    subscription_page_addr, ERASED_MAGIC, PAGE_SIZE, PAUSE_MAGIC, SUBSCRIPTION_MAGIC, WINDOW_MAGIC,
+};
+use crate::modules::tamper_manager::read_tamper_state;
+use crate::modules::wire::{read_u16_le, read_u32_le, read_u64_le};
+#[cfg(feature = "subscribe-checksum")]
+use crate::modules::crc::crc16;
+#[cfg(feature = "rtc-time")]
+use crate::modules::time_source::TimeSource;
+#[cfg(feature = "rekey")]
+use crate::modules::key_manager::DeviceKey;
+use crate::FlashError;
 use bytemuck::{Pod, Zeroable, bytes_of};
-use ed25519_dalek::pkcs8::DecodePublicKey;
-use ed25519_dalek::VerifyingKey;
-use ed25519_dalek::{Signature, Verifier};
+use sha2::{Digest, Sha256};
+use core::fmt;
+use core::mem::{align_of, offset_of, size_of};
+use ed25519_dalek::Signature;
 use chacha20::ChaCha20;
-use chacha20::cipher::{KeyIvInit, StreamCipher};
-use md5::{Digest, Md5};
-use crate::{HOST_KEY_PUB, DECODER_ID, DECODER_KEY, CHANNEL_0_SUBSCRIPTION};
-
-use super::constants::PAGE_SIZE;
+use chacha20::cipher::typenum::Unsigned;
+use chacha20::cipher::{KeyIvInit, KeySizeUser, StreamCipher};
+use crate::{HOST_KEYS_PUB, DECODER_ID, CHANNEL_0_SUBSCRIPTION, KEY_LEN, MAX_CHANNELS};
+#[cfg(not(feature = "rekey"))]
+use crate::DECODER_KEY;
 
 #[derive(Clone, Copy)]
 pub struct ActiveChannel {
     pub channel_id: u32,
     pub last_frame: u64,
     pub received: bool,
+    /// Frames are refused until a signed Pause command resumes the channel.
+    pub paused: bool,
+    /// Frames decoded on the channel, saturating at `u32::MAX`.
+    pub decode_count: u32,
 }
 
-pub type ActiveChannelsList = [Option<ActiveChannel>; 9];
+/// Reject a subscription for an already stored channel unless it ends no earlier than
+/// the stored one, so a replayed older subscription cannot downgrade the window.
+pub const REJECT_OLDER_SUBSCRIPTIONS: bool = true;
+
+/// Wipe a subscription once a frame shows it has expired, freeing its page.
+pub const PRUNE_EXPIRED_SUBSCRIPTIONS: bool = false;
+
+/// Channel 0 plus one slot per stored subscription.
+///
+/// Every subscription takes its own page and there are `MAX_CHANNELS` pages, so each
+/// stored channel always has an active slot: no channel needs evicting to decode
+/// another, and the list needs no replacement policy.
+pub const ACTIVE_CHANNELS_LEN: usize = MAX_CHANNELS + 1;
+
+const _: () = assert!(size_of::<ActiveChannelsList>() == (MAX_CHANNELS + 1) * size_of::<Option<ActiveChannel>>());
+
+pub type ActiveChannelsList = [Option<ActiveChannel>; ACTIVE_CHANNELS_LEN];
+
+// The device key from build.rs is used directly as the subscription cipher key.
+const _: () = assert!(KEY_LEN == <ChaCha20 as KeySizeUser>::KeySize::USIZE);
 
 #[derive(Debug)]
 pub enum SubscriptionError {
     InvalidChannelId,
     NoPageFound,
     FlashManagerError(FlashManagerError),
+    /// The embedded host public key could not be parsed.
+    InvalidKey,
+    /// The message signature was malformed or did not verify.
+    InvalidSignature,
+    /// The subscription was generated for a different decoder.
+    InvalidDecoderId,
+    /// The frame timestamp was not newer than the last decoded frame.
+    InvalidTimestamp,
+    /// No password in the subscription covers the frame timestamp.
+    PasswordNotFound,
+    /// The wall clock is past the end of the subscription window.
+    SubscriptionExpired,
+    /// The subscription would replace a stored one that ends later.
+    StaleSubscription,
+    /// The tree path computed for the frame contained a branch other than 1 or 2.
+    InvalidPath,
+    /// The subscription body is too short or too long to be well formed.
+    InvalidLength,
+    /// A frame arrived for a channel with no stored subscription.
+    NoSubscription,
+    /// The decrypted frame marker did not echo the frame's channel.
+    InconsistentFrame,
+    /// The subscription carries more password entries than a stored record holds.
+    PasswordBlobTooLarge,
+    /// The channel is paused by a signed Pause command.
+    ChannelPaused,
+    /// Emergency-only mode is on and the frame is not for channel 0.
+    EmergencyOnly,
+    /// The subscription nonce is all zeros, the mark of an encoder that never set it.
+    ZeroNonce,
+    /// The Subscribe body's trailing checksum did not match; the host should resend it.
+    ChecksumMismatch,
+    /// The frame's channel is stored but missing from the active channel list.
+    ChannelInactive,
+    /// The channel's stored subscription failed its CRC and was erased.
+    SubscriptionCorrupt,
+    /// The address is not the start of a subscription page.
+    NotSubscriptionPage,
+    /// No decrypted password entry has a node_ext of 1 or 2, the mark of a subscription
+    /// encrypted under another decoder key or nonce.
+    NoUsablePasswords,
+    /// A resumable upload chunk other than the next one expected.
+    UploadOutOfOrder,
+}
+
+impl fmt::Display for SubscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscriptionError::InvalidChannelId => f.write_str("invalid channel id"),
+            SubscriptionError::NoPageFound => f.write_str("no free subscription page"),
+            SubscriptionError::FlashManagerError(e) => write!(f, "flash: {}", e),
+            SubscriptionError::InvalidKey => f.write_str("invalid host key"),
+            SubscriptionError::InvalidSignature => f.write_str("invalid signature"),
+            SubscriptionError::InvalidDecoderId => f.write_str("invalid decoder id"),
+            SubscriptionError::InvalidTimestamp => f.write_str("invalid timestamp"),
+            SubscriptionError::PasswordNotFound => f.write_str("no password for frame"),
+            SubscriptionError::SubscriptionExpired => f.write_str("subscription expired"),
+            SubscriptionError::StaleSubscription => f.write_str("stored subscription ends later"),
+            SubscriptionError::InvalidPath => f.write_str("invalid tree path"),
+            SubscriptionError::InvalidLength => f.write_str("invalid subscription length"),
+            SubscriptionError::NoSubscription => f.write_str("not subscribed to channel"),
+            SubscriptionError::InconsistentFrame => f.write_str("frame marker mismatch"),
+            SubscriptionError::PasswordBlobTooLarge => f.write_str("too many subscription passwords"),
+            SubscriptionError::ChannelPaused => f.write_str("channel paused"),
+            SubscriptionError::EmergencyOnly => f.write_str("emergency channel only"),
+            SubscriptionError::ZeroNonce => f.write_str("all-zero subscription nonce"),
+            SubscriptionError::ChecksumMismatch => f.write_str("subscription checksum mismatch"),
+            SubscriptionError::ChannelInactive => f.write_str("channel not active"),
+            SubscriptionError::SubscriptionCorrupt => f.write_str("stored subscription corrupt, erased"),
+            SubscriptionError::NotSubscriptionPage => f.write_str("not a subscription page"),
+            SubscriptionError::NoUsablePasswords => f.write_str("no usable password after decryption"),
+            SubscriptionError::UploadOutOfOrder => f.write_str("upload chunk out of order"),
+        }
+    }
+}
+
+impl SubscriptionError {
+    /// Error code reported to the host for this failure.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            SubscriptionError::NoPageFound => ErrorCode::SubscriptionsFull,
+            SubscriptionError::SubscriptionExpired => ErrorCode::SubscriptionExpired,
+            SubscriptionError::NoSubscription => ErrorCode::NoSubscription,
+            SubscriptionError::InvalidSignature => ErrorCode::InvalidSignature,
+            SubscriptionError::InvalidTimestamp => ErrorCode::ReplayedTimestamp,
+            SubscriptionError::PasswordNotFound => ErrorCode::KeyNotFound,
+            SubscriptionError::ChannelPaused => ErrorCode::ChannelPaused,
+            SubscriptionError::EmergencyOnly => ErrorCode::EmergencyOnly,
+            SubscriptionError::ChecksumMismatch => ErrorCode::ChecksumMismatch,
+            SubscriptionError::ChannelInactive => ErrorCode::ChannelInactive,
+            SubscriptionError::SubscriptionCorrupt => ErrorCode::SubscriptionCorrupt,
+            SubscriptionError::NoUsablePasswords => ErrorCode::NoUsablePasswords,
+            SubscriptionError::UploadOutOfOrder => ErrorCode::UploadOutOfOrder,
+            _ => ErrorCode::Generic,
+        }
+    }
+}
+
+impl core::error::Error for SubscriptionError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            SubscriptionError::FlashManagerError(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<FlashManagerError> for SubscriptionError {
@@ -34,6 +176,12 @@ impl From<FlashManagerError> for SubscriptionError {
     }
 }
 
+impl From<InvalidHostKey> for SubscriptionError {
+    fn from(_: InvalidHostKey) -> Self {
+        SubscriptionError::InvalidKey
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct ChannelPassword {
@@ -42,10 +190,45 @@ pub struct ChannelPassword {
     pub password: [u8; 16],
 }
 
+impl ChannelPassword {
+    /// Level-order number of the tree node this password belongs to.
+    pub const fn node_num(&self) -> u128 {
+        (self.node_trunc as u128) * 2 + (self.node_ext as u128).saturating_sub(1)
+    }
+}
+
+/// Password entries a stored subscription holds, the most a Subscribe body may carry.
+/// Sizes the password table, the Subscribe length checks and the NodeDump response.
+pub const PASSWORD_TREE_NODES: usize = 128;
+
 #[repr(C, packed)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct ChannelPasswords {
-    pub contents: [ChannelPassword; 128],
+    pub contents: [ChannelPassword; PASSWORD_TREE_NODES],
+}
+
+impl ChannelPasswords {
+    /// Sorts the populated entries by node number so `find` can binary search.
+    ///
+    /// Entries after the first uninitialized one (`node_ext == 0`) are cleared, keeping
+    /// the terminator semantics: populated passwords form a sorted prefix followed only
+    /// by empty entries.
+    pub fn sort(&mut self) {
+        let contents = &mut self.contents;
+        let count = contents.iter().position(|c| c.node_ext == 0).unwrap_or(contents.len());
+        contents[count..].fill(ChannelPassword::zeroed());
+        contents[..count].sort_unstable_by_key(|c| c.node_num());
+    }
+
+    /// Binary searches the sorted, populated prefix for the password of `node_num`.
+    pub fn find(&self, node_num: u128) -> Option<ChannelPassword> {
+        let contents = &self.contents;
+        let count = contents.partition_point(|c| c.node_ext != 0);
+        contents[..count]
+            .binary_search_by_key(&node_num, |c| c.node_num())
+            .ok()
+            .map(|idx| contents[idx])
+    }
 }
 
 #[repr(C, packed)]
@@ -55,85 +238,445 @@ pub struct ChannelSubscription {
     pub passwords: ChannelPasswords,
 }
 
+/// Length of a decoded frame's content, the payload of a Decode response.
+pub const FRAME_CONTENT_LEN: usize = 64;
+/// Length of the marker encrypted after the content: the frame's channel id (u32 LE).
+/// A frame decrypted under the wrong key fails to reproduce it.
+pub const FRAME_MARKER_LEN: usize = 4;
+
+/// Length of the ChaCha20 nonce carried by every frame and subscription.
+pub const NONCE_LEN: usize = 12;
+
+/// ChaCha20 nonce of a frame or subscription, parsed in one place for both paths.
+#[derive(Clone, Copy)]
+struct Nonce([u8; NONCE_LEN]);
+
+impl Nonce {
+    /// The nonce in `bytes`, which must be exactly `NONCE_LEN` long.
+    fn parse(bytes: &[u8]) -> Result<Nonce, SubscriptionError> {
+        bytes.try_into().map(Nonce).map_err(|_| SubscriptionError::InvalidLength)
+    }
+
+    /// Whether every byte is zero. Frames each have their own leaf key, so this only
+    /// matters for subscriptions, which all share the device key: two of them with the
+    /// zero nonce would share a keystream.
+    fn is_zero(&self) -> bool {
+        self.0 == [0; NONCE_LEN]
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct ChannelFrame {
     pub channel: u32,
     pub timestamp: u64,
-    pub nonce: [u8; 12],
-    pub encrypted_content: [u8; 64],
-    pub signature: [u8; 64],
+    pub nonce: [u8; NONCE_LEN],
+    pub encrypted_content: [u8; FRAME_CONTENT_LEN],
+    pub encrypted_marker: [u8; FRAME_MARKER_LEN],
+    pub signature: [u8; SIGNATURE_LEN],
+}
+
+impl ChannelFrame {
+    /// Parse a frame field by field from the wire bytes, so `bytes` can start at any
+    /// offset. Returns None unless `bytes` is exactly one frame.
+    pub fn from_le_bytes(bytes: &[u8]) -> Option<ChannelFrame> {
+        if bytes.len() != size_of::<ChannelFrame>() {
+            return None;
+        }
+        let (_, rest) = bytes.split_at(4 + 8);
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (encrypted_content, rest) = rest.split_at(FRAME_CONTENT_LEN);
+        let (encrypted_marker, signature) = rest.split_at(FRAME_MARKER_LEN);
+
+        Some(ChannelFrame {
+            channel: read_u32_le(bytes, 0),
+            timestamp: read_u64_le(bytes, 4),
+            nonce: nonce.try_into().ok()?,
+            encrypted_content: encrypted_content.try_into().ok()?,
+            encrypted_marker: encrypted_marker.try_into().ok()?,
+            signature: signature.try_into().ok()?,
+        })
+    }
 }
 
-struct SubscriptionPageIterator<'a> {
+// Wire sizes shared with the host tools: a password entry is node_trunc, node_ext and
+// the 16-byte key; a subscription record is its ChannelInfo and PASSWORD_TREE_NODES
+// entries. The field offsets are those of the encoder's `<Qb16s` and of the channel 0
+// entry build.rs writes, which Subscribe copies in and decode reads back as-is.
+const _: () = assert!(size_of::<ChannelPassword>() == 8 + 1 + 16);
+const _: () = assert!(offset_of!(ChannelPassword, node_trunc) == 0);
+const _: () = assert!(offset_of!(ChannelPassword, node_ext) == 8);
+const _: () = assert!(offset_of!(ChannelPassword, password) == 9);
+const _: () = assert!(size_of::<ChannelPasswords>() == PASSWORD_TREE_NODES * 25);
+const _: () = assert!(size_of::<ChannelSubscription>() == 20 + PASSWORD_TREE_NODES * 25);
+const _: () = assert!(size_of::<ChannelFrame>() == 156);
+// Packed, so a view of any of them into a byte buffer can never be misaligned
+const _: () = assert!(align_of::<ChannelPasswords>() == 1 && align_of::<ChannelSubscription>() == 1);
+
+// A stored subscription (4-byte magic + record + 4-byte CRC) must fit within a single flash page.
+const _: () = assert!(4 + size_of::<ChannelSubscription>() + 4 <= PAGE_SIZE as usize);
+// A frame must fit within the body buffer it is decoded from.
+const _: () = assert!(size_of::<ChannelFrame>() <= MAX_BODY_LEN);
+// So must a Subscribe body carrying a full password table.
+const _: () = assert!(SUBSCRIPTION_HEADER_LEN + size_of::<ChannelPasswords>() + SIGNATURE_LEN <= MAX_BODY_LEN);
+#[cfg(feature = "subscribe-checksum")]
+const _: () = assert!(
+    SUBSCRIPTION_HEADER_LEN + size_of::<ChannelPasswords>() + SIGNATURE_LEN + SUBSCRIPTION_CHECKSUM_LEN <= MAX_BODY_LEN
+);
+
+/// Number of leading `ChannelFrame` bytes covered by the frame signature: every field
+/// before `signature`, i.e. channel, timestamp, nonce, encrypted_content and
+/// encrypted_marker.
+pub const FRAME_SIGNED_LEN: usize = offset_of!(ChannelFrame, signature);
+
+// The signed region must cover the timestamp, nonce and ciphertext, and the signature
+// must be the only trailing unsigned bytes, so none can be spliced without detection.
+const _: () = assert!(offset_of!(ChannelFrame, timestamp) + size_of::<u64>() <= FRAME_SIGNED_LEN);
+const _: () = assert!(offset_of!(ChannelFrame, nonce) + NONCE_LEN <= FRAME_SIGNED_LEN);
+const _: () = assert!(offset_of!(ChannelFrame, encrypted_marker) == offset_of!(ChannelFrame, encrypted_content) + FRAME_CONTENT_LEN);
+const _: () = assert!(offset_of!(ChannelFrame, encrypted_marker) + FRAME_MARKER_LEN == FRAME_SIGNED_LEN);
+const _: () = assert!(FRAME_SIGNED_LEN + SIGNATURE_LEN == size_of::<ChannelFrame>());
+// channel, timestamp, nonce, content, marker, signature: no other bytes on the wire.
+const _: () = assert!(size_of::<ChannelFrame>() == 4 + 8 + NONCE_LEN + FRAME_CONTENT_LEN + FRAME_MARKER_LEN + 64);
+
+/// Checks that a Decode body length is exactly one `ChannelFrame`.
+///
+/// Every decode entry point must call this before reading the frame so that
+/// zero-length and oversized bodies are rejected with a distinct error code.
+pub fn validate_frame_length(length: u16) -> Result<(), ErrorCode> {
+    if length as usize != size_of::<ChannelFrame>() {
+        return Err(ErrorCode::InvalidFrameLength);
+    }
+    Ok(())
+}
+
+/// Checks that a Subscribe body fits the body buffer before any of it is read.
+///
+/// A longer body is drained rather than read, so a host claiming an oversized
+/// subscription gets `SubscriptionTooLarge` and stays in step with the decoder.
+pub fn validate_subscribe_length(length: u16) -> Result<(), ErrorCode> {
+    if length as usize > MAX_BODY_LEN {
+        return Err(ErrorCode::SubscriptionTooLarge);
+    }
+    Ok(())
+}
+
+pub struct SubscriptionPageIterator<'a> {
     page_num: usize,
     return_empty: bool,
     flash_manager: &'a mut FlashManager,
 }
 
+impl SubscriptionPageIterator<'_> {
+    /// Read the page's magic, retrying once so a transient read fault is not mistaken
+    /// for a missing subscription.
+    fn read_magic_retry(&mut self, addr: u32) -> Result<u32, FlashError> {
+        self.flash_manager.read_magic(addr).or_else(|_| self.flash_manager.read_magic(addr))
+    }
+
+    /// Read the page's `ChannelInfo` header, retrying once, with the window end of the
+    /// newest window record in the page's log, if any.
+    fn read_info_retry(&mut self, addr: u32) -> Result<ChannelInfo, FlashManagerError> {
+        let mut info = self
+            .flash_manager
+            .read_data::<ChannelInfo>(addr)
+            .or_else(|_| self.flash_manager.read_data::<ChannelInfo>(addr))?;
+        if let Some(end_timestamp) = read_page_log(self.flash_manager, addr).end_timestamp {
+            info.end_timestamp = end_timestamp;
+        }
+        Ok(info)
+    }
+}
+
 impl Iterator for SubscriptionPageIterator<'_>  {
     type Item = (u32, Option<ChannelInfo>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let addr = BASE_ADDRESS + (self.page_num as u32 * PAGE_SIZE);
-
-        if self.page_num >= MAX_SUBS {
-            return None;
-        }
-
-        match self.flash_manager.read_magic(addr) {
-            // Magic present, the page is occupied
-            Ok(0xABCD) => {
-                // Read the ChannelInfo header for the subscription
-                if let Ok(channel) = self.flash_manager.read_data::<ChannelInfo>(addr) {
+        while self.page_num < MAX_CHANNELS {
+            let addr = subscription_page_addr(self.page_num);
+
+            match self.read_magic_retry(addr) {
+                // Magic present, the page is occupied
+                Ok(SUBSCRIPTION_MAGIC) => {
+                    // Read the ChannelInfo header for the subscription
+                    if let Ok(channel) = self.read_info_retry(addr) {
+                        self.page_num += 1;
+
+                        // Channel 0 is built in, a stored page for it is never a subscription
+                        if channel.channel_id == 0 {
+                            continue;
+                        }
+                        return Some((addr, Some(channel)));
+                    }
+                    // Header unreadable: skip the page but keep scanning
                     self.page_num += 1;
-
-                    Some((addr, Some(channel)))
-                } else {
-                    None
+                },
+                // Unoccupied page. Subscriptions are not kept contiguous (an update
+                // frees the old page), so keep scanning past it.
+                Ok(_) => {
+                    self.page_num += 1;
+                    if self.return_empty {
+                        return Some((addr, None));
+                    }
                 }
-            },
-            // Unoccupied page
-            Ok(_) => {
-                if self.return_empty {
-                    return Some((addr, None));
-                } else {
-                    // Empty page reached means none of the subsequent pages should have a subscription
-                    return None;
+                // Persistent flash fault: the page is neither usable nor known to be
+                // empty, so skip it instead of hiding the subscriptions after it
+                Err(_) => {
+                    self.page_num += 1;
                 }
             }
-            Err(_) => { None }
         }
+
+        None
     }
 }
 
-fn channel_subscriptions(flash_manager: &mut FlashManager, return_empty: bool) -> SubscriptionPageIterator {
+/// Iterate the subscription pages in flash, the single source of truth for stored channels.
+pub fn channel_subscriptions(flash_manager: &mut FlashManager, return_empty: bool) -> SubscriptionPageIterator<'_> {
     SubscriptionPageIterator { page_num: 0, return_empty, flash_manager }
 }
 
-pub fn initialize_active_channels(
+/// Number of subscription pages a new channel could be stored in, counted the way
+/// `write_subscription` picks one. Pages that cannot be read, or hold a channel 0
+/// record, are neither occupied nor free. A subscription for an already stored
+/// channel needs no free page.
+pub fn free_subscription_pages(flash_manager: &mut FlashManager) -> u32 {
+    channel_subscriptions(flash_manager, true).filter(|(_, c)| c.is_none()).count() as u32
+}
+
+/// What `validate_all_subscriptions` found on one subscription page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageState {
+    /// No committed record, erased or written but never committed: free for a new
+    /// subscription.
+    Empty,
+    /// A committed subscription whose CRC checks out, for this channel.
+    Valid(u32),
+    /// A committed record whose CRC does not match, e.g. torn by a power loss.
+    CrcMismatch,
+    /// Anything else: a record for channel 0 or with its window backwards, or bytes
+    /// with no magic at all.
+    Malformed,
+}
+
+/// Every subscription page's state, in page order, from one pass over flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionSummary {
+    pub pages: [PageState; MAX_CHANNELS],
+}
+
+impl SubscriptionSummary {
+    fn count(&self, matches: impl Fn(&PageState) -> bool) -> u32 {
+        self.pages.iter().filter(|state| matches(state)).count() as u32
+    }
+
+    pub fn valid(&self) -> u32 {
+        self.count(|state| matches!(state, PageState::Valid(_)))
+    }
+
+    pub fn empty(&self) -> u32 {
+        self.count(|state| *state == PageState::Empty)
+    }
+
+    pub fn corrupt(&self) -> u32 {
+        self.count(|state| matches!(state, PageState::CrcMismatch | PageState::Malformed))
+    }
+
+    /// Addresses of the pages in `state`.
+    pub fn pages_in(&self, state: PageState) -> impl Iterator<Item = u32> + '_ {
+        (0..MAX_CHANNELS).filter(move |&page| self.pages[page] == state).map(subscription_page_addr)
+    }
+}
+
+/// Read every subscription page once, checking magic, CRC and the record's structure,
+/// and report what each holds. Nothing is repaired; corrupt pages are listed, not
+/// skipped. A read that fails twice is a flash fault and ends the pass.
+pub fn validate_all_subscriptions(flash_manager: &mut FlashManager) -> Result<SubscriptionSummary, FlashManagerError> {
+    let mut summary = SubscriptionSummary { pages: [PageState::Empty; MAX_CHANNELS] };
+    for (page, state) in summary.pages.iter_mut().enumerate() {
+        let addr = subscription_page_addr(page);
+        let magic = flash_manager.read_magic(addr).or_else(|_| flash_manager.read_magic(addr))?;
+        *state = match magic {
+            ERASED_MAGIC => PageState::Empty,
+            SUBSCRIPTION_MAGIC => match flash_manager.read_data_verified::<ChannelSubscription>(addr) {
+                Ok(subscription) => {
+                    let info = subscription.info;
+                    if info.channel_id == 0 || info.start_timestamp > info.end_timestamp {
+                        PageState::Malformed
+                    } else {
+                        PageState::Valid(info.channel_id)
+                    }
+                }
+                Err(FlashManagerError::CrcMismatch) => PageState::CrcMismatch,
+                Err(e) => return Err(e),
+            },
+            _ => PageState::Malformed,
+        };
+    }
+    Ok(summary)
+}
+
+/// A structural defect in the built-in channel 0 subscription.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Channel0Error {
+    /// The record is for another channel.
+    WrongChannel(u32),
+    /// The record does not hold exactly one password, in its first entry.
+    PopulatedNodes(usize),
+    /// The password's node_ext is neither 1 (left) nor 2 (right).
+    InvalidNodeExt(u8),
+}
+
+impl fmt::Display for Channel0Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel0Error::WrongChannel(id) => write!(f, "record is for channel {}", id),
+            Channel0Error::PopulatedNodes(n) => write!(f, "{} populated password entries, expected 1", n),
+            Channel0Error::InvalidNodeExt(ext) => write!(f, "node_ext {}, expected 1 or 2", ext),
+        }
+    }
+}
+
+impl core::error::Error for Channel0Error {}
+
+/// Check the shape of a channel 0 subscription as `build.rs` generates it: channel 0,
+/// a single password in the first entry, with a valid node_ext. The 16-byte password
+/// length is fixed by `ChannelPassword`. Emergency decoding reads that first entry
+/// only, so anything else would fail every channel 0 frame.
+pub fn check_channel_0_subscription(subscription: &ChannelSubscription) -> Result<(), Channel0Error> {
+    let channel_id = subscription.info.channel_id;
+    if channel_id != 0 {
+        return Err(Channel0Error::WrongChannel(channel_id));
+    }
+    let contents = &subscription.passwords.contents;
+    let populated = contents.iter().filter(|c| c.node_ext != 0).count();
+    if populated != 1 || contents[0].node_ext == 0 {
+        return Err(Channel0Error::PopulatedNodes(populated));
+    }
+    match contents[0].node_ext {
+        1 | 2 => Ok(()),
+        ext => Err(Channel0Error::InvalidNodeExt(ext)),
+    }
+}
+
+/// Populate the active channel list from flash. Returns whether the tamper flag is set.
+///
+/// Subscription pages whose CRC does not match (e.g. torn by a power loss during
+/// `write_data`) are logged and erased first, so they are never activated.
+///
+/// The list is rebuilt from nothing but the pages that hold a valid subscription, in
+/// any mix of occupied, erased and corrupt pages, so a reset or wipe interrupted
+/// partway leaves a smaller but consistent list.
+///
+/// A malformed built-in channel 0 subscription is reported as an error; it is a
+/// provisioning bug that no flash state can repair.
+pub fn initialize_active_channels<U: UartHalOps, D: UartHalOps>(
     active_channels: &mut ActiveChannelsList,
-    flash_manager: &mut FlashManager
-) {
+    flash_manager: &mut FlashManager,
+    console: &mut HostConsole<U, D>,
+) -> bool {
+    if let Err(e) = check_channel_0_subscription(&CHANNEL_0_SUBSCRIPTION) {
+        console.write_log_fmt(LogLevel::Error, format_args!("Error: Built-in channel 0 subscription malformed: {}\n", e));
+    }
+
+    // Only a CRC mismatch proves a page is corrupt; a read fault may be transient
+    match validate_all_subscriptions(flash_manager) {
+        Ok(summary) => {
+            for addr in summary.pages_in(PageState::CrcMismatch) {
+                console.write_log_fmt(LogLevel::Warn, format_args!("Discarding corrupt subscription page {:#x}\n", addr));
+                let _ = flash_manager.wipe_data(addr);
+            }
+        }
+        Err(e) => console.write_log_fmt(LogLevel::Error, format_args!("Error: Could not check subscription pages: {}\n", e)),
+    }
+
     let mut idx: usize = 1;
+    *active_channels = [None; ACTIVE_CHANNELS_LEN];
 
     // Initialize emergency channel subscription
-    active_channels[0] = Some(ActiveChannel { channel_id: 0, last_frame: 0, received: false });
+    active_channels[0] = Some(ActiveChannel { channel_id: 0, last_frame: 0, received: false, paused: false, decode_count: 0 });
 
-    for (_, c) in channel_subscriptions(flash_manager, false) {
-        if let Some(channel) = c {
-            active_channels[idx] = Some(ActiveChannel {
-                channel_id: channel.channel_id,
-                last_frame: 0,
-                received: false
-            });
+    // Collected first, the pause log is read with the page iterator released
+    let mut found: [Option<(u32, u32)>; MAX_CHANNELS] = [None; MAX_CHANNELS];
+    for (slot, (addr, c)) in found.iter_mut().zip(channel_subscriptions(flash_manager, false)) {
+        *slot = c.map(|channel| (addr, channel.channel_id));
+    }
 
-            idx += 1;
+    for &(addr, channel_id) in found.iter().flatten() {
+        // An interrupted update can leave two pages for one channel
+        if active_channels[..idx].iter().flatten().any(|a| a.channel_id == channel_id) {
+            continue;
         }
+
+        active_channels[idx] = Some(ActiveChannel {
+            channel_id,
+            last_frame: 0,
+            received: false,
+            paused: read_pause_state(flash_manager, addr).paused,
+            decode_count: 0,
+        });
+
+        idx += 1;
     }
+
+    read_tamper_state(flash_manager).is_locked()
 }
 
-pub fn validate_channel_timestamp(frame: &ChannelFrame, active_channels: &mut ActiveChannelsList) -> bool {
+/// Rebuild the active channel list from flash as at boot, for when the two may have
+/// drifted apart. Returns whether the tamper flag is set.
+///
+/// Channels still stored keep their replay state, so a resync never lets an already
+/// decoded frame through again; channels whose page is gone are dropped.
+pub fn resync_active_channels<U: UartHalOps, D: UartHalOps>(
+    active_channels: &mut ActiveChannelsList,
+    flash_manager: &mut FlashManager,
+    console: &mut HostConsole<U, D>,
+) -> bool {
+    let previous = *active_channels;
+    let locked = initialize_active_channels(active_channels, flash_manager, console);
+
+    for channel in active_channels.iter_mut().flatten() {
+        if let Some(old) = previous.iter().flatten().find(|c| c.channel_id == channel.channel_id) {
+            channel.last_frame = old.last_frame;
+            channel.received = old.received;
+            channel.decode_count = old.decode_count;
+        }
+    }
+    locked
+}
+
+/// Outcome of checking a frame timestamp against its channel's subscription window and
+/// last decoded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampCheck {
+    /// Within the window and newer than every frame decoded on the channel; it is now
+    /// the last one.
+    Accepted,
+    /// Not newer than the last decoded frame.
+    Replayed,
+    /// The channel has no entry in the active channel list.
+    Inactive,
+    /// Before the start of the subscription window.
+    NotYetValid,
+    /// Past the end of the subscription window.
+    Expired,
+}
+
+/// Check `frame`'s timestamp against the subscription `window` it decodes under, then
+/// against the channel's last decoded frame, and record it as the last one if both
+/// pass. A frame outside the window is refused however new, and moves nothing.
+pub fn validate_channel_timestamp(
+    frame: &ChannelFrame,
+    window: &ChannelInfo,
+    active_channels: &mut ActiveChannelsList,
+) -> TimestampCheck {
+    if frame.timestamp < window.start_timestamp {
+        return TimestampCheck::NotYetValid;
+    }
+    if frame.timestamp > window.end_timestamp {
+        return TimestampCheck::Expired;
+    }
+
     for channel_opt in active_channels.iter_mut() {
         if let Some(channel) = channel_opt.as_mut() {
             if channel.channel_id != frame.channel {
@@ -143,81 +686,214 @@ pub fn validate_channel_timestamp(frame: &ChannelFrame, active_channels: &mut Ac
             if !channel.received {
                 channel.received = true;
                 channel.last_frame = frame.timestamp;
-                return true;
+                return TimestampCheck::Accepted;
             }
             else if channel.received && frame.timestamp > channel.last_frame {
                 channel.last_frame = frame.timestamp;
-                return true;
+                return TimestampCheck::Accepted;
             }
             else {
-                return false;
+                return TimestampCheck::Replayed;
             }
         }
     }
 
-    false
+    TimestampCheck::Inactive
+}
+
+/// Signed subscription header: decoder id (u32), start and end timestamps (u64),
+/// channel id (u32) and the 12-byte ChaCha20 nonce.
+const SUBSCRIPTION_HEADER_LEN: usize = 4 + 8 + 8 + 4 + NONCE_LEN;
+/// Ed25519 signature trailing every signed message.
+const SIGNATURE_LEN: usize = ed25519_dalek::SIGNATURE_LENGTH;
+
+/// Check that a subscription of `length` bytes, without any checksum, is well formed:
+/// header, at least one password, signature; at most a full password table, and only
+/// whole password entries in between, so a truncated table is not read as a shorter
+/// tree.
+fn check_subscription_length(length: usize) -> Result<(), SubscriptionError> {
+    if length < SUBSCRIPTION_HEADER_LEN + size_of::<ChannelPassword>() + SIGNATURE_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    // The passwords are decrypted in a buffer of one full table
+    if length - SUBSCRIPTION_HEADER_LEN - SIGNATURE_LEN > size_of::<ChannelPasswords>() {
+        return Err(SubscriptionError::PasswordBlobTooLarge);
+    }
+    if !(length - SUBSCRIPTION_HEADER_LEN - SIGNATURE_LEN).is_multiple_of(size_of::<ChannelPassword>()) {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    Ok(())
+}
+
+/// Domain label prefixed to the signed subscribe preamble.
+const PREAMBLE_LABEL: &[u8] = b"ectf25-preamble";
+/// Subscribe preamble body: decoder id (u32 LE), channel id (u32 LE), length of the
+/// Subscribe body to follow (u16 LE), signature.
+pub const PREAMBLE_BODY_LEN: usize = 4 + 4 + 2 + SIGNATURE_LEN;
+/// Signed message: label || the body up to the signature.
+const PREAMBLE_MSG_LEN: usize = PREAMBLE_LABEL.len() + PREAMBLE_BODY_LEN - SIGNATURE_LEN;
+
+/// Check a host-signed preamble announcing a Subscribe, so a subscription this decoder
+/// would refuse outright is turned away before its ~3.2 KB body is sent.
+///
+/// The decoder id, the channel, the room for it and the announced length are checked
+/// first, as in `check_subscription_valid_and_store` they are public; the signature
+/// last. Passing says nothing about the body that follows, which is checked in full.
+pub fn check_subscribe_preamble(flash_manager: &mut FlashManager, body: &[u8]) -> Result<(), SubscriptionError> {
+    if body.len() != PREAMBLE_BODY_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let fields = &body[..PREAMBLE_BODY_LEN - SIGNATURE_LEN];
+    let decoder_id = read_u32_le(fields, 0);
+    let channel_id = read_u32_le(fields, 4);
+    let length = read_u16_le(fields, 8) as usize;
+
+    if decoder_id != DECODER_ID {
+        return Err(SubscriptionError::InvalidDecoderId);
+    }
+    if channel_id == 0 {
+        return Err(SubscriptionError::InvalidChannelId);
+    }
+    #[cfg(feature = "subscribe-checksum")]
+    let length = length.checked_sub(SUBSCRIPTION_CHECKSUM_LEN).ok_or(SubscriptionError::InvalidLength)?;
+    check_subscription_length(length)?;
+    // A stored subscription for the channel is replaced in place of a free page
+    if get_subscription_addr(flash_manager, channel_id).is_none() && free_subscription_pages(flash_manager) == 0 {
+        return Err(SubscriptionError::NoPageFound);
+    }
+
+    let sig = Signature::from_slice(&body[PREAMBLE_BODY_LEN - SIGNATURE_LEN..])
+        .map_err(|_| SubscriptionError::InvalidSignature)?;
+    let mut message = [0u8; PREAMBLE_MSG_LEN];
+    message[..PREAMBLE_LABEL.len()].copy_from_slice(PREAMBLE_LABEL);
+    message[PREAMBLE_LABEL.len()..].copy_from_slice(fields);
+    if !verify_host_signature(&message, &sig)? {
+        return Err(SubscriptionError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Length of the CRC-16 (u16 LE) trailing a Subscribe body.
+#[cfg(feature = "subscribe-checksum")]
+pub const SUBSCRIPTION_CHECKSUM_LEN: usize = 2;
+
+/// Check the CRC-16 over the first `length - 2` bytes of `body` against the two after
+/// them, and return the length of the subscription without it.
+#[cfg(feature = "subscribe-checksum")]
+fn strip_subscription_checksum(body: &MessageBody, length: usize) -> Result<usize, SubscriptionError> {
+    if length > body.data.len() || length < SUBSCRIPTION_CHECKSUM_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let length = length - SUBSCRIPTION_CHECKSUM_LEN;
+    let expected = read_u16_le(&body.data, length);
+    if crc16(&body.data[..length]) != expected {
+        return Err(SubscriptionError::ChecksumMismatch);
+    }
+    Ok(length)
 }
 
 pub fn check_subscription_valid_and_store(
     hdr: &MessageHeader,
-    body: MessageBody,
+    body: &MessageBody,
     flash_manager: &mut FlashManager,
-    active_channels: &mut ActiveChannelsList
-) -> Result<(), ()>  {
-    let verifying_key = VerifyingKey::from_public_key_der(HOST_KEY_PUB).map_err(|_| {})?;
+    active_channels: &mut ActiveChannelsList,
+    #[cfg(feature = "rekey")] device_key: &DeviceKey,
+) -> Result<(), SubscriptionError> {
+    // A corrupted transfer is caught by its checksum, and resent, before any of the
+    // work below is spent on it
+    #[cfg(feature = "subscribe-checksum")]
+    let length = strip_subscription_checksum(body, hdr.length as usize)?;
+    #[cfg(not(feature = "subscribe-checksum"))]
+    let length = hdr.length as usize;
 
-    let header_len = 36;
+    check_subscription_length(length)?;
+    let subscription = body.data.get(..length).ok_or(SubscriptionError::InvalidLength)?;
 
-    let msg_len = hdr.length as usize - 64;
-    let message = &body.data[..msg_len];
-    let signature = &body.data[msg_len..hdr.length as usize];
-    
-    let sig_result = Signature::from_slice(signature);
+    #[cfg(not(feature = "rekey"))]
+    let decoder_key = DECODER_KEY;
+    #[cfg(feature = "rekey")]
+    let decoder_key = *device_key.key();
 
-    if let Err(_) = sig_result {
-        return Err(());
-    }
+    let channel_subscription = open_subscription(subscription, &decoder_key)?;
 
-    let sig = sig_result.unwrap();
-    
-    let result = verifying_key.verify(message, &sig);
-    
-    if result.is_err() {
-        // write_debug(&mut console, "Signature verification failed\n");
-        return Err(());
-    } else {
-        // write_debug(&mut console, "Signature verification succeeded!\n");
-    }
+    // Store the subscription
+    save_subscription(flash_manager, channel_subscription, active_channels)
+}
 
-    let decoder_id = u32::from_le_bytes(message[0..4].try_into().unwrap());
-    let start_timestamp = u64::from_le_bytes(message[4..12].try_into().unwrap());
-    let end_timestamp = u64::from_le_bytes(message[12..20].try_into().unwrap());
-    let channel_id = u32::from_le_bytes(message[20..24].try_into().unwrap());
-    // Parse the 12-byte nonce from bytes 24-36
-    let mut nonce = [0u8; 12];
-    nonce.copy_from_slice(&message[24..36]);
+/// Verify and decrypt `subscription`, a Subscribe body without any checksum whose
+/// length `check_subscription_length` has accepted, into the record to store.
+fn open_subscription(subscription: &[u8], decoder_key: &[u8; KEY_LEN]) -> Result<ChannelSubscription, SubscriptionError> {
+    let header_len = SUBSCRIPTION_HEADER_LEN;
+    let length = subscription.len();
+
+    // The signed region is derived from the field layout, the header plus whole
+    // password entries, and must end exactly where the signature starts, so no
+    // length can move a parsed field out of the signature's coverage
+    let password_count = (length - header_len - SIGNATURE_LEN) / size_of::<ChannelPassword>();
+    let msg_len = header_len + password_count * size_of::<ChannelPassword>();
+    // The signature must be exactly one Ed25519 signature, checked before slicing so
+    // a miscomputed length is an error and not a panic
+    if length.checked_sub(msg_len) != Some(SIGNATURE_LEN) {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let message = &subscription[..msg_len];
+    let signature = &subscription[msg_len..];
 
-    // Check decoder id is valid
+    // The decoder id and channel are public, so a subscription for another decoder or
+    // for the built-in channel 0 is turned away before the signature check, as a bad
+    // length is; both are still covered by the signature below.
+    let decoder_id = read_u32_le(message, 0);
     if decoder_id != DECODER_ID {
-        return Err(());
+        return Err(SubscriptionError::InvalidDecoderId);
     }
-
-    // Check if channel is channel 0
+    let channel_id = read_u32_le(message, 20);
     if channel_id == 0 {
-        return Err(());
+        return Err(SubscriptionError::InvalidChannelId);
     }
 
-    let mut cipher = ChaCha20::new(&DECODER_KEY.into(), &nonce.into());
-
-    let msg_passwords = &message[header_len..msg_len];
+    // The signature outcome is only acted on once the whole message has been parsed
+    // and decrypted, so a rejected subscription takes the same path (and time) as an
+    // accepted one up to the point where it would be stored. Only public lengths and
+    // the decoder id affect the work done before that point.
+    let sig_valid = match Signature::from_slice(signature) {
+        Ok(sig) => verify_host_signature(message, &sig)?,
+        Err(_) => false,
+    };
 
-    let mut passwords_data: [u8; 128*25] = [0; 128*25];
-    passwords_data[..(msg_len-header_len)].copy_from_slice(&msg_passwords);
+    let start_timestamp = read_u64_le(message, 4);
+    let end_timestamp = read_u64_le(message, 12);
+    // The nonce ends the header, bytes 24-36
+    let nonce = Nonce::parse(&message[24..SUBSCRIPTION_HEADER_LEN])?;
+
+    // Exactly the validated password entries; the rest of the table stays zeroed
+    let blob_len = password_count * size_of::<ChannelPassword>();
+    let mut passwords_data = [0u8; size_of::<ChannelPasswords>()];
+    let blob = &mut passwords_data[..blob_len];
+    blob.copy_from_slice(&message[header_len..msg_len]);
+    decrypt_in_place(decoder_key, &nonce, blob);
+
+    // Parse the passwords into ChannelPasswords, sorted for lookup during decode. Read
+    // by copy, so no reference into the byte buffer is ever formed, whatever its
+    // alignment or the table's
+    let mut passwords = bytemuck::pod_read_unaligned::<ChannelPasswords>(&passwords_data);
+    passwords.sort();
+
+    if !sig_valid {
+        return Err(SubscriptionError::InvalidSignature);
+    }
 
-    cipher.apply_keystream(&mut passwords_data[0..(msg_len - header_len)]);
+    // Signed, so the host's encoder really sent it; refused rather than stored, as a
+    // second such subscription would reuse its keystream
+    if nonce.is_zero() {
+        return Err(SubscriptionError::ZeroNonce);
+    }
 
-    // Parse the passwords into ChannelPasswords
-    let passwords = bytemuck::from_bytes::<ChannelPasswords>(&passwords_data);
+    // Any key and nonce "decrypt" the blob. A subscription without a single valid node
+    // could never decode a frame, so a key or nonce mismatch is reported here rather
+    // than as PasswordNotFound on every frame to come.
+    if !passwords.contents.iter().any(|p| matches!(p.node_ext, 1 | 2)) {
+        return Err(SubscriptionError::NoUsablePasswords);
+    }
 
     let channel_info = ChannelInfo {
         channel_id,
@@ -225,32 +901,611 @@ pub fn check_subscription_valid_and_store(
         end_timestamp
     };
 
-    let channel_subscription = ChannelSubscription {
+    Ok(ChannelSubscription {
         info: channel_info,
-        passwords: *passwords,
+        passwords,
+    })
+}
+
+/// Domain label prefixed to the signed subscription bundle.
+const BUNDLE_LABEL: &[u8] = b"ectf25-bundle";
+/// Longest signed bundle message: label || decoder id (u32 LE) || the body up to the
+/// signature.
+const BUNDLE_MSG_MAX_LEN: usize = BUNDLE_LABEL.len() + 4 + MAX_BODY_LEN - SIGNATURE_LEN;
+
+/// Offset and length in a bundle body of each subscription it carries.
+type BundleEntries = [(usize, usize); MAX_CHANNELS];
+
+/// Split a SubscribeBundle body into its subscriptions and check its signature.
+///
+/// The body is a count (u8, 1 to `MAX_CHANNELS`), then per subscription its length
+/// (u16 LE) and a Subscribe body without checksum, then a signature over the label,
+/// the decoder id and everything before it. The framing must cover the body exactly.
+fn parse_bundle(body: &[u8]) -> Result<(usize, BundleEntries), SubscriptionError> {
+    if body.len() > MAX_BODY_LEN || body.len() < 1 + SIGNATURE_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let fields = &body[..body.len() - SIGNATURE_LEN];
+    let count = fields[0] as usize;
+    if count == 0 || count > MAX_CHANNELS {
+        return Err(SubscriptionError::InvalidLength);
+    }
+
+    let mut entries: BundleEntries = [(0, 0); MAX_CHANNELS];
+    let mut at = 1;
+    for entry in entries[..count].iter_mut() {
+        if fields.len() - at < 2 {
+            return Err(SubscriptionError::InvalidLength);
+        }
+        let length = read_u16_le(fields, at) as usize;
+        at += 2;
+        check_subscription_length(length)?;
+        if fields.len() - at < length {
+            return Err(SubscriptionError::InvalidLength);
+        }
+        *entry = (at, length);
+        at += length;
+    }
+    if at != fields.len() {
+        return Err(SubscriptionError::InvalidLength);
+    }
+
+    let sig = Signature::from_slice(&body[fields.len()..]).map_err(|_| SubscriptionError::InvalidSignature)?;
+    let msg_len = BUNDLE_LABEL.len() + 4 + fields.len();
+    let mut message = [0u8; BUNDLE_MSG_MAX_LEN];
+    message[..BUNDLE_LABEL.len()].copy_from_slice(BUNDLE_LABEL);
+    message[BUNDLE_LABEL.len()..BUNDLE_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+    message[BUNDLE_LABEL.len() + 4..msg_len].copy_from_slice(fields);
+    if !verify_host_signature(&message[..msg_len], &sig)? {
+        return Err(SubscriptionError::InvalidSignature);
+    }
+    Ok((count, entries))
+}
+
+/// Magic of the bundle journal's header record, which is written last and commits it.
+const BUNDLE_JOURNAL_MAGIC: u32 = 0xB0DD_1E50;
+/// The journal of a bundle being applied is kept in the scratch page: its header
+/// record (one 16-byte word, the body length), a pause slot per subscription (the
+/// count is a u8), then the body.
+const JOURNAL_ADDRESS: u32 = FlashManager::scratch_page_addr();
+const JOURNAL_PAUSES_ADDRESS: u32 = JOURNAL_ADDRESS + FLASH_WORD_SIZE;
+const JOURNAL_BODY_ADDRESS: u32 = JOURNAL_PAUSES_ADDRESS + u8::MAX as u32 * PAUSE_SLOT_SIZE;
+
+// The header is a single flash write, and a whole body fits the page after the slots.
+const _: () = assert!(4 + size_of::<u32>() + 4 <= FLASH_WORD_SIZE as usize);
+const _: () = assert!(JOURNAL_BODY_ADDRESS + MAX_BODY_LEN as u32 <= JOURNAL_ADDRESS + PAGE_SIZE);
+
+fn journal_pause_addr(entry: usize) -> u32 {
+    JOURNAL_PAUSES_ADDRESS + entry as u32 * PAUSE_SLOT_SIZE
+}
+
+/// Replace every stored subscription with the ones in a host-signed bundle, all of
+/// them or none. Returns the number of subscriptions stored.
+///
+/// Each subscription is checked as a Subscribe would check it, and none may repeat a
+/// channel, before flash is touched. The bundle is then journaled to the scratch page,
+/// discarding any resumable upload there, with the pause state each kept channel
+/// carries over. A failure (or a reset) before the journal's header is written leaves
+/// the stored set as it was. Once it is written the bundle is applied: subscription
+/// `i` goes to page `i` and every other page is wiped, so no free page is needed and a
+/// full decoder takes a bundle as large as its set. Should that fail part way, the
+/// journal stays and `finish_subscription_bundle` completes it at the next boot.
+///
+/// Channels kept by the bundle keep their replay and pause state; the others are dropped.
+pub fn apply_subscription_bundle(
+    flash_manager: &mut FlashManager,
+    body: &[u8],
+    active_channels: &mut ActiveChannelsList,
+    #[cfg(feature = "rekey")] device_key: &DeviceKey,
+) -> Result<u32, SubscriptionError> {
+    let (count, entries) = parse_bundle(body)?;
+    let entries = &entries[..count];
+
+    #[cfg(not(feature = "rekey"))]
+    let decoder_key = DECODER_KEY;
+    #[cfg(feature = "rekey")]
+    let decoder_key = *device_key.key();
+
+    let mut channels = [0u32; MAX_CHANNELS];
+    for (i, &(at, length)) in entries.iter().enumerate() {
+        let info = open_subscription(&body[at..at + length], &decoder_key)?.info;
+        let (channel_id, end_timestamp) = (info.channel_id, info.end_timestamp);
+        if channels[..i].contains(&channel_id) {
+            return Err(SubscriptionError::InvalidChannelId);
+        }
+        if REJECT_OLDER_SUBSCRIPTIONS
+            && find_subscription_page(flash_manager, |c| c.channel_id == channel_id && c.end_timestamp > end_timestamp).is_some()
+        {
+            return Err(SubscriptionError::StaleSubscription);
+        }
+        channels[i] = channel_id;
+    }
+    let channels = &channels[..count];
+
+    // The journal holds the pause states, as applying it overwrites the old pages
+    let mut old: [Option<(u32, u32)>; MAX_CHANNELS] = [None; MAX_CHANNELS];
+    for (slot, (addr, c)) in old.iter_mut().zip(channel_subscriptions(flash_manager, false)) {
+        *slot = c.map(|info| (addr, info.channel_id));
+    }
+    let mut pauses = [PauseState::default(); MAX_CHANNELS];
+    for &(addr, channel_id) in old.iter().flatten() {
+        if let Some(i) = channels.iter().position(|&c| c == channel_id) {
+            pauses[i] = read_pause_state(flash_manager, addr);
+        }
+    }
+    let pauses = &pauses[..count];
+
+    write_bundle_journal(flash_manager, body, pauses)?;
+    replay_bundle(flash_manager, body, entries, pauses, &decoder_key)?;
+
+    let previous = *active_channels;
+    *active_channels = [None; ACTIVE_CHANNELS_LEN];
+    active_channels[0] = previous[0];
+    for (slot, (&channel_id, pause)) in active_channels[1..].iter_mut().zip(channels.iter().zip(pauses)) {
+        let kept = previous.iter().flatten().find(|c| c.channel_id == channel_id);
+        *slot = Some(ActiveChannel {
+            channel_id,
+            last_frame: kept.map_or(0, |c| c.last_frame),
+            received: kept.is_some_and(|c| c.received),
+            paused: pause.paused,
+            decode_count: kept.map_or(0, |c| c.decode_count),
+        });
+    }
+    Ok(count as u32)
+}
+
+/// Complete a bundle whose journal was written but whose pages a reset or a flash
+/// failure interrupted, so the decoder comes up with the whole new set. Call at boot,
+/// before the active list is read from the pages. Returns the number of subscriptions
+/// stored, 0 when no bundle was pending.
+///
+/// The journaled body is read into `body`, e.g. the command buffer before its first
+/// use, and checked again. A journal that no longer verifies is dropped.
+pub fn finish_subscription_bundle(
+    flash_manager: &mut FlashManager,
+    body: &mut MessageBody,
+    #[cfg(feature = "rekey")] device_key: &DeviceKey,
+) -> Result<u32, SubscriptionError> {
+    if flash_manager.read_magic(JOURNAL_ADDRESS).map_err(FlashManagerError::from)? != BUNDLE_JOURNAL_MAGIC {
+        return Ok(0);
+    }
+    let length = flash_manager.read_data_verified::<u32>(JOURNAL_ADDRESS)? as usize;
+    if length > MAX_BODY_LEN {
+        flash_manager.wipe_data(JOURNAL_ADDRESS)?;
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let words = length.next_multiple_of(FLASH_WORD_SIZE as usize);
+    flash_manager.read_raw(JOURNAL_BODY_ADDRESS, &mut body.data[..words]).map_err(FlashManagerError::from)?;
+    body.length = length as u16;
+    let body = &body.data[..length];
+
+    let (count, entries) = match parse_bundle(body) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            flash_manager.wipe_data(JOURNAL_ADDRESS)?;
+            return Err(e);
+        }
     };
+    let mut pauses = [PauseState::default(); MAX_CHANNELS];
+    for (i, pause) in pauses[..count].iter_mut().enumerate() {
+        let addr = journal_pause_addr(i);
+        if matches!(flash_manager.read_magic(addr), Ok(PAUSE_MAGIC)) {
+            let record = flash_manager.read_data_verified::<PauseRecord>(addr)?;
+            *pause = PauseState { sequence: record.sequence, paused: record.paused != 0 };
+        }
+    }
 
-    // Store the subscription
-    return save_subscription(flash_manager, channel_subscription, active_channels).map_err(|_| ());
+    #[cfg(not(feature = "rekey"))]
+    let decoder_key = DECODER_KEY;
+    #[cfg(feature = "rekey")]
+    let decoder_key = *device_key.key();
+
+    replay_bundle(flash_manager, body, &entries[..count], &pauses[..count], &decoder_key)?;
+    Ok(count as u32)
 }
 
-fn get_subscription_addr(
+/// Wipe the scratch page and journal a checked bundle `body` to it, with the pause
+/// state each of its subscriptions carries over. The header goes last and commits it.
+fn write_bundle_journal(flash_manager: &mut FlashManager, body: &[u8], pauses: &[PauseState]) -> Result<(), FlashManagerError> {
+    flash_manager.wipe_data(JOURNAL_ADDRESS)?;
+    flash_manager.write_raw(JOURNAL_BODY_ADDRESS, body)?;
+    for (i, pause) in pauses.iter().enumerate() {
+        // A never paused channel has no state to carry over
+        if pause.sequence != 0 {
+            let record = PauseRecord { sequence: pause.sequence, paused: pause.paused as u32 };
+            flash_manager.write_data(journal_pause_addr(i), PAUSE_MAGIC, &record)?;
+        }
+    }
+    flash_manager.write_data(JOURNAL_ADDRESS, BUNDLE_JOURNAL_MAGIC, &(body.len() as u32))?;
+    flash_manager.read_data_verified::<u32>(JOURNAL_ADDRESS)?;
+    Ok(())
+}
+
+/// Write the journaled bundle's subscriptions to the first pages in order, wipe the
+/// pages after them, then wipe the journal. Every page is written afresh from the
+/// journal, so running it again after an interruption gives the same result.
+fn replay_bundle(
     flash_manager: &mut FlashManager,
-    channel_id: u32
-) -> Option<u32> {
-    let mut page_addr: Option<u32> = None;
+    body: &[u8],
+    entries: &[(usize, usize)],
+    pauses: &[PauseState],
+    decoder_key: &[u8; KEY_LEN],
+) -> Result<(), SubscriptionError> {
+    for (page, (&(at, length), &pause)) in entries.iter().zip(pauses).enumerate() {
+        // Opened again rather than kept, as each is a full password table
+        let subscription = open_subscription(&body[at..at + length], decoder_key)?;
+        write_subscription_page(flash_manager, subscription_page_addr(page), &subscription, pause)?;
+    }
+    for page in entries.len()..MAX_CHANNELS {
+        let addr = subscription_page_addr(page);
+        if !matches!(flash_manager.read_magic(addr), Ok(ERASED_MAGIC)) {
+            flash_manager.wipe_data(addr)?;
+        }
+    }
+    flash_manager.wipe_data(JOURNAL_ADDRESS)?;
+    Ok(())
+}
+
+/// Length of a NodeDump response: entry count (u32 LE), then `node_trunc` (u64 LE) and
+/// `node_ext` (u8) of every populated password.
+#[cfg(feature = "debug-dump")]
+pub const NODE_DUMP_MAX_LEN: usize = 4 + PASSWORD_TREE_NODES * 9;
+
+/// Write the tree nodes held by the stored subscription for `channel_id` into `out`,
+/// returning the response length. Password bytes are never included.
+#[cfg(feature = "debug-dump")]
+pub fn dump_subscription_nodes(
+    flash_manager: &mut FlashManager,
+    channel_id: u32,
+    out: &mut [u8; NODE_DUMP_MAX_LEN],
+) -> Result<usize, SubscriptionError> {
+    let addr = get_subscription_addr(flash_manager, channel_id).ok_or(SubscriptionError::NoSubscription)?;
+    let subscription = read_subscription(flash_manager, addr)?;
+
+    let mut count: u32 = 0;
+    let mut len = 4;
+    for password in subscription.passwords.contents.iter().take_while(|c| c.node_ext != 0) {
+        out[len..len + 8].copy_from_slice(&{ password.node_trunc }.to_le_bytes());
+        out[len + 8] = password.node_ext;
+        len += 9;
+        count += 1;
+    }
+    out[..4].copy_from_slice(&count.to_le_bytes());
+    Ok(len)
+}
+
+/// Length of a PageDump response: the page address (u32 LE), the byte count (u32 LE),
+/// then the page as stored.
+#[cfg(feature = "page-dump")]
+pub const PAGE_DUMP_LEN: usize = 8 + PAGE_SIZE as usize;
+
+/// Write the raw subscription page at `addr` into `out`, passwords and all, returning
+/// the response length. Any address but the start of one of the `MAX_CHANNELS`
+/// subscription pages is refused, so no other flash, code included, can be read.
+#[cfg(feature = "page-dump")]
+pub fn dump_subscription_page(
+    flash_manager: &mut FlashManager,
+    addr: u32,
+    out: &mut [u8; PAGE_DUMP_LEN],
+) -> Result<usize, SubscriptionError> {
+    if !(0..MAX_CHANNELS).any(|page| subscription_page_addr(page) == addr) {
+        return Err(SubscriptionError::NotSubscriptionPage);
+    }
+    out[..4].copy_from_slice(&addr.to_le_bytes());
+    out[4..8].copy_from_slice(&PAGE_SIZE.to_le_bytes());
+    flash_manager.read_raw(addr, &mut out[8..]).map_err(FlashManagerError::from)?;
+    Ok(PAGE_DUMP_LEN)
+}
+
+/// Domain label prefixed to the signed window update message.
+const WINDOW_LABEL: &[u8] = b"ectf25-window";
+/// Window update body: channel id (u32 LE), current end (u64 LE), new end (u64 LE),
+/// flags (u8), signature.
+pub const WINDOW_BODY_LEN: usize = 4 + 8 + 8 + 1 + SIGNATURE_LEN;
+/// Signed message: label || decoder id (u32 LE) || the body up to the signature.
+const WINDOW_MSG_LEN: usize = WINDOW_LABEL.len() + 4 + WINDOW_BODY_LEN - SIGNATURE_LEN;
+/// Window update flag allowing the new end to be earlier than the current one.
+const WINDOW_ALLOW_SHRINK: u8 = 0x01;
+
+/// Move the end of a stored subscription's window from a host-signed update, keeping
+/// its passwords.
+///
+/// The update names the end it replaces, so it only applies to the window it was
+/// issued for and a recorded update cannot be replayed to undo a later one. Frames
+/// past what the stored passwords cover still fail, whatever the window says.
+///
+/// The new end is appended to the page's log as a window record, a single fresh flash
+/// word, so neither the page is erased nor the ~3.2 KB record rewritten, whether the
+/// window grows or shrinks. Only a full log moves the subscription to a fresh page,
+/// with the new end in its record.
+pub fn update_subscription_window(
+    flash_manager: &mut FlashManager,
+    body: &[u8],
+    active_channels: &mut ActiveChannelsList,
+) -> Result<(), SubscriptionError> {
+    if body.len() != WINDOW_BODY_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let fields = &body[..WINDOW_BODY_LEN - SIGNATURE_LEN];
+    let sig = Signature::from_slice(&body[WINDOW_BODY_LEN - SIGNATURE_LEN..])
+        .map_err(|_| SubscriptionError::InvalidSignature)?;
+
+    let mut message = [0u8; WINDOW_MSG_LEN];
+    message[..WINDOW_LABEL.len()].copy_from_slice(WINDOW_LABEL);
+    message[WINDOW_LABEL.len()..WINDOW_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+    message[WINDOW_LABEL.len() + 4..].copy_from_slice(fields);
+
+    if !verify_host_signature(&message, &sig)? {
+        return Err(SubscriptionError::InvalidSignature);
+    }
+
+    let channel_id = read_u32_le(fields, 0);
+    let current_end = read_u64_le(fields, 4);
+    let new_end = read_u64_le(fields, 12);
+    let flags = fields[20];
+
+    let (addr, info) = find_subscription_page(flash_manager, |info| info.channel_id == channel_id)
+        .ok_or(SubscriptionError::NoSubscription)?;
 
-    for (addr, c) in channel_subscriptions(flash_manager, false) {
-        if let Some(stored_sub) = c {
-            if stored_sub.channel_id == channel_id {
-                // Found a matching subscription
-                page_addr = Some(addr);
+    if info.end_timestamp != current_end {
+        return Err(SubscriptionError::StaleSubscription);
+    }
+    if new_end < current_end && flags & WINDOW_ALLOW_SHRINK == 0 {
+        return Err(SubscriptionError::StaleSubscription);
+    }
+    if new_end < info.start_timestamp {
+        return Err(SubscriptionError::InvalidTimestamp);
+    }
+
+    if append_log_record(flash_manager, addr, WINDOW_MAGIC, &WindowRecord { end_timestamp: new_end })? {
+        return Ok(());
+    }
+    // Log full: the record goes through the same replace path as a new subscription
+    let mut subscription = read_subscription(flash_manager, addr)?;
+    subscription.info.end_timestamp = new_end;
+    write_subscription(flash_manager, subscription, active_channels, false)
+}
+
+/// Offset of a subscription page's log of pause and window records: the first 16-byte
+/// chunk after the subscription record, which `write_data` pads to a whole chunk.
+const PAUSE_LOG_OFFSET: u32 = (4 + size_of::<ChannelSubscription>() as u32 + 4).next_multiple_of(16);
+/// A log record is one 16-byte flash write: magic, PauseRecord or WindowRecord, and CRC.
+const PAUSE_SLOT_SIZE: u32 = 16;
+/// Log records a page holds before the subscription has to move to a fresh page.
+const PAUSE_SLOTS: u32 = (PAGE_SIZE - PAUSE_LOG_OFFSET) / PAUSE_SLOT_SIZE;
+
+const _: () = assert!(4 + size_of::<PauseRecord>() + 4 == PAUSE_SLOT_SIZE as usize);
+const _: () = assert!(4 + size_of::<WindowRecord>() + 4 == PAUSE_SLOT_SIZE as usize);
+const _: () = assert!(PAUSE_SLOTS >= 2);
+
+/// Pause state of a stored subscription, as appended to its page's pause log.
+///
+/// Pausing and resuming program the next erased slot of the log instead of erasing
+/// the page, so the ~3.2 KB password record before it is never rewritten for a toggle.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PauseRecord {
+    sequence: u32,
+    paused: u32,
+}
+
+/// End of a stored subscription's window, as a Window command appends it to its page's
+/// log. The newest one replaces the end in the subscription record.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct WindowRecord {
+    end_timestamp: u64,
+}
+
+/// Latest pause state of a stored subscription.
+///
+/// `sequence` counts the Pause commands applied to the channel and is part of the
+/// signed command, so a recorded command cannot be replayed to undo a later one.
+#[derive(Clone, Copy, Default)]
+pub struct PauseState {
+    pub sequence: u32,
+    pub paused: bool,
+}
+
+fn pause_slot_addr(addr: u32, slot: u32) -> u32 {
+    addr + PAUSE_LOG_OFFSET + slot * PAUSE_SLOT_SIZE
+}
+
+/// What the log of a subscription page holds.
+struct PageLog {
+    /// From the newest valid pause record.
+    pause: PauseState,
+    /// From the newest valid window record, if the window was ever moved.
+    end_timestamp: Option<u64>,
+    /// First erased slot after the records, if the log is not full.
+    free_slot: Option<u32>,
+}
+
+/// Read the log of the subscription page at `addr`.
+fn read_page_log(flash_manager: &mut FlashManager, addr: u32) -> PageLog {
+    let mut log = PageLog { pause: PauseState::default(), end_timestamp: None, free_slot: None };
+    for slot in 0..PAUSE_SLOTS {
+        let slot_addr = pause_slot_addr(addr, slot);
+        match flash_manager.read_magic(slot_addr) {
+            Ok(ERASED_MAGIC) => {
+                log.free_slot = Some(slot_addr);
                 break;
             }
+            // Torn or unreadable slots are used up but change nothing
+            Ok(PAUSE_MAGIC) => {
+                if let Ok(record) = flash_manager.read_data_verified::<PauseRecord>(slot_addr) {
+                    log.pause = PauseState { sequence: record.sequence, paused: record.paused != 0 };
+                }
+            }
+            Ok(WINDOW_MAGIC) => {
+                if let Ok(record) = flash_manager.read_data_verified::<WindowRecord>(slot_addr) {
+                    log.end_timestamp = Some(record.end_timestamp);
+                }
+            }
+            _ => {}
         }
     }
+    log
+}
+
+/// Pause state of the subscription stored at `addr`. A never paused channel reads as
+/// sequence 0, not paused.
+pub fn read_pause_state(flash_manager: &mut FlashManager, addr: u32) -> PauseState {
+    read_page_log(flash_manager, addr).pause
+}
+
+/// Append `state` to the log of the subscription page at `addr`. Returns `Ok(false)`,
+/// writing nothing, when the log is full.
+fn append_pause_record(flash_manager: &mut FlashManager, addr: u32, state: PauseState) -> Result<bool, FlashManagerError> {
+    let record = PauseRecord { sequence: state.sequence, paused: state.paused as u32 };
+    append_log_record(flash_manager, addr, PAUSE_MAGIC, &record)
+}
+
+/// Append `record` under `magic` to the first erased slot of the log of the
+/// subscription page at `addr`, and read it back. Returns `Ok(false)`, writing
+/// nothing, when the log is full.
+fn append_log_record<T: Pod>(
+    flash_manager: &mut FlashManager,
+    addr: u32,
+    magic: u32,
+    record: &T,
+) -> Result<bool, FlashManagerError> {
+    let Some(slot_addr) = read_page_log(flash_manager, addr).free_slot else {
+        return Ok(false);
+    };
+    flash_manager.write_data(slot_addr, magic, record)?;
+    flash_manager.read_data_verified::<T>(slot_addr)?;
+    Ok(true)
+}
+
+/// Domain label prefixed to the signed pause message.
+const PAUSE_LABEL: &[u8] = b"ectf25-pause";
+/// Pause body: channel id (u32 LE), current sequence (u32 LE), paused (u8), signature.
+pub const PAUSE_BODY_LEN: usize = 4 + 4 + 1 + SIGNATURE_LEN;
+/// Signed message: label || decoder id (u32 LE) || the body up to the signature.
+const PAUSE_MSG_LEN: usize = PAUSE_LABEL.len() + 4 + PAUSE_BODY_LEN - SIGNATURE_LEN;
+
+/// Pause or resume a stored subscription from a host-signed command, keeping its
+/// passwords. Returns the new sequence the next command has to be signed for.
+///
+/// The command names the sequence it applies to, as reported by the previous one (0
+/// for a channel never paused), so it applies once and a recorded command cannot be
+/// replayed later. A paused channel stays paused across reboots and subscription
+/// updates.
+pub fn set_channel_paused(
+    flash_manager: &mut FlashManager,
+    body: &[u8],
+    active_channels: &mut ActiveChannelsList,
+) -> Result<u32, SubscriptionError> {
+    if body.len() != PAUSE_BODY_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let fields = &body[..PAUSE_BODY_LEN - SIGNATURE_LEN];
+    let sig = Signature::from_slice(&body[PAUSE_BODY_LEN - SIGNATURE_LEN..])
+        .map_err(|_| SubscriptionError::InvalidSignature)?;
 
-    return page_addr;
+    let mut message = [0u8; PAUSE_MSG_LEN];
+    message[..PAUSE_LABEL.len()].copy_from_slice(PAUSE_LABEL);
+    message[PAUSE_LABEL.len()..PAUSE_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+    message[PAUSE_LABEL.len() + 4..].copy_from_slice(fields);
+
+    if !verify_host_signature(&message, &sig)? {
+        return Err(SubscriptionError::InvalidSignature);
+    }
+
+    let channel_id = read_u32_le(fields, 0);
+    let sequence = read_u32_le(fields, 4);
+    let paused = fields[8] != 0;
+
+    // Channel 0 has no page and can never be paused
+    let addr = get_subscription_addr(flash_manager, channel_id).ok_or(SubscriptionError::NoSubscription)?;
+    let current = read_pause_state(flash_manager, addr);
+    if current.sequence != sequence {
+        return Err(SubscriptionError::StaleSubscription);
+    }
+
+    let next = PauseState { sequence: sequence.wrapping_add(1), paused };
+    if !append_pause_record(flash_manager, addr, next)? {
+        // Log full: move the subscription to a fresh page with just the new state
+        let subscription = read_subscription(flash_manager, addr)?;
+        let free_addr = channel_subscriptions(flash_manager, true).find_map(|(a, c)| c.is_none().then_some(a));
+        store_subscription(flash_manager, &subscription, Some(addr), free_addr, next)?;
+    }
+
+    if let Some(channel) = active_channels.iter_mut().flatten().find(|c| c.channel_id == channel_id) {
+        channel.paused = paused;
+    }
+    Ok(next.sequence)
+}
+
+/// Length of a ReplayState response: entry count (u32 LE), then `channel_id` (u32 LE),
+/// `received` (u8) and `last_frame` (u64 LE) of every active channel.
+#[cfg(feature = "debug-dump")]
+pub const REPLAY_STATE_MAX_LEN: usize = 4 + ACTIVE_CHANNELS_LEN * 13;
+
+/// Write the replay state the decoder checks frame timestamps against into `out`,
+/// returning the response length.
+#[cfg(feature = "debug-dump")]
+pub fn dump_replay_state(active_channels: &ActiveChannelsList, out: &mut [u8; REPLAY_STATE_MAX_LEN]) -> usize {
+    let mut count: u32 = 0;
+    let mut len = 4;
+    for channel in active_channels.iter().flatten() {
+        out[len..len + 4].copy_from_slice(&channel.channel_id.to_le_bytes());
+        out[len + 4] = channel.received as u8;
+        out[len + 5..len + 13].copy_from_slice(&channel.last_frame.to_le_bytes());
+        len += 13;
+        count += 1;
+    }
+    out[..4].copy_from_slice(&count.to_le_bytes());
+    len
+}
+
+/// Read the full subscription record at `addr`, passwords included, and check its CRC.
+///
+/// The page iterator only reads each page's `ChannelInfo` header, so List and lookups
+/// do not pull in the ~3.2 KB password table; callers needing the passwords read the
+/// record once with this instead.
+///
+/// A page not committed under `SUBSCRIPTION_MAGIC`, such as one whose write a reset cut
+/// short, is refused with `MagicMismatch`: it holds no subscription until committed.
+pub fn read_subscription(flash_manager: &mut FlashManager, addr: u32) -> Result<ChannelSubscription, FlashManagerError> {
+    let mut subscription = ChannelSubscription::zeroed();
+    read_subscription_into(flash_manager, addr, &mut subscription)?;
+    Ok(subscription)
+}
+
+/// `read_subscription` into a caller-owned record, e.g. the decode context's copy.
+fn read_subscription_into(
+    flash_manager: &mut FlashManager,
+    addr: u32,
+    subscription: &mut ChannelSubscription,
+) -> Result<(), FlashManagerError> {
+    if flash_manager.read_magic(addr)? != SUBSCRIPTION_MAGIC {
+        return Err(FlashManagerError::MagicMismatch);
+    }
+    flash_manager.read_data_verified_into(addr, subscription)?;
+    if let Some(end_timestamp) = read_page_log(flash_manager, addr).end_timestamp {
+        subscription.info.end_timestamp = end_timestamp;
+    }
+    Ok(())
+}
+
+/// First stored subscription whose header satisfies `predicate`, with its page address.
+pub fn find_subscription_page(
+    flash_manager: &mut FlashManager,
+    predicate: impl Fn(&ChannelInfo) -> bool,
+) -> Option<(u32, ChannelInfo)> {
+    channel_subscriptions(flash_manager, false)
+        .find_map(|(addr, c)| c.filter(|info| predicate(info)).map(|info| (addr, info)))
+}
+
+/// Page of the stored subscription for `channel_id`. Pages written but not committed
+/// read as free to the page iterator, so an uncommitted subscription is never found.
+fn get_subscription_addr(
+    flash_manager: &mut FlashManager,
+    channel_id: u32
+) -> Option<u32> {
+    find_subscription_page(flash_manager, |info| info.channel_id == channel_id).map(|(addr, _)| addr)
 }
 
 pub fn save_subscription(
@@ -258,196 +1513,714 @@ pub fn save_subscription(
     subscription: ChannelSubscription,
     active_channels: &mut ActiveChannelsList,
 ) -> Result<(), SubscriptionError> {
+    write_subscription(flash_manager, subscription, active_channels, REJECT_OLDER_SUBSCRIPTIONS)
+}
+
+/// Store `subscription`, replacing any stored one for the channel. With `reject_older`,
+/// a subscription ending before the stored one is refused.
+fn write_subscription(
+    flash_manager: &mut FlashManager,
+    subscription: ChannelSubscription,
+    active_channels: &mut ActiveChannelsList,
+    reject_older: bool,
+) -> Result<(), SubscriptionError> {
 
     let channel_id = subscription.info.channel_id;
+    // The emergency channel is never stored, whatever path the subscription came from
+    if channel_id == 0 {
+        return Err(SubscriptionError::InvalidChannelId);
+    }
 
-    let mut page_addr: Option<u32> = None;
+    let mut existing_addr: Option<u32> = None;
+    let mut free_addr: Option<u32> = None;
 
     for (addr, c) in channel_subscriptions(flash_manager, true) {
-        if let Some(stored_sub) = c {
-            if stored_sub.channel_id == channel_id {
-                // Found a matching subscription
-                page_addr = Some(addr);
-                break;
+        match c {
+            Some(stored_sub) if stored_sub.channel_id == channel_id => {
+                if reject_older && subscription.info.end_timestamp < stored_sub.end_timestamp {
+                    return Err(SubscriptionError::StaleSubscription);
+                }
+                existing_addr.get_or_insert(addr);
             }
-        } else {
-            // Found an unoccupied page
-            page_addr = Some(addr);
-            break;
+            None => {
+                free_addr.get_or_insert(addr);
+            }
+            _ => {}
         }
     }
 
-    if let Some(addr) = page_addr {
-        flash_manager
-            .wipe_data(addr)?;
-        flash_manager
-            .write_data(addr, 0xABCD, &subscription)?;
+    // A host retrying after a lost response resends the same subscription. The stored
+    // copy is then already the result, so it is not erased and rewritten. The record
+    // has to be read to check its CRC anyway, so it is compared in full rather than by
+    // CRC, which cannot tell two records apart on its own.
+    if let Some(addr) = existing_addr {
+        if read_subscription(flash_manager, addr).is_ok_and(|stored| bytes_of(&stored) == bytes_of(&subscription)) {
+            return Ok(());
+        }
+    }
 
-        // Activate subscription
-        for i in 0..active_channels.len() {
-            let channel_opt = &mut active_channels[i];
-            if let Some(channel) = channel_opt.as_mut() {
-                // Do nothing if subscription exists (don't reset monotonic timestamp counter)
-                if channel.channel_id == channel_id {
-                    break;
-                }
-            } else {
-                // None of the existing channels match - create new entry
-                active_channels[i] = Some(ActiveChannel {
-                    channel_id,
-                    received: false,
-                    last_frame: 0,
-                });
+    // The pause state moves with the subscription to its new page
+    let pause = existing_addr.map(|addr| read_pause_state(flash_manager, addr)).unwrap_or_default();
+    store_subscription(flash_manager, &subscription, existing_addr, free_addr, pause)?;
+
+    // Activate subscription
+    for channel_opt in active_channels.iter_mut() {
+        if let Some(channel) = channel_opt.as_mut() {
+            // Do nothing if subscription exists (don't reset monotonic timestamp counter)
+            if channel.channel_id == channel_id {
                 break;
             }
+        } else {
+            // None of the existing channels match - create new entry
+            *channel_opt = Some(ActiveChannel {
+                channel_id,
+                received: false,
+                last_frame: 0,
+                paused: pause.paused,
+                decode_count: 0,
+            });
+            break;
         }
+    }
 
-        return Ok(());
-    } else {
+    Ok(())
+}
+
+/// Write `subscription` with `pause` as its pause log to `free_addr`, or over
+/// `existing_addr` when no page is free, then retire `existing_addr`.
+fn store_subscription(
+    flash_manager: &mut FlashManager,
+    subscription: &ChannelSubscription,
+    existing_addr: Option<u32>,
+    free_addr: Option<u32>,
+    pause: PauseState,
+) -> Result<(), SubscriptionError> {
+    // Prefer a free page so the old subscription stays intact until the new one is
+    // verified; overwrite in place only when every page is taken
+    let Some(addr) = free_addr.or(existing_addr) else {
         // No empty page or matching channel was found, max subscriptions reached
         return Err(SubscriptionError::NoPageFound);
+    };
+
+    if let Err(e) = write_subscription_page(flash_manager, addr, subscription, pause) {
+        // Don't leave a half-written page that looks occupied
+        if Some(addr) == free_addr {
+            let _ = flash_manager.wipe_data(addr);
+        }
+        return Err(e.into());
+    }
+
+    // New copy verified, retire the old one
+    if let Some(old_addr) = existing_addr {
+        if old_addr != addr {
+            flash_manager.wipe_data(old_addr)?;
+        }
     }
+    Ok(())
 }
 
-pub fn read_channel(
+/// Erase the page at `addr` and write `subscription` to it, read back, with `pause`
+/// as its pause log.
+///
+/// The record's magic is written last, so until the whole record is in the page reads
+/// as free and decode never sees it half written.
+fn write_subscription_page(
     flash_manager: &mut FlashManager,
-    address: u32,
-) -> Result<ChannelInfo, FlashManagerError> {
-    match flash_manager.read_magic(address) {
-        Ok(_) => Ok(flash_manager.read_data::<ChannelSubscription>(address)?.info),
-        Err(e) => Err(FlashManagerError::FlashError(e)),
+    addr: u32,
+    subscription: &ChannelSubscription,
+    pause: PauseState,
+) -> Result<(), FlashManagerError> {
+    flash_manager.wipe_data(addr)?;
+    flash_manager.stage_data(addr, SUBSCRIPTION_MAGIC, subscription)?;
+    flash_manager.commit_data(addr, SUBSCRIPTION_MAGIC, subscription)?;
+    read_subscription(flash_manager, addr)?;
+    // A never paused channel has no log to carry over
+    if pause.sequence != 0 {
+        append_pause_record(flash_manager, addr, pause)?;
     }
+    Ok(())
 }
 
-pub fn decode_frame(
+/// Remove an expired subscription: wipe its page and drop its active channel entry.
+pub fn expire_subscription(
     flash_manager: &mut FlashManager,
-    frame: &ChannelFrame,
+    addr: u32,
+    channel_id: u32,
     active_channels: &mut ActiveChannelsList,
-) -> Result<[u8; 64], ()> {
-    // Verify frame signature
-    let verifying_key = VerifyingKey::from_public_key_der(HOST_KEY_PUB).map_err(|_| {})?;
+) -> Result<(), SubscriptionError> {
+    flash_manager.wipe_data(addr)?;
+
+    // Shift the remaining entries down so the list has no holes; activation in
+    // save_subscription stops at the first empty slot
+    if let Some(idx) = active_channels
+        .iter()
+        .position(|c| matches!(c, Some(c) if c.channel_id == channel_id))
+    {
+        active_channels.copy_within(idx + 1.., idx);
+        active_channels[ACTIVE_CHANNELS_LEN - 1] = None;
+    }
 
-    let message = &bytes_of(frame)[..core::mem::size_of::<ChannelFrame>() - 64];
-    let signature = &frame.signature;
-    
-    let sig_result = Signature::from_slice(signature);
+    Ok(())
+}
+
+/// Levels between the root (node 1) and a leaf (node 2^64 + timestamp).
+pub const TREE_DEPTH: usize = 64;
 
-    if let Err(_) = sig_result {
-        return Err(());
+/// Every subscribed frame key costs `TREE_DEPTH` derivations, wherever the stored
+/// password sits on its path (`constant-depth` feature); see `derive_frame_key`.
+pub const CONSTANT_DEPTH: bool = cfg!(feature = "constant-depth");
+
+/// Node number of the `branch` (1 left, 2 right) child of `node_num`. Checked, and
+/// capped at the leaf level, so a descent that ran on past the leaves is an error
+/// instead of a wrapped or out-of-tree node number.
+pub fn child_node(node_num: u128, branch: u8) -> Result<u128, SubscriptionError> {
+    if branch != 1 && branch != 2 {
+        return Err(SubscriptionError::InvalidPath);
     }
+    node_num
+        .checked_mul(2)
+        .and_then(|n| n.checked_add(branch as u128 - 1))
+        .filter(|n| n >> (TREE_DEPTH + 1) == 0)
+        .ok_or(SubscriptionError::InvalidPath)
+}
 
-    let sig = sig_result.unwrap();
-    
-    let result = verifying_key.verify(message, &sig);
-    
-    if result.is_err() {
-        // write_debug(&mut console, "Signature verification failed\n");
-        return Err(());
-    } else {
-        // write_debug(&mut console, "Signature verification succeeded!\n");
+/// First password stored on the path from the root to `timestamp`'s leaf, with its
+/// depth. At most `TREE_DEPTH + 1` nodes are looked up, root and leaf included; with
+/// `CONSTANT_DEPTH` all of them are, so the search stops at no particular depth.
+pub fn stored_ancestor(subscription: &ChannelSubscription, timestamp: u64) -> Result<(usize, ChannelPassword), SubscriptionError> {
+    let mut node_num: u128 = 1;
+    let mut found = None;
+    for depth in 0..=TREE_DEPTH {
+        let password = subscription.passwords.find(node_num);
+        if found.is_none() {
+            found = password.map(|password| (depth, password));
+        }
+        if found.is_some() && !CONSTANT_DEPTH {
+            break;
+        }
+        if depth < TREE_DEPTH {
+            node_num = child_node(node_num, tree_branch(timestamp, depth))?;
+        }
     }
+    found.ok_or(SubscriptionError::PasswordNotFound)
+}
 
-    // Signature verified; let's decrypt the frame
-    let subscription: &ChannelSubscription = match frame.channel {
-        0 => {
-            &CHANNEL_0_SUBSCRIPTION
+/// Branch taken at `depth` on the way to `timestamp`'s leaf: bit 63 of the timestamp
+/// first, bit 0 last.
+fn tree_branch(timestamp: u64, depth: usize) -> u8 {
+    ((timestamp >> (63 - depth)) & 1) as u8 + 1
+}
+
+/// Walks the subscription's key tree down to the leaf for `timestamp`: finds the
+/// first stored password on the path, then derives the remaining levels from it.
+///
+/// The number of derivations says how deep that password is, which a host timing
+/// Decode could read off. With `CONSTANT_DEPTH` the levels above it are derived too,
+/// into a discarded key, and the frame key cache is neither read nor filled, so every
+/// frame costs `TREE_DEPTH` derivations. That is the cost of a whole-tree subscription
+/// every time, where the cache would mostly save all but the last few levels.
+pub fn derive_frame_key(
+    subscription: &ChannelSubscription,
+    timestamp: u64,
+    frame_keys: &mut FrameKeyCache,
+) -> Result<[u8; 16], SubscriptionError> {
+    // The leaf is node 2^64 + timestamp, 65 bits wide for every timestamp from 0 to
+    // u64::MAX, so the path from the root is always exactly 64 branches. Indexing by
+    // depth keeps every write in bounds.
+    let mut path: [u8; TREE_DEPTH] = [0; TREE_DEPTH];
+    for (depth, branch) in path.iter_mut().enumerate() {
+        *branch = tree_branch(timestamp, depth);
+    }
+
+    let (mut i, password_node) = stored_ancestor(subscription, timestamp)?;
+    let mut password_bytes: [u8; 16] = password_node.password;
+    let mut node_num = password_node.node_num();
+
+    // Invariant for the descent: the starting node is the depth-`i` ancestor of the
+    // leaf, i == 0 for the root and i == 64 for the leaf itself, so exactly
+    // `path.len() - i` derivations remain. Anything else is refused rather than
+    // derived into a wrong key.
+    let leaf = (1u128 << 64) | timestamp as u128;
+    if i > path.len() || node_num != leaf >> (path.len() - i) {
+        return Err(SubscriptionError::InvalidPath);
+    }
+
+    // Only nodes below the stored password are taken from the cache, so it never
+    // widens what the subscription can decrypt
+    let channel_id = subscription.info.channel_id;
+    if !CONSTANT_DEPTH {
+        if let Some((depth, key)) = frame_keys.deepest_ancestor(channel_id, leaf, i) {
+            i = depth;
+            password_bytes = key;
+            node_num = leaf >> (64 - depth);
         }
-        _ => {
-            let sub_page_addr = match get_subscription_addr(flash_manager, frame.channel) {
-                Some(addr) => addr,
-                None => return Err(()),
-            };
+    }
 
-            &flash_manager.read_data::<ChannelSubscription>(sub_page_addr).map_err(|_| {})?
+    for (depth, branch) in path.iter().enumerate().skip(i) {
+        node_num = child_node(node_num, *branch)?;
+        password_bytes = derive_child_key(&password_bytes, *branch, node_num);
+        // Leaves are never shared between frames, only their ancestors
+        if depth + 1 < path.len() && !CONSTANT_DEPTH {
+            frame_keys.insert(channel_id, node_num, password_bytes);
         }
-    };
+    }
 
-    if !validate_channel_timestamp(frame, active_channels) {
-        return Err(());
+    // Make up the levels above the stored password
+    if CONSTANT_DEPTH {
+        let mut padding = password_bytes;
+        for (depth, branch) in path.iter().enumerate().take(i) {
+            padding = derive_child_key(&padding, *branch, leaf >> (path.len() - 1 - depth));
+        }
+        core::hint::black_box(padding);
     }
 
-    let mut node_num: u128 = (frame.timestamp as u128) + ((1 as u128) << 64);
+    if node_num != leaf {
+        return Err(SubscriptionError::InvalidPath);
+    }
+
+    Ok(password_bytes)
+}
+
+/// Number of derived nodes kept by `FrameKeyCache`.
+const FRAME_KEY_CACHE_LEN: usize = 16;
+
+#[derive(Clone, Copy)]
+struct CachedKey {
+    channel_id: u32,
+    node_num: u128,
+    key: [u8; 16],
+    last_used: u32,
+}
 
-    let mut path: [u8; 64] = [0; 64];
-    let mut path_idx = 64;
+/// Least recently used cache of derived node keys for subscribed channels.
+///
+/// Every derivation inserts the nodes it passes, so the cache ends up holding the
+/// deepest levels of recent paths. The next frame on the channel usually shares all
+/// but its last few levels and starts from the deepest cached ancestor instead of the
+/// stored password node.
+pub struct FrameKeyCache {
+    entries: [Option<CachedKey>; FRAME_KEY_CACHE_LEN],
+    tick: u32,
+}
 
-    while node_num > 1 {
-        let branch: u8 = (node_num % 2 + 1).try_into().unwrap();
-        path[path_idx-1] = branch;
-        path_idx -= 1;
-        node_num = node_num / 2;
+impl FrameKeyCache {
+    pub const fn new() -> Self {
+        FrameKeyCache { entries: [None; FRAME_KEY_CACHE_LEN], tick: 0 }
     }
 
-    let mut password_node: Option<ChannelPassword> = None;
+    /// Forget every cached key, e.g. after the stored subscriptions change.
+    pub fn clear(&mut self) {
+        self.entries = [None; FRAME_KEY_CACHE_LEN];
+    }
 
-    node_num = 1;
-    let mut i = 0;
-    while i < 65 {
-        // Look for corresponding node in subscription package
-        for sub_idx in 0..128 {
-            let c = &subscription.passwords.contents[sub_idx];
-            // Password is uninitialized, break
-            if c.node_ext == 0 {
-                break;
+    /// Deepest cached ancestor of `leaf` on `channel_id` below `min_depth`, with its depth.
+    fn deepest_ancestor(&mut self, channel_id: u32, leaf: u128, min_depth: usize) -> Option<(usize, [u8; 16])> {
+        self.tick = self.tick.wrapping_add(1);
+        let mut best: Option<&mut CachedKey> = None;
+        for entry in self.entries.iter_mut().flatten() {
+            let depth = (127 - entry.node_num.leading_zeros()) as usize;
+            if entry.channel_id != channel_id || depth <= min_depth || depth >= 64 {
+                continue;
             }
-            
-            let c_node_num: u128 = (c.node_trunc as u128)*2  + (c.node_ext - 1) as u128;
-            if c_node_num == node_num {
-                password_node = Some(*c);
-                break;
+            if leaf >> (64 - depth) != entry.node_num {
+                continue;
+            }
+            if best.as_ref().is_none_or(|b| b.node_num < entry.node_num) {
+                best = Some(entry);
             }
         }
+        let best = best?;
+        best.last_used = self.tick;
+        Some(((127 - best.node_num.leading_zeros()) as usize, best.key))
+    }
 
-        // Password found, or we have checked the last node
-        if password_node.is_some() || i == 64 {
-            break;
+    /// Key of `node_num` on `channel_id`, if cached.
+    fn get(&self, channel_id: u32, node_num: u128) -> Option<[u8; 16]> {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|e| e.channel_id == channel_id && e.node_num == node_num)
+            .map(|e| e.key)
+    }
+
+    fn insert(&mut self, channel_id: u32, node_num: u128, key: [u8; 16]) {
+        let entry = CachedKey { channel_id, node_num, key, last_used: self.tick };
+        let slot = self
+            .entries
+            .iter()
+            .position(|e| matches!(e, Some(e) if e.channel_id == channel_id && e.node_num == node_num))
+            .or_else(|| self.entries.iter().position(|e| e.is_none()))
+            .unwrap_or_else(|| {
+                // Evict the least recently used entry
+                (0..FRAME_KEY_CACHE_LEN)
+                    .min_by_key(|&idx| self.entries[idx].map_or(0, |e| e.last_used))
+                    .unwrap_or(0)
+            });
+        self.entries[slot] = Some(entry);
+    }
+}
+
+impl Default for FrameKeyCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Node of channel 0's only password: the root, unless build.rs was given a node below
+/// it, in which case only the timestamps under that node decode.
+const CHANNEL_0_NODE: u128 = CHANNEL_0_SUBSCRIPTION.passwords.contents[0].node_num();
+/// Depth of `CHANNEL_0_NODE`, 0 for the root.
+const CHANNEL_0_DEPTH: usize = (127 - CHANNEL_0_NODE.leading_zeros()) as usize;
+const _: () = assert!(CHANNEL_0_NODE != 0 && CHANNEL_0_DEPTH <= TREE_DEPTH);
+
+/// Whether `timestamp`'s leaf is below `CHANNEL_0_NODE`.
+fn channel_0_covers(timestamp: u64) -> bool {
+    let leaf = (1u128 << 64) | timestamp as u128;
+    leaf >> (TREE_DEPTH - CHANNEL_0_DEPTH) == CHANNEL_0_NODE
+}
+
+/// Keys along the tree path of the last decoded channel 0 frame.
+///
+/// Channel 0's only password is a single node, so every emergency frame derives every
+/// level below it. Consecutive frames share a long timestamp prefix, so the derivation
+/// is restarted from the deepest node shared with the previous frame instead.
+pub struct Channel0KeyCache {
+    /// Timestamp whose path is held in `keys`, if `filled`.
+    timestamp: u64,
+    filled: bool,
+    /// `keys[d]` is the key of the depth-`d` node on the cached path.
+    keys: [[u8; 16]; 65],
+}
+
+impl Channel0KeyCache {
+    pub const fn new() -> Self {
+        let mut keys = [[0; 16]; 65];
+        keys[CHANNEL_0_DEPTH] = CHANNEL_0_SUBSCRIPTION.passwords.contents[0].password;
+        Channel0KeyCache { timestamp: 0, filled: false, keys }
+    }
+
+    /// Leaf key for `timestamp`, identical to `derive_frame_key` on channel 0.
+    fn frame_key(&mut self, timestamp: u64) -> Result<[u8; 16], SubscriptionError> {
+        if !channel_0_covers(timestamp) {
+            return Err(SubscriptionError::PasswordNotFound);
+        }
+
+        // Depth of the deepest node shared with the cached path, never above the
+        // stored node
+        let shared = if self.filled { (timestamp ^ self.timestamp).leading_zeros() as usize } else { 0 };
+        let shared = shared.max(CHANNEL_0_DEPTH);
+
+        for depth in shared..64 {
+            let branch = ((timestamp >> (63 - depth)) & 1) as u8 + 1;
+            let node_num = (1u128 << (depth + 1)) | (timestamp >> (63 - depth)) as u128;
+            self.keys[depth + 1] = derive_child_key(&self.keys[depth], branch, node_num);
+        }
+
+        self.timestamp = timestamp;
+        self.filled = true;
+        Ok(self.keys[64])
+    }
+}
+
+impl Default for Channel0KeyCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parent of the last decoded leaf on a subscribed channel, the node it shares with
+/// the neighbouring timestamp.
+///
+/// A leaf is never decoded twice, so the deepest node the next frame can reuse is this
+/// one: a frame whose leaf is its other child takes one derivation from it, without
+/// searching the password table or the key cache.
+#[derive(Clone, Copy)]
+struct LastNode {
+    channel_id: u32,
+    /// Page of the subscription the key was derived from.
+    addr: u32,
+    node_num: u128,
+    key: [u8; 16],
+}
+
+impl LastNode {
+    /// Leaf key for `timestamp`, if its leaf is a child of this node under the
+    /// subscription at `addr`.
+    fn leaf_key(&self, channel_id: u32, addr: u32, timestamp: u64) -> Option<[u8; 16]> {
+        let leaf = (1u128 << 64) | timestamp as u128;
+        if self.channel_id != channel_id || self.addr != addr || leaf >> 1 != self.node_num {
+            return None;
         }
+        Some(derive_child_key(&self.key, (timestamp & 1) as u8 + 1, leaf))
+    }
+}
 
-        // Go to next child according to branch path
-        node_num = node_num * 2 + (path[i] - 1) as u128;
-        i += 1;
+/// State kept between `decode_frame` calls: the key caches and the subscription last
+/// read from flash, so a frame on the same channel as the previous one neither
+/// re-reads nor re-checks the ~3.2 KB record and no per-frame copy is made.
+pub struct DecodeContext {
+    channel_0_keys: Channel0KeyCache,
+    frame_keys: FrameKeyCache,
+    subscription: ChannelSubscription,
+    /// Page `subscription` was read from, `None` if it holds nothing valid.
+    loaded_addr: Option<u32>,
+    last_node: Option<LastNode>,
+    /// Frames whose key came from `last_node`.
+    last_node_hits: u32,
+    /// Refuse every channel but 0, as the emergency-only flag in flash says.
+    emergency_only: bool,
+}
+
+impl DecodeContext {
+    pub fn new() -> Self {
+        DecodeContext {
+            channel_0_keys: Channel0KeyCache::new(),
+            frame_keys: FrameKeyCache::new(),
+            subscription: ChannelSubscription::zeroed(),
+            loaded_addr: None,
+            last_node: None,
+            last_node_hits: 0,
+            emergency_only: false,
+        }
     }
 
-    if password_node.is_none() {
-        return Err(());
+    /// Number of frames decoded with one derivation from the last decoded leaf's
+    /// parent, rather than from the subscription.
+    pub fn last_node_hits(&self) -> u32 {
+        self.last_node_hits
     }
 
-    let mut password_bytes: [u8; 16] = password_node.ok_or(())?.password;
+    /// Follow the emergency-only flag, at boot from flash and after each change.
+    pub fn set_emergency_only(&mut self, emergency_only: bool) {
+        self.emergency_only = emergency_only;
+    }
 
-    for branch in path[i..].iter() {
-        let mut hasher = Md5::new();
+    /// Drop everything derived from stored subscriptions, after they changed.
+    pub fn invalidate(&mut self) {
+        self.frame_keys.clear();
+        self.loaded_addr = None;
+        self.last_node = None;
+    }
+}
 
-        let mut pass_in: [u8; 17] = [0; 17];
-        pass_in[..16].copy_from_slice(&password_bytes);
+impl Default for DecodeContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        match branch {
-            1 => {
-                pass_in[16] = b'L';
+/// Verify, check and decrypt `frame`, taken by value as `ChannelFrame::from_le_bytes`
+/// parsed it, so nothing below refers back to the body buffer it came from.
+pub fn decode_frame(
+    flash_manager: &mut FlashManager,
+    frame: ChannelFrame,
+    active_channels: &mut ActiveChannelsList,
+    context: &mut DecodeContext,
+    #[cfg(feature = "rtc-time")] clock: &impl TimeSource,
+) -> Result<[u8; FRAME_CONTENT_LEN], SubscriptionError> {
+    // The mode is public too; channel 0 decodes whatever it says
+    if context.emergency_only && frame.channel != 0 {
+        return Err(SubscriptionError::EmergencyOnly);
+    }
+
+    // Which channels are stored is public (List reports them), so a frame for a channel
+    // with no subscription, e.g. on a freshly flashed decoder, is turned away before
+    // the signature check, as a subscription for another decoder is. Nothing secret
+    // decides this, and it spares the costliest step of a frame that cannot decode.
+    let sub_page_addr = match frame.channel {
+        0 => None,
+        channel => Some(get_subscription_addr(flash_manager, channel).ok_or(SubscriptionError::NoSubscription)?),
+    };
+
+    // Verify frame signature
+    // The signature field is a fixed-size array, so it is always exactly one Ed25519
+    // signature; no slice length is involved
+    let message = &bytes_of(&frame)[..FRAME_SIGNED_LEN];
+    let signature = Signature::from_bytes(&frame.signature);
+
+    // As for subscriptions, the subscription is loaded whatever the signature
+    // outcome; the result gates everything that mutates state or derives keys.
+    let sig_valid = verify_host_signature(message, &signature)?;
+
+    let subscription: &ChannelSubscription = match sub_page_addr {
+        None => {
+            &CHANNEL_0_SUBSCRIPTION
+        }
+        Some(addr) => {
+            // Consecutive frames on one channel reuse the copy already in RAM
+            if context.loaded_addr != Some(addr) {
+                context.loaded_addr = None;
+                match read_subscription_into(flash_manager, addr, &mut context.subscription) {
+                    Ok(()) => {}
+                    // The page is no longer committed, e.g. it was replaced after the
+                    // lookup above; until it is, the channel has no subscription
+                    Err(FlashManagerError::MagicMismatch) => return Err(SubscriptionError::NoSubscription),
+                    // As at boot, only a CRC mismatch proves the page corrupt: it is
+                    // erased and dropped rather than failing every later frame, and the
+                    // host can subscribe the channel again. Only a signed frame may
+                    // erase it, or a forged one could wipe any subscription
+                    Err(FlashManagerError::CrcMismatch) => {
+                        if !sig_valid {
+                            return Err(SubscriptionError::InvalidSignature);
+                        }
+                        context.last_node = None;
+                        expire_subscription(flash_manager, addr, frame.channel, active_channels)?;
+                        return Err(SubscriptionError::SubscriptionCorrupt);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+                context.loaded_addr = Some(addr);
             }
-            2 => {
-                pass_in[16] = b'R';
+            &context.subscription
+        }
+    };
+
+    if !sig_valid {
+        return Err(SubscriptionError::InvalidSignature);
+    }
+
+    // Channel 0 never expires; other channels end with their window by wall clock too.
+    // The frame timestamp is held against the window along with the replay check.
+    #[cfg(feature = "rtc-time")]
+    let clock_expired = sub_page_addr.is_some() && clock.now().is_some_and(|now| now > subscription.info.end_timestamp);
+    #[cfg(not(feature = "rtc-time"))]
+    let clock_expired = false;
+
+    // A channel 0 password below the root decodes its own subtree only, refused here
+    // before the replay counter moves
+    if sub_page_addr.is_none() && !channel_0_covers(frame.timestamp) {
+        return Err(SubscriptionError::PasswordNotFound);
+    }
+
+    // A paused channel keeps its subscription but decodes nothing, and its replay
+    // counter stays where it was
+    if active_channels.iter().flatten().any(|c| c.channel_id == frame.channel && c.paused) {
+        return Err(SubscriptionError::ChannelPaused);
+    }
+
+    let check = if clock_expired {
+        TimestampCheck::Expired
+    } else {
+        validate_channel_timestamp(&frame, &subscription.info, active_channels)
+    };
+    match check {
+        TimestampCheck::Accepted => {}
+        TimestampCheck::Replayed => return Err(SubscriptionError::InvalidTimestamp),
+        TimestampCheck::Inactive => return Err(SubscriptionError::ChannelInactive),
+        // No password in the subscription covers it
+        TimestampCheck::NotYetValid => return Err(SubscriptionError::PasswordNotFound),
+        TimestampCheck::Expired => {
+            if PRUNE_EXPIRED_SUBSCRIPTIONS {
+                if let Some(addr) = sub_page_addr {
+                    context.loaded_addr = None;
+                    context.last_node = None;
+                    expire_subscription(flash_manager, addr, frame.channel, active_channels)?;
+                }
+            }
+            return Err(SubscriptionError::SubscriptionExpired);
+        }
+    }
+
+    let password_bytes = match sub_page_addr {
+        None => context.channel_0_keys.frame_key(frame.timestamp)?,
+        Some(addr) => {
+            // Reusing the last frame's parent would skip the padded derivation
+            let cached = context
+                .last_node
+                .filter(|_| !CONSTANT_DEPTH)
+                .and_then(|last| last.leaf_key(frame.channel, addr, frame.timestamp));
+            match cached {
+                Some(key) => {
+                    context.last_node_hits = context.last_node_hits.wrapping_add(1);
+                    key
+                }
+                None => {
+                    let key = derive_frame_key(subscription, frame.timestamp, &mut context.frame_keys)?;
+                    // The parent was cached on the way down, unless the subscription
+                    // stores the leaf itself
+                    let parent = ((1u128 << 64) | frame.timestamp as u128) >> 1;
+                    context.last_node = context.frame_keys.get(frame.channel, parent).map(|key| LastNode {
+                        channel_id: frame.channel,
+                        addr,
+                        node_num: parent,
+                        key,
+                    });
+                    key
+                }
             }
-            _ => return Err(())
         }
+    };
+
+    let extended_password = extend_key(&password_bytes);
+
+    // Content and marker are one ciphertext, the marker continuing the keystream
+    let mut plaintext = [0u8; FRAME_CONTENT_LEN + FRAME_MARKER_LEN];
+    plaintext[..FRAME_CONTENT_LEN].copy_from_slice(&frame.encrypted_content);
+    plaintext[FRAME_CONTENT_LEN..].copy_from_slice(&frame.encrypted_marker);
+    decrypt_in_place(&extended_password, &Nonce(frame.nonce), &mut plaintext);
 
-        hasher.update(&pass_in);
-        password_bytes = hasher.finalize().into();
+    let (content, marker) = plaintext.split_at(FRAME_CONTENT_LEN);
+    if marker != { frame.channel }.to_le_bytes() {
+        return Err(SubscriptionError::InconsistentFrame);
     }
 
-    // Extend password to 32 bytes
-    let mut extended_password: [u8; 32] = [0; 32];
-    extended_password[..16].copy_from_slice(&password_bytes);
-    let mut hasher = Md5::new();
-    hasher.update(&password_bytes);
-    extended_password[16..].copy_from_slice(&hasher.finalize());
+    if let Some(channel) = active_channels.iter_mut().flatten().find(|c| c.channel_id == frame.channel) {
+        channel.decode_count = channel.decode_count.saturating_add(1);
+    }
+
+    let mut decrypted_frame = [0u8; FRAME_CONTENT_LEN];
+    decrypted_frame.copy_from_slice(content);
+    Ok(decrypted_frame)
+}
+
+/// Decode `frame` as `decode_frame` does, replay counter included, but return the
+/// SHA-256 of its content instead of the content, so the host can check a stored
+/// subscription decodes without the plaintext leaving the decoder.
+pub fn verify_probe_frame(
+    flash_manager: &mut FlashManager,
+    frame: ChannelFrame,
+    active_channels: &mut ActiveChannelsList,
+    context: &mut DecodeContext,
+    #[cfg(feature = "rtc-time")] clock: &impl TimeSource,
+) -> Result<[u8; 32], SubscriptionError> {
+    let content = decode_frame(
+        flash_manager,
+        frame,
+        active_channels,
+        context,
+        #[cfg(feature = "rtc-time")]
+        clock,
+    )?;
+    Ok(Sha256::digest(content).into())
+}
 
-    // Decrypt frame
-    let mut cipher = ChaCha20::new(&extended_password.into(), &frame.nonce.into());
+/// Length of the host key fingerprint: SHA-256 truncated to 128 bits.
+pub const HOST_KEY_FINGERPRINT_LEN: usize = 16;
 
-    let mut decrypted_frame: [u8; 64] = [0; 64];
-    decrypted_frame.copy_from_slice(&frame.encrypted_content[0..64]);
+/// Fingerprint of every key the decoder accepts signatures from, `HOST_KEYS_PUB`, so
+/// the host can tell a decoder provisioned for another signer, or with other fallback
+/// keys, from one whose signatures fail for some other reason. The keys are public, so
+/// this reveals nothing.
+pub fn host_key_fingerprint() -> [u8; HOST_KEY_FINGERPRINT_LEN] {
+    host_keys_fingerprint(&HOST_KEYS_PUB)
+}
 
-    cipher.apply_keystream(&mut decrypted_frame);
+/// Leading bytes of the SHA-256 over `keys` in order, each DER prefixed by its length
+/// (u32 LE) so no two key lists hash the same bytes.
+pub fn host_keys_fingerprint(keys: &[&[u8]]) -> [u8; HOST_KEY_FINGERPRINT_LEN] {
+    let mut hasher = Sha256::new();
+    for der in keys {
+        hasher.update((der.len() as u32).to_le_bytes());
+        hasher.update(der);
+    }
+    let mut fingerprint = [0u8; HOST_KEY_FINGERPRINT_LEN];
+    fingerprint.copy_from_slice(&hasher.finalize()[..HOST_KEY_FINGERPRINT_LEN]);
+    fingerprint
+}
 
-    return Ok(decrypted_frame)
+/// Decrypts `buf` in place with ChaCha20 under `key` and `nonce`. Both the frame and
+/// the subscription paths pass a slice sized from their validated lengths, so no
+/// length reaches the cipher unchecked.
+fn decrypt_in_place(key: &[u8; KEY_LEN], nonce: &Nonce, buf: &mut [u8]) {
+    ChaCha20::new(key.into(), (&nonce.0).into()).apply_keystream(buf);
 }
\ No newline at end of file
@@ -0,0 +1,130 @@
+//! Wall-clock time from the MAX78000 RTC (`rtc-time` feature).
+//!
+//! The RTC counts seconds from when the host last seeded it with a signed SetTime
+//! command. Until then the clock is unset and only frame timestamps are enforced.
+use crate::hal::gcr::GcrRegisters;
+use crate::modules::host_keys::verify_host_signature;
+use crate::modules::time_source::TimeSource;
+use crate::modules::wire::read_u64_le;
+use crate::pac;
+use crate::DECODER_ID;
+use core::fmt;
+use ed25519_dalek::Signature;
+
+/// Frame timestamp units per RTC second.
+pub const TIMESTAMP_TICKS_PER_SEC: u64 = 1_000_000;
+
+/// Domain label prefixed to the signed set-time message.
+const SET_TIME_LABEL: &[u8] = b"ectf25-time";
+/// SetTime body: timestamp (u64 LE) || signature (64 bytes).
+pub const SET_TIME_BODY_LEN: usize = 8 + 64;
+/// Signed message: label || decoder id (u32 LE) || timestamp (u64 LE).
+const SET_TIME_MSG_LEN: usize = SET_TIME_LABEL.len() + 4 + 8;
+
+#[derive(Debug)]
+pub enum ClockError {
+    InvalidKey,
+    InvalidSignature,
+    /// The requested time is earlier than the current clock.
+    TimeRollback,
+}
+
+impl fmt::Display for ClockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockError::InvalidKey => f.write_str("invalid host key"),
+            ClockError::InvalidSignature => f.write_str("invalid signature"),
+            ClockError::TimeRollback => f.write_str("time would move backwards"),
+        }
+    }
+}
+
+impl core::error::Error for ClockError {}
+
+pub struct WallClock {
+    rtc: pac::Rtc,
+    /// Timestamp corresponding to RTC second 0, `None` until seeded.
+    base: Option<u64>,
+}
+
+impl WallClock {
+    /// Start the 32 kHz oscillator and the RTC counter.
+    pub fn new(rtc: pac::Rtc, reg: &mut GcrRegisters) -> Self {
+        reg.gcr.clkctrl().modify(|_, w| w.ertco_en().set_bit());
+        while reg.gcr.clkctrl().read().ertco_rdy().bit_is_clear() {}
+
+        let mut clock = WallClock { rtc, base: None };
+        clock.write_seconds(0);
+        clock
+    }
+
+    fn wait_idle(&self) {
+        while self.rtc.ctrl().read().busy().bit_is_set() {}
+    }
+
+    fn write_seconds(&mut self, sec: u32) {
+        self.wait_idle();
+        self.rtc.ctrl().modify(|_, w| w.wr_en().set_bit());
+        self.wait_idle();
+        self.rtc.ctrl().modify(|_, w| w.en().clear_bit());
+        self.wait_idle();
+        unsafe {
+            self.rtc.sec().write(|w| w.sec().bits(sec));
+        }
+        self.wait_idle();
+        self.rtc.ctrl().modify(|_, w| w.en().set_bit());
+        self.wait_idle();
+        self.rtc.ctrl().modify(|_, w| w.wr_en().clear_bit());
+    }
+
+    fn read_seconds(&self) -> u32 {
+        // SEC is only stable while RDY is set
+        while self.rtc.ctrl().read().rdy().bit_is_clear() {}
+        self.rtc.sec().read().sec().bits()
+    }
+
+    /// Current time in frame timestamp units, or `None` if the clock was never set.
+    pub fn now(&self) -> Option<u64> {
+        let base = self.base?;
+        Some(base.saturating_add(self.read_seconds() as u64 * TIMESTAMP_TICKS_PER_SEC))
+    }
+
+    /// Seed the clock from a SetTime body signed by the host key.
+    pub fn set_time_signed(&mut self, body: &[u8]) -> Result<(), ClockError> {
+        let timestamp = verify_set_time(body, self.now())?;
+        self.write_seconds(0);
+        self.base = Some(timestamp);
+        Ok(())
+    }
+}
+
+/// Check a SetTime body against the host key and the clock's current time `now`, and
+/// return the time it sets.
+pub fn verify_set_time(body: &[u8], now: Option<u64>) -> Result<u64, ClockError> {
+    let timestamp = read_u64_le(body, 0);
+    let sig = Signature::from_slice(&body[8..SET_TIME_BODY_LEN]).map_err(|_| ClockError::InvalidSignature)?;
+
+    let mut message = [0u8; SET_TIME_MSG_LEN];
+    message[..SET_TIME_LABEL.len()].copy_from_slice(SET_TIME_LABEL);
+    message[SET_TIME_LABEL.len()..SET_TIME_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+    message[SET_TIME_LABEL.len() + 4..].copy_from_slice(&timestamp.to_le_bytes());
+
+    if !verify_host_signature(&message, &sig).map_err(|_| ClockError::InvalidKey)? {
+        return Err(ClockError::InvalidSignature);
+    }
+
+    // Refuse to move the clock back, so an old SetTime cannot be replayed to revive
+    // an expired subscription
+    if let Some(now) = now {
+        if timestamp < now {
+            return Err(ClockError::TimeRollback);
+        }
+    }
+    Ok(timestamp)
+}
+
+impl TimeSource for WallClock {
+    fn now(&self) -> Option<u64> {
+        WallClock::now(self)
+    }
+}
@@ -1,3 +1,115 @@
+use crate::{
+    FIRMWARE_FLASH_END, FIRMWARE_FLASH_START, MAX_CHANNELS, PRIMARY_CHANNELS, RESERVED_FLASH_END, RESERVED_FLASH_START,
+    SECONDARY_CHANNELS, SECONDARY_FLASH_END, SECONDARY_FLASH_START,
+};
+use bytemuck::{Pod, Zeroable};
+
 pub const PAGE_SIZE: u32 = 0x2000;
-pub const MAX_SUBS: usize = 8;
-pub const BASE_ADDRESS: u32 = 0x10062000;
\ No newline at end of file
+pub const BASE_ADDRESS: u32 = 0x10062000;
+
+/// Bounds of the `RESERVED` flash region in memory.x, which holds all persistent data.
+pub const RESERVED_START: u32 = RESERVED_FLASH_START;
+pub const RESERVED_END: u32 = RESERVED_FLASH_END;
+
+/// Magic marking an occupied subscription page. It is written after the rest of the
+/// record, so a page whose write was cut short still reads as free.
+pub const SUBSCRIPTION_MAGIC: u32 = 0xABCD;
+/// Magic of a pause record in a subscription page's log, after the subscription.
+pub const PAUSE_MAGIC: u32 = 0x9A05_E7C3;
+/// Magic of a window record in a subscription page's log, moving the window's end.
+pub const WINDOW_MAGIC: u32 = 0x3E4D_71A9;
+/// Magic value of an erased flash word.
+pub const ERASED_MAGIC: u32 = 0xFFFF_FFFF;
+
+/// The first `PRIMARY_CHANNELS` subscription pages (from build.rs) start at
+/// BASE_ADDRESS. Two pages after them hold the channel state log.
+pub const STATE_BASE_ADDRESS: u32 = BASE_ADDRESS + PRIMARY_CHANNELS as u32 * PAGE_SIZE;
+pub const STATE_PAGES: u32 = 2;
+
+/// Page holding the tamper lock record, directly after the state log.
+pub const TAMPER_ADDRESS: u32 = STATE_BASE_ADDRESS + STATE_PAGES * PAGE_SIZE;
+
+/// Page holding the rotated device key (`rekey` feature), directly after the tamper page.
+#[cfg(feature = "rekey")]
+pub const KEY_ADDRESS: u32 = TAMPER_ADDRESS + PAGE_SIZE;
+
+/// Page holding the emergency-only flag, after the tamper page and the key page if any.
+#[cfg(not(feature = "rekey"))]
+pub const EMERGENCY_ADDRESS: u32 = TAMPER_ADDRESS + PAGE_SIZE;
+#[cfg(feature = "rekey")]
+pub const EMERGENCY_ADDRESS: u32 = KEY_ADDRESS + PAGE_SIZE;
+
+/// Scratch page, after the emergency-only page, for transient data: a resumable
+/// Subscribe upload, or the journal of a subscription bundle being applied. It is never
+/// a subscription page, so it can be erased and written freely; whatever it holds may
+/// be wiped by its next user, so a bundle discards an unfinished upload.
+pub const SCRATCH_ADDRESS: u32 = EMERGENCY_ADDRESS + PAGE_SIZE;
+
+/// The other `SECONDARY_CHANNELS` subscription pages fill memory.x's SUBSCRIPTIONS2
+/// region, if it has one.
+pub const SECONDARY_BASE_ADDRESS: u32 = SECONDARY_FLASH_START;
+
+/// Address of subscription page `page`, out of `MAX_CHANNELS`: the RESERVED pages
+/// first, then the second region.
+pub const fn subscription_page_addr(page: usize) -> u32 {
+    if page < PRIMARY_CHANNELS {
+        BASE_ADDRESS + page as u32 * PAGE_SIZE
+    } else {
+        SECONDARY_BASE_ADDRESS + (page - PRIMARY_CHANNELS) as u32 * PAGE_SIZE
+    }
+}
+
+/// End of the last page used for persistent data.
+pub const FLASH_DATA_END: u32 = SCRATCH_ADDRESS + PAGE_SIZE;
+
+// Every flash page used by the decoder must be page aligned and inside RESERVED.
+const _: () = assert!(BASE_ADDRESS.is_multiple_of(PAGE_SIZE));
+const _: () = assert!(BASE_ADDRESS >= RESERVED_START);
+const _: () = assert!(FLASH_DATA_END <= RESERVED_END);
+// Erasing a data page must never touch the firmware image in memory.x's FLASH region.
+const _: () = assert!(
+    FLASH_DATA_END <= FIRMWARE_FLASH_START || BASE_ADDRESS >= FIRMWARE_FLASH_END,
+    "persistent data pages overlap the firmware image"
+);
+// So must the second subscription region, which build.rs sizes to whole pages.
+const _: () = assert!(SECONDARY_BASE_ADDRESS.is_multiple_of(PAGE_SIZE));
+const _: () = assert!(SECONDARY_BASE_ADDRESS + SECONDARY_CHANNELS as u32 * PAGE_SIZE <= SECONDARY_FLASH_END);
+const _: () = assert!(
+    SECONDARY_CHANNELS == 0 || SECONDARY_BASE_ADDRESS >= FIRMWARE_FLASH_END || SECONDARY_FLASH_END <= FIRMWARE_FLASH_START,
+    "the second subscription region overlaps the firmware image"
+);
+// The scratch page is erased at will, so it must be a RESERVED page that is neither
+// firmware nor any subscription page, in either region.
+const _: () = assert!(SCRATCH_ADDRESS.is_multiple_of(PAGE_SIZE));
+const _: () = assert!(SCRATCH_ADDRESS >= RESERVED_START && SCRATCH_ADDRESS + PAGE_SIZE <= RESERVED_END);
+const _: () = assert!(
+    SCRATCH_ADDRESS + PAGE_SIZE <= FIRMWARE_FLASH_START || SCRATCH_ADDRESS >= FIRMWARE_FLASH_END,
+    "the scratch page overlaps the firmware image"
+);
+const _: () = {
+    let mut page = 0;
+    while page < MAX_CHANNELS {
+        assert!(subscription_page_addr(page) != SCRATCH_ADDRESS, "the scratch page is a subscription page");
+        page += 1;
+    }
+};
+
+/// Flash parameters reported by the FlashLayout command, as little-endian u32 values.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FlashLayout {
+    pub base_address: u32,
+    pub page_size: u32,
+    pub max_channels: u32,
+    /// Subscription pages from `base_address` on; the rest start at `secondary_base_address`.
+    pub primary_channels: u32,
+    pub secondary_base_address: u32,
+}
+
+pub const FLASH_LAYOUT: FlashLayout = FlashLayout {
+    base_address: BASE_ADDRESS,
+    page_size: PAGE_SIZE,
+    max_channels: MAX_CHANNELS as u32,
+    primary_channels: PRIMARY_CHANNELS as u32,
+    secondary_base_address: SECONDARY_BASE_ADDRESS,
+};
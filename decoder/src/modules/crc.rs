@@ -0,0 +1,87 @@
+//! CRC-32 (IEEE 802.3, reflected) used to check the integrity of flash records, and
+//! the CRC-16 trailing Subscribe bodies under the `subscribe-checksum` feature.
+//!
+//! With the `hw-crc` feature the MAX78000 CRC peripheral computes the checksum;
+//! otherwise a bitwise software implementation is used. Both produce the same value.
+#[cfg(feature = "hw-crc")]
+use crate::hal::gcr::{ClockForPeripheral, GcrRegisters};
+#[cfg(feature = "hw-crc")]
+use crate::pac;
+
+/// Reflected CRC-32 polynomial.
+const CRC32_POLY: u32 = 0xEDB8_8320;
+/// CRC-16/CCITT-FALSE polynomial (unreflected, initial value 0xFFFF).
+const CRC16_POLY: u16 = 0x1021;
+
+/// Compute the CRC-16/CCITT-FALSE of `data`, in software. Light enough to check a
+/// Subscribe body on its way in, before it is verified.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            let mask = (crc >> 15).wrapping_neg();
+            crc = (crc << 1) ^ (CRC16_POLY & mask);
+        }
+    }
+    crc
+}
+
+pub struct Crc32 {
+    #[cfg(feature = "hw-crc")]
+    crc: pac::Crc,
+}
+
+#[cfg(feature = "hw-crc")]
+impl Crc32 {
+    /// Enable the CRC peripheral and configure it for reflected CRC-32.
+    pub fn new(crc: pac::Crc, reg: &mut GcrRegisters) -> Self {
+        unsafe {
+            crc.enable_clock(&mut reg.gcr);
+            crc.poly().write(|w| w.poly().bits(CRC32_POLY));
+        }
+        crc.ctrl().write(|w| w.en().set_bit().msb().clear_bit());
+        Crc32 { crc }
+    }
+
+    /// Compute the CRC-32 of `data`.
+    pub fn checksum(&mut self, data: &[u8]) -> u32 {
+        unsafe {
+            self.crc.val().write(|w| w.value().bits(0xFFFF_FFFF));
+        }
+        for &b in data {
+            unsafe {
+                self.crc.datain8().write(|w| w.data().bits(b));
+            }
+            while self.crc.ctrl().read().busy().bit_is_set() {}
+        }
+        !self.crc.val().read().value().bits()
+    }
+}
+
+#[cfg(not(feature = "hw-crc"))]
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32 {}
+    }
+
+    /// Compute the CRC-32 of `data`.
+    pub fn checksum(&mut self, data: &[u8]) -> u32 {
+        let mut crc: u32 = 0xFFFF_FFFF;
+        for &b in data {
+            crc ^= b as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (CRC32_POLY & mask);
+            }
+        }
+        !crc
+    }
+}
+
+#[cfg(not(feature = "hw-crc"))]
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,59 @@
+//! DMA reception from the host UART (`dma-uart` feature).
+//!
+//! The UART0 RX FIFO is drained by DMA channel 0 straight into the body buffer while
+//! the CPU waits, instead of one `read_byte` call per byte.
+use crate::hal::gcr::{ClockForPeripheral, GcrRegisters};
+use crate::pac;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Bodies shorter than this are read byte by byte; DMA setup is not worth it.
+pub const DMA_MIN_BODY_LEN: usize = 256;
+
+pub struct DmaRx {
+    dma: pac::Dma,
+}
+
+impl DmaRx {
+    /// Enable the DMA clock and let UART0 raise an RX DMA request per received byte.
+    pub fn new(dma: pac::Dma, reg: &mut GcrRegisters) -> Self {
+        unsafe {
+            dma.enable_clock(&mut reg.gcr);
+            // The UART itself is owned by the HAL, which does not touch its DMA register
+            let uart = &*pac::Uart0::ptr();
+            uart.dma().modify(|_, w| w.rx_thd_val().bits(1).rx_en().set_bit());
+        }
+        DmaRx { dma }
+    }
+
+    /// Receive exactly `buf.len()` bytes from UART0 into `buf`, blocking until done.
+    pub fn read(&mut self, buf: &mut [u8]) {
+        if buf.is_empty() {
+            return;
+        }
+        let ch = self.dma.ch(0);
+        let fifo = unsafe { (*pac::Uart0::ptr()).fifo().as_ptr() } as u32;
+
+        ch.status().write(|w| w.ctz_if().clear_bit_by_one());
+        unsafe {
+            ch.src().write(|w| w.addr().bits(fifo));
+            ch.dst().write(|w| w.addr().bits(buf.as_mut_ptr() as u32));
+            ch.cnt().write(|w| w.cnt().bits(buf.len() as u32));
+        }
+        compiler_fence(Ordering::SeqCst);
+        ch.ctrl().write(|w| unsafe {
+            w.request().uart0rx()
+                .srcwd().byte()
+                .srcinc().dis()
+                .dstwd().byte()
+                .dstinc().en()
+                .burst_size().bits(0)
+                .en().en()
+        });
+
+        // The channel raises CTZ once the count reaches zero
+        while ch.status().read().ctz_if().bit_is_clear() {}
+        ch.status().write(|w| w.ctz_if().clear_bit_by_one());
+        ch.ctrl().modify(|_, w| w.en().dis());
+        compiler_fence(Ordering::SeqCst);
+    }
+}
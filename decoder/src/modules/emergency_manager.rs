@@ -0,0 +1,111 @@
+//! Emergency-only mode: a persisted, host-signed flag under which the decoder refuses
+//! every channel but the emergency channel 0.
+//!
+//! Unlike the tamper lock, which refuses Decode altogether, channel 0 keeps decoding,
+//! and the stored subscriptions are kept for when the mode is cleared.
+use crate::modules::constants::{EMERGENCY_ADDRESS, ERASED_MAGIC};
+use crate::modules::flash_manager::{FlashManager, FlashManagerError};
+use crate::modules::host_keys::verify_host_signature;
+use crate::DECODER_ID;
+use bytemuck::{Pod, Zeroable};
+use core::fmt;
+use ed25519_dalek::Signature;
+
+/// Magic marking a written emergency-only record.
+const EMERGENCY_MAGIC: u32 = 0x3E3E_C0A1;
+
+/// Domain label prefixed to the signed emergency-only message.
+const EMERGENCY_LABEL: &[u8] = b"ectf25-emergency";
+/// Emergency body: enabled (u8) || signature (64 bytes).
+pub const EMERGENCY_BODY_LEN: usize = 1 + 64;
+/// Signed message: label || decoder id (u32 LE) || epoch (u32 LE) || enabled (u8).
+const EMERGENCY_MSG_LEN: usize = EMERGENCY_LABEL.len() + 4 + 4 + 1;
+
+#[derive(Debug)]
+pub enum EmergencyError {
+    InvalidKey,
+    InvalidSignature,
+    FlashManagerError(FlashManagerError),
+}
+
+impl fmt::Display for EmergencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmergencyError::InvalidKey => f.write_str("invalid host key"),
+            EmergencyError::InvalidSignature => f.write_str("invalid signature"),
+            EmergencyError::FlashManagerError(e) => write!(f, "flash: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for EmergencyError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            EmergencyError::FlashManagerError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FlashManagerError> for EmergencyError {
+    fn from(e: FlashManagerError) -> Self {
+        EmergencyError::FlashManagerError(e)
+    }
+}
+
+/// Emergency-only flag as stored in flash.
+///
+/// `epoch` is bumped by every change and is part of the signed message, so a recorded
+/// command cannot be replayed to undo a later one.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct EmergencyState {
+    pub enabled: u32, // 0 = all channels, anything else = channel 0 only
+    pub epoch: u32,
+}
+
+impl EmergencyState {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != 0
+    }
+}
+
+/// Read the emergency-only record. A never-written page leaves every channel on; a
+/// record that is present but unreadable or corrupt is treated as emergency-only.
+pub fn read_emergency_state(flash_manager: &mut FlashManager) -> EmergencyState {
+    match flash_manager.read_magic(EMERGENCY_ADDRESS) {
+        Ok(ERASED_MAGIC) => EmergencyState { enabled: 0, epoch: 0 },
+        Ok(EMERGENCY_MAGIC) => flash_manager
+            .read_data_verified::<EmergencyState>(EMERGENCY_ADDRESS)
+            .unwrap_or(EmergencyState { enabled: 1, epoch: 0 }),
+        _ => EmergencyState { enabled: 1, epoch: 0 },
+    }
+}
+
+/// Turn emergency-only mode on or off as `body` says, if its signature is the host
+/// key's over the message for this decoder and the current epoch. Returns the new
+/// state, whose epoch the next command has to be signed for.
+pub fn set_emergency_only(flash_manager: &mut FlashManager, body: &[u8]) -> Result<EmergencyState, EmergencyError> {
+    if body.len() != EMERGENCY_BODY_LEN {
+        return Err(EmergencyError::InvalidSignature);
+    }
+    let enabled = body[0];
+    let sig = Signature::from_slice(&body[1..]).map_err(|_| EmergencyError::InvalidSignature)?;
+
+    let state = read_emergency_state(flash_manager);
+
+    let mut message = [0u8; EMERGENCY_MSG_LEN];
+    message[..EMERGENCY_LABEL.len()].copy_from_slice(EMERGENCY_LABEL);
+    message[EMERGENCY_LABEL.len()..EMERGENCY_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+    message[EMERGENCY_LABEL.len() + 4..EMERGENCY_MSG_LEN - 1].copy_from_slice(&state.epoch.to_le_bytes());
+    message[EMERGENCY_MSG_LEN - 1] = enabled;
+
+    if !verify_host_signature(&message, &sig).map_err(|_| EmergencyError::InvalidKey)? {
+        return Err(EmergencyError::InvalidSignature);
+    }
+
+    let next = EmergencyState { enabled: (enabled != 0) as u32, epoch: state.epoch.wrapping_add(1) };
+    flash_manager.wipe_data(EMERGENCY_ADDRESS)?;
+    flash_manager.write_data(EMERGENCY_ADDRESS, EMERGENCY_MAGIC, &next)?;
+    Ok(next)
+}
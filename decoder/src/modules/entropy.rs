@@ -0,0 +1,95 @@
+//! Randomness for anything the decoder generates itself, e.g. its own nonces.
+//!
+//! Every such value must come from the MAX78000 TRNG, and only once the TRNG has
+//! passed a self-test: a stuck or disconnected source reads back as a constant, which
+//! would make every generated nonce the same.
+use core::fmt;
+use rand::RngCore;
+
+/// 32-bit words drawn by the self-test.
+const SELF_TEST_WORDS: usize = 16;
+/// Fewest set bits accepted in the self-test sample, a quarter of it; a working TRNG
+/// falls below this with negligible probability.
+const SELF_TEST_MIN_ONES: u32 = (SELF_TEST_WORDS * 32 / 4) as u32;
+/// Most set bits accepted, three quarters of the sample.
+const SELF_TEST_MAX_ONES: u32 = (SELF_TEST_WORDS * 32 * 3 / 4) as u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntropyError {
+    /// Two consecutive words of the sample were equal, e.g. all zeros or all ones.
+    Stuck,
+    /// The sample was far from half ones.
+    Biased,
+    /// No self-test has passed since the last failure.
+    Unhealthy,
+}
+
+impl fmt::Display for EntropyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntropyError::Stuck => f.write_str("rng output stuck"),
+            EntropyError::Biased => f.write_str("rng output biased"),
+            EntropyError::Unhealthy => f.write_str("rng failed its self-test"),
+        }
+    }
+}
+
+impl core::error::Error for EntropyError {}
+
+/// A random source, the TRNG on the device, that hands out bytes only while its last
+/// self-test passed.
+pub struct CheckedRng<R> {
+    rng: R,
+    healthy: bool,
+}
+
+impl<R: RngCore> CheckedRng<R> {
+    /// Wrap `rng` without testing it; it is unusable until `self_test` passes.
+    pub fn new(rng: R) -> Self {
+        CheckedRng { rng, healthy: false }
+    }
+
+    /// Wrap `rng` once it passes the self-test.
+    pub fn init(rng: R) -> Result<Self, EntropyError> {
+        let mut checked = Self::new(rng);
+        checked.self_test()?;
+        Ok(checked)
+    }
+
+    /// Draw a sample and reject repeated words or a sample far from half ones. Run at
+    /// startup and by the SelfTest command; a failure disables the source until the
+    /// next pass.
+    pub fn self_test(&mut self) -> Result<(), EntropyError> {
+        self.healthy = false;
+
+        let mut previous = self.rng.next_u32();
+        let mut ones = previous.count_ones();
+        for _ in 1..SELF_TEST_WORDS {
+            let word = self.rng.next_u32();
+            if word == previous {
+                return Err(EntropyError::Stuck);
+            }
+            ones += word.count_ones();
+            previous = word;
+        }
+        if !(SELF_TEST_MIN_ONES..=SELF_TEST_MAX_ONES).contains(&ones) {
+            return Err(EntropyError::Biased);
+        }
+
+        self.healthy = true;
+        Ok(())
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+
+    /// Fill `buf` with random bytes, if the source is healthy.
+    pub fn try_fill_bytes(&mut self, buf: &mut [u8]) -> Result<(), EntropyError> {
+        if !self.healthy {
+            return Err(EntropyError::Unhealthy);
+        }
+        self.rng.fill_bytes(buf);
+        Ok(())
+    }
+}
@@ -1,117 +1,310 @@
 pub extern crate max7800x_hal as hal;
-pub use hal::flc::{FlashError, Flc};
-use panic_halt as _; // Import module from lib.rs
+pub use hal::flc::FlashError;
+#[cfg(not(feature = "std"))]
+pub use hal::flc::Flc;
+// Host builds keep flash in RAM
+#[cfg(feature = "std")]
+pub use crate::modules::mock_flash::MockFlc as Flc;
 
 use core::convert::TryInto;
+use core::fmt;
 use core::mem::size_of;
+use core::ops::Range;
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::modules::constants::SCRATCH_ADDRESS;
+use crate::modules::crc::Crc32;
+#[cfg(all(feature = "brownout", not(feature = "std")))]
+use crate::modules::supply_monitor::SupplyMonitor;
+#[cfg(all(feature = "brownout", feature = "std"))]
+use crate::modules::supply_monitor::MockSupplyMonitor as SupplyMonitor;
+
 #[derive(Debug)]
 pub enum FlashManagerError {
     /// An error occurred in the underlying flash operations.
     FlashError(FlashError),
     /// The magic value in flash did not match the expected value.
     MagicMismatch,
+    /// The stored CRC did not match the record read back from flash.
+    CrcMismatch,
+    /// A record was to be written at an address not aligned to the 16-byte flash word.
+    Misaligned,
+    /// The supply is too low to erase or program flash safely.
+    #[cfg(feature = "brownout")]
+    LowVoltage,
+}
+
+impl fmt::Display for FlashManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlashManagerError::FlashError(FlashError::InvalidAddress) => f.write_str("invalid flash address"),
+            FlashManagerError::FlashError(FlashError::AccessViolation) => f.write_str("flash access violation"),
+            FlashManagerError::FlashError(FlashError::NeedsErase) => f.write_str("flash needs erase"),
+            FlashManagerError::MagicMismatch => f.write_str("magic mismatch"),
+            FlashManagerError::CrcMismatch => f.write_str("crc mismatch"),
+            FlashManagerError::Misaligned => f.write_str("misaligned flash address"),
+            #[cfg(feature = "brownout")]
+            FlashManagerError::LowVoltage => f.write_str("supply voltage too low"),
+        }
+    }
 }
 
+impl core::error::Error for FlashManagerError {}
+
 impl From<FlashError> for FlashManagerError {
     fn from(err: FlashError) -> Self {
         FlashManagerError::FlashError(err)
     }
 }
 
+/// Attempts made at each 128-bit write or page erase before its error is returned.
+pub const FLASH_OP_ATTEMPTS: u32 = 3;
+
+/// The flash controller programs whole 128-bit words, so records start on this boundary.
+///
+/// Only erased words are ever programmed. Programming can only clear bits, and setting
+/// one back takes an erase of the whole 8 KB page, which is slow and what wears flash
+/// out; but whether the MAX78000 controller may program a word a second time between
+/// erases, even to clear more bits, is not documented, so nothing here relies on it.
+/// Data that changes is appended to fresh words instead, as the subscription page log
+/// and the state log do, and a page is erased once it has no fresh words left.
+pub const FLASH_WORD_SIZE: u32 = 16;
+
 // The manager struct that holds a reference to the flash controller.
 pub struct FlashManager {
     flc: Flc,
+    crc: Crc32,
+    #[cfg(feature = "brownout")]
+    supply: Option<SupplyMonitor>,
 }
 
 impl FlashManager {
-    pub fn new(flc: Flc) -> Self {
-        FlashManager { flc }
+    pub fn new(flc: Flc, crc: Crc32) -> Self {
+        FlashManager {
+            flc,
+            crc,
+            #[cfg(feature = "brownout")]
+            supply: None,
+        }
+    }
+
+    /// Address of the scratch page, for transient data that must never land on a
+    /// subscription page; see `SCRATCH_ADDRESS`.
+    pub const fn scratch_page_addr() -> u32 {
+        SCRATCH_ADDRESS
+    }
+
+    /// Check the supply with `supply` before every erase and write from now on.
+    #[cfg(feature = "brownout")]
+    pub fn with_supply_monitor(mut self, supply: SupplyMonitor) -> Self {
+        self.supply = Some(supply);
+        self
+    }
+
+    /// Refuse to modify flash while the supply is below the safe level.
+    #[cfg(feature = "brownout")]
+    fn check_supply(&mut self) -> Result<(), FlashManagerError> {
+        if let Some(supply) = self.supply.as_mut() {
+            if !supply.supply_ok() {
+                return Err(FlashManagerError::LowVoltage);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a flash write or erase, retrying while the controller reports an access
+    /// violation (busy or locked), which can clear on its own. An invalid address or a
+    /// write needing an erase fails the same way every time and is returned at once.
+    /// The supply is checked again before each retry.
+    fn with_retry(&mut self, mut op: impl FnMut(&Flc) -> Result<(), FlashError>) -> Result<(), FlashManagerError> {
+        let mut attempt = 1;
+        loop {
+            match op(&self.flc) {
+                Err(FlashError::AccessViolation) if attempt < FLASH_OP_ATTEMPTS => {
+                    attempt += 1;
+                    #[cfg(feature = "brownout")]
+                    self.check_supply()?;
+                }
+                result => return Ok(result?),
+            }
+        }
     }
 
-    /// Write data with a magic value prepended.
+    /// Write data with a magic value prepended and a CRC appended.
     ///
     /// The flash page will begin with the 4‑byte little‑endian representation of `magic`
-    /// followed immediately by the bytes of `data`. The combined data is then written in 16‑byte
-    /// chunks.
+    /// followed immediately by the bytes of `data` and the 4-byte little-endian CRC-32 of
+    /// `data`. The combined data is then written in 16‑byte chunks.
+    ///
+    /// `start_address` must be a multiple of `FLASH_WORD_SIZE`; anything else is refused
+    /// with `Misaligned` before flash is touched, as the controller would fault on it.
     pub fn write_data<T: Pod>(
         &mut self,
         start_address: u32,
         magic: u32,
         data: &T,
     ) -> Result<(), FlashManagerError> {
-        // Convert the data to a byte slice.
-        let data_bytes = bytemuck::bytes_of(data);
-        // Total bytes = magic (4 bytes) + data
-        let total_bytes = 4 + data_bytes.len();
-        // For this example we use a stack buffer of fixed size.
-        assert!(total_bytes <= 4096, "Combined data too large for buffer");
-        let mut buffer = [0u8; 4096];
+        self.write_record(start_address, magic, data, 0..usize::MAX)
+    }
 
-        // Write the magic (in little-endian order) into the first 4 bytes.
-        buffer[..4].copy_from_slice(&magic.to_le_bytes());
-        // Then copy the data immediately after.
-        buffer[4..total_bytes].copy_from_slice(data_bytes);
+    /// Write a record as `write_data` does, except for its first 16-byte chunk, which
+    /// holds the magic. Until `commit_data` writes that chunk the record reads as
+    /// erased flash, so a write cut short by a reset never shows a valid magic.
+    pub fn stage_data<T: Pod>(&mut self, start_address: u32, magic: u32, data: &T) -> Result<(), FlashManagerError> {
+        self.write_record(start_address, magic, data, 1..usize::MAX)
+    }
+
+    /// Write the first chunk of a record `stage_data` wrote, with the same arguments.
+    /// It is a single flash write, so the record appears whole or not at all.
+    pub fn commit_data<T: Pod>(&mut self, start_address: u32, magic: u32, data: &T) -> Result<(), FlashManagerError> {
+        self.write_record(start_address, magic, data, 0..1)
+    }
+
+    /// Program the 16-byte chunks in `chunks` of the record `magic || data || CRC`,
+    /// clipped to its length.
+    fn write_record<T: Pod>(
+        &mut self,
+        start_address: u32,
+        magic: u32,
+        data: &T,
+        chunks: Range<usize>,
+    ) -> Result<(), FlashManagerError> {
+        check_aligned(start_address)?;
+        #[cfg(feature = "brownout")]
+        self.check_supply()?;
+
+        let mut buffer = [0u8; 4096];
+        let total_bytes = self.record_bytes(magic, data, &mut buffer);
 
         // Write the combined buffer to flash in 16-byte chunks.
-        let chunks = (total_bytes + 15) / 16;
-        for i in 0..chunks {
-            let offset = i * 16;
-            let chunk: [u8; 16] = if offset + 16 <= total_bytes {
-                buffer[offset..offset + 16].try_into().unwrap()
-            } else {
-                // For the last chunk, pad with zeros if needed.
-                let mut padded = [0u8; 16];
-                let remaining = total_bytes - offset;
-                padded[..remaining].copy_from_slice(&buffer[offset..offset + remaining]);
-                padded
-            };
-            // Convert the 16-byte chunk into four u32 words.
-            let word_arr: [u32; 4] = bytemuck::try_from_bytes::<[u32; 4]>(&chunk)
-                .expect("Chunk conversion failed")
-                .clone();
-            self.flc
-                .write_128(start_address + (i as u32 * 16), &word_arr)?;
+        for i in chunks.start..chunks.end.min(total_bytes.div_ceil(16)) {
+            let word_arr = record_chunk(&buffer, total_bytes, i);
+            self.with_retry(|flc| flc.write_128(start_address + (i as u32 * 16), &word_arr))?;
         }
         Ok(())
     }
 
+    /// Lay out `magic || data || CRC-32(data)` in `buffer`, returning its length.
+    fn record_bytes<T: Pod>(&mut self, magic: u32, data: &T, buffer: &mut [u8; 4096]) -> usize {
+        // Convert the data to a byte slice.
+        let data_bytes = bytemuck::bytes_of(data);
+        // Total bytes = magic (4 bytes) + data + crc (4 bytes)
+        let total_bytes = 4 + data_bytes.len() + 4;
+        assert!(total_bytes <= buffer.len(), "Combined data too large for buffer");
+
+        // Write the magic (in little-endian order) into the first 4 bytes.
+        buffer[..4].copy_from_slice(&magic.to_le_bytes());
+        // Then copy the data immediately after, followed by its CRC.
+        buffer[4..total_bytes - 4].copy_from_slice(data_bytes);
+        let crc = self.crc.checksum(data_bytes);
+        buffer[total_bytes - 4..total_bytes].copy_from_slice(&crc.to_le_bytes());
+        total_bytes
+    }
+
     /// Read data with a magic value at the beginning.
     ///
     /// This function reads enough bytes to cover a 4-byte magic value plus the size of T.
     /// It then checks that the first 4 bytes match `expected_magic`. If so, it returns the T
     /// (constructed from the bytes following the magic). Otherwise, it returns an error.
     pub fn read_data<T: Pod + Zeroable>(&mut self, start_address: u32) -> Result<T, FlashManagerError> {
+        let mut data = T::zeroed();
+        self.read_data_into(start_address, &mut data)?;
+        Ok(data)
+    }
+
+    /// Like `read_data`, but fills a caller-owned `data` so large records can live in a
+    /// long-lived buffer instead of a new stack value per read.
+    pub fn read_data_into<T: Pod>(&mut self, start_address: u32, data: &mut T) -> Result<(), FlashManagerError> {
         let data_size = size_of::<T>();
         // Total bytes to read = 4 (magic) + size of data.
         let total_bytes = 4 + data_size;
-        let chunks = (total_bytes + 15) / 16;
-        // For demonstration, we use a fixed-size buffer.
-        assert!(
-            chunks * 16 <= 4096,
-            "Data too large for our temporary buffer"
-        );
-        let mut buffer = [0u8; 4096];
+        let chunks = total_bytes.div_ceil(16);
+        // Copy each chunk straight into the result, skipping the magic, instead of
+        // staging the record in a page-sized scratch buffer.
+        let out = bytemuck::bytes_of_mut(data);
         for i in 0..chunks {
             let addr = start_address + (i as u32 * 16);
             let word_arr = self.flc.read_128(addr)?;
             let chunk: &[u8] = bytemuck::cast_slice(&word_arr);
-            let offset = i * 16;
-            buffer[offset..offset + 16].copy_from_slice(chunk);
+            // Byte range of this chunk within the record, clipped to the data
+            let start = core::cmp::max(i * 16, 4);
+            let end = core::cmp::min(i * 16 + 16, total_bytes);
+            out[start - 4..end - 4].copy_from_slice(&chunk[start - i * 16..end - i * 16]);
+        }
+        Ok(())
+    }
+
+    /// Read data written by `write_data` and verify its trailing CRC.
+    ///
+    /// Unlike `read_data`, which may read just a prefix of a record (e.g. its header),
+    /// `T` must be the full record type that was written.
+    pub fn read_data_verified<T: Pod + Zeroable>(&mut self, start_address: u32) -> Result<T, FlashManagerError> {
+        let mut data = T::zeroed();
+        self.read_data_verified_into(start_address, &mut data)?;
+        Ok(data)
+    }
+
+    /// `read_data_verified` into a caller-owned `data`. On error `data` holds whatever
+    /// was read and must not be used.
+    pub fn read_data_verified_into<T: Pod>(&mut self, start_address: u32, data: &mut T) -> Result<(), FlashManagerError> {
+        self.read_data_into(start_address, data)?;
+        let crc_addr = start_address + 4 + size_of::<T>() as u32;
+        // The CRC may straddle a 16-byte boundary, so read the chunk(s) containing it.
+        let aligned = crc_addr & !0xF;
+        let offset = (crc_addr - aligned) as usize;
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(bytemuck::cast_slice(&self.flc.read_128(aligned)?));
+        if offset + 4 > 16 {
+            bytes[16..].copy_from_slice(bytemuck::cast_slice(&self.flc.read_128(aligned + 16)?));
+        }
+        let stored = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        if stored != self.crc.checksum(bytemuck::bytes_of(data)) {
+            return Err(FlashManagerError::CrcMismatch);
         }
-        // Convert the bytes after the magic into T.
-        let data_bytes = &buffer[4..4 + data_size];
-        let data =
-            bytemuck::try_from_bytes(data_bytes).expect("Failed to cast bytes to target type");
-        Ok(*data)
+        Ok(())
     }
 
     /// Erase the flash page at `start_address`.
     pub fn wipe_data(&mut self, start_address: u32) -> Result<(), FlashManagerError> {
+        #[cfg(feature = "brownout")]
+        self.check_supply()?;
+
         // The erase function is unsafe so we wrap it here.
-        unsafe { Ok(self.flc.erase_page(start_address)?) }
+        self.with_retry(|flc| unsafe { flc.erase_page(start_address) })
+    }
+
+    /// Program `data` from `start_address` as it is, with no magic or CRC around it.
+    ///
+    /// The last 16-byte word is padded with 0xFF, which leaves those bytes erased. A
+    /// word already holding its bytes is not programmed again, so data a reset cut
+    /// short can be written anew without an erase; any other written word fails with
+    /// `NeedsErase`. `start_address` must be aligned as for `write_data`.
+    pub fn write_raw(&mut self, start_address: u32, data: &[u8]) -> Result<(), FlashManagerError> {
+        check_aligned(start_address)?;
+        #[cfg(feature = "brownout")]
+        self.check_supply()?;
+
+        for (i, chunk) in data.chunks(16).enumerate() {
+            let addr = start_address + i as u32 * 16;
+            let mut word = [0xFFu8; 16];
+            word[..chunk.len()].copy_from_slice(chunk);
+            let word_arr: [u32; 4] = bytemuck::cast(word);
+            if self.flc.read_128(addr)? != word_arr {
+                self.with_retry(|flc| flc.write_128(addr, &word_arr))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy `out.len()` bytes of flash from `start_address` as they are, magic, CRC and
+    /// erased words included. Both must be multiples of the 16-byte read size.
+    pub fn read_raw(&mut self, start_address: u32, out: &mut [u8]) -> Result<(), FlashError> {
+        for (i, chunk) in out.chunks_exact_mut(16).enumerate() {
+            let word_arr = self.flc.read_128(start_address + i as u32 * 16)?;
+            chunk.copy_from_slice(bytemuck::cast_slice(&word_arr));
+        }
+        Ok(())
     }
 
     /// Reads the first 4 bytes (magic) from the flash page at `start_address`
@@ -125,4 +318,28 @@ impl FlashManager {
         let magic = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
         Ok(magic)
     }
+
+    /// Blocks until the flash controller has finished its current write or erase.
+    pub fn wait_idle(&self) {
+        while self.flc.is_busy() {}
+    }
+}
+
+/// Refuse a record address the controller cannot program a 128-bit word at.
+fn check_aligned(start_address: u32) -> Result<(), FlashManagerError> {
+    if !start_address.is_multiple_of(FLASH_WORD_SIZE) {
+        return Err(FlashManagerError::Misaligned);
+    }
+    Ok(())
+}
+
+/// Chunk `i` of a record laid out by `record_bytes`, as the four words `write_128`
+/// takes. The last chunk is padded with zeros.
+fn record_chunk(buffer: &[u8; 4096], total_bytes: usize, i: usize) -> [u32; 4] {
+    let offset = i * 16;
+    let mut chunk = [0u8; 16];
+    let end = core::cmp::min(offset + 16, total_bytes);
+    chunk[..end - offset].copy_from_slice(&buffer[offset..end]);
+    // Convert the 16-byte chunk into four u32 words, by value so alignment never matters.
+    bytemuck::cast(chunk)
 }
@@ -0,0 +1,62 @@
+//! Where decoded frames go. By default they are sent back to the host in the Decode
+//! response; a decoder feeding a downstream codec can hand them to another peripheral
+//! (SPI, I2S, a DMA buffer) instead, and answer the host with an empty Decode packet.
+use crate::modules::channel_manager::FRAME_CONTENT_LEN;
+use crate::modules::hostcom_manager::ErrorCode;
+use core::fmt;
+
+#[derive(Debug)]
+pub enum SinkError {
+    /// The peripheral could not take the frame yet.
+    Busy,
+    /// The peripheral reported a fault.
+    Fault,
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkError::Busy => f.write_str("frame sink busy"),
+            SinkError::Fault => f.write_str("frame sink fault"),
+        }
+    }
+}
+
+impl core::error::Error for SinkError {}
+
+impl SinkError {
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::SinkFailure
+    }
+}
+
+/// Destination of decoded frames, chosen at init.
+pub trait FrameSink {
+    /// Hand one decoded frame on.
+    fn write_frame(&mut self, frame: &[u8; FRAME_CONTENT_LEN]) -> Result<(), SinkError>;
+
+    /// Whether the Decode response carries the frame itself. Only the host sink's does.
+    fn returns_to_host(&self) -> bool {
+        false
+    }
+}
+
+/// The host UART: the frame goes out as the Decode response body.
+pub struct HostSink;
+
+impl FrameSink for HostSink {
+    fn write_frame(&mut self, _frame: &[u8; FRAME_CONTENT_LEN]) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    fn returns_to_host(&self) -> bool {
+        true
+    }
+}
+
+/// Hand `frame` to `sink` and return the Decode response body: the frame for the host
+/// sink, nothing for any other.
+pub fn route_frame<'a, S: FrameSink>(sink: &mut S, frame: &'a [u8; FRAME_CONTENT_LEN]) -> Result<Option<&'a [u8]>, SinkError> {
+    sink.write_frame(frame)?;
+    Ok(sink.returns_to_host().then_some(&frame[..]))
+}
@@ -0,0 +1,21 @@
+//! The one check of a host signature, shared by every signed command, so a key rotated
+//! in through `HOST_KEYS_PUB` is accepted by all of them alike.
+use crate::HOST_KEYS_PUB;
+use ed25519_dalek::pkcs8::DecodePublicKey;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+/// An embedded host key is not a DER-encoded Ed25519 public key.
+#[derive(Debug)]
+pub struct InvalidHostKey;
+
+/// Whether `signature` over `message` verifies under any of `HOST_KEYS_PUB`, the
+/// primary key first. Every embedded key is tried, a number fixed by the build and
+/// never by the message.
+pub fn verify_host_signature(message: &[u8], signature: &Signature) -> Result<bool, InvalidHostKey> {
+    let mut valid = false;
+    for der in HOST_KEYS_PUB {
+        let verifying_key = VerifyingKey::from_public_key_der(der).map_err(|_| InvalidHostKey)?;
+        valid |= verifying_key.verify(message, signature).is_ok();
+    }
+    Ok(valid)
+}
@@ -1,11 +1,63 @@
 // Re-export the HAL as needed.
 pub extern crate max7800x_hal as hal;
-use crate::modules::channel_manager::read_channel;
+use crate::modules::channel_manager::channel_subscriptions;
+#[cfg(feature = "decode-passthrough")]
+use crate::modules::channel_manager::ChannelFrame;
+use crate::modules::constants::FLASH_LAYOUT;
+#[cfg(feature = "chunk-crc")]
+use crate::modules::crc::crc16;
 use crate::modules::flash_manager::FlashManager;
+#[cfg(feature = "dma-uart")]
+use crate::modules::dma_uart::{DmaRx, DMA_MIN_BODY_LEN};
+use crate::modules::wire::read_u16_le;
+use crate::{DECODER_ID, FIRMWARE_COMMIT, MAX_CHANNELS};
 use bytemuck::{Pod, Zeroable};
+use core::fmt;
+use core::mem::size_of;
 
 pub const MSG_MAGIC: u8 = b'%';
 
+/// Capacity of the body buffer used for incoming messages.
+pub const MAX_BODY_LEN: usize = 4096;
+
+/// Bytes sent between ACKs once a packet header has been acknowledged. Must match the
+/// host tools exactly, or the handshake deadlocks.
+pub const CHUNK_SIZE: usize = 256;
+
+/// Empty polls of the RX FIFO, roughly a second at the default clock, after which a
+/// body the host has stopped sending is given up on.
+pub const READ_TIMEOUT_POLLS: u32 = 10_000_000;
+
+/// Resends of one corrupt body chunk the decoder asks for with a NACK before giving up
+/// on the body (`chunk-crc` feature).
+#[cfg(feature = "chunk-crc")]
+pub const MAX_CHUNK_RETRIES: u32 = 3;
+
+/// A body `HostConsole::read_body` gave up on.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BodyError {
+    /// The host stopped sending partway through the body.
+    Timeout,
+    /// A chunk still failed its CRC after `MAX_CHUNK_RETRIES` resends.
+    #[cfg(feature = "chunk-crc")]
+    ChunkCorrupt,
+}
+
+impl fmt::Display for BodyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyError::Timeout => f.write_str("timed out reading body"),
+            #[cfg(feature = "chunk-crc")]
+            BodyError::ChunkCorrupt => f.write_str("body chunk corrupt after retries"),
+        }
+    }
+}
+
+impl core::error::Error for BodyError {}
+
+// A full body buffer is a whole number of chunks.
+const _: () = assert!(MAX_BODY_LEN.is_multiple_of(CHUNK_SIZE));
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MsgType {
@@ -15,6 +67,169 @@ pub enum MsgType {
     Ack = b'A',
     Debug = b'G',
     Error = b'E',
+    Tamper = b'T',
+    Recover = b'R',
+    DecoderId = b'I',
+    SetTime = b'C',
+    Telemetry = b'M',
+    FlashLayout = b'F',
+    Rekey = b'K',
+    /// Recoverable rejection asking the peer to resend, as opposed to a terminal Error.
+    Nack = b'N',
+    NodeDump = b'P',
+    /// Signed change to the end of a stored subscription's window.
+    Window = b'W',
+    /// Liveness probe, answered at once with an empty Ping and no ACK exchange.
+    Ping = b'Q',
+    ReplayState = b'V',
+    /// Number of free subscription pages (u32 LE).
+    FreeSlots = b'O',
+    /// Rebuild the active channel list from flash, answered like ReplayState.
+    Resync = b'Y',
+    /// Signed pause or resume of a stored subscription, answered with its new sequence.
+    Pause = b'U',
+    /// Signed switch of emergency-only mode, answered with the new epoch.
+    Emergency = b'X',
+    /// Rerun the TRNG self-test, answered with an empty SelfTest if it passes.
+    SelfTest = b'H',
+    /// Signed announcement of a Subscribe, answered with an empty SubscribePreamble if
+    /// the decoder would take it and an Error otherwise, before the body is sent.
+    SubscribePreamble = b'B',
+    /// A frame decoded like Decode, answered with the SHA-256 of its content instead.
+    VerifyProbe = b'J',
+    /// Truncated SHA-256 over every embedded host public key.
+    KeyFingerprint = b'Z',
+    /// Raw bytes of a subscription page (`page-dump` feature).
+    PageDump = b'p',
+    /// Signed set of subscriptions replacing every stored one, answered with their count.
+    SubscribeBundle = b's',
+    /// ACKed, then a system reset (`soft-reset` feature); nothing else is sent.
+    Reset = b'r',
+    /// One chunk of a resumable Subscribe upload (`resumable-upload` feature),
+    /// answered with the bytes received so far (u16 LE).
+    UploadChunk = b'u',
+    /// Bytes of a resumable upload already received (u16 LE), to resume from.
+    UploadStatus = b'q',
+    /// Git commit the firmware was built from, as ASCII; "unknown" without git.
+    Version = b'v',
+}
+
+impl From<MsgType> for u8 {
+    fn from(msg_type: MsgType) -> u8 {
+        msg_type as u8
+    }
+}
+
+impl TryFrom<u8> for MsgType {
+    type Error = u8;
+
+    /// Maps an opcode byte to its message type, returning the byte back if unknown.
+    fn try_from(opcode: u8) -> Result<Self, u8> {
+        match opcode {
+            b'D' => Ok(MsgType::Decode),
+            b'S' => Ok(MsgType::Subscribe),
+            b'L' => Ok(MsgType::List),
+            b'A' => Ok(MsgType::Ack),
+            b'G' => Ok(MsgType::Debug),
+            b'E' => Ok(MsgType::Error),
+            b'T' => Ok(MsgType::Tamper),
+            b'R' => Ok(MsgType::Recover),
+            b'I' => Ok(MsgType::DecoderId),
+            b'C' => Ok(MsgType::SetTime),
+            b'M' => Ok(MsgType::Telemetry),
+            b'F' => Ok(MsgType::FlashLayout),
+            b'K' => Ok(MsgType::Rekey),
+            b'N' => Ok(MsgType::Nack),
+            b'P' => Ok(MsgType::NodeDump),
+            b'W' => Ok(MsgType::Window),
+            b'Q' => Ok(MsgType::Ping),
+            b'V' => Ok(MsgType::ReplayState),
+            b'O' => Ok(MsgType::FreeSlots),
+            b'Y' => Ok(MsgType::Resync),
+            b'U' => Ok(MsgType::Pause),
+            b'X' => Ok(MsgType::Emergency),
+            b'H' => Ok(MsgType::SelfTest),
+            b'B' => Ok(MsgType::SubscribePreamble),
+            b'J' => Ok(MsgType::VerifyProbe),
+            b'Z' => Ok(MsgType::KeyFingerprint),
+            b'p' => Ok(MsgType::PageDump),
+            b's' => Ok(MsgType::SubscribeBundle),
+            b'r' => Ok(MsgType::Reset),
+            b'u' => Ok(MsgType::UploadChunk),
+            b'q' => Ok(MsgType::UploadStatus),
+            b'v' => Ok(MsgType::Version),
+            _ => Err(opcode),
+        }
+    }
+}
+
+/// Error codes sent as the single body byte of an Error packet.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Unspecified failure.
+    Generic = 0x00,
+    /// A Decode body was not exactly the size of a `ChannelFrame`.
+    InvalidFrameLength = 0x01,
+    /// Every subscription page is occupied by another channel.
+    SubscriptionsFull = 0x02,
+    /// The tamper flag is set; Decode and Subscribe are refused until recovery.
+    Locked = 0x03,
+    /// The opcode is not one the decoder handles.
+    UnknownCommand = 0x04,
+    /// The channel's subscription window has ended.
+    SubscriptionExpired = 0x05,
+    /// A Decode arrived for a channel with no stored subscription.
+    NoSubscription = 0x06,
+    /// The frame or subscription signature did not verify.
+    InvalidSignature = 0x07,
+    /// The frame timestamp was not newer than the last decoded frame.
+    ReplayedTimestamp = 0x08,
+    /// The subscription holds no key covering the frame timestamp.
+    KeyNotFound = 0x09,
+    /// The channel is paused; its subscription is kept until a Pause command resumes it.
+    ChannelPaused = 0x0A,
+    /// Emergency-only mode is on; only channel 0 frames are decoded.
+    EmergencyOnly = 0x0B,
+    /// The Subscribe body's checksum did not match, or with `chunk-crc` a body chunk
+    /// failed its CRC on every resend; resending the command may succeed.
+    ChecksumMismatch = 0x0C,
+    /// The channel has a stored subscription but no active channel entry, e.g. after
+    /// its page was written without the list being rebuilt.
+    ChannelInactive = 0x0D,
+    /// The TRNG failed its self-test.
+    EntropyFailure = 0x0E,
+    /// The channel's stored subscription was corrupt and has been erased; subscribe again.
+    SubscriptionCorrupt = 0x0F,
+    /// The frame decoded but the downstream frame sink did not take it.
+    SinkFailure = 0x10,
+    /// The Subscribe body was longer than the body buffer; it was drained unread.
+    SubscriptionTooLarge = 0x11,
+    /// The subscription decrypted to no usable password; it was likely encrypted for
+    /// another decoder key.
+    NoUsablePasswords = 0x12,
+    /// An upload chunk was not the next one the decoder expects; the host should ask
+    /// UploadStatus where to resume.
+    UploadOutOfOrder = 0x13,
+}
+
+/// Severity sent as the first body byte of every Debug packet, so the host can filter.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Verbose tracing, only sent in builds with the `trace-log` feature.
+    Trace = 0x00,
+    Info = 0x01,
+    Warn = 0x02,
+    Error = 0x03,
+}
+
+impl LogLevel {
+    /// Whether messages at this level are sent in this build. None are without the
+    /// `debug-output` feature, and every log call then compiles down to nothing.
+    pub const fn enabled(self) -> bool {
+        cfg!(feature = "debug-output") && (!matches!(self, LogLevel::Trace) || cfg!(feature = "trace-log"))
+    }
 }
 
 #[repr(C, packed)]
@@ -25,10 +240,21 @@ pub struct MessageHeader {
     pub length: u16,
 }
 
+impl MessageHeader {
+    /// Header for an outgoing packet of `length` body bytes, with the magic filled in.
+    pub fn new(opcode: MsgType, length: u16) -> Self {
+        MessageHeader {
+            magic: MSG_MAGIC,
+            opcode: opcode.into(),
+            length,
+        }
+    }
+}
+
 #[repr(C, packed)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct MessageBody {
-    pub data: [u8; 4096],
+    pub data: [u8; MAX_BODY_LEN],
     pub length: u16,
 }
 
@@ -40,11 +266,20 @@ pub struct ChannelInfo {
     pub end_timestamp: u64,
 }
 
+// Wire sizes shared with the host tools: magic, opcode and u16 length; channel id and
+// the two timestamps, as sent in List responses.
+const _: () = assert!(size_of::<MessageHeader>() == 1 + 1 + 2);
+const _: () = assert!(size_of::<ChannelInfo>() == 4 + 8 + 8);
+
 /// A minimal trait that exposes the HAL’s blocking read_byte and write_byte methods.
 /// (This is provided to decouple our functions from a specific UART type.)
 pub trait UartHalOps {
     fn read_byte(&mut self) -> u8;
     fn write_byte(&mut self, byte: u8);
+    /// Returns the next received byte, or `None` immediately if none is available.
+    fn try_read_byte(&mut self) -> Option<u8>;
+    /// Blocks until every written byte has left the transmitter.
+    fn flush(&mut self) {}
 }
 
 // Implement UartHalOps for the HAL’s BuiltUartPeripheral.
@@ -60,6 +295,393 @@ where
     fn write_byte(&mut self, byte: u8) {
         Self::write_byte(self, byte)
     }
+    #[inline(always)]
+    fn try_read_byte(&mut self) -> Option<u8> {
+        // Non-blocking read backed by the RX FIFO empty flag.
+        embedded_hal_nb::serial::Read::read(self).ok()
+    }
+    #[inline(always)]
+    fn flush(&mut self) {
+        let _ = embedded_hal_nb::nb::block!(embedded_hal_nb::serial::Write::flush(self));
+    }
+}
+
+/// Host connection over a UART, exposing the protocol helpers below as methods.
+///
+/// Owning the UART here gives per-connection protocol state a single home; the free
+/// functions remain for code that only has a bare `UartHalOps`.
+///
+/// Debug output goes to the host as Debug packets unless a separate debug sink `D` is
+/// attached with `with_debug_sink`, in which case it is written there as plain text and
+/// the host stream carries protocol packets only.
+pub struct HostConsole<U: UartHalOps, D: UartHalOps = U> {
+    uart: U,
+    debug: Option<D>,
+    #[cfg(feature = "dma-uart")]
+    dma: Option<DmaRx>,
+    /// Header bytes received so far by `poll_once`, starting with the magic.
+    header: [u8; size_of::<MessageHeader>()],
+    header_len: usize,
+    read_timeout_polls: u32,
+    /// Report body upload progress after every chunk ACK, see `with_progress_reports`.
+    progress: bool,
+}
+
+impl<U: UartHalOps> HostConsole<U> {
+    pub fn new(uart: U) -> Self {
+        HostConsole {
+            uart,
+            debug: None,
+            #[cfg(feature = "dma-uart")]
+            dma: None,
+            header: [0; size_of::<MessageHeader>()],
+            header_len: 0,
+            read_timeout_polls: READ_TIMEOUT_POLLS,
+            progress: false,
+        }
+    }
+}
+
+impl<U: UartHalOps, D: UartHalOps> HostConsole<U, D> {
+    /// Route debug output to `sink` instead of the host UART.
+    pub fn with_debug_sink<S: UartHalOps>(self, sink: S) -> HostConsole<U, S> {
+        HostConsole {
+            uart: self.uart,
+            debug: Some(sink),
+            #[cfg(feature = "dma-uart")]
+            dma: self.dma,
+            header: self.header,
+            header_len: self.header_len,
+            read_timeout_polls: self.read_timeout_polls,
+            progress: self.progress,
+        }
+    }
+
+    /// Give up on a body after `polls` empty polls instead of `READ_TIMEOUT_POLLS`.
+    pub fn with_read_timeout(mut self, polls: u32) -> Self {
+        self.read_timeout_polls = polls;
+        self
+    }
+
+    /// Follow the ACK of every body chunk `read_body` receives with an Info Debug packet
+    /// `progress <received>/<total>`, so host tooling can show a long upload moving.
+    /// Off by default, as the reference host expects only ACKs while it sends a body.
+    pub fn with_progress_reports(mut self) -> Self {
+        self.progress = true;
+        self
+    }
+
+    /// Receive large bodies through `dma` from now on.
+    #[cfg(feature = "dma-uart")]
+    pub fn with_dma(mut self, dma: DmaRx) -> Self {
+        self.dma = Some(dma);
+        self
+    }
+
+    /// Gives back the underlying UART.
+    pub fn release(self) -> U {
+        self.uart
+    }
+
+    pub fn read_ack(&mut self) -> i32 {
+        read_ack(&mut self.uart)
+    }
+
+    /// Blocks until everything written to the host UART has been sent.
+    pub fn flush(&mut self) {
+        self.uart.flush();
+    }
+
+    pub fn write_ack(&mut self) -> i32 {
+        write_ack(&mut self.uart)
+    }
+
+    pub fn write_packet(&mut self, msg_type: MsgType, body: Option<&[u8]>) -> i32 {
+        write_packet(&mut self.uart, msg_type, body)
+    }
+
+    /// Sends only a packet header, leaving the host's ACK of it to `read_ack`.
+    pub fn write_header(&mut self, msg_type: MsgType, length: u16) {
+        write_header(&mut self.uart, msg_type, length)
+    }
+
+    /// Blocks until a whole command header has arrived, continuing one `poll_once`
+    /// has started.
+    pub fn read_header(&mut self) -> MessageHeader {
+        loop {
+            let byte = self.uart.read_byte();
+            if let Some(header) = self.feed_header(byte) {
+                return header;
+            }
+        }
+    }
+
+    /// Takes whatever header bytes have already arrived without waiting for more, and
+    /// returns the header once all of it is in. `None` leaves the bytes read so far
+    /// buffered for the next call, so a main loop can poll between other work.
+    ///
+    /// The body is still read with `read_body` once the header is dispatched: the host
+    /// sends it only after the ACK, so there is nothing to wait for before then.
+    pub fn poll_once(&mut self) -> Option<MessageHeader> {
+        while let Some(byte) = self.uart.try_read_byte() {
+            if let Some(header) = self.feed_header(byte) {
+                return Some(header);
+            }
+        }
+        None
+    }
+
+    /// Adds `byte` to the header being received, skipping anything before the magic as
+    /// `read_header` does, and returns the header once it is complete.
+    fn feed_header(&mut self, byte: u8) -> Option<MessageHeader> {
+        if self.header_len == 0 && byte != MSG_MAGIC {
+            return None;
+        }
+        self.header[self.header_len] = byte;
+        self.header_len += 1;
+        if self.header_len < self.header.len() {
+            return None;
+        }
+        self.header_len = 0;
+        Some(MessageHeader {
+            magic: MSG_MAGIC,
+            opcode: self.header[1],
+            length: read_u16_le(&self.header, 2),
+        })
+    }
+
+    /// Reads a body as `read_body` does and returns the number of bytes read. If the host
+    /// stops sending partway, the rest of the command is given up on with `resync`,
+    /// `body.length` is set to the bytes that did arrive and `BodyError::Timeout` is
+    /// returned; the body must then not be used. DMA reads block as before.
+    pub fn read_body(&mut self, length: u16, body: &mut MessageBody) -> Result<u16, BodyError> {
+        let total = length as usize;
+        let mut offset = 0;
+        while offset < total {
+            offset = self.read_body_chunk(body, offset, total)?;
+        }
+        body.length = length;
+        Ok(length)
+    }
+
+    /// Reads the chunk at `offset` of a `total`-byte body into `body` and ACKs it,
+    /// returning the offset after it. A host that stops partway is given up on with
+    /// `resync` as in `read_body`, and `body.length` is set to the bytes received;
+    /// otherwise `body.length` is left for the caller to set.
+    ///
+    /// With `chunk-crc`, each chunk is followed by its CRC-16 (u16 LE). A chunk that
+    /// does not match is NACKed and read again, up to `MAX_CHUNK_RETRIES` times; after
+    /// that the body is refused with a ChecksumMismatch error and `body.length` is set
+    /// to the chunk's offset.
+    pub fn read_body_chunk(&mut self, body: &mut MessageBody, offset: usize, total: usize) -> Result<usize, BodyError> {
+        let end = core::cmp::min(offset + CHUNK_SIZE, total);
+        self.fill_chunk(
+            body,
+            offset,
+            end,
+            #[cfg(feature = "dma-uart")]
+            total,
+        )?;
+        #[cfg(feature = "chunk-crc")]
+        {
+            let mut retries = 0;
+            while !self.chunk_crc_matches(body, offset, end)? {
+                if retries == MAX_CHUNK_RETRIES {
+                    body.length = offset as u16;
+                    self.write_log(LogLevel::Error, "Error: Body chunk corrupt, giving up\n");
+                    let _ = self.write_error(ErrorCode::ChecksumMismatch);
+                    return Err(BodyError::ChunkCorrupt);
+                }
+                retries += 1;
+                let _ = write_nack(&mut self.uart);
+                self.fill_chunk(
+                    body,
+                    offset,
+                    end,
+                    #[cfg(feature = "dma-uart")]
+                    total,
+                )?;
+            }
+        }
+        let _ = write_ack(&mut self.uart);
+        if self.progress {
+            write_progress(&mut self.uart, end, total);
+        }
+        Ok(end)
+    }
+
+    /// Receives `body.data[offset..end]`, resyncing and setting `body.length` to the
+    /// bytes received if the host stops partway.
+    fn fill_chunk(
+        &mut self,
+        body: &mut MessageBody,
+        offset: usize,
+        end: usize,
+        #[cfg(feature = "dma-uart")] total: usize,
+    ) -> Result<(), BodyError> {
+        if let Err(received) = self.receive_chunk(
+            &mut body.data[offset..end],
+            #[cfg(feature = "dma-uart")]
+            total,
+        ) {
+            body.length = (offset + received) as u16;
+            self.resync();
+            return Err(BodyError::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Reads the CRC-16 following `body.data[offset..end]` and checks the chunk against it.
+    #[cfg(feature = "chunk-crc")]
+    fn chunk_crc_matches(&mut self, body: &mut MessageBody, offset: usize, end: usize) -> Result<bool, BodyError> {
+        let mut crc = [0u8; 2];
+        for b in crc.iter_mut() {
+            *b = match self.read_byte_timeout() {
+                Some(byte) => byte,
+                None => {
+                    body.length = end as u16;
+                    self.resync();
+                    return Err(BodyError::Timeout);
+                }
+            };
+        }
+        Ok(u16::from_le_bytes(crc) == crc16(&body.data[offset..end]))
+    }
+
+    /// Fills `chunk` from the host, through DMA when the `total`-byte body is large enough.
+    /// On a timeout, returns how many bytes of `chunk` were filled.
+    fn receive_chunk(&mut self, chunk: &mut [u8], #[cfg(feature = "dma-uart")] total: usize) -> Result<(), usize> {
+        #[cfg(feature = "dma-uart")]
+        if let Some(dma) = self.dma.as_mut() {
+            if total >= DMA_MIN_BODY_LEN {
+                // Same CHUNK_SIZE / ACK framing, only the bytes are moved by DMA
+                dma.read(chunk);
+                return Ok(());
+            }
+        }
+        for (i, b) in chunk.iter_mut().enumerate() {
+            *b = self.read_byte_timeout().ok_or(i)?;
+        }
+        Ok(())
+    }
+
+    /// Discards a body as `discard_body` does, resyncing if the host stops partway.
+    pub fn discard_body(&mut self, length: u16) {
+        let mut remaining = length as usize;
+        while remaining > 0 {
+            let chunk_size = core::cmp::min(CHUNK_SIZE, remaining);
+            // With `chunk-crc` the chunk's CRC follows it, drained unchecked
+            let wire_len = if cfg!(feature = "chunk-crc") { chunk_size + 2 } else { chunk_size };
+            for _ in 0..wire_len {
+                if self.read_byte_timeout().is_none() {
+                    self.resync();
+                    return;
+                }
+            }
+            remaining -= chunk_size;
+            let _ = write_ack(&mut self.uart);
+        }
+    }
+
+    /// Waits up to the read timeout for the next byte.
+    fn read_byte_timeout(&mut self) -> Option<u8> {
+        (0..self.read_timeout_polls).find_map(|_| self.uart.try_read_byte())
+    }
+
+    /// Recover from a command cut off partway: drop any partial header, and drain the
+    /// RX FIFO until the line has been idle for a read timeout, so the late rest of
+    /// the old command is not parsed as a new one. The next header is then found by
+    /// its `MSG_MAGIC` as usual.
+    pub fn resync(&mut self) {
+        self.header_len = 0;
+        let mut idle = 0;
+        while idle < self.read_timeout_polls {
+            match self.uart.try_read_byte() {
+                Some(_) => idle = 0,
+                None => idle += 1,
+            }
+        }
+        self.write_log(LogLevel::Warn, "Warning: Command timed out, resynced\n");
+    }
+
+    pub fn write_debug(&mut self, msg: &str) {
+        self.write_log(LogLevel::Info, msg)
+    }
+
+    pub fn write_debug_fmt(&mut self, args: fmt::Arguments) {
+        self.write_log_fmt(LogLevel::Info, args)
+    }
+
+    /// Log `msg` at `level`. The debug sink gets the plain text, the host a Debug packet.
+    pub fn write_log(&mut self, level: LogLevel, msg: &str) {
+        if !level.enabled() {
+            return;
+        }
+        match self.debug.as_mut() {
+            Some(sink) => msg.bytes().for_each(|b| sink.write_byte(b)),
+            None => write_log(&mut self.uart, level, msg),
+        }
+    }
+
+    /// Log a formatted message at `level`, formatting only if the level is enabled.
+    pub fn write_log_fmt(&mut self, level: LogLevel, args: fmt::Arguments) {
+        if !level.enabled() {
+            return;
+        }
+        match self.debug.as_mut() {
+            Some(sink) => {
+                let mut msg = DebugBuffer { buf: [0; 128], len: 0 };
+                let _ = fmt::write(&mut msg, args);
+                msg.buf[..msg.len].iter().for_each(|&b| sink.write_byte(b));
+            }
+            None => write_log_fmt(&mut self.uart, level, args),
+        }
+    }
+
+    pub fn write_list(&mut self, flash_manager: &mut FlashManager) -> i32 {
+        write_list(&mut self.uart, flash_manager)
+    }
+
+    pub fn write_version(&mut self) -> i32 {
+        write_version(&mut self.uart)
+    }
+
+    pub fn write_decoder_id(&mut self) -> i32 {
+        write_decoder_id(&mut self.uart)
+    }
+
+    pub fn write_flash_layout(&mut self) -> i32 {
+        write_flash_layout(&mut self.uart)
+    }
+
+    pub fn write_error(&mut self, code: ErrorCode) -> i32 {
+        write_error(&mut self.uart, code)
+    }
+
+    #[cfg(feature = "decode-passthrough")]
+    pub fn write_passthrough(&mut self, body: &[u8]) -> i32 {
+        write_passthrough(&mut self.uart, body)
+    }
+
+    /// Answer a Ping header with an empty Ping and no ACK either way. A body would
+    /// need the ACK handshake a ping is meant to skip, so one carrying a body is
+    /// rejected as an unknown command.
+    pub fn answer_ping(&mut self, length: u16) {
+        if length == 0 {
+            let _ = self.write_packet(MsgType::Ping, None);
+        } else {
+            self.reject_command(length);
+        }
+    }
+
+    /// ACK and drain a command the decoder does not handle, then report it, so the
+    /// next header is read in sync.
+    pub fn reject_command(&mut self, length: u16) {
+        let _ = self.write_ack();
+        self.discard_body(length);
+        self.write_log(LogLevel::Warn, "Error: Unsupported command\n");
+        let _ = self.write_error(ErrorCode::UnknownCommand);
+    }
 }
 
 /// Reads an ACK packet. Returns 0 on success, -1 on error.
@@ -71,7 +693,7 @@ pub fn read_ack<U: UartHalOps>(console: &mut U) -> i32 {
         byte = console.read_byte();
     }
     let cmd = console.read_byte();
-    if cmd != MsgType::Ack as u8 {
+    if cmd != u8::from(MsgType::Ack) {
         return -1;
     }
     // Skip the 2-byte length.
@@ -83,13 +705,60 @@ pub fn read_ack<U: UartHalOps>(console: &mut U) -> i32 {
 /// Writes an ACK packet.
 #[inline(always)]
 pub fn write_ack<U: UartHalOps>(console: &mut U) -> i32 {
-    let ack = [MSG_MAGIC, MsgType::Ack as u8, 0, 0];
-    for &b in &ack {
+    for &b in bytemuck::bytes_of(&MessageHeader::new(MsgType::Ack, 0)) {
         console.write_byte(b);
     }
     0
 }
 
+/// Asks the host to resend the body chunk it just sent (`chunk-crc` feature).
+#[cfg(feature = "chunk-crc")]
+pub fn write_nack<U: UartHalOps>(console: &mut U) -> i32 {
+    for &b in bytemuck::bytes_of(&MessageHeader::new(MsgType::Nack, 0)) {
+        console.write_byte(b);
+    }
+    0
+}
+
+/// Writes a complete packet: header, ACK handshake, and body in `CHUNK_SIZE` chunks.
+///
+/// The magic and length are filled in from `msg_type` and `body`. Debug and ACK
+/// packets are not acknowledged by the host, so no handshake is performed for them.
+/// Returns 0 on success, -1 if the host does not ACK or the body is longer than a u16
+/// length can describe (nothing is sent then).
+#[inline(always)]
+pub fn write_packet<U: UartHalOps>(console: &mut U, msg_type: MsgType, body: Option<&[u8]>) -> i32 {
+    let body = body.unwrap_or(&[]);
+    let needs_ack = !matches!(msg_type, MsgType::Debug | MsgType::Ack | MsgType::Ping);
+
+    // A truncated length would desync the host, so oversized bodies are never sent
+    let Ok(length) = u16::try_from(body.len()) else {
+        return -1;
+    };
+    write_header(console, msg_type, length);
+    if needs_ack && read_ack(console) != 0 {
+        return -1;
+    }
+
+    for chunk in body.chunks(CHUNK_SIZE) {
+        for &b in chunk {
+            console.write_byte(b);
+        }
+        if needs_ack && read_ack(console) != 0 {
+            return -1;
+        }
+    }
+    0
+}
+
+/// Writes only a packet header, without waiting for the ACK.
+pub fn write_header<U: UartHalOps>(console: &mut U, msg_type: MsgType, length: u16) {
+    let header = MessageHeader::new(msg_type, length);
+    for &b in bytemuck::bytes_of(&header) {
+        console.write_byte(b);
+    }
+}
+
 /// Reads a message header from UART.
 #[inline(always)]
 pub fn read_header<U: UartHalOps>(console: &mut U) -> MessageHeader {
@@ -103,102 +772,163 @@ pub fn read_header<U: UartHalOps>(console: &mut U) -> MessageHeader {
     MessageHeader {
         magic: MSG_MAGIC,
         opcode,
-        length: u16::from_le_bytes([b0, b1]),
+        length: read_u16_le(&[b0, b1], 0),
     }
 }
 
-/// Reads the message body in 256-byte chunks.
-/// Acknowledges each chunk. Returns the filled MessageBody.
+/// Reads the message body in `CHUNK_SIZE` chunks into the caller-provided `body`.
+/// Acknowledges each chunk. The buffer is filled in place so the 4 KB body
+/// is never copied across the call boundary.
+/// Returns the number of bytes read, which `body.length` is set to as well, so a
+/// caller can tell a body that came up short. `read_byte` blocks until each byte
+/// arrives, so here that is always `length`.
 #[inline(always)]
-pub fn read_body<U: UartHalOps>(console: &mut U, length: u16) -> MessageBody {
-    let mut body = MessageBody::zeroed();
+pub fn read_body<U: UartHalOps>(console: &mut U, length: u16, body: &mut MessageBody) -> u16 {
     let total = length as usize;
     let mut offset = 0;
-    let mut chunk = [0u8; 256];
     while offset < total {
-        let chunk_size = core::cmp::min(256, total - offset);
-        for i in 0..chunk_size {
-            chunk[i] = console.read_byte();
+        let chunk_size = core::cmp::min(CHUNK_SIZE, total - offset);
+        for b in body.data[offset..offset + chunk_size].iter_mut() {
+            *b = console.read_byte();
         }
-        body.data[offset..offset + chunk_size].copy_from_slice(&chunk[..chunk_size]);
         offset += chunk_size;
         let _ = write_ack(console);
     }
     body.length = length;
-    body
+    length
+}
+
+/// Reads and discards a message body of `length` bytes, ACKing each chunk as
+/// `read_body` would so the host stays in sync after a rejected command.
+#[inline(always)]
+pub fn discard_body<U: UartHalOps>(console: &mut U, length: u16) {
+    let mut remaining = length as usize;
+    while remaining > 0 {
+        let chunk_size = core::cmp::min(CHUNK_SIZE, remaining);
+        for _ in 0..chunk_size {
+            let _ = console.read_byte();
+        }
+        remaining -= chunk_size;
+        let _ = write_ack(console);
+    }
+}
+
+/// Reports `received` of `total` body bytes to the host as an Info Debug packet. It
+/// goes to the host UART even when a debug sink is attached, as host tooling asked for it.
+fn write_progress<U: UartHalOps>(console: &mut U, received: usize, total: usize) {
+    // Asked for with its own feature, so sent with or without `debug-output`
+    send_log_packet(console, LogLevel::Info, format_args!("progress {}/{}\n", received, total));
 }
 
 /// Writes a debug message. (Debug messages do not require ACKs.)
 #[inline(always)]
 pub fn write_debug<U: UartHalOps>(console: &mut U, msg: &str) {
-    let bytes = msg.as_bytes();
-    let header = MessageHeader {
-        magic: MSG_MAGIC,
-        opcode: MsgType::Debug as u8,
-        length: bytes.len() as u16,
-    };
-    let hdr_bytes = bytemuck::bytes_of(&header);
-    for &b in hdr_bytes {
-        console.write_byte(b);
-    }
-    for &b in bytes {
-        console.write_byte(b);
+    write_log(console, LogLevel::Info, msg)
+}
+
+/// Fixed-capacity buffer used to format debug messages without allocation.
+/// Output beyond the capacity is silently truncated.
+struct DebugBuffer {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl fmt::Write for DebugBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = core::cmp::min(s.len(), self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
     }
 }
 
-/// Writes a ChannelInfo structure.
-#[inline(always)]
-pub fn write_channel<U: UartHalOps>(console: &mut U, channel: &ChannelInfo) -> i32 {
-    let bytes = bytemuck::bytes_of(channel);
-    for &b in bytes {
-        console.write_byte(b);
+/// Writes a formatted debug message, e.g. to log an error's `Display` text.
+pub fn write_debug_fmt<U: UartHalOps>(console: &mut U, args: fmt::Arguments) {
+    write_log_fmt(console, LogLevel::Info, args)
+}
+
+/// Writes a Debug packet whose body is the `level` byte followed by `msg`. Messages
+/// longer than the 127 bytes left after the level byte are truncated.
+pub fn write_log<U: UartHalOps>(console: &mut U, level: LogLevel, msg: &str) {
+    write_log_fmt(console, level, format_args!("{}", msg))
+}
+
+/// Writes a formatted Debug packet prefixed with the `level` byte.
+pub fn write_log_fmt<U: UartHalOps>(console: &mut U, level: LogLevel, args: fmt::Arguments) {
+    if !level.enabled() {
+        return;
     }
-    0
+    send_log_packet(console, level, args);
 }
 
+fn send_log_packet<U: UartHalOps>(console: &mut U, level: LogLevel, args: fmt::Arguments) {
+    let mut msg = DebugBuffer { buf: [0; 128], len: 1 };
+    msg.buf[0] = level as u8;
+    let _ = fmt::write(&mut msg, args);
+    let _ = write_packet(console, MsgType::Debug, Some(&msg.buf[..msg.len]));
+}
+
+/// Largest List body: the channel count and one ChannelInfo per subscription page.
+pub const LIST_MAX_LEN: usize = size_of::<u32>() + MAX_CHANNELS * size_of::<ChannelInfo>();
+
+// The List body length must fit the u16 header field for every MAX_CHANNELS.
+const _: () = assert!(LIST_MAX_LEN <= u16::MAX as usize);
+
 /// Writes a "list" message with channel information.
 #[inline(always)]
 pub fn write_list<U: UartHalOps>(console: &mut U, flash_manager: &mut FlashManager) -> i32 {
-    let mut count: u32 = 0;
-    for i in 0..8 {
-        let addr = 0x1006_2000 + (i as u32 * 0x2000);
-        if flash_manager.read_magic(addr).unwrap_or(0) == 0xABCD {
+    // Sorted by channel id, so the list does not depend on which pages hold which channel
+    let mut channels = [ChannelInfo::zeroed(); MAX_CHANNELS];
+    let mut count = 0;
+    for (_, c) in channel_subscriptions(flash_manager, false) {
+        if let Some(ch) = c {
+            channels[count] = ch;
             count += 1;
         }
     }
-    let header = MessageHeader {
-        magic: MSG_MAGIC,
-        opcode: MsgType::List as u8,
-        length: (core::mem::size_of::<u32>() + count as usize * core::mem::size_of::<ChannelInfo>())
-            as u16,
-    };
-    let hdr_bytes = bytemuck::bytes_of(&header);
-    for &b in hdr_bytes {
-        console.write_byte(b);
-    }
-    if read_ack(console) != 0 {
-        return -1;
-    }
-    // Write the channel count (u32 little-endian)
-    for &b in &count.to_le_bytes() {
-        console.write_byte(b);
+    let channels = &mut channels[..count];
+    channels.sort_unstable_by_key(|ch| ch.channel_id);
+
+    // Body: channel count (u32 little-endian) followed by each ChannelInfo.
+    let mut list = [0u8; LIST_MAX_LEN];
+    let mut len = size_of::<u32>();
+    for ch in channels.iter() {
+        list[len..len + size_of::<ChannelInfo>()].copy_from_slice(bytemuck::bytes_of(ch));
+        len += size_of::<ChannelInfo>();
     }
-    for i in 0..count {
-        let addr = 0x1006_2000 + (i as u32 * 0x2000);
-        let ch = read_channel(flash_manager, addr).unwrap();
-        if write_channel(console, &ch) != 0 {
-            return -1;
-        }
+    list[..4].copy_from_slice(&(count as u32).to_le_bytes());
+    write_packet(console, MsgType::List, Some(&list[..len]))
+}
+
+/// Writes a Version message carrying the build's `FIRMWARE_COMMIT`.
+pub fn write_version<U: UartHalOps>(console: &mut U) -> i32 {
+    write_packet(console, MsgType::Version, Some(FIRMWARE_COMMIT.as_bytes()))
+}
+
+/// Writes a DecoderId message carrying the compiled-in `DECODER_ID`, little-endian.
+pub fn write_decoder_id<U: UartHalOps>(console: &mut U) -> i32 {
+    write_packet(console, MsgType::DecoderId, Some(&DECODER_ID.to_le_bytes()))
+}
+
+/// Writes a FlashLayout message: base address, page size and subscription capacity of
+/// this build, then where the subscription pages are split, each a u32 LE.
+pub fn write_flash_layout<U: UartHalOps>(console: &mut U) -> i32 {
+    write_packet(console, MsgType::FlashLayout, Some(bytemuck::bytes_of(&FLASH_LAYOUT)))
+}
+
+/// Answers a Decode body by echoing its frame's encrypted content, without checking the
+/// signature or subscription or decrypting anything (`decode-passthrough` feature). A
+/// body that is not exactly one frame is answered with InvalidFrameLength.
+#[cfg(feature = "decode-passthrough")]
+pub fn write_passthrough<U: UartHalOps>(console: &mut U, body: &[u8]) -> i32 {
+    match ChannelFrame::from_le_bytes(body) {
+        Some(frame) => write_packet(console, MsgType::Decode, Some(&{ frame.encrypted_content })),
+        None => write_error(console, ErrorCode::InvalidFrameLength),
     }
-    0
 }
 
-/// Writes an error message.
+/// Writes an error message carrying `code` as its one-byte body.
 #[inline(always)]
-pub fn write_error<U: UartHalOps>(console: &mut U) -> i32 {
-    let err = [MSG_MAGIC, MsgType::Error as u8, 0, 0];
-    for &b in &err {
-        console.write_byte(b);
-    }
-    0
+pub fn write_error<U: UartHalOps>(console: &mut U, code: ErrorCode) -> i32 {
+    write_packet(console, MsgType::Error, Some(&[code as u8]))
 }
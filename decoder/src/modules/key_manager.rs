@@ -0,0 +1,134 @@
+//! Rotatable device key used to decrypt subscription passwords (`rekey` feature).
+//!
+//! The key starts as the build-time `DECODER_KEY`. Each signed Rekey command derives
+//! the next key as HKDF-SHA512(current key, context) and stores it in the key page.
+//! Stored subscriptions hold decrypted passwords, so nothing in flash needs
+//! re-encrypting; only subscriptions received after the rotation use the new key.
+use crate::modules::constants::KEY_ADDRESS;
+use crate::modules::flash_manager::{FlashManager, FlashManagerError};
+use crate::modules::host_keys::verify_host_signature;
+use crate::modules::wire::read_u32_le;
+use crate::{DECODER_ID, DECODER_KEY, KEY_LEN};
+use bytemuck::{Pod, Zeroable};
+use core::fmt;
+use ed25519_dalek::Signature;
+use hkdf::Hkdf;
+use sha2::Sha512;
+
+/// Magic marking a written key record.
+const KEY_MAGIC: u32 = 0x4B45_5931;
+
+/// Domain label prefixed to the signed rekey message.
+const REKEY_LABEL: &[u8] = b"ectf25-rekey";
+/// Length of the derivation context supplied by the host.
+const CONTEXT_LEN: usize = 32;
+/// Rekey body: generation (u32 LE) || context (32 bytes) || signature (64 bytes).
+pub const REKEY_BODY_LEN: usize = 4 + CONTEXT_LEN + 64;
+/// Signed message: label || decoder id (u32 LE) || generation (u32 LE) || context.
+const REKEY_MSG_LEN: usize = REKEY_LABEL.len() + 4 + 4 + CONTEXT_LEN;
+
+#[derive(Debug)]
+pub enum KeyError {
+    InvalidKey,
+    InvalidSignature,
+    /// The command is not for the generation following the current key.
+    InvalidGeneration,
+    FlashManagerError(FlashManagerError),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::InvalidKey => f.write_str("invalid host key"),
+            KeyError::InvalidSignature => f.write_str("invalid signature"),
+            KeyError::InvalidGeneration => f.write_str("unexpected key generation"),
+            KeyError::FlashManagerError(e) => write!(f, "flash: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for KeyError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            KeyError::FlashManagerError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FlashManagerError> for KeyError {
+    fn from(e: FlashManagerError) -> Self {
+        KeyError::FlashManagerError(e)
+    }
+}
+
+/// Device key as stored in flash.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct KeyRecord {
+    generation: u32,
+    key: [u8; KEY_LEN],
+}
+
+pub struct DeviceKey {
+    generation: u32,
+    key: [u8; KEY_LEN],
+}
+
+impl DeviceKey {
+    /// Load the rotated key, or the build-time key (generation 0) if none is stored.
+    ///
+    /// A corrupt record also falls back to generation 0. Key derivation is
+    /// deterministic, so the host recovers by replaying its rekey commands in order.
+    pub fn load(flash_manager: &mut FlashManager) -> Self {
+        let record = match flash_manager.read_magic(KEY_ADDRESS) {
+            Ok(KEY_MAGIC) => flash_manager.read_data_verified::<KeyRecord>(KEY_ADDRESS).ok(),
+            _ => None,
+        };
+        match record {
+            Some(record) => DeviceKey { generation: record.generation, key: record.key },
+            None => DeviceKey { generation: 0, key: DECODER_KEY },
+        }
+    }
+
+    /// Key that decrypts incoming subscription passwords.
+    pub fn key(&self) -> &[u8; KEY_LEN] {
+        &self.key
+    }
+
+    /// Rotate to the next key from a Rekey body signed by the host key. Returns the new
+    /// generation.
+    pub fn rekey_signed(&mut self, flash_manager: &mut FlashManager, body: &[u8]) -> Result<u32, KeyError> {
+        let generation = read_u32_le(body, 0);
+        let context = &body[4..4 + CONTEXT_LEN];
+        let sig = Signature::from_slice(&body[4 + CONTEXT_LEN..REKEY_BODY_LEN]).map_err(|_| KeyError::InvalidSignature)?;
+
+        let mut message = [0u8; REKEY_MSG_LEN];
+        message[..REKEY_LABEL.len()].copy_from_slice(REKEY_LABEL);
+        message[REKEY_LABEL.len()..REKEY_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+        message[REKEY_LABEL.len() + 4..REKEY_LABEL.len() + 8].copy_from_slice(&generation.to_le_bytes());
+        message[REKEY_LABEL.len() + 8..].copy_from_slice(context);
+
+        if !verify_host_signature(&message, &sig).map_err(|_| KeyError::InvalidKey)? {
+            return Err(KeyError::InvalidSignature);
+        }
+
+        // Only the next generation is accepted, so a recorded command cannot be replayed
+        if generation != self.generation.wrapping_add(1) {
+            return Err(KeyError::InvalidGeneration);
+        }
+
+        let mut key = [0u8; KEY_LEN];
+        Hkdf::<Sha512>::new(None, &self.key)
+            .expand(context, &mut key)
+            .expect("KEY_LEN is a valid HKDF-SHA512 output length");
+
+        let record = KeyRecord { generation, key };
+        flash_manager.wipe_data(KEY_ADDRESS)?;
+        flash_manager.write_data(KEY_ADDRESS, KEY_MAGIC, &record)?;
+
+        self.generation = generation;
+        self.key = key;
+        Ok(generation)
+    }
+}
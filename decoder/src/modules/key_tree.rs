@@ -0,0 +1,34 @@
+//! Key tree derivation shared by the decoder and build.rs, which checks the embedded
+//! channel 0 password against it. Depends on nothing but `md5`, so it builds both in
+//! the firmware and on the host.
+use md5::{Digest, Md5};
+
+/// Domain separation label for deriving a child node key from its parent.
+pub const CHILD_KEY_LABEL: &[u8] = b"ectf25-child";
+/// Domain separation label for expanding a 16-byte leaf key to a 32-byte cipher key.
+pub const EXTEND_KEY_LABEL: &[u8] = b"ectf25-extend";
+
+/// Derives the key of child `node_num` from its parent's key.
+///
+/// The input is `CHILD_KEY_LABEL || parent || branch || node_num (16 bytes, LE)`,
+/// where `branch` is `'L'` or `'R'`. Including the child's level-order number binds
+/// the derivation to its depth and index, so no two nodes share a hash input.
+pub fn derive_child_key(parent: &[u8; 16], branch: u8, node_num: u128) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(CHILD_KEY_LABEL);
+    hasher.update(parent);
+    hasher.update([if branch == 1 { b'L' } else { b'R' }]);
+    hasher.update(node_num.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Extends a 16-byte leaf key to 32 bytes as `key || MD5(EXTEND_KEY_LABEL || key)`.
+pub fn extend_key(key: &[u8; 16]) -> [u8; 32] {
+    let mut extended: [u8; 32] = [0; 32];
+    extended[..16].copy_from_slice(key);
+    let mut hasher = Md5::new();
+    hasher.update(EXTEND_KEY_LABEL);
+    hasher.update(key);
+    extended[16..].copy_from_slice(&hasher.finalize());
+    extended
+}
@@ -0,0 +1,192 @@
+//! RAM stand-in for the HAL flash controller (`std` feature), for host tests.
+//!
+//! It keeps the HAL's rules: 128-bit aligned reads and writes inside the MAX78000
+//! flash, and page erases setting every byte to 0xFF. A write is only taken into a
+//! word that is still erased; any other fails with the HAL's `NeedsErase`, even one
+//! that would only clear bits, as nothing documents the controller programming a word
+//! twice between erases (see `FLASH_WORD_SIZE`).
+//! Clones share the same memory, so a test can build a second `FlashManager` over the
+//! flash a first one wrote, as a decoder does after a reset.
+use crate::hal::flc::{FlashError, FLASH_BASE, FLASH_SIZE};
+use crate::modules::constants::PAGE_SIZE;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec;
+use std::vec::Vec;
+
+#[derive(Clone)]
+pub struct MockFlc {
+    flash: Rc<RefCell<MockFlash>>,
+}
+
+struct MockFlash {
+    bytes: Vec<u8>,
+    /// 128-bit writes still allowed before every write fails, as after a power loss.
+    writes_left: Option<u32>,
+    /// `is_busy` polls still to answer busy, as during a write or erase.
+    busy_polls: u32,
+    /// Word address whose reads fail, and how many more times they do, as an ECC fault.
+    read_fault: Option<(u32, u32)>,
+    /// Writes and erases still to fail once each, as while the controller is locked.
+    transient_faults: u32,
+    /// Writes and erases attempted, failed ones included.
+    attempts: u32,
+    writes: u32,
+    erases: u32,
+    reads: u32,
+}
+
+impl Default for MockFlc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockFlc {
+    /// Fully erased flash.
+    pub fn new() -> Self {
+        MockFlc {
+            flash: Rc::new(RefCell::new(MockFlash {
+                bytes: vec![0xFF; FLASH_SIZE as usize],
+                writes_left: None,
+                busy_polls: 0,
+                read_fault: None,
+                transient_faults: 0,
+                attempts: 0,
+                writes: 0,
+                erases: 0,
+                reads: 0,
+            })),
+        }
+    }
+
+    fn offset(address: u32, len: u32) -> Result<usize, FlashError> {
+        if !address.is_multiple_of(16) || address < FLASH_BASE || address + len > FLASH_BASE + FLASH_SIZE {
+            return Err(FlashError::InvalidAddress);
+        }
+        Ok((address - FLASH_BASE) as usize)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        let mut flash = self.flash.borrow_mut();
+        let busy = flash.busy_polls > 0;
+        flash.busy_polls = flash.busy_polls.saturating_sub(1);
+        busy
+    }
+
+    pub fn read_128(&self, address: u32) -> Result<[u32; 4], FlashError> {
+        let offset = Self::offset(address, 16)?;
+        let mut flash = self.flash.borrow_mut();
+        flash.reads += 1;
+        if let Some((fault_address, ref mut left)) = flash.read_fault {
+            if fault_address == address && *left > 0 {
+                *left -= 1;
+                return Err(FlashError::AccessViolation);
+            }
+        }
+        let mut chunk = [0u8; 16];
+        chunk.copy_from_slice(&flash.bytes[offset..offset + 16]);
+        Ok(bytemuck::cast(chunk))
+    }
+
+    pub fn write_128(&self, address: u32, data: &[u32; 4]) -> Result<(), FlashError> {
+        let mut flash = self.flash.borrow_mut();
+        flash.attempts += 1;
+        let offset = Self::offset(address, 16)?;
+        flash.transient_fault()?;
+        match flash.writes_left {
+            Some(0) => return Err(FlashError::AccessViolation),
+            Some(ref mut left) => *left -= 1,
+            None => {}
+        }
+        let new: [u8; 16] = bytemuck::cast(*data);
+        let old = &mut flash.bytes[offset..offset + 16];
+        if old.iter().any(|&o| o != 0xFF) {
+            return Err(FlashError::NeedsErase);
+        }
+        old.copy_from_slice(&new);
+        flash.writes += 1;
+        Ok(())
+    }
+
+    /// # Safety
+    /// Mirrors the HAL signature; erasing RAM is always safe.
+    pub unsafe fn erase_page(&self, address: u32) -> Result<(), FlashError> {
+        let mut flash = self.flash.borrow_mut();
+        flash.attempts += 1;
+        let offset = Self::offset(address & !(PAGE_SIZE - 1), PAGE_SIZE)?;
+        flash.transient_fault()?;
+        if flash.writes_left == Some(0) {
+            return Err(FlashError::AccessViolation);
+        }
+        flash.bytes[offset..offset + PAGE_SIZE as usize].fill(0xFF);
+        flash.erases += 1;
+        Ok(())
+    }
+
+    /// Let `writes` more 128-bit writes through, then fail every write and erase with
+    /// `AccessViolation`, leaving a record torn where the power would have gone.
+    pub fn fail_after_writes(&self, writes: u32) {
+        self.flash.borrow_mut().writes_left = Some(writes);
+    }
+
+    /// Fail the next `count` reads of the 128-bit word at `address` with
+    /// `AccessViolation`, as the HAL reports an uncorrectable ECC error.
+    pub fn fail_reads(&self, address: u32, count: u32) {
+        self.flash.borrow_mut().read_fault = Some((address, count));
+    }
+
+    /// Fail the next `count` writes and erases with `AccessViolation`, each once, as a
+    /// controller that is busy or locked for a moment and then takes the same call.
+    pub fn fail_next_ops(&self, count: u32) {
+        self.flash.borrow_mut().transient_faults = count;
+    }
+
+    /// Report the controller busy for the next `polls` calls to `is_busy`.
+    pub fn hold_busy(&self, polls: u32) {
+        self.flash.borrow_mut().busy_polls = polls;
+    }
+
+    /// Let writes and erases succeed again, as after the next power-up.
+    pub fn clear_failures(&self) {
+        let mut flash = self.flash.borrow_mut();
+        flash.writes_left = None;
+        flash.transient_faults = 0;
+    }
+
+    /// Flip every bit of the byte at `address`, e.g. to break a record's CRC.
+    pub fn corrupt_byte(&self, address: u32) {
+        self.flash.borrow_mut().bytes[(address - FLASH_BASE) as usize] ^= 0xFF;
+    }
+
+    /// Successful 128-bit writes so far.
+    pub fn write_count(&self) -> u32 {
+        self.flash.borrow().writes
+    }
+
+    /// Successful page erases so far.
+    pub fn erase_count(&self) -> u32 {
+        self.flash.borrow().erases
+    }
+
+    /// 128-bit writes and page erases attempted so far, failed ones included.
+    pub fn attempt_count(&self) -> u32 {
+        self.flash.borrow().attempts
+    }
+
+    /// 128-bit reads so far.
+    pub fn read_count(&self) -> u32 {
+        self.flash.borrow().reads
+    }
+}
+
+impl MockFlash {
+    /// Take one of the faults `fail_next_ops` left, if any remain.
+    fn transient_fault(&mut self) -> Result<(), FlashError> {
+        if self.transient_faults == 0 {
+            return Ok(());
+        }
+        self.transient_faults -= 1;
+        Err(FlashError::AccessViolation)
+    }
+}
@@ -1,4 +1,34 @@
 pub mod channel_manager;
+#[cfg(feature = "rtc-time")]
+pub mod clock;
+pub mod crc;
+#[cfg(feature = "dma-uart")]
+pub mod dma_uart;
+pub mod emergency_manager;
+pub mod entropy;
 pub mod flash_manager;
+pub mod frame_sink;
+pub mod host_keys;
 pub mod hostcom_manager;
+#[cfg(feature = "rekey")]
+pub mod key_manager;
+pub mod key_tree;
+#[cfg(feature = "std")]
+pub mod mock_flash;
+pub mod rate_limiter;
+pub mod reset_cause;
+#[cfg(feature = "soft-reset")]
+pub mod reset;
+pub mod state_manager;
+pub mod subscribe_handshake;
+#[cfg(feature = "brownout")]
+pub mod supply_monitor;
+pub mod tamper_manager;
+pub mod telemetry;
+#[cfg(feature = "std")]
+pub mod test_vectors;
+pub mod time_source;
+#[cfg(feature = "resumable-upload")]
+pub mod upload_manager;
+pub mod wire;
 pub mod constants;
\ No newline at end of file
@@ -0,0 +1,96 @@
+use crate::modules::channel_manager::SubscriptionError;
+use cortex_m::delay::Delay;
+
+/// Consecutive signature failures tolerated before commands are delayed.
+pub const FAILURES_BEFORE_DELAY: u32 = 3;
+/// Delay after the first failure past the threshold; it doubles with each further one.
+pub const BASE_DELAY_MS: u32 = 250;
+/// Upper bound on the delay.
+pub const MAX_DELAY_MS: u32 = 5000;
+
+/// Something that can block for a number of milliseconds: the SysTick delay on the
+/// decoder, `MockDelay` in host tests.
+pub trait DelayMs {
+    fn delay_ms(&mut self, ms: u32);
+}
+
+impl DelayMs for Delay {
+    fn delay_ms(&mut self, ms: u32) {
+        Delay::delay_ms(self, ms);
+    }
+}
+
+/// Delay that only adds up the time it was asked to block for, so a test can check
+/// the throttle without waiting on it.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct MockDelay {
+    slept_ms: u64,
+}
+
+#[cfg(feature = "std")]
+impl MockDelay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total milliseconds blocked for so far.
+    pub fn slept_ms(&self) -> u64 {
+        self.slept_ms
+    }
+}
+
+#[cfg(feature = "std")]
+impl DelayMs for MockDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.slept_ms += ms as u64;
+    }
+}
+
+/// Slows down a host that keeps sending badly signed subscriptions or frames, so the
+/// decoder cannot be driven through Ed25519 verifications at full speed. A correctly
+/// signed message resets it.
+pub struct RateLimiter<D: DelayMs> {
+    delay: D,
+    consecutive_failures: u32,
+}
+
+impl<D: DelayMs> RateLimiter<D> {
+    pub fn new(delay: D) -> Self {
+        RateLimiter { delay, consecutive_failures: 0 }
+    }
+
+    /// Update the failure count from the outcome of a signed command.
+    pub fn record<T>(&mut self, result: &Result<T, SubscriptionError>) {
+        match result {
+            Ok(_) => self.consecutive_failures = 0,
+            Err(SubscriptionError::InvalidSignature) => {
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+            }
+            // Other failures say nothing about the signature
+            Err(_) => {}
+        }
+    }
+
+    /// Delay owed before the next command, in milliseconds.
+    pub fn current_delay_ms(&self) -> u32 {
+        if self.consecutive_failures < FAILURES_BEFORE_DELAY {
+            return 0;
+        }
+        let doublings = core::cmp::min(self.consecutive_failures - FAILURES_BEFORE_DELAY, 31);
+        core::cmp::min(BASE_DELAY_MS.saturating_mul(1 << doublings), MAX_DELAY_MS)
+    }
+
+    /// Block for the owed delay, if any.
+    pub fn throttle(&mut self) {
+        let ms = self.current_delay_ms();
+        if ms > 0 {
+            self.delay.delay_ms(ms);
+        }
+    }
+
+    /// The delay the limiter blocks with.
+    pub fn delay(&self) -> &D {
+        &self.delay
+    }
+}
@@ -0,0 +1,39 @@
+//! Software reset on host request (`soft-reset` feature), so test automation can
+//! return the decoder to its boot state without a power cycle.
+use crate::modules::flash_manager::FlashManager;
+use crate::modules::hostcom_manager::{HostConsole, UartHalOps};
+
+/// Resets the core. The firmware's reset never returns; a host test's records the
+/// call and does.
+pub trait SystemReset {
+    fn reset(&mut self);
+}
+
+/// System reset through the Cortex-M SCB, as the reset pin would.
+#[cfg(not(feature = "std"))]
+pub struct ScbReset;
+
+#[cfg(not(feature = "std"))]
+impl SystemReset for ScbReset {
+    fn reset(&mut self) {
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+}
+
+/// Answer a Reset command of `length` body bytes and reset the decoder.
+///
+/// The command is ACKed and any body dropped. Flash writes and erases block until
+/// they finish, but the controller is still polled until idle so a reset can never
+/// land in the middle of one, and the UART is drained so the host sees the ACK.
+pub fn handle_reset<U: UartHalOps, D: UartHalOps, R: SystemReset>(
+    console: &mut HostConsole<U, D>,
+    length: u16,
+    flash_manager: &mut FlashManager,
+    reset: &mut R,
+) {
+    let _ = console.write_ack();
+    console.discard_body(length);
+    flash_manager.wait_idle();
+    console.flush();
+    reset.reset();
+}
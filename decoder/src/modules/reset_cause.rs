@@ -0,0 +1,54 @@
+//! Why the decoder last reset, read once at startup and reported by Telemetry.
+//!
+//! The MAX78000 latches a reset flag in each watchdog's control register and in no
+//! other place, so a power-on, the reset pin, a brownout and a system reset cannot be
+//! told apart and all read as `PowerOn`. A watchdog reset after a hang, or after a
+//! glitch sent execution astray, stands out from them.
+#[cfg(not(feature = "std"))]
+use crate::pac;
+
+/// Reset flags latched by the hardware, as found at startup.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResetFlags {
+    /// A watchdog ran past its reset period (CTRL.RST_LATE of either WDT).
+    pub watchdog_late: bool,
+    /// A windowed watchdog was fed before its window opened (CTRL.RST_EARLY).
+    pub watchdog_early: bool,
+}
+
+/// Cause of the last reset, sent as a u32 LE in the Telemetry response.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetReason {
+    /// No flag set: power-on, reset pin, brownout or system reset.
+    PowerOn = 0,
+    WatchdogTimeout = 1,
+    WatchdogEarlyFeed = 2,
+}
+
+impl ResetReason {
+    /// A late reset wins if both flags are set, since the watchdog fired either way.
+    pub fn from_flags(flags: ResetFlags) -> Self {
+        if flags.watchdog_late {
+            ResetReason::WatchdogTimeout
+        } else if flags.watchdog_early {
+            ResetReason::WatchdogEarlyFeed
+        } else {
+            ResetReason::PowerOn
+        }
+    }
+}
+
+/// Read and clear the reset flags of both watchdogs, so the next reset is not
+/// blamed on this one's cause.
+#[cfg(not(feature = "std"))]
+pub fn take_reset_flags(wdts: [&pac::wdt::RegisterBlock; 2]) -> ResetFlags {
+    let mut flags = ResetFlags::default();
+    for wdt in wdts {
+        let ctrl = wdt.ctrl().read();
+        flags.watchdog_late |= ctrl.rst_late().bit_is_set();
+        flags.watchdog_early |= ctrl.rst_early().bit_is_set();
+        wdt.ctrl().modify(|_, w| w.rst_late().clear_bit().rst_early().clear_bit());
+    }
+    flags
+}
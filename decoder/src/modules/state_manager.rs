@@ -0,0 +1,190 @@
+use crate::modules::channel_manager::{ActiveChannelsList, ACTIVE_CHANNELS_LEN};
+use crate::modules::constants::{ERASED_MAGIC, PAGE_SIZE, STATE_BASE_ADDRESS, STATE_PAGES};
+use crate::modules::flash_manager::{FlashManager, FlashManagerError};
+use bytemuck::{Pod, Zeroable};
+use core::mem::size_of;
+
+/// Magic marking a written channel state record.
+const STATE_MAGIC: u32 = 0x5157_A7E1;
+
+/// Flash footprint of one record: magic + record + CRC, rounded up to the 16-byte write size.
+const SLOT_SIZE: u32 = ((4 + size_of::<ChannelStateRecord>() + 4) as u32).div_ceil(16) * 16;
+/// Number of records that fit in one state page.
+const SLOTS_PER_PAGE: u32 = PAGE_SIZE / SLOT_SIZE;
+
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct PersistedChannel {
+    pub channel_id: u32,
+    pub last_frame: u64,
+    pub received: u8,  // 0 = no frame received yet, 1 = last_frame is valid
+    pub active: u8,    // 0 = empty active slot
+    pub decode_count: u32,
+}
+
+/// Carry each channel's decode count across reboots in the state log. Off, the counts
+/// are written as 0 and start from 0 at every boot.
+pub const PERSIST_DECODE_COUNTS: bool = true;
+
+/// Snapshot of the replay-protection state of every active channel.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ChannelStateRecord {
+    pub generation: u32,
+    /// Number of boots recorded so far, never decreasing.
+    pub boot_count: u32,
+    pub channels: [PersistedChannel; ACTIVE_CHANNELS_LEN],
+}
+
+/// Crash-consistent store for the active channels' `last_frame` and decode counters.
+///
+/// Records are appended to a log spread over two flash pages (ping-pong). Every save
+/// writes a new record with the next generation number into a fresh slot, and a page
+/// is only erased when the log moves onto it, so the previous good record always
+/// survives until a newer one is fully written. Each record carries a CRC; on boot the
+/// valid record with the highest generation wins and torn writes are ignored.
+pub struct StateManager {
+    generation: u32,
+    boot_count: u32,
+    page: u32,
+    slot: u32,
+}
+
+/// Whether a record of generation `candidate` was written after one of `current`.
+///
+/// Generations wrap, so they are compared as serial numbers: the one less than half
+/// the range ahead is newer. Both state pages together hold far fewer records than
+/// that, so a wrapped generation 0 still beats u32::MAX and the log never falls back
+/// to replay counters from before the wrap.
+pub fn generation_is_newer(candidate: u32, current: u32) -> bool {
+    (candidate.wrapping_sub(current) as i32) > 0
+}
+
+fn slot_addr(page: u32, slot: u32) -> u32 {
+    STATE_BASE_ADDRESS + page * PAGE_SIZE + slot * SLOT_SIZE
+}
+
+impl StateManager {
+    /// Scan both state pages, restore the newest valid record into `active_channels`
+    /// and position the log after it.
+    pub fn load(flash_manager: &mut FlashManager, active_channels: &mut ActiveChannelsList) -> Self {
+        let mut best: Option<(u32, u32, ChannelStateRecord)> = None;
+
+        for page in 0..STATE_PAGES {
+            for slot in 0..SLOTS_PER_PAGE {
+                let addr = slot_addr(page, slot);
+                match flash_manager.read_magic(addr) {
+                    Ok(STATE_MAGIC) => {}
+                    // Erased slot: the rest of this page's log is empty
+                    Ok(ERASED_MAGIC) => break,
+                    _ => continue,
+                }
+                // Torn or corrupt records fail the CRC check and are skipped
+                if let Ok(record) = flash_manager.read_data_verified::<ChannelStateRecord>(addr) {
+                    let newer = match &best {
+                        Some((_, _, b)) => generation_is_newer(record.generation, b.generation),
+                        None => true,
+                    };
+                    if newer {
+                        best = Some((page, slot, record));
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((page, slot, record)) => {
+                restore(&record, active_channels);
+                StateManager { generation: record.generation, boot_count: record.boot_count, page, slot: slot + 1 }
+            }
+            // No record yet: the first save erases page 0 and starts the log there
+            None => StateManager { generation: 0, boot_count: 0, page: STATE_PAGES - 1, slot: SLOTS_PER_PAGE },
+        }
+    }
+
+    /// Boots recorded so far, the current one included once `record_boot` succeeds.
+    pub fn boot_count(&self) -> u32 {
+        self.boot_count
+    }
+
+    /// Count this boot in a new record and return the boot count.
+    ///
+    /// The count travels with the channel state, so it is as crash-safe as the rest of
+    /// the log: a torn write leaves the previous count, which is never higher.
+    pub fn record_boot(
+        &mut self,
+        flash_manager: &mut FlashManager,
+        active_channels: &ActiveChannelsList,
+    ) -> Result<u32, FlashManagerError> {
+        let previous = self.boot_count;
+        self.boot_count = previous.saturating_add(1);
+        if let Err(e) = self.save(flash_manager, active_channels) {
+            self.boot_count = previous;
+            return Err(e);
+        }
+        Ok(self.boot_count)
+    }
+
+    /// Append a new record holding the current state of `active_channels`.
+    pub fn save(
+        &mut self,
+        flash_manager: &mut FlashManager,
+        active_channels: &ActiveChannelsList,
+    ) -> Result<(), FlashManagerError> {
+        // Skip slots left behind by a torn write, they cannot be rewritten without an erase
+        while self.slot < SLOTS_PER_PAGE
+            && flash_manager.read_magic(slot_addr(self.page, self.slot))? != ERASED_MAGIC
+        {
+            self.slot += 1;
+        }
+
+        if self.slot >= SLOTS_PER_PAGE {
+            // Move to the other page. The newest record lives on the current page, so
+            // erasing the other one never destroys the last good state.
+            self.page = (self.page + 1) % STATE_PAGES;
+            self.slot = 0;
+            flash_manager.wipe_data(slot_addr(self.page, 0))?;
+        }
+
+        let mut record = ChannelStateRecord {
+            generation: self.generation.wrapping_add(1),
+            boot_count: self.boot_count,
+            channels: [PersistedChannel::zeroed(); ACTIVE_CHANNELS_LEN],
+        };
+        for (persisted, active) in record.channels.iter_mut().zip(active_channels.iter()) {
+            if let Some(channel) = active {
+                *persisted = PersistedChannel {
+                    channel_id: channel.channel_id,
+                    last_frame: channel.last_frame,
+                    received: channel.received as u8,
+                    active: 1,
+                    decode_count: if PERSIST_DECODE_COUNTS { channel.decode_count } else { 0 },
+                };
+            }
+        }
+
+        let result = flash_manager.write_data(slot_addr(self.page, self.slot), STATE_MAGIC, &record);
+        // Never reuse this slot, even if the write failed part way
+        self.slot += 1;
+        result?;
+        self.generation = record.generation;
+        Ok(())
+    }
+}
+
+/// Copy persisted counters onto the matching active channels.
+fn restore(record: &ChannelStateRecord, active_channels: &mut ActiveChannelsList) {
+    for channel in active_channels.iter_mut().flatten() {
+        let persisted = record
+            .channels
+            .iter()
+            .find(|p| p.active != 0 && p.channel_id == channel.channel_id);
+        if let Some(p) = persisted {
+            channel.last_frame = p.last_frame;
+            channel.received = p.received != 0;
+            if PERSIST_DECODE_COUNTS {
+                channel.decode_count = p.decode_count;
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! The Subscribe exchange as explicit steps: ACK the header, read the body in ACKed
+//! chunks, verify and store it, respond, and wait for the host's ACK of the response.
+//!
+//! Each `step` makes one transition, so a test can stop the exchange anywhere and
+//! check what was sent. A refused length or subscription ends it with an Error packet;
+//! a host that stops sending or answers out of turn ends it with a resync, so the next
+//! header is read in step either way.
+use crate::modules::channel_manager::{validate_subscribe_length, SubscriptionError};
+use crate::modules::hostcom_manager::{HostConsole, LogLevel, MessageBody, MsgType, UartHalOps};
+
+/// Where a Subscribe exchange stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeState {
+    /// The header was read; the ACK inviting the body is next.
+    Header,
+    /// `received` body bytes are in and ACKed.
+    Body { received: usize },
+    /// The whole body is in and is to be verified and stored.
+    Verify,
+    /// The outcome is to be sent: a Subscribe response or an Error.
+    Respond,
+    /// The Subscribe response header is out; the host's ACK of it is next.
+    AwaitAck,
+    /// Finished in step with the host.
+    Done,
+    /// The host stopped sending or answered out of turn, and the console resynced.
+    Aborted,
+}
+
+impl SubscribeState {
+    /// No further step changes anything.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, SubscribeState::Done | SubscribeState::Aborted)
+    }
+}
+
+/// One Subscribe command, from its header to the host's last ACK.
+pub struct SubscribeHandshake {
+    length: u16,
+    state: SubscribeState,
+    outcome: Option<Result<(), SubscriptionError>>,
+}
+
+impl SubscribeHandshake {
+    /// Start the exchange for a Subscribe header announcing `length` body bytes.
+    pub fn new(length: u16) -> Self {
+        SubscribeHandshake { length, state: SubscribeState::Header, outcome: None }
+    }
+
+    pub fn state(&self) -> SubscribeState {
+        self.state
+    }
+
+    /// The result of verifying and storing the body, once that step has run.
+    pub fn outcome(&self) -> Option<&Result<(), SubscriptionError>> {
+        self.outcome.as_ref()
+    }
+
+    /// Make one transition and return the new state. `verify` checks and stores the
+    /// received body; it is only called from `Verify`.
+    pub fn step<U: UartHalOps, D: UartHalOps>(
+        &mut self,
+        console: &mut HostConsole<U, D>,
+        body: &mut MessageBody,
+        verify: &mut impl FnMut(&MessageBody) -> Result<(), SubscriptionError>,
+    ) -> SubscribeState {
+        self.state = match self.state {
+            SubscribeState::Header => {
+                let _ = console.write_ack();
+                match validate_subscribe_length(self.length) {
+                    Ok(()) => self.after_chunk(body, 0),
+                    Err(code) => {
+                        console.discard_body(self.length);
+                        console.write_log(LogLevel::Error, "Error: Subscription larger than the body buffer\n");
+                        let _ = console.write_error(code);
+                        SubscribeState::Done
+                    }
+                }
+            }
+            SubscribeState::Body { received } => match console.read_body_chunk(body, received, self.length as usize) {
+                Ok(received) => self.after_chunk(body, received),
+                Err(_) => SubscribeState::Aborted,
+            },
+            SubscribeState::Verify => {
+                self.outcome = Some(verify(body));
+                SubscribeState::Respond
+            }
+            SubscribeState::Respond => match &self.outcome {
+                Some(Err(e)) => {
+                    console.write_log_fmt(LogLevel::Error, format_args!("Failed to add subscription: {}\n", e));
+                    let _ = console.write_error(e.error_code());
+                    SubscribeState::Done
+                }
+                _ => {
+                    console.write_header(MsgType::Subscribe, 0);
+                    SubscribeState::AwaitAck
+                }
+            },
+            SubscribeState::AwaitAck => {
+                if console.read_ack() == 0 {
+                    SubscribeState::Done
+                } else {
+                    console.resync();
+                    SubscribeState::Aborted
+                }
+            }
+            finished => finished,
+        };
+        self.state
+    }
+
+    /// Step until finished, returning the outcome if the body was verified.
+    pub fn run<U: UartHalOps, D: UartHalOps>(
+        mut self,
+        console: &mut HostConsole<U, D>,
+        body: &mut MessageBody,
+        mut verify: impl FnMut(&MessageBody) -> Result<(), SubscriptionError>,
+    ) -> Option<Result<(), SubscriptionError>> {
+        while !self.step(console, body, &mut verify).is_finished() {}
+        self.outcome
+    }
+
+    fn after_chunk(&self, body: &mut MessageBody, received: usize) -> SubscribeState {
+        if received < self.length as usize {
+            return SubscribeState::Body { received };
+        }
+        body.length = self.length;
+        SubscribeState::Verify
+    }
+}
@@ -0,0 +1,91 @@
+//! Supply voltage check before flash programming (`brownout` feature).
+//!
+//! The ADC samples VDDIO/4 against the internal 1.22 V reference. FlashManager asks
+//! for a fresh sample before every erase and write and refuses to touch flash while
+//! the supply is below `VDDIO_MIN_MV`, so a sagging supply cannot leave a torn page.
+//! Host tests hand FlashManager a `MockSupplyMonitor` instead and set its voltage.
+#[cfg(feature = "std")]
+use std::{cell::Cell, rc::Rc};
+
+use crate::hal::gcr::{ClockForPeripheral, GcrRegisters};
+use crate::pac;
+
+/// Lowest VDDIO at which flash is programmed, the bottom of the MAX78000's VDDIO range.
+pub const VDDIO_MIN_MV: u32 = 1710;
+/// Internal ADC reference voltage.
+const ADC_REF_MV: u32 = 1220;
+/// Full-scale reading of the 10-bit ADC.
+const ADC_FULL_SCALE: u32 = 1023;
+/// The VDDIO channel is divided by 4 before conversion.
+const VDDIO_DIVIDER: u32 = 4;
+
+pub struct SupplyMonitor {
+    adc: pac::Adc,
+}
+
+impl SupplyMonitor {
+    /// Power up the ADC with its internal reference, set to sample VDDIO.
+    pub fn new(adc: pac::Adc, reg: &mut GcrRegisters) -> Self {
+        unsafe {
+            adc.enable_clock(&mut reg.gcr);
+            // ADC clock is PCLK / 10, inside the converter's limit
+            reg.gcr.pclkdiv().modify(|_, w| w.adcfrq().bits(10));
+        }
+        adc.ctrl().write(|w| {
+            w.clk_en().set_bit()
+                .pwr().set_bit()
+                .refbuf_pwr().set_bit()
+                .ref_sel().clear_bit()
+                .ch_sel().vddio()
+        });
+        while adc.status().read().afe_pwr_up_active().bit_is_set() {}
+        SupplyMonitor { adc }
+    }
+
+    /// Sample VDDIO once, in millivolts.
+    fn vddio_mv(&mut self) -> u32 {
+        self.adc.intr().modify(|_, w| w.done_if().clear_bit_by_one());
+        self.adc.ctrl().modify(|_, w| w.start().set_bit());
+        while self.adc.intr().read().done_if().bit_is_clear() {}
+        self.adc.intr().modify(|_, w| w.done_if().clear_bit_by_one());
+
+        let sample = (self.adc.data().read().adc_data().bits() as u32).min(ADC_FULL_SCALE);
+        sample * ADC_REF_MV * VDDIO_DIVIDER / ADC_FULL_SCALE
+    }
+
+    /// Whether the supply is high enough to erase or program flash.
+    pub fn supply_ok(&mut self) -> bool {
+        self.vddio_mv() >= VDDIO_MIN_MV
+    }
+}
+
+/// Supply whose voltage only changes when a test sets it, at `VDDIO_MIN_MV` at first.
+/// Clones share the voltage, so a test can drop it under a FlashManager holding one.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct MockSupplyMonitor {
+    mv: Rc<Cell<u32>>,
+}
+
+#[cfg(feature = "std")]
+impl Default for MockSupplyMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl MockSupplyMonitor {
+    pub fn new() -> Self {
+        MockSupplyMonitor { mv: Rc::new(Cell::new(VDDIO_MIN_MV)) }
+    }
+
+    /// Set the VDDIO the next checks read, in millivolts.
+    pub fn set_mv(&self, mv: u32) {
+        self.mv.set(mv);
+    }
+
+    pub fn supply_ok(&mut self) -> bool {
+        self.mv.get() >= VDDIO_MIN_MV
+    }
+}
@@ -0,0 +1,134 @@
+use crate::modules::constants::{ERASED_MAGIC, TAMPER_ADDRESS};
+use crate::modules::flash_manager::{FlashManager, FlashManagerError};
+use crate::modules::host_keys::verify_host_signature;
+use crate::modules::hostcom_manager::{ErrorCode, MsgType};
+use crate::DECODER_ID;
+use bytemuck::{Pod, Zeroable};
+use core::fmt;
+use ed25519_dalek::Signature;
+
+/// Magic marking a written tamper record.
+const TAMPER_MAGIC: u32 = 0x7A3F_E0C1;
+
+/// Domain label prefixed to the signed recovery message.
+const UNLOCK_LABEL: &[u8] = b"ectf25-unlock";
+/// Length of the recovery message: label || decoder id (u32 LE) || epoch (u32 LE).
+const UNLOCK_MSG_LEN: usize = UNLOCK_LABEL.len() + 4 + 4;
+
+#[derive(Debug)]
+pub enum TamperError {
+    InvalidKey,
+    InvalidSignature,
+    FlashManagerError(FlashManagerError),
+}
+
+impl fmt::Display for TamperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TamperError::InvalidKey => write!(f, "Invalid host key"),
+            TamperError::InvalidSignature => write!(f, "Invalid signature"),
+            TamperError::FlashManagerError(e) => write!(f, "Flash error: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for TamperError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            TamperError::FlashManagerError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FlashManagerError> for TamperError {
+    fn from(e: FlashManagerError) -> Self {
+        TamperError::FlashManagerError(e)
+    }
+}
+
+/// Tamper lock as stored in flash.
+///
+/// `epoch` is bumped every time the lock is cleared and is part of the signed recovery
+/// message, so a recorded recovery command cannot be replayed to clear a later lock.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TamperState {
+    pub locked: u32,  // 0 = unlocked, anything else = locked
+    pub epoch: u32,
+}
+
+impl TamperState {
+    pub fn is_locked(&self) -> bool {
+        self.locked != 0
+    }
+}
+
+/// Read the tamper record. A never-written page is unlocked; a record that is present
+/// but unreadable or corrupt is treated as locked.
+pub fn read_tamper_state(flash_manager: &mut FlashManager) -> TamperState {
+    match flash_manager.read_magic(TAMPER_ADDRESS) {
+        Ok(ERASED_MAGIC) => TamperState { locked: 0, epoch: 0 },
+        Ok(TAMPER_MAGIC) => flash_manager
+            .read_data_verified::<TamperState>(TAMPER_ADDRESS)
+            .unwrap_or(TamperState { locked: 1, epoch: 0 }),
+        _ => TamperState { locked: 1, epoch: 0 },
+    }
+}
+
+/// Whether `cmd` may run while the decoder is `locked`, checked once before dispatch.
+///
+/// A locked decoder stores, changes and uses no subscription until the lock is cleared,
+/// so those commands are refused with `Locked`. Status queries, the lock commands
+/// themselves and the rest stay available.
+pub fn can_accept_command(cmd: MsgType, locked: bool) -> Result<(), ErrorCode> {
+    let gated = matches!(
+        cmd,
+        MsgType::Subscribe
+            | MsgType::SubscribeBundle
+            | MsgType::SubscribePreamble
+            | MsgType::UploadChunk
+            | MsgType::Decode
+            | MsgType::VerifyProbe
+            | MsgType::Window
+            | MsgType::Pause
+    );
+    if locked && gated {
+        return Err(ErrorCode::Locked);
+    }
+    Ok(())
+}
+
+fn write_tamper_state(flash_manager: &mut FlashManager, state: &TamperState) -> Result<(), FlashManagerError> {
+    flash_manager.wipe_data(TAMPER_ADDRESS)?;
+    flash_manager.write_data(TAMPER_ADDRESS, TAMPER_MAGIC, state)
+}
+
+/// Lock the decoder. Returns the epoch a recovery command has to be signed for.
+pub fn set_tamper_flag(flash_manager: &mut FlashManager) -> Result<u32, FlashManagerError> {
+    let mut state = read_tamper_state(flash_manager);
+    state.locked = 1;
+    write_tamper_state(flash_manager, &state)?;
+    Ok(state.epoch)
+}
+
+/// Clear the lock if `signature` is the host key's signature over the recovery message
+/// for this decoder and the current epoch.
+pub fn clear_tamper_flag(flash_manager: &mut FlashManager, signature: &[u8]) -> Result<(), TamperError> {
+    let sig = Signature::from_slice(signature).map_err(|_| TamperError::InvalidSignature)?;
+
+    let state = read_tamper_state(flash_manager);
+
+    let mut message = [0u8; UNLOCK_MSG_LEN];
+    message[..UNLOCK_LABEL.len()].copy_from_slice(UNLOCK_LABEL);
+    message[UNLOCK_LABEL.len()..UNLOCK_LABEL.len() + 4].copy_from_slice(&DECODER_ID.to_le_bytes());
+    message[UNLOCK_LABEL.len() + 4..].copy_from_slice(&state.epoch.to_le_bytes());
+
+    if !verify_host_signature(&message, &sig).map_err(|_| TamperError::InvalidKey)? {
+        return Err(TamperError::InvalidSignature);
+    }
+
+    let cleared = TamperState { locked: 0, epoch: state.epoch.wrapping_add(1) };
+    write_tamper_state(flash_manager, &cleared)?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::modules::channel_manager::{
+    ActiveChannelsList, SubscriptionError, SubscriptionSummary, ACTIVE_CHANNELS_LEN, FRAME_CONTENT_LEN,
+};
+use bytemuck::{Pod, Zeroable};
+use core::mem::size_of;
+
+/// Longest Telemetry response: the counters, the decode total, the number of channels,
+/// and a channel id (u32 LE) and decode count (u32 LE) per active channel.
+pub const TELEMETRY_MAX_LEN: usize = size_of::<Telemetry>() + 4 + 4 + ACTIVE_CHANNELS_LEN * 8;
+
+/// In-RAM decoder counters, the start of the Telemetry response. They reset on reboot.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Telemetry {
+    pub frames_decoded: u32,
+    /// Frames rejected for a bad signature.
+    pub frames_bad_signature: u32,
+    /// Frames rejected as replayed or out of order.
+    pub frames_bad_timestamp: u32,
+    /// Frames for a channel without a (valid) subscription.
+    pub frames_no_subscription: u32,
+    /// Frames rejected for any other reason, including bad lengths.
+    pub frames_other_error: u32,
+    pub subscriptions_stored: u32,
+    pub subscriptions_rejected: u32,
+    /// Signature failures across frames and subscriptions.
+    pub signature_failures: u32,
+    /// Persistent boot count from the state log, unlike the counters above.
+    pub boot_count: u32,
+    /// `ResetReason` of this boot.
+    pub reset_reason: u32,
+    /// Subscription pages by state, as last checked by `validate_all_subscriptions`.
+    pub pages_valid: u32,
+    pub pages_empty: u32,
+    pub pages_corrupt: u32,
+}
+
+/// Counters saturate rather than wrap.
+fn bump(counter: &mut u32) {
+    *counter = counter.saturating_add(1);
+}
+
+impl Telemetry {
+    pub fn record_decode(&mut self, result: &Result<[u8; FRAME_CONTENT_LEN], SubscriptionError>) {
+        match result {
+            Ok(_) => bump(&mut self.frames_decoded),
+            Err(SubscriptionError::InvalidSignature) => {
+                bump(&mut self.frames_bad_signature);
+                bump(&mut self.signature_failures);
+            }
+            Err(SubscriptionError::InvalidTimestamp) => bump(&mut self.frames_bad_timestamp),
+            Err(SubscriptionError::NoSubscription)
+            | Err(SubscriptionError::PasswordNotFound)
+            | Err(SubscriptionError::SubscriptionExpired) => bump(&mut self.frames_no_subscription),
+            Err(_) => bump(&mut self.frames_other_error),
+        }
+    }
+
+    pub fn record_page_summary(&mut self, summary: &SubscriptionSummary) {
+        self.pages_valid = summary.valid();
+        self.pages_empty = summary.empty();
+        self.pages_corrupt = summary.corrupt();
+    }
+
+    /// A Decode body that was not a `ChannelFrame` in size.
+    pub fn record_bad_frame_length(&mut self) {
+        bump(&mut self.frames_other_error);
+    }
+
+    /// Write the Telemetry response into `out`, returning its length: these counters,
+    /// then the active channels' decode counts, which follow the state log.
+    pub fn write_report(&self, active_channels: &ActiveChannelsList, out: &mut [u8; TELEMETRY_MAX_LEN]) -> usize {
+        let mut len = size_of::<Telemetry>();
+        out[..len].copy_from_slice(bytemuck::bytes_of(self));
+
+        let channels = active_channels.iter().flatten();
+        let total = channels.clone().fold(0u32, |total, c| total.saturating_add(c.decode_count));
+        out[len..len + 4].copy_from_slice(&total.to_le_bytes());
+        out[len + 4..len + 8].copy_from_slice(&(channels.clone().count() as u32).to_le_bytes());
+        len += 8;
+        for channel in channels {
+            out[len..len + 4].copy_from_slice(&channel.channel_id.to_le_bytes());
+            out[len + 4..len + 8].copy_from_slice(&channel.decode_count.to_le_bytes());
+            len += 8;
+        }
+        len
+    }
+
+    pub fn record_subscription<T>(&mut self, result: &Result<T, SubscriptionError>) {
+        match result {
+            Ok(_) => bump(&mut self.subscriptions_stored),
+            Err(e) => {
+                bump(&mut self.subscriptions_rejected);
+                if let SubscriptionError::InvalidSignature = e {
+                    bump(&mut self.signature_failures);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Host-side (`std` feature) encoder for the decoder's inputs: signed, encrypted frames
+//! and subscriptions built the way `ectf25_design`'s Encoder and gen_subscription build
+//! them, for tests that need inputs the firmware accepts.
+//!
+//! Ed25519 signatures are deterministic, so with the same nonce these functions
+//! reproduce the Python output byte for byte, which the host tests check.
+use crate::modules::channel_manager::{
+    ChannelFrame, ChannelPassword, FRAME_CONTENT_LEN, FRAME_MARKER_LEN, FRAME_SIGNED_LEN,
+};
+use crate::modules::crc::crc16;
+use crate::modules::key_tree::{derive_child_key, extend_key};
+use crate::KEY_LEN;
+use bytemuck::bytes_of;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use ed25519_dalek::{Signer, SigningKey};
+use std::vec::Vec;
+
+/// Level-order number of the leaf of `timestamp`; the root is node 1.
+pub const fn leaf_node(timestamp: u64) -> u128 {
+    (1u128 << 64) | timestamp as u128
+}
+
+/// Key of tree node `node_num`, derived down from the channel's root key.
+pub fn node_key(channel_root: &[u8; 16], node_num: u128) -> [u8; 16] {
+    let depth = 127 - node_num.leading_zeros();
+    let mut key = *channel_root;
+    for level in 1..=depth {
+        let node = node_num >> (depth - level);
+        key = derive_child_key(&key, (node & 1) as u8 + 1, node);
+    }
+    key
+}
+
+/// The fewest tree nodes whose leaves are exactly `start..=end`, left to right, as
+/// gen_subscription's `get_covering_nodes` picks them.
+pub fn covering_nodes(start: u64, end: u64) -> Vec<u128> {
+    assert!(start <= end, "start after end");
+    let mut nodes = Vec::new();
+    cover(1, 0, u64::MAX, start, end, &mut nodes);
+    nodes
+}
+
+/// Cover the part of `start..=end` below `node`, whose leaves are `first..=last`.
+fn cover(node: u128, first: u64, last: u64, start: u64, end: u64, nodes: &mut Vec<u128>) {
+    if last < start || first > end {
+        return;
+    }
+    if start <= first && last <= end {
+        nodes.push(node);
+        return;
+    }
+    let mid = first + (last - first) / 2;
+    cover(node * 2, first, mid, start, end, nodes);
+    cover(node * 2 + 1, mid + 1, last, start, end, nodes);
+}
+
+/// A Decode body: header (channel, timestamp, nonce), then `content` and the channel
+/// id (u32 LE) encrypted under the extended key of the timestamp's leaf, then the
+/// Ed25519 signature over everything before it.
+pub fn encode_frame(
+    host_key: &SigningKey,
+    channel_root: &[u8; 16],
+    channel: u32,
+    timestamp: u64,
+    content: &[u8; FRAME_CONTENT_LEN],
+    nonce: [u8; 12],
+) -> Vec<u8> {
+    let key = extend_key(&node_key(channel_root, leaf_node(timestamp)));
+    let mut plaintext = [0u8; FRAME_CONTENT_LEN + FRAME_MARKER_LEN];
+    plaintext[..FRAME_CONTENT_LEN].copy_from_slice(content);
+    plaintext[FRAME_CONTENT_LEN..].copy_from_slice(&channel.to_le_bytes());
+    ChaCha20::new(&key.into(), &nonce.into()).apply_keystream(&mut plaintext);
+
+    let mut frame = ChannelFrame {
+        channel,
+        timestamp,
+        nonce,
+        encrypted_content: plaintext[..FRAME_CONTENT_LEN].try_into().unwrap(),
+        encrypted_marker: plaintext[FRAME_CONTENT_LEN..].try_into().unwrap(),
+        signature: [0; 64],
+    };
+    frame.signature = host_key.sign(&bytes_of(&frame)[..FRAME_SIGNED_LEN]).to_bytes();
+    bytes_of(&frame).to_vec()
+}
+
+/// A Subscribe body for `decoder_id`: header (decoder id, start, end, channel, nonce),
+/// the covering node passwords encrypted under `decoder_key`, and the Ed25519 signature
+/// over both.
+#[allow(clippy::too_many_arguments)]
+pub fn encode_subscription(
+    host_key: &SigningKey,
+    decoder_key: &[u8; KEY_LEN],
+    decoder_id: u32,
+    channel_root: &[u8; 16],
+    channel: u32,
+    start: u64,
+    end: u64,
+    nonce: [u8; 12],
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&decoder_id.to_le_bytes());
+    body.extend_from_slice(&start.to_le_bytes());
+    body.extend_from_slice(&end.to_le_bytes());
+    body.extend_from_slice(&channel.to_le_bytes());
+    body.extend_from_slice(&nonce);
+
+    let header_len = body.len();
+    for node in covering_nodes(start, end) {
+        let password = ChannelPassword {
+            node_trunc: (node >> 1) as u64,
+            node_ext: (node & 1) as u8 + 1,
+            password: node_key(channel_root, node),
+        };
+        body.extend_from_slice(bytes_of(&password));
+    }
+    ChaCha20::new(decoder_key.into(), &nonce.into()).apply_keystream(&mut body[header_len..]);
+
+    let signature = host_key.sign(&body).to_bytes();
+    body.extend_from_slice(&signature);
+    body
+}
+
+/// A Window body for `decoder_id`: channel, the end it replaces, the new end and the
+/// shrink flag, signed over the "ectf25-window" label as gen_window signs it.
+pub fn encode_window(
+    host_key: &SigningKey,
+    decoder_id: u32,
+    channel: u32,
+    current_end: u64,
+    new_end: u64,
+    allow_shrink: bool,
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&channel.to_le_bytes());
+    body.extend_from_slice(&current_end.to_le_bytes());
+    body.extend_from_slice(&new_end.to_le_bytes());
+    body.push(allow_shrink as u8);
+
+    let mut message = b"ectf25-window".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&body);
+    let signature = host_key.sign(&message).to_bytes();
+    body.extend_from_slice(&signature);
+    body
+}
+
+/// A Pause body for `decoder_id`: channel, the sequence it applies to and the paused
+/// flag, signed over the "ectf25-pause" label as gen_pause signs it.
+pub fn encode_pause(host_key: &SigningKey, decoder_id: u32, channel: u32, sequence: u32, paused: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&channel.to_le_bytes());
+    body.extend_from_slice(&sequence.to_le_bytes());
+    body.push(paused as u8);
+
+    let mut message = b"ectf25-pause".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&body);
+    let signature = host_key.sign(&message).to_bytes();
+    body.extend_from_slice(&signature);
+    body
+}
+
+/// An Emergency body for `decoder_id` at `epoch`: the enabled flag and its signature
+/// over the "ectf25-emergency" label, as gen_emergency signs it.
+pub fn encode_emergency(host_key: &SigningKey, decoder_id: u32, epoch: u32, enabled: bool) -> Vec<u8> {
+    let mut message = b"ectf25-emergency".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&epoch.to_le_bytes());
+    message.push(enabled as u8);
+
+    let mut body = Vec::from([enabled as u8]);
+    body.extend_from_slice(&host_key.sign(&message).to_bytes());
+    body
+}
+
+/// A Recover body for `decoder_id` at `epoch`: the signature over the "ectf25-unlock"
+/// label, as gen_unlock signs it.
+pub fn encode_unlock(host_key: &SigningKey, decoder_id: u32, epoch: u32) -> Vec<u8> {
+    let mut message = b"ectf25-unlock".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&epoch.to_le_bytes());
+    host_key.sign(&message).to_bytes().to_vec()
+}
+
+/// A SetTime body for `decoder_id`: the timestamp and its signature over the
+/// "ectf25-time" label, as gen_set_time signs it.
+pub fn encode_set_time(host_key: &SigningKey, decoder_id: u32, timestamp: u64) -> Vec<u8> {
+    let mut message = b"ectf25-time".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&timestamp.to_le_bytes());
+
+    let mut body = timestamp.to_le_bytes().to_vec();
+    body.extend_from_slice(&host_key.sign(&message).to_bytes());
+    body
+}
+
+/// A Rekey body for `decoder_id` moving to `generation`: the generation, the derivation
+/// context and their signature over the "ectf25-rekey" label, as gen_rekey signs it.
+pub fn encode_rekey(host_key: &SigningKey, decoder_id: u32, generation: u32, context: &[u8; 32]) -> Vec<u8> {
+    let mut body = generation.to_le_bytes().to_vec();
+    body.extend_from_slice(context);
+
+    let mut message = b"ectf25-rekey".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&body);
+    body.extend_from_slice(&host_key.sign(&message).to_bytes());
+    body
+}
+
+/// `subscription` followed by its CRC-16 (u16 LE), as add_subscription_checksum
+/// appends it for a decoder built with `subscribe-checksum`.
+pub fn add_subscription_checksum(subscription: &[u8]) -> Vec<u8> {
+    let mut body = subscription.to_vec();
+    body.extend_from_slice(&crc16(subscription).to_le_bytes());
+    body
+}
+
+/// A SubscribePreamble body announcing a `length`-byte Subscribe for `channel`, signed
+/// over the "ectf25-preamble" label as gen_subscribe_preamble signs it.
+pub fn encode_subscribe_preamble(host_key: &SigningKey, decoder_id: u32, channel: u32, length: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&decoder_id.to_le_bytes());
+    body.extend_from_slice(&channel.to_le_bytes());
+    body.extend_from_slice(&length.to_le_bytes());
+
+    let mut message = b"ectf25-preamble".to_vec();
+    message.extend_from_slice(&body);
+    body.extend_from_slice(&host_key.sign(&message).to_bytes());
+    body
+}
+
+/// A SubscribeBundle body carrying `subscriptions`, each a Subscribe body without
+/// checksum, signed over the "ectf25-bundle" label as gen_subscription_bundle signs it.
+pub fn encode_subscription_bundle(host_key: &SigningKey, decoder_id: u32, subscriptions: &[Vec<u8>]) -> Vec<u8> {
+    let mut body = Vec::from([subscriptions.len() as u8]);
+    for subscription in subscriptions {
+        body.extend_from_slice(&(subscription.len() as u16).to_le_bytes());
+        body.extend_from_slice(subscription);
+    }
+
+    let mut message = b"ectf25-bundle".to_vec();
+    message.extend_from_slice(&decoder_id.to_le_bytes());
+    message.extend_from_slice(&body);
+    body.extend_from_slice(&host_key.sign(&message).to_bytes());
+    body
+}
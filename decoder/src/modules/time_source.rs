@@ -0,0 +1,42 @@
+//! The current time for checks that depend on it, such as wall-clock expiry.
+//!
+//! The firmware's source is the RTC-backed `WallClock` (`rtc-time` feature); host
+//! tests use `MockClock` and move its time by hand, so nothing waits on a real clock.
+#[cfg(feature = "std")]
+use core::cell::Cell;
+
+pub trait TimeSource {
+    /// Current time in frame timestamp units, or `None` while the clock is unset.
+    fn now(&self) -> Option<u64>;
+}
+
+/// Clock whose time only changes when a test sets or advances it. Unset at first,
+/// like a `WallClock` before its first SetTime.
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct MockClock {
+    now: Cell<Option<u64>>,
+}
+
+#[cfg(feature = "std")]
+impl MockClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.set(Some(now));
+    }
+
+    /// Move the time forward by `ticks` frame timestamp units. An unset clock stays unset.
+    pub fn advance(&self, ticks: u64) {
+        self.now.set(self.now.get().map(|now| now.saturating_add(ticks)));
+    }
+}
+
+#[cfg(feature = "std")]
+impl TimeSource for MockClock {
+    fn now(&self) -> Option<u64> {
+        self.now.get()
+    }
+}
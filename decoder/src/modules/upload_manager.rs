@@ -0,0 +1,177 @@
+//! Resumable Subscribe uploads (`resumable-upload` feature).
+//!
+//! A ~3.2 KB Subscribe that fails near its end over a flaky link would otherwise be
+//! sent again from the start. Here the host sends the same body as UploadChunk
+//! commands of `UPLOAD_CHUNK_LEN` bytes, each kept in a scratch flash page, so the
+//! chunks survive a dropped connection or a reset. After reconnecting the host asks
+//! UploadStatus how much arrived and carries on from there. Only once the last chunk
+//! is in is the body checked and stored exactly as a Subscribe would be, and the
+//! scratch page is cleared whatever the outcome.
+//!
+//! While it holds an upload, the scratch page has the upload header record (one
+//! 16-byte word), a mark word per chunk, then the body. A chunk counts as received
+//! once its mark is written, which happens after its data, so a chunk torn by a reset
+//! is simply sent again.
+use crate::modules::channel_manager::{check_subscription_valid_and_store, ActiveChannelsList, SubscriptionError};
+use crate::modules::constants::PAGE_SIZE;
+use crate::modules::flash_manager::{FlashManager, FlashManagerError, FLASH_WORD_SIZE};
+use crate::modules::hostcom_manager::{MessageBody, MessageHeader, MsgType, MAX_BODY_LEN};
+#[cfg(feature = "rekey")]
+use crate::modules::key_manager::DeviceKey;
+use crate::modules::wire::{read_u16_le, read_u32_le};
+use bytemuck::{Pod, Zeroable};
+
+/// Magic of the upload header record.
+const UPLOAD_MAGIC: u32 = 0x0B10_AD5C;
+/// Magic of a chunk's received mark.
+const CHUNK_MARK_MAGIC: u32 = 0xC4A2_4C0D;
+
+/// Body bytes per chunk; only the last one of an upload may be shorter.
+pub const UPLOAD_CHUNK_LEN: usize = 256;
+/// UploadChunk header: upload id (u32 LE), total body length (u16 LE), chunk index (u16 LE).
+pub const UPLOAD_CHUNK_HEADER_LEN: usize = 4 + 2 + 2;
+/// Longest UploadChunk body.
+pub const UPLOAD_CHUNK_MAX_LEN: usize = UPLOAD_CHUNK_HEADER_LEN + UPLOAD_CHUNK_LEN;
+/// UploadStatus body: the upload id (u32 LE).
+pub const UPLOAD_STATUS_BODY_LEN: usize = 4;
+
+/// The upload is kept in the scratch page.
+const UPLOAD_ADDRESS: u32 = FlashManager::scratch_page_addr();
+/// Chunks of the largest body a Subscribe could carry.
+const MAX_UPLOAD_CHUNKS: usize = MAX_BODY_LEN.div_ceil(UPLOAD_CHUNK_LEN);
+const MARKS_ADDRESS: u32 = UPLOAD_ADDRESS + FLASH_WORD_SIZE;
+const DATA_ADDRESS: u32 = MARKS_ADDRESS + MAX_UPLOAD_CHUNKS as u32 * FLASH_WORD_SIZE;
+
+// Chunks start on flash words, and a whole body fits the page after the marks.
+const _: () = assert!(UPLOAD_CHUNK_LEN.is_multiple_of(FLASH_WORD_SIZE as usize));
+const _: () = assert!(DATA_ADDRESS + MAX_BODY_LEN as u32 <= UPLOAD_ADDRESS + PAGE_SIZE);
+
+/// The upload the scratch page holds.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct UploadHeader {
+    upload_id: u32,
+    total_len: u32,
+}
+
+/// Where an upload stands after a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub received: u16,
+    pub total: u16,
+}
+
+impl UploadProgress {
+    /// The whole body arrived and was stored as a subscription.
+    pub fn is_complete(&self) -> bool {
+        self.received == self.total
+    }
+}
+
+fn mark_addr(index: usize) -> u32 {
+    MARKS_ADDRESS + index as u32 * FLASH_WORD_SIZE
+}
+
+fn read_header(flash_manager: &mut FlashManager) -> Option<UploadHeader> {
+    match flash_manager.read_magic(UPLOAD_ADDRESS) {
+        Ok(UPLOAD_MAGIC) => flash_manager.read_data_verified::<UploadHeader>(UPLOAD_ADDRESS).ok(),
+        _ => None,
+    }
+}
+
+/// Chunks received in a row from the first, for an upload of `total_len` bytes.
+fn received_chunks(flash_manager: &mut FlashManager, total_len: usize) -> usize {
+    (0..total_len.div_ceil(UPLOAD_CHUNK_LEN))
+        .take_while(|&index| {
+            matches!(flash_manager.read_magic(mark_addr(index)), Ok(CHUNK_MARK_MAGIC))
+                && flash_manager.read_data_verified::<u32>(mark_addr(index)).is_ok_and(|i| i as usize == index)
+        })
+        .count()
+}
+
+fn received_bytes(chunks: usize, total_len: usize) -> u16 {
+    (chunks * UPLOAD_CHUNK_LEN).min(total_len) as u16
+}
+
+/// Bytes of upload `upload_id` the scratch page holds, 0 for any other upload.
+pub fn upload_received(flash_manager: &mut FlashManager, upload_id: u32) -> u16 {
+    match read_header(flash_manager) {
+        Some(header) if { header.upload_id } == upload_id => {
+            let total_len = header.total_len as usize;
+            received_bytes(received_chunks(flash_manager, total_len), total_len)
+        }
+        _ => 0,
+    }
+}
+
+/// Take the UploadChunk of `length` bytes in `body`.
+///
+/// Chunk 0 of an upload the page does not hold starts it over a cleared page. Any
+/// other chunk must be the next one missing, or `UploadOutOfOrder` is returned and
+/// nothing changes. The chunk completing the upload has the whole body checked and
+/// stored as by Subscribe, using `body` to hold it, and its result returned.
+pub fn receive_upload_chunk(
+    flash_manager: &mut FlashManager,
+    body: &mut MessageBody,
+    length: usize,
+    active_channels: &mut ActiveChannelsList,
+    #[cfg(feature = "rekey")] device_key: &DeviceKey,
+) -> Result<UploadProgress, SubscriptionError> {
+    if !(UPLOAD_CHUNK_HEADER_LEN..=UPLOAD_CHUNK_MAX_LEN).contains(&length) {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let upload_id = read_u32_le(&body.data, 0);
+    let total_len = read_u16_le(&body.data, 4) as usize;
+    let index = read_u16_le(&body.data, 6) as usize;
+    let data = &body.data[UPLOAD_CHUNK_HEADER_LEN..length];
+
+    if total_len == 0 || total_len > MAX_BODY_LEN {
+        return Err(SubscriptionError::InvalidLength);
+    }
+    let chunks = total_len.div_ceil(UPLOAD_CHUNK_LEN);
+    if index >= chunks {
+        return Err(SubscriptionError::UploadOutOfOrder);
+    }
+    if data.len() != UPLOAD_CHUNK_LEN.min(total_len - index * UPLOAD_CHUNK_LEN) {
+        return Err(SubscriptionError::InvalidLength);
+    }
+
+    let held = read_header(flash_manager).filter(|h| { h.upload_id } == upload_id && h.total_len as usize == total_len);
+    let received = match held {
+        Some(_) => received_chunks(flash_manager, total_len),
+        None if index == 0 => {
+            flash_manager.wipe_data(UPLOAD_ADDRESS)?;
+            let header = UploadHeader { upload_id, total_len: total_len as u32 };
+            flash_manager.write_data(UPLOAD_ADDRESS, UPLOAD_MAGIC, &header)?;
+            0
+        }
+        None => return Err(SubscriptionError::UploadOutOfOrder),
+    };
+    if index != received {
+        return Err(SubscriptionError::UploadOutOfOrder);
+    }
+
+    flash_manager.write_raw(DATA_ADDRESS + (index * UPLOAD_CHUNK_LEN) as u32, data)?;
+    flash_manager.write_data(mark_addr(index), CHUNK_MARK_MAGIC, &(index as u32))?;
+    if index + 1 < chunks {
+        return Ok(UploadProgress { received: received_bytes(index + 1, total_len), total: total_len as u16 });
+    }
+
+    // Every chunk is in: read the body back and store it as Subscribe would
+    let words = total_len.next_multiple_of(FLASH_WORD_SIZE as usize);
+    flash_manager.read_raw(DATA_ADDRESS, &mut body.data[..words]).map_err(FlashManagerError::from)?;
+    body.length = total_len as u16;
+    let hdr = MessageHeader::new(MsgType::Subscribe, total_len as u16);
+    let result = check_subscription_valid_and_store(
+        &hdr,
+        body,
+        flash_manager,
+        active_channels,
+        #[cfg(feature = "rekey")]
+        device_key,
+    );
+    // A body that failed once fails again, so it is not kept for a resume
+    flash_manager.wipe_data(UPLOAD_ADDRESS)?;
+    result?;
+    Ok(UploadProgress { received: total_len as u16, total: total_len as u16 })
+}
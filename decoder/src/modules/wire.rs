@@ -0,0 +1,29 @@
+//! Byte order of the host protocol: every multi-byte integer in a header or body is
+//! little-endian, whatever the target. Hand-parsed bodies read their fields through
+//! these helpers so the contract lives in one place.
+//!
+//! `ChannelFrame` and `ChannelInfo` are sent as their `repr(C, packed)` bytes, which
+//! only matches this order on a little-endian target, as the Cortex-M4 and the host
+//! test machines are.
+const _: () = assert!(cfg!(target_endian = "little"), "the packed wire structs assume a little-endian target");
+
+/// The u16 at `bytes[at..at + 2]`. Panics if that is out of bounds, as indexing does.
+pub fn read_u16_le(bytes: &[u8], at: usize) -> u16 {
+    let mut word = [0u8; 2];
+    word.copy_from_slice(&bytes[at..at + 2]);
+    u16::from_le_bytes(word)
+}
+
+/// The u32 at `bytes[at..at + 4]`. Panics if that is out of bounds, as indexing does.
+pub fn read_u32_le(bytes: &[u8], at: usize) -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&bytes[at..at + 4]);
+    u32::from_le_bytes(word)
+}
+
+/// The u64 at `bytes[at..at + 8]`. Panics if that is out of bounds, as indexing does.
+pub fn read_u64_le(bytes: &[u8], at: usize) -> u64 {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(word)
+}
@@ -6,6 +6,28 @@ from Crypto.Protocol.KDF import HKDF
 from typing import TypedDict, Dict, Tuple, List
 from dataclasses import dataclass
 
+# Domain separation labels, must match the decoder's channel_manager
+CHILD_KEY_LABEL = b"ectf25-child"
+EXTEND_KEY_LABEL = b"ectf25-extend"
+# Must match the decoder's tamper_manager
+UNLOCK_LABEL = b"ectf25-unlock"
+# Must match the decoder's clock module
+SET_TIME_LABEL = b"ectf25-time"
+# Must match the decoder's key_manager
+REKEY_LABEL = b"ectf25-rekey"
+# Must match the decoder's channel_manager
+WINDOW_LABEL = b"ectf25-window"
+# Window update flag allowing the window to end earlier than before
+WINDOW_ALLOW_SHRINK = 0x01
+# Must match the decoder's channel_manager
+PAUSE_LABEL = b"ectf25-pause"
+# Must match the decoder's emergency_manager
+EMERGENCY_LABEL = b"ectf25-emergency"
+# Must match the decoder's channel_manager
+PREAMBLE_LABEL = b"ectf25-preamble"
+# Must match the decoder's channel_manager
+BUNDLE_LABEL = b"ectf25-bundle"
+
 
 class Secrets(TypedDict):
     channels: Dict[str, str]  # Maps channel IDs to hex-encoded 16-byte secrets
@@ -115,11 +137,18 @@ class ChannelKeyDerivation:
 
         return nodes
 
-    def get_left_subkey(self, key: bytes):
-        return MD5.new(key + b"L").digest()
+    def get_child_subkey(self, key: bytes, branch: bytes, node_num: int) -> bytes:
+        """Derive the key of child node_num from its parent key, domain-separated by
+        a label, the branch taken and the child's level-order number"""
+        return MD5.new(
+            CHILD_KEY_LABEL + key + branch + node_num.to_bytes(16, "little")
+        ).digest()
+
+    def get_left_subkey(self, key: bytes, node_num: int):
+        return self.get_child_subkey(key, b"L", node_num)
 
-    def get_right_subkey(self, key: bytes):
-        return MD5.new(key + b"R").digest()
+    def get_right_subkey(self, key: bytes, node_num: int):
+        return self.get_child_subkey(key, b"R", node_num)
 
     def get_key_for_node(self, node_num: int) -> ChannelTreeNode:
         """Generate the key for a given node in the tree from the root key"""
@@ -132,11 +161,13 @@ class ChannelKeyDerivation:
 
         # Traverse from root, generating subkeys along the way
         curr_key = self.root
+        curr_node = 1
         for t in traversal[::-1]:
+            curr_node = curr_node * 2 + t
             if t == 0:
-                curr_key = self.get_left_subkey(curr_key)
+                curr_key = self.get_left_subkey(curr_key, curr_node)
             else:
-                curr_key = self.get_right_subkey(curr_key)
+                curr_key = self.get_right_subkey(curr_key, curr_node)
 
         return ChannelTreeNode(node_num=node_num, key=curr_key)
 
@@ -152,8 +183,8 @@ class ChannelKeyDerivation:
         return nodes
 
     def extend_key(self, key: bytes) -> bytes:
-        """Extends 16-byte key to 32 by returning (k | H(k))"""
-        return key + MD5.new(key).digest()
+        """Extends 16-byte key to 32 by returning (k | H(label | k))"""
+        return key + MD5.new(EXTEND_KEY_LABEL + key).digest()
 
     def get_frame_key(self, frame_num: int) -> bytes:
         """Returns a 16-byte key to be used for encrypting a given frame, based on the hash tree derivation"""
@@ -230,19 +261,234 @@ class ChannelKeyDerivation:
         for branch in traversal[closest_node_idx:]:
             if branch == 0:
                 curr_node = curr_node * 2
-                curr_key = self.get_left_subkey(curr_key)
+                curr_key = self.get_left_subkey(curr_key, curr_node)
             else:
                 curr_node = curr_node * 2 + 1
-                curr_key = self.get_right_subkey(curr_key)
+                curr_key = self.get_right_subkey(curr_key, curr_node)
 
         return curr_key
 
 
-def get_decoder_key(decoder_dk: bytes, decoder_id: int):
+def get_decoder_key(decoder_dk: bytes, decoder_id: int, rekey_contexts: List[bytes] = ()):
+    """Derive a decoder's subscription key, following any Rekey commands it accepted
+
+    :param rekey_contexts: Contexts of the Rekey commands sent to the decoder, in order
+    """
     decoder_id_bytes = decoder_id.to_bytes(length=4, byteorder="little")
-    return HKDF(
+    key = HKDF(
         master=decoder_dk, key_len=32, hashmod=SHA512, context=decoder_id_bytes, salt=""
     )
+    for context in rekey_contexts:
+        key = HKDF(master=key, key_len=32, hashmod=SHA512, context=context, salt="")
+    return key
+
+
+def gen_unlock(secrets: bytes, decoder_id: int, epoch: int) -> bytes:
+    """Generate the body of a Recover command clearing a decoder's tamper flag
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the locked Decoder
+    :param epoch: Epoch reported by the Decoder when the flag was set
+
+    :returns: 64-byte Ed25519 signature over the recovery message
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    message = UNLOCK_LABEL + decoder_id.to_bytes(4, "little") + epoch.to_bytes(4, "little")
+    return signer.sign(message)
+
+
+def gen_set_time(secrets: bytes, decoder_id: int, timestamp: int) -> bytes:
+    """Generate the body of a SetTime command seeding a decoder's wall clock
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param timestamp: Current time in frame timestamp units
+
+    :returns: Timestamp (8 bytes) followed by a 64-byte Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    timestamp_bytes = timestamp.to_bytes(8, "little")
+    message = SET_TIME_LABEL + decoder_id.to_bytes(4, "little") + timestamp_bytes
+    return timestamp_bytes + signer.sign(message)
+
+
+def gen_rekey(secrets: bytes, decoder_id: int, generation: int, context: bytes) -> bytes:
+    """Generate the body of a Rekey command rotating a decoder's subscription key
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param generation: Key generation after the rotation, one more than the current
+    :param context: 32-byte derivation context for the new key
+
+    :returns: Generation (4 bytes), context and a 64-byte Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    assert len(context) == 32
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    generation_bytes = generation.to_bytes(4, "little")
+    message = REKEY_LABEL + decoder_id.to_bytes(4, "little") + generation_bytes + context
+    return generation_bytes + context + signer.sign(message)
+
+
+def gen_window(
+    secrets: bytes,
+    decoder_id: int,
+    channel: int,
+    current_end: int,
+    new_end: int,
+    allow_shrink: bool = False,
+) -> bytes:
+    """Generate the body of a Window command moving the end of a stored subscription
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param channel: Channel of the stored subscription
+    :param current_end: End timestamp currently stored; the update only applies to it
+    :param new_end: New end timestamp
+    :param allow_shrink: Whether the new end may be earlier than the current one
+
+    :returns: Channel (4 bytes), current and new end (8 bytes each), flags (1 byte)
+        and a 64-byte Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    flags = WINDOW_ALLOW_SHRINK if allow_shrink else 0
+    fields = (
+        channel.to_bytes(4, "little")
+        + current_end.to_bytes(8, "little")
+        + new_end.to_bytes(8, "little")
+        + bytes([flags])
+    )
+    message = WINDOW_LABEL + decoder_id.to_bytes(4, "little") + fields
+    return fields + signer.sign(message)
+
+
+def gen_pause(secrets: bytes, decoder_id: int, channel: int, sequence: int, paused: bool) -> bytes:
+    """Generate the body of a Pause command pausing or resuming a stored subscription
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param channel: Channel of the stored subscription
+    :param sequence: Sequence reported by the previous Pause command, 0 for the first
+    :param paused: Whether to pause (True) or resume (False) the channel
+
+    :returns: Channel and sequence (4 bytes each), paused (1 byte) and a 64-byte
+        Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    fields = channel.to_bytes(4, "little") + sequence.to_bytes(4, "little") + bytes([paused])
+    message = PAUSE_LABEL + decoder_id.to_bytes(4, "little") + fields
+    return fields + signer.sign(message)
+
+
+def gen_emergency(secrets: bytes, decoder_id: int, epoch: int, enabled: bool) -> bytes:
+    """Generate the body of an Emergency command switching emergency-only mode
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param epoch: Epoch reported by the previous Emergency command, 0 for the first
+    :param enabled: Whether to refuse every channel but 0 (True) or none (False)
+
+    :returns: Enabled (1 byte) followed by a 64-byte Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    message = (
+        EMERGENCY_LABEL
+        + decoder_id.to_bytes(4, "little")
+        + epoch.to_bytes(4, "little")
+        + bytes([enabled])
+    )
+    return bytes([enabled]) + signer.sign(message)
+
+
+def gen_subscribe_preamble(secrets: bytes, decoder_id: int, channel: int, length: int) -> bytes:
+    """Generate the body of a SubscribePreamble command announcing a subscription
+
+    Sent before the Subscribe command; the Decoder answers with an Error if it would
+    refuse a subscription of this length for this channel outright.
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param channel: Channel of the subscription
+    :param length: Length of the Subscribe body to follow
+
+    :returns: Decoder ID and channel (4 bytes each), length (2 bytes) and a 64-byte
+        Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    fields = decoder_id.to_bytes(4, "little") + channel.to_bytes(4, "little") + length.to_bytes(2, "little")
+    return fields + signer.sign(PREAMBLE_LABEL + fields)
+
+
+def gen_subscription_bundle(secrets: bytes, decoder_id: int, subscriptions: List[bytes]) -> bytes:
+    """Generate the body of a SubscribeBundle command replacing every stored subscription
+
+    The Decoder stores all of the subscriptions or, if any is refused or cannot be
+    written, none of them; subscriptions for channels not in the bundle are removed.
+
+    :param secrets: Contents of the secrets file
+    :param decoder_id: Device ID of the Decoder
+    :param subscriptions: Subscribe bodies from gen_subscription, without checksum,
+        each for a different channel
+
+    :returns: Count (1 byte), then each subscription's length (2 bytes) and body, then
+        a 64-byte Ed25519 signature
+    """
+    from Crypto.Signature import eddsa
+
+    secrets = json.loads(secrets)
+    host_key = ECC.import_key(bytes.fromhex(secrets["host_key_priv"]))
+    signer = eddsa.new(host_key, "rfc8032")
+    fields = bytes([len(subscriptions)])
+    for subscription in subscriptions:
+        fields += len(subscription).to_bytes(2, "little") + subscription
+    return fields + signer.sign(BUNDLE_LABEL + decoder_id.to_bytes(4, "little") + fields)
+
+
+def crc16(data: bytes) -> int:
+    """CRC-16/CCITT-FALSE of data, as the Decoder's subscribe-checksum feature computes it"""
+    crc = 0xFFFF
+    for b in data:
+        crc ^= b << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x1021 if crc & 0x8000 else crc << 1) & 0xFFFF
+    return crc
+
+
+def add_subscription_checksum(subscription: bytes) -> bytes:
+    """Append the checksum a Decoder built with subscribe-checksum expects to a subscription
+
+    :param subscription: Output of gen_subscription
+
+    :returns: The subscription followed by its CRC-16 (2 bytes, little-endian)
+    """
+    return subscription + crc16(subscription).to_bytes(2, "little")
 
 
 def gen_secrets(channels: list[int]) -> bytes:
@@ -70,7 +70,9 @@ class Encoder:
 
         nonce = get_random_bytes(12)
         cipher = ChaCha20.new(key=frame_key, nonce=nonce)
-        encrypted_frame_data = cipher.encrypt(frame)
+        # The channel id is encrypted after the frame as a marker the decoder checks,
+        # so a decrypt under the wrong key is caught
+        encrypted_frame_data = cipher.encrypt(frame + struct.pack("<I", channel))
 
         header_bytes = struct.pack("<IQ12s", channel, timestamp, nonce)
 
@@ -26,7 +26,12 @@ NODE_PASSWORD_SIZE = 25
 
 
 def gen_subscription(
-    secrets: bytes, device_id: int, start: int, end: int, channel: int
+    secrets: bytes,
+    device_id: int,
+    start: int,
+    end: int,
+    channel: int,
+    rekey_contexts: list[bytes] = (),
 ) -> bytes:
     """Generate the contents of a subscription.
 
@@ -50,6 +55,7 @@ def gen_subscription(
     :param start: First timestamp the subscription is valid for
     :param end: Last timestamp the subscription is valid for
     :param channel: Channel to enable
+    :param rekey_contexts: Contexts of the Rekey commands the Decoder accepted, in order
     """
     # Load the json of the secrets file
     secrets: Secrets = json.loads(secrets)
@@ -79,7 +85,7 @@ def gen_subscription(
     # Pad password bytes to size of 128 node passwords
     # passwords_bytes += b"\x00" * (128 * NODE_PASSWORD_SIZE - len(passwords_bytes))
 
-    decoder_key = get_decoder_key(secrets["decoder_dk"], device_id)
+    decoder_key = get_decoder_key(secrets["decoder_dk"], device_id, rekey_contexts)
     nonce = get_random_bytes(12)
     cipher = ChaCha20.new(key=decoder_key, nonce=nonce)
     passwords_enc_bytes = cipher.encrypt(passwords_bytes)
@@ -0,0 +1,105 @@
+"""
+Generate the known-answer vectors the decoder's host tests decode
+(decoder/host-tests/vectors/decode.json).
+
+Every frame and subscription comes from the real Encoder and gen_subscription, so the
+tests pin the firmware's decode path to this package: a change on either side that
+breaks compatibility fails them. Regenerate after an intentional format change with
+
+    python -m ectf25_design.gen_test_vectors ../decoder/host-tests/test.secrets \
+        ../decoder/host-tests/vectors/decode.json
+"""
+
+import argparse
+import json
+from pathlib import Path
+
+from ectf25_design import ChannelKeyDerivation
+from ectf25_design.encoder import Encoder
+from ectf25_design.gen_subscription import gen_subscription
+
+# Decoder id the decoder's `std` build is compiled for
+TEST_DECODER_ID = 0xDEADBEEF
+
+U64_MAX = 2**64 - 1
+
+# (name, channel, subscription start and end or None for channel 0, frame timestamps)
+CASES = [
+    # The root password is stored: every frame key is derived over all 64 levels
+    ("root", 1, (0, U64_MAX), [0x0123456789ABCDEF, U64_MAX]),
+    # The leaf password is stored: the frame key is the stored password itself
+    ("leaf", 2, (1_700_000_000_000_000, 1_700_000_000_000_000), [1_700_000_000_000_000]),
+    # Timestamp 0, the leftmost leaf
+    ("timestamp-zero", 3, (0, 1000), [0, 1000]),
+    # A window covered by several nodes of different depths
+    ("mid-range", 1, (1000, 5_000_000), [1000, 1024, 4_999_999, 5_000_000]),
+    # The emergency channel, decoded from the built-in channel 0 key
+    ("channel-0", 0, None, [0, 1_700_000_000_000_000]),
+]
+
+
+def frame_content(name: str, timestamp: int) -> bytes:
+    """64 recognizable bytes for a frame of a case"""
+    text = f"{name}@{timestamp}".encode()
+    return (text * (64 // len(text) + 1))[:64]
+
+
+def covering_node(deriv: ChannelKeyDerivation, start: int, end: int, timestamp: int) -> int:
+    """The node of the subscription's cover the decoder derives `timestamp`'s key from"""
+    nodes = [n for n, covered in deriv.get_node_traversal_for_frame(start, end, timestamp) if covered]
+    # The root is not part of the traversal, which starts at its children
+    return nodes[0] if nodes else 1
+
+
+def gen_test_vectors(secrets: bytes) -> dict:
+    encoder = Encoder(secrets)
+    channel_roots = json.loads(secrets)["channels"]
+
+    cases = []
+    for name, channel, window, timestamps in CASES:
+        deriv = ChannelKeyDerivation(root=bytes.fromhex(channel_roots[str(channel)]), height=64)
+        case = {"name": name, "channel": channel, "frames": []}
+        if window is not None:
+            start, end = window
+            case["start"] = start
+            case["end"] = end
+            case["subscription"] = gen_subscription(
+                secrets, TEST_DECODER_ID, start, end, channel
+            ).hex()
+
+        for timestamp in timestamps:
+            content = frame_content(name, timestamp)
+            frame = {
+                "timestamp": timestamp,
+                "frame": encoder.encode(channel, content, timestamp).hex(),
+                "content": content.hex(),
+                "frame_key": deriv.get_frame_key(timestamp).hex(),
+            }
+            if window is not None:
+                # Leaf numbers are 65 bits wide, past what JSON readers take as a number
+                frame["node"] = str(covering_node(deriv, start, end, timestamp))
+            case["frames"].append(frame)
+        cases.append(case)
+
+    return {"decoder_id": TEST_DECODER_ID, "cases": cases}
+
+
+def parse_args():
+    parser = argparse.ArgumentParser()
+    parser.add_argument(
+        "secrets_file",
+        type=argparse.FileType("rb"),
+        help="Secrets the decoder's host tests are built with",
+    )
+    parser.add_argument("vectors_file", type=Path, help="Vectors output")
+    return parser.parse_args()
+
+
+def main():
+    args = parse_args()
+    vectors = gen_test_vectors(args.secrets_file.read())
+    args.vectors_file.write_text(json.dumps(vectors, indent=2) + "\n")
+
+
+if __name__ == "__main__":
+    main()